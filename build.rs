@@ -4,7 +4,13 @@ use std::path::PathBuf;
 
 fn main() {
     let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
-    fs::copy("memory.x", out.join("memory.x")).unwrap();
+    let memory = if env::var_os("CARGO_FEATURE_F512").is_some() {
+        "memory-f512.x"
+    } else {
+        "memory.x"
+    };
+    fs::copy(memory, out.join("memory.x")).unwrap();
     println!("cargo:rustc-link-search={}", out.display());
     println!("cargo:rerun-if-changed=memory.x");
+    println!("cargo:rerun-if-changed=memory-f512.x");
 }
@@ -0,0 +1,84 @@
+//! Logs two analog inputs with a timestamp on every press of BTN0.
+//!
+//! PC6 (APORT1XCH6) and PC7 (APORT1YCH7) on the expansion header are sampled
+//! against the internal 2.5 V reference. The pins stay in their reset state,
+//! which disconnects the digital input. The button reaches the ADC and the
+//! RTCC through PRS channel 0, the timestamps are taken in hardware.
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use cortex_m::{interrupt::Mutex, peripheral::NVIC};
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    adc::{
+        self,
+        logging::{self, AportChannel, LoggingAdc},
+        Adc,
+    },
+    cmu::Cmu,
+    gpio::{Gpio, InterruptEdge},
+    pac::{interrupt, Peripherals},
+    prs::{self, Prs},
+};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+
+static LOGGER: Mutex<RefCell<Option<LoggingAdc<2, 16>>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    cmu.use_lfxo_or_fallback();
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+    let (_prs, mut channels) = Prs::new(peripherals.PRS, &mut cmu);
+
+    // BTN0 pulls PF6 low when pressed.
+    let btn0 = gpio.pf6.pull_up().input().into_exti(InterruptEdge::Falling);
+    channels.ch0.connect(btn0.prs_source(), prs::Edge::NEGEDGE);
+
+    let adc = Adc::new(peripherals.ADC0, &adc::Config::default(), &mut cmu);
+    let logger = LoggingAdc::new(
+        adc,
+        peripherals.RTCC,
+        [AportChannel::new(1, 6), AportChannel::new(1, 7)],
+        channels.ch0,
+        &logging::Config::default(),
+        &mut cmu,
+    );
+    let tick_frequency = logger.timestamp_frequency(&cmu.clocks()).0;
+    let full_scale = adc::reference_millivolts(adc::Reference::_2V5).unwrap();
+    let bits = logger.result_bits();
+
+    let interrupt = logger.interrupt();
+    cortex_m::interrupt::free(|cs| LOGGER.borrow(cs).replace(Some(logger)));
+    unsafe { NVIC::unmask(interrupt) };
+
+    loop {
+        let (record, dropped) = cortex_m::interrupt::free(|cs| {
+            let mut logger = LOGGER.borrow(cs).borrow_mut();
+            let logger = logger.as_mut().unwrap();
+            (logger.read(), logger.dropped())
+        });
+        if dropped != 0 {
+            rprintln!("{} records dropped", dropped);
+        }
+        if let Some(record) = record {
+            let ms = record.timestamp as u64 * 1000 / tick_frequency as u64;
+            let [pc6, pc7] = record.samples.map(|s| (s as u32 * full_scale) >> bits);
+            rprintln!("{} ms: PC6 {} mV, PC7 {} mV", ms, pc6, pc7);
+        }
+    }
+}
+
+#[interrupt]
+fn ADC0() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(logger) = LOGGER.borrow(cs).borrow_mut().as_mut() {
+            logger.on_interrupt();
+        }
+    });
+}
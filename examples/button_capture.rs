@@ -0,0 +1,44 @@
+//! Timestamps presses of BTN0 with WTIMER0 through the PRS, without any CPU
+//! involvement in the capture itself.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    gpio::{Gpio, InterruptEdge},
+    pac::Peripherals,
+    prs::{self, Prs},
+    timer::{self, Edge, Timer},
+};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+    let (_prs, mut channels) = Prs::new(peripherals.PRS, &mut cmu);
+
+    // BTN0 pulls PF6 low when pressed, which is line 6 on the GPIOL source.
+    let btn0 = gpio.pf6.pull_up().input().into_exti(InterruptEdge::Falling);
+    channels.ch0.connect(btn0.prs_source(), prs::Edge::OFF);
+
+    let timer = Timer::new(peripherals.WTIMER0, &timer::Config::default(), &mut cmu);
+    let mut capture = timer.into_prs_capture(&channels.ch0, Edge::FALLING);
+    let tick_frequency = capture.tick_frequency().0;
+
+    let mut last = None;
+    loop {
+        let timestamp = nb::block!(capture.read()).unwrap();
+        if let Some(last) = last {
+            let ticks: u32 = timestamp.wrapping_sub(last);
+            let ms = ticks as u64 * 1000 / tick_frequency as u64;
+            rprintln!("{} ms since the last press", ms);
+        }
+        last = Some(timestamp);
+    }
+}
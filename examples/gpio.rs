@@ -2,7 +2,12 @@
 #![no_main]
 
 use cortex_m_rt::entry;
-use efm32pg12_hal::{cmu::Cmu, gpio::Gpio, pac::Peripherals, prelude::*};
+use efm32pg12_hal::{
+    cmu::Cmu,
+    gpio::{ActiveHigh, ActiveLow, Gpio},
+    pac::Peripherals,
+    prelude::*,
+};
 use panic_rtt_target as _;
 use rtt_target::rtt_init_default;
 
@@ -14,24 +19,24 @@ fn main() -> ! {
     let mut cmu = Cmu::new(peripherals.CMU);
     let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
 
-    let mut led0 = gpio.pf4.push_pull_output(false);
-    let mut led1 = gpio.pf5.push_pull_output(false);
+    let mut led0 = ActiveHigh::new(gpio.pf4.push_pull_output(false));
+    let mut led1 = ActiveHigh::new(gpio.pf5.push_pull_output(false));
 
     // External pull-up resistor is too weak. Touching the backside of the
     // board makes the input toggle. Enable the internal pull-up improve
-    // input noise resistance.
-    let btn0 = gpio.pf6.pull_up().input();
-    let btn1 = gpio.pf7.pull_up().input();
+    // input noise resistance. The buttons pull the pins low when pressed.
+    let btn0 = ActiveLow::new(gpio.pf6.pull_up().input());
+    let btn1 = ActiveLow::new(gpio.pf7.pull_up().input());
 
     // Each button controls a LED.
     loop {
-        if btn0.is_low().unwrap() {
+        if btn0.is_high().unwrap() {
             led0.set_high().ok();
         } else {
             led0.set_low().ok();
         }
 
-        if btn1.is_low().unwrap() {
+        if btn1.is_high().unwrap() {
             led1.set_high().ok();
         } else {
             led1.set_low().ok();
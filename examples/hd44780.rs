@@ -0,0 +1,111 @@
+//! Writes a text to a HD44780 character display in 4-bit mode.
+//!
+//! Connect the display data lines D4..D7 to PD9..PD12, RS to PD13 and E to
+//! PD14. R/W must be tied to ground.
+#![no_std]
+#![no_main]
+
+use cortex_m::asm::delay;
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    gpio::{Gpio, Output, Pin, PinTrait, Pins4},
+    pac::Peripherals,
+    prelude::*,
+};
+use panic_rtt_target as _;
+use rtt_target::rtt_init_default;
+
+// Cycles per microsecond with the default 19 MHz HFRCO.
+const CYCLES_PER_US: u32 = 19;
+
+struct Display<D0, D1, D2, D3, RS, E>
+where
+    D0: PinTrait,
+    D1: PinTrait,
+    D2: PinTrait,
+    D3: PinTrait,
+    RS: PinTrait,
+    E: PinTrait,
+{
+    data: Pins4<D0, D1, D2, D3>,
+    rs: Pin<RS, Output>,
+    e: Pin<E, Output>,
+}
+
+impl<D0, D1, D2, D3, RS, E> Display<D0, D1, D2, D3, RS, E>
+where
+    D0: PinTrait,
+    D1: PinTrait,
+    D2: PinTrait,
+    D3: PinTrait,
+    RS: PinTrait,
+    E: PinTrait,
+{
+    fn write_nibble(&mut self, nibble: u8) {
+        self.data.write(nibble);
+        self.e.set_high().ok();
+        delay(CYCLES_PER_US);
+        self.e.set_low().ok();
+        delay(50 * CYCLES_PER_US);
+    }
+
+    fn write_byte(&mut self, data: bool, byte: u8) {
+        if data {
+            self.rs.set_high().ok();
+        } else {
+            self.rs.set_low().ok();
+        }
+        self.write_nibble(byte >> 4);
+        self.write_nibble(byte & 0x0F);
+    }
+
+    fn init(&mut self) {
+        delay(40_000 * CYCLES_PER_US);
+
+        // Switch to 4-bit mode regardless of the current mode.
+        self.write_nibble(0x3);
+        delay(4_100 * CYCLES_PER_US);
+        self.write_nibble(0x3);
+        delay(100 * CYCLES_PER_US);
+        self.write_nibble(0x3);
+        self.write_nibble(0x2);
+
+        self.write_byte(false, 0x28); // 4-bit, 2 lines, 5x8 font
+        self.write_byte(false, 0x0C); // Display on, cursor off
+        self.write_byte(false, 0x01); // Clear
+        delay(2_000 * CYCLES_PER_US);
+        self.write_byte(false, 0x06); // Increment, no shift
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    rtt_init_default!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+
+    // All data pins are on port D and change with a single register write.
+    let data = Pins4::new((
+        gpio.pd9.push_pull_output(false),
+        gpio.pd10.push_pull_output(false),
+        gpio.pd11.push_pull_output(false),
+        gpio.pd12.push_pull_output(false),
+    ));
+    let mut display = Display {
+        data,
+        rs: gpio.pd13.push_pull_output(false),
+        e: gpio.pd14.push_pull_output(false),
+    };
+
+    display.init();
+    for &c in b"Hello, world!" {
+        display.write_byte(true, c);
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
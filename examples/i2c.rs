@@ -2,7 +2,13 @@
 #![no_main]
 
 use cortex_m_rt::entry;
-use efm32pg12_hal::{cmu::Cmu, gpio::Gpio, i2c::I2c, pac::Peripherals, prelude::*};
+use efm32pg12_hal::{
+    cmu::Cmu,
+    gpio::Gpio,
+    i2c::{Config, I2c},
+    pac::Peripherals,
+    prelude::*,
+};
 use panic_rtt_target as _;
 use rtt_target::{rprintln, rtt_init_print};
 
@@ -21,7 +27,10 @@ fn main() -> ! {
     let _sensor_enable = gpio.pb10.push_pull_output(true);
     let scl = gpio.pc11.filter().open_drain_output(true);
     let sda = gpio.pc10.filter().open_drain_output(true);
-    let mut i2c = I2c::new(peripherals.I2C0, scl, sda, &mut cmu);
+    let mut i2c = I2c::new(peripherals.I2C0, scl, sda, &Config::default(), &mut cmu).unwrap();
+    // The sensor may still hold SDA low when a debugger halted the MCU in
+    // the middle of a read.
+    i2c.recover(&cmu.clocks()).unwrap();
 
     let mut prev_button_state = false;
     loop {
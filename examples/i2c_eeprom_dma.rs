@@ -0,0 +1,64 @@
+//! Writes and reads back a page of a 24LC256 EEPROM at address 0x50,
+//! connected to I2C0 on the expansion header, with the LDMA moving the data.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    gpio::Gpio,
+    i2c::{Config, I2c},
+    ldma::Ldma,
+    pac::Peripherals,
+    prelude::*,
+};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+
+const EEPROM_ADDRESS: u8 = 0x50;
+const PAGE_SIZE: usize = 64;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+    let (_ldma, mut channels) = Ldma::new(peripherals.LDMA, &mut cmu);
+
+    let scl = gpio.pc11.filter().open_drain_output(true);
+    let sda = gpio.pc10.filter().open_drain_output(true);
+    let mut i2c = I2c::new(peripherals.I2C0, scl, sda, &Config::default(), &mut cmu).unwrap();
+
+    // Two address bytes followed by the page data.
+    let page_address: u16 = 0x0100;
+    let mut write_buffer = [0u8; 2 + PAGE_SIZE];
+    write_buffer[..2].copy_from_slice(&page_address.to_be_bytes());
+    for (i, b) in write_buffer[2..].iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    i2c.write_dma(EEPROM_ADDRESS, &write_buffer, &mut channels.ch0)
+        .unwrap();
+
+    // The EEPROM does not acknowledge its address while the write cycle is in
+    // progress. The dummy write sets the address pointer for the read.
+    while i2c
+        .write(EEPROM_ADDRESS, &page_address.to_be_bytes())
+        .is_err()
+    {}
+
+    let mut read_buffer = [0u8; PAGE_SIZE];
+    i2c.read_dma(EEPROM_ADDRESS, &mut read_buffer, &mut channels.ch0)
+        .unwrap();
+
+    if read_buffer[..] == write_buffer[2..] {
+        rprintln!("page verified");
+    } else {
+        rprintln!("page mismatch: {:?}", &read_buffer[..]);
+    }
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
@@ -0,0 +1,63 @@
+//! Shares the SDA pin of the sensor bus on the starter kit between I2C and a
+//! GPIO output.
+//!
+//! Reads the humidity, hands SDA (PC10) over to GPIO to send a few pulses
+//! and takes it back for the next measurement. The handover itself leaves
+//! both lines high, no edge appears on the bus. The pulses show up as start
+//! and stop conditions without an address, which the sensor ignores.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    cryotimer::{self, Cryotimer},
+    gpio::Gpio,
+    i2c::{Config, I2c},
+    pac::Peripherals,
+    prelude::*,
+};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+    let cryotimer = Cryotimer::new(
+        peripherals.CRYOTIMER,
+        &cryotimer::Config::default(),
+        &mut cmu,
+    );
+
+    let _sensor_enable = gpio.pb10.push_pull_output(true);
+    let scl = gpio.pc11.filter().open_drain_output(true);
+    let sda = gpio.pc10.filter().open_drain_output(true);
+    let mut i2c = I2c::new(peripherals.I2C0, scl, sda, &Config::default(), &mut cmu).unwrap();
+
+    loop {
+        let mut humidity_raw = [0u8; 2];
+        match i2c.write_read(0x40, &[0xE5], &mut humidity_raw) {
+            Ok(()) => rprintln!("humidity raw: {}", u16::from_be_bytes(humidity_raw)),
+            Err(_) => rprintln!("error"),
+        }
+
+        // Hand SDA over to GPIO. It starts high like the idle bus.
+        let (i2c0, scl, sda) = i2c.release_with_pins();
+        let mut sda = sda.reset().push_pull_output(true);
+        for _ in 0..4 {
+            sda.set_low().unwrap();
+            sda.set_high().unwrap();
+        }
+
+        // Back to I2C, released (high) before the route is enabled.
+        let sda = sda.reset().filter().open_drain_output(true);
+        i2c = I2c::new(i2c0, scl, sda, &Config::default(), &mut cmu).unwrap();
+
+        let deadline = cryotimer.deadline(1000.ms());
+        while !deadline.expired() {}
+    }
+}
@@ -0,0 +1,39 @@
+//! Scans the I2C bus of the sensor on the starter kit and prints the
+//! addresses of all responding devices.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    gpio::Gpio,
+    i2c::{Config, I2c},
+    pac::Peripherals,
+};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+
+    // Enable the SI7021 humidity sensor with I2C interface.
+    let _sensor_enable = gpio.pb10.push_pull_output(true);
+    let scl = gpio.pc11.filter().open_drain_output(true);
+    let sda = gpio.pc10.filter().open_drain_output(true);
+    let mut i2c = I2c::new(peripherals.I2C0, scl, sda, &Config::default(), &mut cmu).unwrap();
+
+    rprintln!("scanning I2C bus");
+    for address in i2c.scan() {
+        rprintln!("device found at 0x{:02x}", address);
+    }
+    rprintln!("done");
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
@@ -0,0 +1,37 @@
+//! Running light over pins of different ports stored in one array.
+//!
+//! LED0 and LED1 of the starter kit plus PC6 and PA6 on the expansion header,
+//! where further LEDs can be connected.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{cmu::Cmu, delay::Delay, gpio::Gpio, pac::Peripherals, prelude::*};
+use panic_rtt_target as _;
+use rtt_target::rtt_init_default;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_default!();
+
+    let core = cortex_m::Peripherals::take().unwrap();
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+    let mut delay = Delay::new(core.SYST, &cmu.clocks());
+
+    let mut leds = [
+        gpio.pf4.push_pull_output(false).downgrade(),
+        gpio.pf5.push_pull_output(false).downgrade(),
+        gpio.pc6.push_pull_output(false).downgrade(),
+        gpio.pa6.push_pull_output(false).downgrade(),
+    ];
+
+    loop {
+        for led in leds.iter_mut() {
+            led.set_high().ok();
+            delay.delay_ms(125u32);
+            led.set_low().ok();
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Wakes from EM2 once per second, counts the wake-ups and sleeps again.
+//!
+//! The CRYOTIMER runs from the ULFRCO. Between the ticks only the CRYOTIMER
+//! and the always on domain draw current, the average current is dominated
+//! by the time spent awake, which is a few microseconds per tick. The RTT
+//! output keeps the debugger attached, which prevents EM2 on some probes.
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use cortex_m::{
+    interrupt::Mutex,
+    peripheral::{Peripherals as CorePeripherals, NVIC},
+};
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    cryotimer::{self, Cryotimer, PeriodicTick},
+    pac::{interrupt, Interrupt, Peripherals},
+    prelude::*,
+};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+
+static TICK: Mutex<RefCell<Option<PeriodicTick>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let mut core = CorePeripherals::take().unwrap();
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let cryotimer = Cryotimer::new(
+        peripherals.CRYOTIMER,
+        &cryotimer::Config::default(),
+        &mut cmu,
+    );
+
+    let tick = cryotimer.into_periodic(1000.ms());
+    rprintln!("period: {} ticks of 1 ms", tick.period_ticks());
+    cortex_m::interrupt::free(|cs| TICK.borrow(cs).replace(Some(tick)));
+    unsafe { NVIC::unmask(Interrupt::CRYOTIMER) };
+
+    // WFI enters EM2 instead of EM1.
+    core.SCB.set_sleepdeep();
+
+    loop {
+        cortex_m::asm::wfi();
+        let count = cortex_m::interrupt::free(|cs| {
+            TICK.borrow(cs)
+                .borrow()
+                .as_ref()
+                .map_or(0, |tick| tick.tick_count())
+        });
+        rprintln!("tick {}", count);
+    }
+}
+
+#[interrupt]
+fn CRYOTIMER() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(tick) = TICK.borrow(cs).borrow_mut().as_mut() {
+            tick.on_interrupt();
+        }
+    });
+}
@@ -0,0 +1,117 @@
+//! Reads two holding registers from Modbus RTU slave 1 on the Starter Kit
+//! virtual COM port (VCOM). The GPCRC appends the CRC-16 to the request and
+//! checks the response while it is received.
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use cortex_m::{interrupt::Mutex, peripheral::NVIC};
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    gpcrc::{Gpcrc, Preset},
+    gpio::Gpio,
+    pac::{interrupt, Interrupt, Peripherals, USART0},
+    usart::{
+        buffered::{BufferedRx, BufferedTx},
+        Config, Usart,
+    },
+};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+
+static RX: Mutex<RefCell<Option<BufferedRx<USART0, 64>>>> = Mutex::new(RefCell::new(None));
+static TX: Mutex<RefCell<Option<BufferedTx<USART0, 64>>>> = Mutex::new(RefCell::new(None));
+
+// Slave address, function code "read holding registers", start address and
+// number of registers. The CRC is appended by the driver.
+const REQUEST: [u8; 6] = [0x01, 0x03, 0x00, 0x00, 0x00, 0x02];
+// Slave address, function code, byte count, 4 data bytes and the CRC.
+const RESPONSE_LEN: usize = 9;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+
+    // Enable VCOM connection on the starter kit.
+    let _vcom_enable = gpio.pa5.push_pull_output(true);
+
+    let tx_pin = gpio.pa0.push_pull_output(true);
+    let rx_pin = gpio.pa1.input();
+    let usart0 = Usart::new(
+        peripherals.USART0,
+        tx_pin,
+        rx_pin,
+        &Config::default(),
+        &mut cmu,
+    )
+    .unwrap();
+    let (tx, rx) = usart0.split();
+
+    let mut rx = BufferedRx::new(rx);
+    rx.enable_crc(Gpcrc::new(peripherals.GPCRC, Preset::Crc16Modbus, &mut cmu));
+
+    cortex_m::interrupt::free(|cs| {
+        RX.borrow(cs).replace(Some(rx));
+        TX.borrow(cs).replace(Some(BufferedTx::new(tx)));
+    });
+
+    unsafe {
+        NVIC::unmask(Interrupt::USART0_RX);
+        NVIC::unmask(Interrupt::USART0_TX);
+    }
+
+    loop {
+        // The request borrows the GPCRC from the receiver, the response
+        // calculation starts from scratch afterwards.
+        cortex_m::interrupt::free(|cs| {
+            let mut rx = RX.borrow(cs).borrow_mut();
+            let mut tx = TX.borrow(cs).borrow_mut();
+            let rx = rx.as_mut().unwrap();
+            let crc = rx.crc_mut().unwrap();
+            tx.as_mut().unwrap().write_frame_with_crc(&REQUEST, crc);
+            rx.reset_crc();
+        });
+
+        let mut response = [0u8; RESPONSE_LEN];
+        let mut received = 0;
+        while received < RESPONSE_LEN {
+            received += cortex_m::interrupt::free(|cs| {
+                let mut rx = RX.borrow(cs).borrow_mut();
+                rx.as_mut().unwrap().read_all(&mut response[received..])
+            });
+        }
+
+        let crc_ok =
+            cortex_m::interrupt::free(|cs| RX.borrow(cs).borrow().as_ref().unwrap().crc_ok());
+        if crc_ok {
+            let register0 = u16::from_be_bytes([response[3], response[4]]);
+            let register1 = u16::from_be_bytes([response[5], response[6]]);
+            rprintln!("registers: {} {}", register0, register1);
+        } else {
+            rprintln!("CRC error in response");
+        }
+    }
+}
+
+#[interrupt]
+fn USART0_RX() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(rx) = RX.borrow(cs).borrow_mut().as_mut() {
+            rx.on_interrupt();
+        }
+    });
+}
+
+#[interrupt]
+fn USART0_TX() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(tx) = TX.borrow(cs).borrow_mut().as_mut() {
+            tx.on_interrupt();
+        }
+    });
+}
@@ -0,0 +1,71 @@
+//! Measures how long a humidity measurement of the SI7021 on the starter kit
+//! takes, using a free-running timer as time base.
+//!
+//! Also measures a read of the user register, which the sensor answers
+//! without clock stretching. The transfer occupies the bus for 38 SCL
+//! periods (start, address, register, repeated start, address, data, stop),
+//! the remainder of the duration is the overhead of the driver.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    gpio::Gpio,
+    i2c::{Config, I2c},
+    pac::Peripherals,
+    prelude::*,
+    timer::{self, Timer},
+};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+
+    let _sensor_enable = gpio.pb10.push_pull_output(true);
+    let scl = gpio.pc11.filter().open_drain_output(true);
+    let sda = gpio.pc10.filter().open_drain_output(true);
+    let mut i2c = I2c::new(peripherals.I2C0, scl, sda, &Config::default(), &mut cmu).unwrap();
+
+    // A 32-bit counter covers minutes at full resolution.
+    let timer = Timer::new(peripherals.WTIMER0, &timer::Config::default(), &mut cmu);
+    let monotonic = timer.into_monotonic();
+    let ticks_per_us = monotonic.ticks_per_second().0 / 1_000_000;
+
+    loop {
+        // Measure humidity, the sensor stretches the clock until done.
+        let mut humidity_raw = [0u8; 2];
+        let start = monotonic.now();
+        let result = i2c.write_read(0x40, &[0xE5], &mut humidity_raw);
+        let ticks = monotonic.elapsed(start);
+
+        match result {
+            Ok(()) => rprintln!("measurement took {} us", ticks / ticks_per_us),
+            Err(_) => rprintln!("error"),
+        }
+
+        // Read the user register, 38 SCL periods on the bus.
+        let mut user_register = [0u8; 1];
+        let start = monotonic.now();
+        let result = i2c.write_read(0x40, &[0xE7], &mut user_register);
+        let ticks = monotonic.elapsed(start);
+
+        match result {
+            Ok(()) => rprintln!(
+                "register read took {} us, the bus needs {} us",
+                ticks / ticks_per_us,
+                38 * 1_000_000 / i2c.actual_frequency(&cmu.clocks()).0
+            ),
+            Err(_) => rprintln!("error"),
+        }
+
+        let start = monotonic.now();
+        while monotonic.elapsed(start) < monotonic.ticks_per_second().0 {}
+    }
+}
@@ -0,0 +1,45 @@
+//! Fades LED0 and LED1 of the starter kit in opposite directions.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    delay::Delay,
+    gpio::Gpio,
+    pac::Peripherals,
+    prelude::*,
+    timer::{self, Timer},
+};
+use panic_rtt_target as _;
+use rtt_target::rtt_init_print;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let core = cortex_m::Peripherals::take().unwrap();
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+    let mut delay = Delay::new(core.SYST, &cmu.clocks());
+
+    let led0 = gpio.pf4.push_pull_output(false);
+    let led1 = gpio.pf5.push_pull_output(false);
+    let timer0 = Timer::new(peripherals.TIMER0, &timer::Config::default(), &mut cmu);
+    let timer1 = Timer::new(peripherals.TIMER1, &timer::Config::default(), &mut cmu);
+    let mut pwm0 = timer0.into_pwm(led0, 1.khz());
+    let mut pwm1 = timer1.into_pwm(led1, 1.khz());
+    pwm0.enable();
+    pwm1.enable();
+
+    let max_duty = pwm0.get_max_duty();
+    loop {
+        for step in (0..=100).chain((0..100).rev()) {
+            let duty = max_duty / 100 * step;
+            pwm0.set_duty(duty);
+            pwm1.set_duty(max_duty - duty);
+            delay.delay_ms(10u32);
+        }
+    }
+}
@@ -39,7 +39,8 @@ fn main() -> ! {
             vcom_rx_pin,
             &Config::default(),
             &mut cmu,
-        );
+        )
+        .unwrap();
         usart0.split()
     };
 
@@ -52,7 +53,8 @@ fn main() -> ! {
             rx_pin,
             &Config::default(),
             &mut cmu,
-        );
+        )
+        .unwrap();
         usart3.split()
     };
 
@@ -0,0 +1,95 @@
+//! Interrupt driven echo on the Starter Kit virtual COM port (VCOM).
+//! In contrast to the `serial_echo` example no bytes are lost when the main
+//! loop is busy, as long as the ring buffers do not fill up.
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use cortex_m::{interrupt::Mutex, peripheral::NVIC};
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    gpio::Gpio,
+    pac::{interrupt, Peripherals, USART0},
+    usart::{
+        buffered::{BufferedRx, BufferedTx},
+        Config, Usart,
+    },
+};
+use panic_rtt_target as _;
+use rtt_target::rtt_init_default;
+
+static RX: Mutex<RefCell<Option<BufferedRx<USART0, 64>>>> = Mutex::new(RefCell::new(None));
+static TX: Mutex<RefCell<Option<BufferedTx<USART0, 64>>>> = Mutex::new(RefCell::new(None));
+
+#[entry]
+fn main() -> ! {
+    rtt_init_default!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+
+    // Enable VCOM connection on the starter kit.
+    let _vcom_enable = gpio.pa5.push_pull_output(true);
+
+    let tx_pin = gpio.pa0.push_pull_output(true);
+    let rx_pin = gpio.pa1.input();
+    let usart0 = Usart::new(
+        peripherals.USART0,
+        tx_pin,
+        rx_pin,
+        &Config::default(),
+        &mut cmu,
+    )
+    .unwrap();
+    let (tx, rx) = usart0.split();
+
+    let rx = BufferedRx::new(rx);
+    let tx = BufferedTx::new(tx);
+    let (rx_interrupt, tx_interrupt) = (rx.interrupt(), tx.interrupt());
+    cortex_m::interrupt::free(|cs| {
+        RX.borrow(cs).replace(Some(rx));
+        TX.borrow(cs).replace(Some(tx));
+    });
+
+    unsafe {
+        NVIC::unmask(rx_interrupt);
+        NVIC::unmask(tx_interrupt);
+    }
+
+    // Bytes which did not fit into the TX ring buffer stay in the local
+    // buffer until the next iteration.
+    let mut buffer = [0u8; 16];
+    let mut start = 0;
+    let mut end = 0;
+    loop {
+        cortex_m::interrupt::free(|cs| {
+            let mut rx = RX.borrow(cs).borrow_mut();
+            let mut tx = TX.borrow(cs).borrow_mut();
+            if start == end {
+                start = 0;
+                end = rx.as_mut().unwrap().read_all(&mut buffer);
+            }
+            start += tx.as_mut().unwrap().write(&buffer[start..end]);
+        });
+    }
+}
+
+#[interrupt]
+fn USART0_RX() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(rx) = RX.borrow(cs).borrow_mut().as_mut() {
+            rx.on_interrupt();
+        }
+    });
+}
+
+#[interrupt]
+fn USART0_TX() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(tx) = TX.borrow(cs).borrow_mut().as_mut() {
+            tx.on_interrupt();
+        }
+    });
+}
@@ -24,21 +24,18 @@ fn main() -> ! {
     // Enable VCOM connection on the starter kit.
     let _vcom_enable = gpio.pa5.push_pull_output(true);
 
-    // Configure the serial pins
-    let tx_pin = gpio.pa0.push_pull_output(true);
-    let rx_pin = gpio.pa1.input();
-
-    // Configures the serial port with 115200bps, 8 data bits and 1 stop bit.
-    // The peripheral can easily be changed to USART1.
+    // Configures the serial pins and the serial port with 115200bps, 8 data
+    // bits and 1 stop bit. The peripheral can easily be changed to USART1.
     // For USART2 or USART3 there is an compiler error because the selected
     // pins are not supported by these peripheral instances.
-    let usart0 = Usart::new(
+    let usart0 = Usart::with_pins(
         peripherals.USART0,
-        tx_pin,
-        rx_pin,
+        gpio.pa0,
+        gpio.pa1,
         &Config::default(),
         &mut cmu,
-    );
+    )
+    .unwrap();
     let (mut tx, mut rx) = usart0.split();
 
     // Echo back each received byte.
@@ -0,0 +1,90 @@
+//! Initializes independent parts of the firmware from individual peripherals.
+//!
+//! `Peripherals::take()` is called once in `main()`. Each module only
+//! receives the peripherals and pins it needs, plus a `&mut Cmu` to enable
+//! their clocks during initialization.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{cmu::Cmu, gpio::Gpio, pac::Peripherals, prelude::*};
+use panic_rtt_target as _;
+use rtt_target::rtt_init_default;
+
+/// Serial console on the starter kit virtual COM port.
+mod console {
+    use efm32pg12_hal::{
+        cmu::Cmu,
+        gpio::{Input, Output, Pin, PA0, PA1},
+        pac::USART0,
+        usart::{Config, Rx, Tx, Usart},
+    };
+
+    pub struct Console {
+        pub tx: Tx<USART0>,
+        pub rx: Rx<USART0>,
+    }
+
+    pub fn init(
+        usart: USART0,
+        tx: Pin<PA0, Output>,
+        rx: Pin<PA1, Input>,
+        cmu: &mut Cmu,
+    ) -> Console {
+        let (tx, rx) = Usart::new(usart, tx, rx, &Config::default(), cmu)
+            .unwrap()
+            .split();
+        Console { tx, rx }
+    }
+}
+
+/// Toggles a LED for each received byte.
+mod indicator {
+    use efm32pg12_hal::{
+        gpio::{Output, Pin, PF4},
+        prelude::*,
+    };
+
+    pub struct Indicator {
+        led: Pin<PF4, Output>,
+    }
+
+    impl Indicator {
+        pub fn new(led: Pin<PF4, Output>) -> Self {
+            Indicator { led }
+        }
+
+        pub fn blink(&mut self) {
+            self.led.toggle().ok();
+        }
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    rtt_init_default!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+
+    // Enable VCOM connection on the starter kit.
+    let _vcom_enable = gpio.pa5.push_pull_output(true);
+
+    // Moving fields out of `peripherals` and `gpio` is allowed, each module
+    // owns its part exclusively.
+    let mut console = console::init(
+        peripherals.USART0,
+        gpio.pa0.push_pull_output(true),
+        gpio.pa1.input(),
+        &mut cmu,
+    );
+    let mut indicator = indicator::Indicator::new(gpio.pf4.push_pull_output(false));
+
+    loop {
+        if let Ok(b) = nb::block!(console.rx.read()) {
+            nb::block!(console.tx.write(b)).ok();
+            indicator.blink();
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Plays a melody on a piezo buzzer connected to PC8 on the expansion header.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    cmu::Cmu,
+    cryotimer::{self, Cryotimer},
+    gpio::Gpio,
+    pac::Peripherals,
+    prelude::*,
+    timer::{self, Timer},
+};
+use panic_rtt_target as _;
+use rtt_target::rtt_init_print;
+
+// Note frequencies in Hz and durations in ms, 0 Hz is a rest.
+const MELODY: [(u32, u32); 8] = [
+    (262, 250),
+    (294, 250),
+    (330, 250),
+    (349, 250),
+    (392, 500),
+    (0, 100),
+    (392, 500),
+    (0, 1000),
+];
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+    let cryotimer = Cryotimer::new(
+        peripherals.CRYOTIMER,
+        &cryotimer::Config::default(),
+        &mut cmu,
+    );
+
+    let buzzer = gpio.pc8.push_pull_output(false);
+    let timer = Timer::new(peripherals.TIMER0, &timer::Config::default(), &mut cmu);
+    let mut tone = timer.into_square_wave(buzzer, 1.khz());
+
+    loop {
+        for &(frequency, duration) in MELODY.iter() {
+            if frequency == 0 {
+                tone.pause();
+            } else {
+                // Restarts the output.
+                tone.set_frequency(frequency.hz());
+            }
+
+            let deadline = cryotimer.deadline(duration.ms());
+            while !deadline.expired() {}
+        }
+    }
+}
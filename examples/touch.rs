@@ -0,0 +1,64 @@
+//! Toggles LED0 whenever a touch pad connected to PC6 on the expansion header
+//! is touched.
+//!
+//! The pad can be anything conductive, e.g. a piece of copper tape with a
+//! layer of tape on top. PC6 reaches ACMP0 through APORT1XCH6.
+#![no_std]
+#![no_main]
+
+use cortex_m_rt::entry;
+use efm32pg12_hal::{
+    acmp::{self, AportChannel, TouchSensor},
+    cmu::Cmu,
+    gpio::Gpio,
+    pac::Peripherals,
+    prelude::*,
+    prs::Prs,
+    timer::{self, Timer},
+};
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+
+// Charge time increase in ticks that counts as a touch.
+const THRESHOLD: u16 = 4;
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let peripherals = Peripherals::take().unwrap();
+    let mut cmu = Cmu::new(peripherals.CMU);
+    let gpio = Gpio::new(peripherals.GPIO, &mut cmu);
+    let (_prs, channels) = Prs::new(peripherals.PRS, &mut cmu);
+
+    let mut led = gpio.pf4.push_pull_output(false);
+    let pad = gpio.pc6.push_pull_output(false);
+    let timer = Timer::new(peripherals.TIMER0, &timer::Config::default(), &mut cmu);
+    let mut touch = TouchSensor::new(
+        peripherals.ACMP0,
+        timer,
+        pad,
+        AportChannel::new(1, 6),
+        channels.ch0,
+        &acmp::Config::default(),
+        &mut cmu,
+    );
+
+    let mut baseline = touch.calibrate(64);
+    rprintln!("baseline {}", baseline);
+
+    let mut touched = false;
+    loop {
+        let reading = touch.read();
+        let now_touched = reading > baseline.saturating_add(THRESHOLD);
+        if now_touched && !touched {
+            rprintln!("touch {}", reading);
+            led.toggle().unwrap();
+        }
+        if !now_touched {
+            // Follow slow drift while the pad is not touched.
+            baseline = ((baseline as u32 * 15 + reading as u32) / 16) as u16;
+        }
+        touched = now_touched;
+    }
+}
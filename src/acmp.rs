@@ -0,0 +1,291 @@
+//! Analog comparator (ACMP) API
+//!
+//! [`TouchSensor`] measures the capacitance of a touch pad with the RC charge
+//! time method, for boards that connect their pads to ACMP capable pins. The
+//! pad is discharged by driving the pin low, then charged through the pull-up
+//! resistor while a timer counts. The ACMP compares the pad voltage with a
+//! fraction of VDD and its output stops the count through a PRS channel and an
+//! input capture, so the result does not depend on interrupt latency. A finger
+//! on the pad adds capacitance and extends the charge time.
+//!
+//! # Calibration
+//!
+//! The untouched charge time depends on the pad, the board and the supply
+//! voltage and slowly drifts with temperature and humidity. Take a baseline
+//! with [`TouchSensor::calibrate()`] while the pad is not touched and compare
+//! readings against it with [`TouchSensor::is_touched()`]. To follow drift,
+//! move the baseline a small step towards each reading that is not a touch:
+//!
+//! ```ignore
+//! let reading = touch.read();
+//! if reading > baseline + threshold {
+//!     // touched, keep the baseline
+//! } else {
+//!     baseline = (baseline * 15 + reading) / 16;
+//! }
+//! ```
+//!
+//! A threshold of a quarter to half of the increase measured with a finger on
+//! the pad works well. The internal pull-up charges typical pads within a few
+//! microseconds, i.e. a few dozen counts at 19 MHz. An external resistor of
+//! around 1 MΩ from the pad to VDD with [`Config::internal_pull_up`] disabled
+//! increases the resolution accordingly.
+use crate::{
+    cmu::{ClockControlExt, Cmu},
+    gpio::{Output, Pin, PinMode, PinTrait},
+    pac::{
+        acmp0::{inputsel::VASEL_A, RegisterBlock},
+        timer0::cc0_ctrl::MODE_A,
+        ACMP0, ACMP1,
+    },
+    prs::{self, PrsSource},
+    timer::{self, Timer},
+};
+use core::ops::Deref;
+
+// `INPUTSEL.NEGSEL` value of the divided VA reference.
+const NEGSEL_VADIV: u8 = 0xFD;
+
+// Counter ticks the pad is driven low before each measurement.
+const DISCHARGE_TICKS: u32 = 200;
+
+/// Positive ACMP input or ADC scan input, an analog port (APORT) channel.
+///
+/// Look up the bus and channel of the pad pin in the alternate functionality
+/// table of the datasheet, e.g. `APORT1XCH6` is bus 1 channel 6. Whether the
+/// channel belongs to the X or the Y bus follows from the channel number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AportChannel(u8);
+
+impl AportChannel {
+    /// Panics when the bus is not in `0..=4` or the channel does not exist.
+    pub const fn new(bus: u8, channel: u8) -> Self {
+        assert!(bus <= 4);
+        assert!(channel < if bus == 0 { 16 } else { 32 });
+        AportChannel(bus * 0x20 + channel)
+    }
+
+    /// APORT bus number.
+    pub fn bus(self) -> u8 {
+        self.0 / 0x20
+    }
+
+    /// Channel number on the bus.
+    pub fn channel(self) -> u8 {
+        self.0 % 0x20
+    }
+}
+
+/// Touch sensor configuration.
+///
+/// Defaults to charging through the internal pull-up and a threshold of
+/// 40/64 VDD, about one RC time constant.
+pub struct Config {
+    /// Charges the pad through the internal pull-up resistor. Disable when
+    /// the pad has an external pull-up.
+    pub internal_pull_up: bool,
+    /// Comparator threshold in 1/64 VDD, `1..=64`.
+    pub threshold: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            internal_pull_up: true,
+            threshold: 40,
+        }
+    }
+}
+
+/// Capacitive touch sensor on a single pad.
+pub struct TouchSensor<A, T, P: PinTrait> {
+    acmp: A,
+    timer: Timer<T>,
+    pin: Pin<P, Output>,
+    channel: prs::Channel,
+    internal_pull_up: bool,
+}
+
+impl<A, T, P> TouchSensor<A, T, P>
+where
+    A: Instance,
+    T: timer::Instance,
+    P: PinTrait,
+{
+    /// Sets up the measurement of the pad on `pin`, which the ACMP reaches
+    /// through `input`.
+    ///
+    /// The ACMP output is connected to `channel`, which in turn triggers the
+    /// captures of `timer`. The prescaler of the timer configuration sets the
+    /// resolution of the charge time. The pad is kept discharged in between
+    /// measurements.
+    ///
+    /// Panics when the threshold is out of range.
+    pub fn new(
+        acmp: A,
+        timer: Timer<T>,
+        mut pin: Pin<P, Output>,
+        input: AportChannel,
+        mut channel: prs::Channel,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Self {
+        assert!((1..=64).contains(&config.threshold));
+
+        cmu.enable_clock(&acmp);
+        let _ = crate::hal::digital::v2::OutputPin::set_low(&mut pin);
+
+        // Both hysteresis registers select the same threshold, i.e. no
+        // hysteresis, so the output rises at the same voltage every time.
+        let divva = config.threshold - 1;
+        acmp.hysteresis0.write(|w| unsafe { w.divva().bits(divva) });
+        acmp.hysteresis1.write(|w| unsafe { w.divva().bits(divva) });
+        acmp.inputsel.write(|w| unsafe {
+            w.possel()
+                .bits(input.0)
+                .negsel()
+                .bits(NEGSEL_VADIV)
+                .vasel()
+                .variant(VASEL_A::VDD)
+        });
+        // Full bias for the fastest response, the delay is part of every
+        // reading.
+        acmp.ctrl.write(|w| w.en().set_bit().fullbias().set_bit());
+        while acmp.status.read().acmpact().bit_is_clear() {}
+
+        channel.connect(A::prs_source(), prs::Edge::OFF);
+
+        let regs = timer.regs();
+        regs.top.write(|w| unsafe { w.bits(T::MAX_TOP) });
+        regs.cc0_ctrl.write(|w| unsafe {
+            w.mode()
+                .variant(MODE_A::INPUTCAPTURE)
+                .icedge()
+                .variant(timer::Edge::RISING)
+                .insel()
+                .set_bit()
+                .prssel()
+                .bits(channel.nr())
+        });
+
+        TouchSensor {
+            acmp,
+            timer,
+            pin,
+            channel,
+            internal_pull_up: config.internal_pull_up,
+        }
+    }
+
+    /// Measures the charge time of the pad in timer ticks.
+    ///
+    /// Saturates at `u16::MAX`, e.g. when the pad does not reach the
+    /// threshold.
+    pub fn read(&mut self) -> u16 {
+        let timer = self.timer.regs();
+
+        // Discharge the pad. It is kept low in between measurements, this
+        // only covers back to back reads.
+        timer.cnt.write(|w| unsafe { w.bits(0) });
+        timer.cmd.write(|w| w.start().set_bit());
+        while timer.cnt.read().bits() < DISCHARGE_TICKS {}
+        timer.cmd.write(|w| w.stop().set_bit());
+
+        // Drop stale captures.
+        while timer.status.read().icv0().bit_is_set() {
+            timer.cc0_ccv.read();
+        }
+        timer.ifc.write(|w| w.of().set_bit());
+        timer.cnt.write(|w| unsafe { w.bits(0) });
+
+        // Release the pad and start counting. With DOUT set the disabled pin
+        // pulls up, which also keeps the digital input off.
+        let pin = self.pin.pin_trait_mut();
+        timer.cmd.write(|w| w.start().set_bit());
+        pin.clear_mode();
+        if self.internal_pull_up {
+            pin.set_dout_bit();
+        }
+
+        let ticks = loop {
+            if timer.status.read().icv0().bit_is_set() {
+                break timer.cc0_ccv.read().bits().min(u16::MAX as u32) as u16;
+            }
+            if timer.if_.read().of().bit_is_set() {
+                break u16::MAX;
+            }
+        };
+
+        timer.cmd.write(|w| w.stop().set_bit());
+        pin.clear_dout_bit();
+        pin.set_mode(PinMode::PUSHPULL);
+
+        ticks
+    }
+
+    /// Averages `samples` readings, taken while the pad is not touched, to
+    /// get the baseline for [`TouchSensor::is_touched()`].
+    pub fn calibrate(&mut self, samples: u16) -> u16 {
+        let samples = samples.max(1);
+        let sum: u32 = (0..samples).map(|_| self.read() as u32).sum();
+        (sum / samples as u32) as u16
+    }
+
+    /// Returns `true` when a reading exceeds `baseline` by more than
+    /// `threshold` ticks.
+    pub fn is_touched(&mut self, baseline: u16, threshold: u16) -> bool {
+        self.read() > baseline.saturating_add(threshold)
+    }
+
+    /// Returns the registers of the underlying ACMP without giving up the
+    /// driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// changing the threshold or the input invalidates the baseline.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.acmp
+    }
+
+    /// Returns the underlying ACMP without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`TouchSensor::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut A {
+        &mut self.acmp
+    }
+
+    /// Disables the ACMP and returns the resources.
+    ///
+    /// The timer is in its reset state, the PRS channel is disconnected and
+    /// the pin drives the pad low.
+    pub fn release(mut self) -> (A, Timer<T>, Pin<P, Output>, prs::Channel) {
+        self.acmp.ctrl.reset();
+        self.acmp.inputsel.reset();
+        self.acmp.hysteresis0.reset();
+        self.acmp.hysteresis1.reset();
+        self.channel.disconnect();
+        self.timer.reset();
+        (self.acmp, self.timer, self.pin, self.channel)
+    }
+}
+
+/// Internal trait used to implement the ACMP API for PAC ACMP instances.
+pub trait Instance: ClockControlExt + Deref<Target = RegisterBlock> {
+    #[doc(hidden)]
+    fn prs_source() -> PrsSource;
+}
+
+impl Instance for ACMP0 {
+    fn prs_source() -> PrsSource {
+        PrsSource::new(prs::Source::ACMP0, 0)
+    }
+}
+
+impl Instance for ACMP1 {
+    fn prs_source() -> PrsSource {
+        PrsSource::new(prs::Source::ACMP1, 0)
+    }
+}
@@ -0,0 +1,461 @@
+//! Analog to digital converter (ADC) API
+//!
+//! Conversions use the factory calibration of the selected reference stored
+//! in the DEVINFO page. With the internal bandgap references the result can
+//! be converted to an absolute voltage with [`Adc::read_millivolts()`].
+//!
+//! Analog pins are inputs of the ADC in the [`Disabled`] mode, which turns
+//! off their digital input buffer. The pins reach the ADC through the
+//! analog port (APORT): ports C and F through APORT1, ports A, B and D
+//! through APORT3. [`Adc`] implements the embedded-hal `OneShot` trait for
+//! these pins and the internal [`Temperature`] and [`AVdd`] channels, with
+//! the reference of [`Config::reference`]:
+//!
+//! ```ignore
+//! let mut pin = gpio.pc6.disabled();
+//! let raw: u16 = block!(adc.read(&mut pin)).unwrap();
+//! ```
+//!
+//! Timestamped scans triggered through the PRS are provided by the
+#![cfg_attr(feature = "heapless", doc = "[`logging`]")]
+#![cfg_attr(not(feature = "heapless"), doc = "`logging`")]
+//! module with the `heapless` feature.
+pub use crate::pac::adc0::{
+    biasprog::ADCBIASPROG_A as Bias,
+    ctrl::{OVSRSEL_A as Oversampling, WARMUPMODE_A as WarmupMode},
+    singlectrl::REF_A as Reference,
+};
+
+use crate::{
+    cmu::Cmu,
+    devinfo,
+    gpio::*,
+    hal::adc::{self, OneShot},
+    pac::{adc0::singlectrl::AT_A, adc0::singlectrl::RES_A, ADC0},
+};
+use core::convert::Infallible;
+
+#[cfg(feature = "heapless")]
+pub mod logging;
+
+/// Implemented by types that can be selected as positive ADC input.
+pub trait Channel {
+    /// Value of the `SINGLECTRL.POSSEL` field.
+    const POSSEL: u8;
+}
+
+macro_rules! impl_channels {
+    ($($CHANNEL:ty: $possel:expr,)*) => {
+        $(
+            impl Channel for $CHANNEL {
+                const POSSEL: u8 = $possel;
+            }
+
+            impl adc::Channel<Adc> for $CHANNEL {
+                type ID = u8;
+
+                fn channel() -> u8 {
+                    $possel
+                }
+            }
+        )*
+    }
+}
+
+/// Internal temperature sensor.
+pub struct Temperature;
+
+/// Analog supply voltage (AVDD).
+pub struct AVdd;
+
+impl_channels!(
+    Temperature: 0xF3,
+    AVdd: 0xE0,
+);
+
+// A pin connects to the X bus of its APORT with even and to the Y bus with
+// odd channel numbers, `POSSEL` selects either bus with `0x20 * n + channel`.
+impl_channels!(
+    Pin<PC0, Disabled>: 0x20,
+    Pin<PC1, Disabled>: 0x21,
+    Pin<PC2, Disabled>: 0x22,
+    Pin<PC3, Disabled>: 0x23,
+    Pin<PC4, Disabled>: 0x24,
+    Pin<PC5, Disabled>: 0x25,
+    Pin<PC6, Disabled>: 0x26,
+    Pin<PC7, Disabled>: 0x27,
+    Pin<PC8, Disabled>: 0x28,
+    Pin<PC9, Disabled>: 0x29,
+    Pin<PC10, Disabled>: 0x2A,
+    Pin<PC11, Disabled>: 0x2B,
+    // Overwriting debug pins is not supported
+    // Pin<PF0, Disabled>: 0x30,
+    // Pin<PF1, Disabled>: 0x31,
+    // Pin<PF2, Disabled>: 0x32,
+    // Pin<PF3, Disabled>: 0x33,
+    Pin<PF4, Disabled>: 0x34,
+    Pin<PF5, Disabled>: 0x35,
+    Pin<PF6, Disabled>: 0x36,
+    Pin<PF7, Disabled>: 0x37,
+    Pin<PF8, Disabled>: 0x38,
+    Pin<PF9, Disabled>: 0x39,
+    Pin<PF10, Disabled>: 0x3A,
+    Pin<PF11, Disabled>: 0x3B,
+    Pin<PF12, Disabled>: 0x3C,
+    Pin<PF13, Disabled>: 0x3D,
+    Pin<PF14, Disabled>: 0x3E,
+    Pin<PF15, Disabled>: 0x3F,
+    Pin<PD8, Disabled>: 0x60,
+    Pin<PD9, Disabled>: 0x61,
+    Pin<PD10, Disabled>: 0x62,
+    Pin<PD11, Disabled>: 0x63,
+    Pin<PD12, Disabled>: 0x64,
+    Pin<PD13, Disabled>: 0x65,
+    Pin<PD14, Disabled>: 0x66,
+    Pin<PD15, Disabled>: 0x67,
+    Pin<PA0, Disabled>: 0x68,
+    Pin<PA1, Disabled>: 0x69,
+    Pin<PA2, Disabled>: 0x6A,
+    Pin<PA3, Disabled>: 0x6B,
+    Pin<PA4, Disabled>: 0x6C,
+    Pin<PA5, Disabled>: 0x6D,
+    Pin<PA6, Disabled>: 0x6E,
+    Pin<PA7, Disabled>: 0x6F,
+    Pin<PA8, Disabled>: 0x70,
+    Pin<PA9, Disabled>: 0x71,
+    Pin<PB6, Disabled>: 0x76,
+    Pin<PB7, Disabled>: 0x77,
+    Pin<PB8, Disabled>: 0x78,
+    Pin<PB9, Disabled>: 0x79,
+    Pin<PB10, Disabled>: 0x7A,
+    Pin<PB11, Disabled>: 0x7B,
+    Pin<PB12, Disabled>: 0x7C,
+    Pin<PB13, Disabled>: 0x7D,
+    Pin<PB14, Disabled>: 0x7E,
+    Pin<PB15, Disabled>: 0x7F,
+);
+
+// `SINGLECTRL.NEGSEL` value for single ended conversions.
+const NEGSEL_VSS: u8 = 0xFF;
+
+/// Returns the full scale voltage of `reference` in millivolts.
+///
+/// The internal bandgap references are trimmed by the factory calibration
+/// which [`Adc`] loads for every conversion, so the nominal value is exact
+/// within the specified accuracy. Returns `None` for references that depend
+/// on the supply voltage or an external reference voltage.
+pub fn reference_millivolts(reference: Reference) -> Option<u32> {
+    match reference {
+        Reference::_1V25 => Some(1250),
+        Reference::_2V5 => Some(2500),
+        Reference::_5V => Some(5000),
+        _ => None,
+    }
+}
+
+// Returns the single conversion calibration (offset, inverted offset and
+// gain) for `reference`, laid out like the lower half of the `CAL` register.
+fn calibration(reference: Reference) -> Option<u32> {
+    let word = match reference {
+        Reference::_1V25 => devinfo::adc0_cal(0),
+        Reference::_2V5 => devinfo::adc0_cal(0) >> 16,
+        Reference::VDD => devinfo::adc0_cal(1),
+        Reference::_5V => devinfo::adc0_cal(1) >> 16,
+        // Only offset calibration is available for 2xAVDD.
+        Reference::_2XVDD => return Some(devinfo::adc0_cal(2) & 0xFF),
+        _ => return None,
+    };
+    Some(word & 0x7FFF)
+}
+
+/// ADC configuration.
+///
+/// Defaults to 12-bit conversions against the internal 2.5 V reference
+/// without oversampling, full bias current and shutting the ADC down after
+/// each conversion.
+pub struct Config {
+    /// Reference of the conversions through the `OneShot` trait.
+    /// [`Adc::read_raw()`] takes the reference of each conversion instead.
+    pub reference: Reference,
+    /// Accumulates multiple samples per conversion result.
+    ///
+    /// Averaging `N` samples reduces white noise by `sqrt(N)`, i.e. the
+    /// effective resolution increases by half a bit per doubling of the
+    /// oversampling ratio while the conversion time increases by the factor
+    /// `N`. The result register holds at most 16 bits:
+    ///
+    /// | Ratio  | Result bits | Effective resolution (white noise) |
+    /// |--------|-------------|------------------------------------|
+    /// | 2x     | 13          | 12.5 bits                          |
+    /// | 4x     | 14          | 13 bits                            |
+    /// | 8x     | 15          | 13.5 bits                          |
+    /// | 16x    | 16          | 14 bits                            |
+    /// | 64x    | 16          | 15 bits                            |
+    /// | 256x   | 16          | 16 bits                            |
+    /// | 4096x  | 16          | 16 bits                            |
+    ///
+    /// Ratios above 256x do not add resolution but filter more noise.
+    pub oversampling: Option<Oversampling>,
+    /// Scales down the bias current of the ADC.
+    ///
+    /// The ADC is specified for its rated accuracy up to an ADC clock of
+    /// 16 MHz, i.e. 1 Msps, with [`Bias::NORMAL`]. Scaling the bias current
+    /// down by `N` lowers the maximum ADC clock and sample rate by the same
+    /// factor. The ADC clock prescaler is chosen accordingly, so the accuracy
+    /// is kept at the cost of a longer conversion time:
+    ///
+    /// | Bias      | Maximum ADC clock | Maximum sample rate |
+    /// |-----------|------------------:|--------------------:|
+    /// | `NORMAL`  |           16 MHz  |             1 Msps  |
+    /// | `SCALE2`  |            8 MHz  |           500 ksps  |
+    /// | `SCALE4`  |            4 MHz  |           250 ksps  |
+    /// | `SCALE8`  |            2 MHz  |           125 ksps  |
+    /// | `SCALE16` |            1 MHz  |          62.5 ksps  |
+    /// | `SCALE32` |          500 kHz  |         31.25 ksps  |
+    ///
+    /// The bias current flows while the ADC is warm, so a lower bias saves
+    /// energy mainly with [`WarmupMode::KEEPADCWARM`] or frequent
+    /// conversions. For occasional reads the shorter warm-up and conversion
+    /// of the normal bias need less energy per sample.
+    pub bias: Bias,
+    /// State of the ADC in between conversions.
+    ///
+    /// Trades the warm-up time before each conversion against the current
+    /// drawn while idle:
+    ///
+    /// - `NORMAL`: shut down, 5 µs warm-up. Lowest energy for infrequent
+    ///   conversions, e.g. a few per second from EM2.
+    /// - `KEEPINSTANDBY`: standby, 1 µs warm-up.
+    /// - `KEEPINSLOWACC`: slow acquisition mode, 1 µs warm-up.
+    /// - `KEEPADCWARM`: stays on, no warm-up. Only worth it for continuous
+    ///   conversions, the full bias current flows all the time.
+    pub warmup: WarmupMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            reference: Reference::_2V5,
+            oversampling: None,
+            bias: Bias::NORMAL,
+            warmup: WarmupMode::NORMAL,
+        }
+    }
+}
+
+// Returns the factor by which `bias` scales down the bias current.
+fn bias_scale(bias: Bias) -> u32 {
+    match bias {
+        Bias::NORMAL => 1,
+        Bias::SCALE2 => 2,
+        Bias::SCALE4 => 4,
+        Bias::SCALE8 => 8,
+        Bias::SCALE16 => 16,
+        Bias::SCALE32 => 32,
+    }
+}
+
+/// Returns the width of the conversion result in bits.
+///
+/// The hardware sums up `N` 12-bit samples and shifts the sum right to fit
+/// into 16 bits, so ratios of 16x and above all return 16-bit results.
+pub fn result_bits(oversampling: Option<Oversampling>) -> u32 {
+    // `OVSRSEL` only matters for `RES = OVS`, its reset value stands in
+    // without oversampling.
+    resolution_bits(
+        resolution(oversampling),
+        oversampling.unwrap_or(Oversampling::X2),
+    )
+}
+
+// Returns the `SINGLECTRL.RES` value for the oversampling setting.
+fn resolution(oversampling: Option<Oversampling>) -> RES_A {
+    match oversampling {
+        None => RES_A::_12BIT,
+        Some(_) => RES_A::OVS,
+    }
+}
+
+// Returns the width of `SINGLEDATA` in bits for the `SINGLECTRL.RES` and
+// `CTRL.OVSRSEL` values.
+fn resolution_bits(res: RES_A, ovs: Oversampling) -> u32 {
+    match res {
+        RES_A::_12BIT => 12,
+        RES_A::_8BIT => 8,
+        RES_A::_6BIT => 6,
+        // log2(N) = OVSRSEL + 1
+        RES_A::OVS => 12 + (u8::from(ovs) as u32 + 1).min(4),
+    }
+}
+
+/// ADC API
+pub struct Adc {
+    raw: ADC0,
+    oversampling: Option<Oversampling>,
+    reference: Reference,
+}
+
+impl Adc {
+    /// Creates the HAL instance and configures the ADC clock.
+    pub fn new(adc: ADC0, config: &Config, cmu: &mut Cmu) -> Adc {
+        cmu.enable_clock(&adc);
+
+        let hfperclk = cmu.hfperclk().0;
+
+        // The ADC clock must not exceed 16 MHz, scaled down with the bias
+        // current. The timebase must cover at least 1 µs for the warm-up.
+        let max_adc_clk = 16_000_000 / bias_scale(config.bias);
+        let presc = (hfperclk.div_ceil(max_adc_clk) - 1) as u8;
+        let timebase = (hfperclk.div_ceil(1_000_000) - 1) as u8;
+        adc.biasprog
+            .modify(|_, w| w.adcbiasprog().variant(config.bias));
+        adc.ctrl.modify(|_, w| unsafe {
+            w.presc()
+                .bits(presc)
+                .timebase()
+                .bits(timebase)
+                .warmupmode()
+                .variant(config.warmup)
+        });
+        debug!("ADC: PRESC {=u8}, TIMEBASE {=u8}", presc, timebase);
+
+        if let Some(ovs) = config.oversampling {
+            adc.ctrl.modify(|_, w| w.ovsrsel().variant(ovs));
+        }
+
+        Adc {
+            raw: adc,
+            oversampling: config.oversampling,
+            reference: config.reference,
+        }
+    }
+
+    /// Performs a single ended conversion of `C` and returns the raw result.
+    ///
+    /// The result has the width returned by [`Adc::result_bits()`].
+    pub fn read_raw<C: Channel>(&mut self, _input: &mut C, reference: Reference) -> u16 {
+        self.convert(C::POSSEL, reference)
+    }
+
+    fn convert(&mut self, possel: u8, reference: Reference) -> u16 {
+        if let Some(cal) = calibration(reference) {
+            // Keep the scan calibration in the upper half.
+            self.raw
+                .cal
+                .modify(|r, w| unsafe { w.bits((r.bits() & !0x7FFF) | (cal & 0x7FFF)) });
+        }
+
+        self.raw.singlectrl.write(|w| unsafe {
+            w.ref_()
+                .variant(reference)
+                .possel()
+                .bits(possel)
+                .negsel()
+                .bits(NEGSEL_VSS)
+                .res()
+                .variant(resolution(self.oversampling))
+                .at()
+                .variant(AT_A::_16CYCLES)
+        });
+
+        self.raw.cmd.write(|w| w.singlestart().set_bit());
+        while self.raw.status.read().singledv().bit_is_clear() {}
+        let data = self.raw.singledata.read().data().bits() as u16;
+        trace!("ADC: POSSEL {=u8:#04x} -> {=u16}", possel, data);
+        data
+    }
+
+    /// Performs a conversion of `C` and returns the input voltage in
+    /// millivolts.
+    ///
+    /// Returns `None` for references without a known full scale voltage, see
+    /// [`reference_millivolts()`].
+    pub fn read_millivolts<C: Channel>(
+        &mut self,
+        input: &mut C,
+        reference: Reference,
+    ) -> Option<u32> {
+        let full_scale = reference_millivolts(reference)?;
+        let raw = self.read_raw(input, reference) as u32;
+        Some((raw * full_scale) >> self.result_bits())
+    }
+
+    /// Width of the conversion results in bits.
+    pub fn result_bits(&self) -> u32 {
+        result_bits(self.oversampling)
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// the cached oversampling setting used to scale the conversion results.
+    pub unsafe fn raw(&self) -> &crate::pac::adc0::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Adc::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut ADC0 {
+        &mut self.raw
+    }
+
+    /// Return the raw interface to the underlying peripheral.
+    pub fn release(self) -> ADC0 {
+        self.raw
+    }
+}
+
+/// Single ended conversion with the reference of [`Config::reference`]. The
+/// raw result has the width returned by [`Adc::result_bits()`].
+impl<C> OneShot<Adc, u16, C> for Adc
+where
+    C: adc::Channel<Adc, ID = u8>,
+{
+    type Error = Infallible;
+
+    fn read(&mut self, _pin: &mut C) -> nb::Result<u16, Self::Error> {
+        Ok(self.convert(C::channel(), self.reference))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_millivolts_of_all_references() {
+        assert_eq!(reference_millivolts(Reference::_1V25), Some(1250));
+        assert_eq!(reference_millivolts(Reference::_2V5), Some(2500));
+        assert_eq!(reference_millivolts(Reference::VDD), None);
+        assert_eq!(reference_millivolts(Reference::_5V), Some(5000));
+        assert_eq!(reference_millivolts(Reference::EXTSINGLE), None);
+        assert_eq!(reference_millivolts(Reference::_2XEXTDIFF), None);
+        assert_eq!(reference_millivolts(Reference::_2XVDD), None);
+        assert_eq!(reference_millivolts(Reference::CONF), None);
+    }
+
+    #[test]
+    fn resolution_bits_of_all_resolutions() {
+        assert_eq!(resolution_bits(RES_A::_12BIT, Oversampling::X2), 12);
+        assert_eq!(resolution_bits(RES_A::_8BIT, Oversampling::X2), 8);
+        assert_eq!(resolution_bits(RES_A::_6BIT, Oversampling::X2), 6);
+        assert_eq!(resolution_bits(RES_A::OVS, Oversampling::X2), 13);
+    }
+
+    #[test]
+    fn result_bits_with_oversampling() {
+        assert_eq!(result_bits(None), 12);
+        assert_eq!(result_bits(Some(Oversampling::X2)), 13);
+        assert_eq!(result_bits(Some(Oversampling::X4)), 14);
+        assert_eq!(result_bits(Some(Oversampling::X8)), 15);
+        assert_eq!(result_bits(Some(Oversampling::X16)), 16);
+        assert_eq!(result_bits(Some(Oversampling::X256)), 16);
+        assert_eq!(result_bits(Some(Oversampling::X4096)), 16);
+    }
+}
@@ -0,0 +1,306 @@
+//! Timestamped multi-channel acquisition for data loggers
+//!
+//! [`LoggingAdc`] runs an ADC scan over up to four inputs on every pulse of a
+//! PRS channel, e.g. from a button or a comparator. The same pulse captures
+//! the RTCC counter, so each [`Record`] carries the time at which the scan was
+//! triggered, independent of the conversion time and the interrupt latency.
+//! The records are collected in a ring buffer of size `N` from the `ADC0`
+//! interrupt handler, call [`LoggingAdc::on_interrupt()`] there and unmask
+//! [`LoggingAdc::interrupt()`]. A ring buffer of size `N` holds at most
+//! `N - 1` records.
+//!
+//! The records are moved by the CPU rather than the LDMA: a record combines
+//! the scan FIFO and the RTCC capture register, which would need a linked list
+//! of descriptors, and the [`ldma`](crate::ldma) driver only programs single
+//! transfers. The handler runs once per scan and reads at most five words.
+//!
+//! # Timestamps
+//!
+//! Timestamps are raw RTCC counter values. The RTCC counts LFECLK divided by
+//! [`Config::prescaler`], see [`LoggingAdc::timestamp_frequency()`]. With a
+//! 32768 Hz LFXO:
+//!
+//! | Prescaler  | Resolution | Wrap around |
+//! |------------|-----------:|------------:|
+//! | `DIV1`     |   30.5 µs  |     36.4 h  |
+//! | `DIV32`    |  976.6 µs  |     48.5 d  |
+//! | `DIV1024`  |   31.3 ms  |      4.3 a  |
+//! | `DIV32768` |      1 s   |      136 a  |
+//!
+//! The duration between two records is
+//! `later.timestamp.wrapping_sub(earlier.timestamp)`.
+//!
+//! # Record format
+//!
+//! [`Record::samples`] holds one raw conversion result per input, in the
+//! order of the inputs passed to [`LoggingAdc::new()`], with the width
+//! returned by [`Adc::result_bits()`]. The ADC itself stores the results in
+//! the order of its internal scan input numbers, the driver sorts them back
+//! by their input ID.
+//!
+//! The ADC runs from HFPERCLK, so scans are only triggered in EM0 and EM1.
+//! The RTCC keeps counting in EM2.
+pub use crate::{acmp::AportChannel, pac::rtcc::ctrl::CNTPRESC_A as Prescaler};
+
+use super::{calibration, Adc, Reference};
+use crate::{
+    cmu::{Clocks, Cmu},
+    pac::{
+        adc0::scanctrl::{AT_A, RES_A},
+        rtcc::cc0_ctrl::{ICEDGE_A, MODE_A},
+        Interrupt, RTCC,
+    },
+    prs,
+    time::Hertz,
+};
+use heapless::spsc::Queue;
+
+// Depth of the scan FIFO, which holds the results of one complete scan.
+const MAX_INPUTS: usize = 4;
+
+/// Results of a single scan.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Record<const C: usize> {
+    /// RTCC counter value at the trigger pulse.
+    pub timestamp: u32,
+    /// Raw conversion results in the order of the inputs.
+    pub samples: [u16; C],
+}
+
+/// Logging configuration.
+///
+/// Defaults to the internal 2.5 V reference and undivided RTCC ticks.
+pub struct Config {
+    /// Reference of all scanned inputs.
+    pub reference: Reference,
+    /// Divider of LFECLK for the timestamps.
+    pub prescaler: Prescaler,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            reference: Reference::_2V5,
+            prescaler: Prescaler::DIV1,
+        }
+    }
+}
+
+/// Scans `C` inputs on every trigger and buffers up to `N - 1` records.
+pub struct LoggingAdc<const C: usize, const N: usize> {
+    adc: Adc,
+    rtcc: RTCC,
+    trigger: prs::Channel,
+    // Position in `Record::samples` of each scan input ID.
+    slots: [u8; 32],
+    queue: Queue<Record<C>, N>,
+    dropped: u32,
+}
+
+impl<const C: usize, const N: usize> LoggingAdc<C, N> {
+    /// Sets up scans of `inputs` and timestamps on each pulse of `trigger`.
+    ///
+    /// The producer must be connected to `trigger` by the application, with
+    /// an edge setting that creates pulses. The RTCC is started from zero and
+    /// its other compare channels stay unused.
+    ///
+    /// Panics when no LF clock source is selected, when `C` is not in `1..=4`
+    /// or when the inputs span more than four groups of eight APORT channels.
+    pub fn new(
+        adc: Adc,
+        rtcc: RTCC,
+        inputs: [AportChannel; C],
+        trigger: prs::Channel,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Self {
+        assert!(C >= 1 && C <= MAX_INPUTS);
+        assert!(cmu.lfeclk().0 != 0, "no LF clock source selected");
+
+        // Each group of eight scan inputs selects one group of eight APORT
+        // channels, the lower bits of the channel select the input within.
+        let mut groups: [Option<u8>; 4] = [None; 4];
+        let mut mask = 0u32;
+        let mut slots = [0u8; 32];
+        for (position, input) in inputs.iter().enumerate() {
+            let group = match input.bus() {
+                0 => input.channel() / 8,
+                bus => bus * 4 + input.channel() / 8,
+            };
+            let index = match groups.iter().position(|&g| g == Some(group)) {
+                Some(index) => index,
+                None => {
+                    let index = groups
+                        .iter()
+                        .position(Option::is_none)
+                        .expect("too many APORT channel groups");
+                    groups[index] = Some(group);
+                    index
+                }
+            };
+            let id = index * 8 + (input.channel() % 8) as usize;
+            mask |= 1 << id;
+            slots[id] = position as u8;
+        }
+
+        let raw = &adc.raw;
+        if let Some(cal) = calibration(config.reference) {
+            // The scan calibration is the upper half of the register.
+            raw.cal
+                .modify(|r, w| unsafe { w.bits((r.bits() & 0x7FFF) | ((cal & 0x7FFF) << 16)) });
+        }
+        raw.scaninputsel.write(|w| unsafe {
+            w.input0to7sel()
+                .bits(groups[0].unwrap_or(0))
+                .input8to15sel()
+                .bits(groups[1].unwrap_or(0))
+                .input16to23sel()
+                .bits(groups[2].unwrap_or(0))
+                .input24to31sel()
+                .bits(groups[3].unwrap_or(0))
+        });
+        raw.scanmask
+            .write(|w| unsafe { w.scaninputen().bits(mask) });
+        raw.scanctrl.write(|w| {
+            w.ref_()
+                .bits(u8::from(config.reference))
+                .res()
+                .variant(match adc.oversampling {
+                    None => RES_A::_12BIT,
+                    Some(_) => RES_A::OVS,
+                })
+                .at()
+                .variant(AT_A::_16CYCLES)
+                .prsen()
+                .set_bit()
+        });
+        // SCANDV is set once all results of a scan are in the FIFO.
+        raw.scanctrlx.modify(|_, w| unsafe {
+            w.prssel()
+                .bits(trigger.nr())
+                .dvl()
+                .bits(C as u8 - 1)
+                .fifoofact()
+                .clear_bit()
+        });
+        raw.scanfifoclear.write(|w| w.scanfifoclear().set_bit());
+        raw.ifc.write(|w| w.scanof().set_bit());
+        raw.ien.modify(|_, w| w.scan().set_bit().scanof().set_bit());
+
+        cmu.enable_clock(&rtcc);
+        rtcc.ctrl.reset();
+        rtcc.cnt.reset();
+        rtcc.cc0_ctrl.write(|w| unsafe {
+            w.mode()
+                .variant(MODE_A::INPUTCAPTURE)
+                .icedge()
+                .variant(ICEDGE_A::RISING)
+                .prssel()
+                .bits(trigger.nr())
+        });
+        rtcc.ctrl
+            .write(|w| w.cntpresc().variant(config.prescaler).enable().set_bit());
+
+        LoggingAdc {
+            adc,
+            rtcc,
+            trigger,
+            slots,
+            queue: Queue::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Returns the NVIC line whose handler must call
+    /// [`LoggingAdc::on_interrupt()`]. The application unmasks it.
+    pub fn interrupt(&self) -> Interrupt {
+        Interrupt::ADC0
+    }
+
+    /// Moves the results of a completed scan together with its timestamp to
+    /// the ring buffer.
+    ///
+    /// Must be called from the `ADC0` interrupt handler. Records that do not
+    /// fit into the ring buffer are dropped and counted, see
+    /// [`LoggingAdc::dropped()`].
+    pub fn on_interrupt(&mut self) {
+        let raw = &self.adc.raw;
+        if raw.if_.read().scanof().bit_is_set() {
+            // A scan finished before the previous one was read, the FIFO no
+            // longer holds complete scans.
+            raw.scanfifoclear.write(|w| w.scanfifoclear().set_bit());
+            raw.ifc.write(|w| w.scanof().set_bit());
+            self.dropped += 1;
+            warn!("ADC: scan FIFO overflow");
+        }
+
+        // Reading the results also clears the `SCAN` flag.
+        while raw.status.read().scandv().bit_is_set() {
+            let mut samples = [0; C];
+            for _ in 0..C {
+                let data = raw.scandatax.read();
+                let slot = self.slots[data.scaninputid().bits() as usize];
+                samples[slot as usize] = data.data().bits();
+            }
+            let record = Record {
+                timestamp: self.rtcc.cc0_ccv.read().bits(),
+                samples,
+            };
+            if self.queue.enqueue(record).is_err() {
+                self.dropped += 1;
+            }
+        }
+    }
+
+    /// Returns the oldest buffered record.
+    pub fn read(&mut self) -> Option<Record<C>> {
+        self.queue.dequeue()
+    }
+
+    /// Number of buffered records.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns `true` when no record is buffered.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Returns the number of records lost since the last call because the
+    /// ring buffer was full or the scan FIFO overflowed, and resets the
+    /// count.
+    pub fn dropped(&mut self) -> u32 {
+        core::mem::take(&mut self.dropped)
+    }
+
+    /// Frequency of the timestamp counter.
+    pub fn timestamp_frequency(&self, clocks: &Clocks) -> Hertz {
+        let div = 1 << u8::from(self.rtcc.ctrl.read().cntpresc().variant());
+        Hertz(clocks.lfeclk().0 / div)
+    }
+
+    /// Returns the width of the conversion results in bits.
+    pub fn result_bits(&self) -> u32 {
+        self.adc.result_bits()
+    }
+
+    /// Stops triggering scans and returns the resources.
+    ///
+    /// The RTCC is disabled, the ADC keeps its single conversion
+    /// configuration and the trigger stays connected to its producer.
+    pub fn release(self) -> (Adc, RTCC, prs::Channel) {
+        let raw = &self.adc.raw;
+        raw.ien
+            .modify(|_, w| w.scan().clear_bit().scanof().clear_bit());
+        raw.scanctrl.reset();
+        raw.scanctrlx.reset();
+        raw.scanmask.reset();
+        raw.cmd.write(|w| w.scanstop().set_bit());
+        raw.scanfifoclear.write(|w| w.scanfifoclear().set_bit());
+        raw.ifc.write(|w| w.scanof().set_bit());
+        self.rtcc.ctrl.reset();
+        self.rtcc.cc0_ctrl.reset();
+        (self.adc, self.rtcc, self.trigger)
+    }
+}
@@ -1,35 +1,508 @@
 //! Clock Managened Unit (CMU) API
-use crate::pac::*;
+pub use crate::pac::cmu::{
+    hfclkstatus::SELECTED_A as HfClkSource, lfaclksel::LFA_A as LfClkSource,
+    lfbclksel::LFB_A as LfbClkSource,
+};
+use crate::{
+    devinfo,
+    pac::{
+        cmu::{
+            calctrl::{DOWNSEL_A, UPSEL_A},
+            hfclksel::HF_AW,
+            hfpresc::HFCLKLEPRESC_A,
+            lfapresc0::LETIMER0_A as LetimerPrescaler,
+        },
+        generic::Variant,
+        msc::readctrl::MODE_A,
+        *,
+    },
+    time::Hertz,
+};
+use embedded_error::ImplError;
 
+// Highest HFCLK frequency at which the flash is read without wait states.
+const MAX_FREQ_0WS: u32 = 25_000_000;
+
+// Highest frequency of the clock to the low energy peripheral interface.
+const MAX_FREQ_HFLE: u32 = 32_000_000;
+
+// `HFRCOCTRL.CLKDIV` field and its divide by 4 value.
+const HFRCOCTRL_CLKDIV_MASK: u32 = 0x3 << 25;
+const HFRCOCTRL_CLKDIV_DIV4: u32 = 0x2 << 25;
+
+/// CMU API
+///
+/// Lifecycle: `CMU` -> [`CmuExt::constrain()`] (or [`CmuExt::freeze()`] or
+/// [`Cmu::new()`]) -> [`Cmu`] -> [`Cmu::free()`] -> `CMU`.
+///
+/// The clock frequencies are computed when the configuration changes through
+/// this API and cached. Writing to the CMU registers directly, e.g. through
+/// the raw peripheral, does not update the cached values.
 pub struct Cmu {
     raw: CMU,
-    hfclk: u32,
+    hfclk: Hertz,
+    // Source last selected through this API.
+    hfclk_requested: HfClkSource,
+    // Frequency of the HFRCO, also while another source drives HFCLK.
+    hfrco: Hertz,
+    hfcoreclk: Hertz,
+    hfperclk: Hertz,
+    lfaclk: Hertz,
+    lfbclk: Hertz,
+    lfeclk: Hertz,
 }
 
 impl Cmu {
     /// Creates the HAL instance for the clock management unit.
     pub fn new(cmu: CMU) -> Cmu {
-        Cmu {
+        let mut cmu = Cmu {
             raw: cmu,
-            hfclk: 19_000_000,
+            hfclk: Hertz(19_000_000),
+            hfclk_requested: HfClkSource::HFRCO,
+            hfrco: Hertz(19_000_000),
+            hfcoreclk: Hertz(0),
+            hfperclk: Hertz(0),
+            lfaclk: Hertz(0),
+            lfbclk: Hertz(0),
+            lfeclk: Hertz(0),
+        };
+        cmu.update_frequencies();
+        debug!(
+            "CMU: HFCLK source {=u8}, assuming {=u32} Hz",
+            cmu.raw.hfclkstatus.read().selected().bits(),
+            cmu.hfclk.0
+        );
+        cmu
+    }
+
+    // Recomputes the cached frequencies of the derived clocks. Must be called
+    // after every change of the clock source or a prescaler.
+    fn update_frequencies(&mut self) {
+        let hfcorepresc = self.raw.hfcorepresc.read().presc().bits() as u32;
+        let hfperpresc = self.raw.hfperpresc.read().presc().bits() as u32;
+        self.hfcoreclk = Hertz(self.hfclk.0 / (hfcorepresc + 1));
+        self.hfperclk = Hertz(self.hfclk.0 / (hfperpresc + 1));
+    }
+
+    /// Returns the clock source currently selected for HFCLK.
+    ///
+    /// Reads the selection back from the hardware. A mismatch with
+    /// [`Cmu::requested_hfclk_source()`] indicates that the clock switch did
+    /// not take effect or that the CMU was reconfigured behind the back of
+    /// this HAL. Returns `None` when the register holds a reserved value.
+    pub fn hfclk_source(&self) -> Option<HfClkSource> {
+        match self.raw.hfclkstatus.read().selected().variant() {
+            Variant::Val(source) => Some(source),
+            Variant::Res(_) => None,
         }
     }
 
+    /// Returns the clock source last selected for HFCLK through this HAL,
+    /// HFRCO after [`Cmu::new()`].
+    ///
+    /// The frequencies returned by the HAL are computed for this source.
+    pub fn requested_hfclk_source(&self) -> HfClkSource {
+        self.hfclk_requested
+    }
+
+    /// Frequency of the high frequency clock (HFCLK).
+    ///
+    /// This is the frequency assumed by the HAL for the requested clock
+    /// configuration and not a measured value.
+    pub fn hfclk(&self) -> Hertz {
+        self.hfclk
+    }
+
     /// This clock drives the Core Modules, which consists of the CPU and modules
     /// that are tightly coupled to the CPU, e.g. the cache.
-    pub fn hfcoreclk(&self) -> u32 {
-        self.hfclk / (self.raw.hfcorepresc.read().bits() + 1)
+    pub fn hfcoreclk(&self) -> Hertz {
+        self.hfcoreclk
     }
 
     /// This clock drives the Bus and Memory System. It is also used to drive the
     /// bus interface to the Low Energy Peripherals.
-    pub fn hfbusclk(&self) -> u32 {
+    pub fn hfbusclk(&self) -> Hertz {
         self.hfclk
     }
 
     /// This clock drives the High-Frequency Peripherals.
-    pub fn hfperclk(&self) -> u32 {
-        self.hfclk / (self.raw.hfperpresc.read().bits() + 1)
+    pub fn hfperclk(&self) -> Hertz {
+        self.hfperclk
+    }
+
+    /// Divides HFCLK by `div` (1 to 512) to derive HFCORECLK.
+    ///
+    /// Peripherals configured before the change keep their old dividers and
+    /// must be reconfigured.
+    pub fn set_hfcoreclk_divider(&mut self, div: u16) {
+        assert!((1..=512).contains(&div));
+        self.raw
+            .hfcorepresc
+            .write(|w| unsafe { w.presc().bits(div - 1) });
+        self.update_frequencies();
+    }
+
+    /// Divides HFCLK by `div` (1 to 512) to derive HFPERCLK.
+    ///
+    /// Peripherals configured before the change keep their old dividers and
+    /// must be reconfigured.
+    pub fn set_hfperclk_divider(&mut self, div: u16) {
+        assert!((1..=512).contains(&div));
+        self.raw
+            .hfperpresc
+            .write(|w| unsafe { w.presc().bits(div - 1) });
+        self.update_frequencies();
+    }
+
+    /// Measures the HFRCO frequency with the calibration counters of the CMU,
+    /// using the LFXO as reference.
+    ///
+    /// Counts HFRCO cycles during 256 LFXO cycles, i.e. for 7.8 ms. The
+    /// resolution is one HFRCO cycle in that time (about 7 ppm at 19 MHz),
+    /// the accuracy is otherwise that of the crystal, typically a few 10 ppm.
+    ///
+    /// When the HFRCO drives HFCLK, the measured value replaces the assumed
+    /// HFCLK frequency and the derived frequencies are updated. Peripherals
+    /// configured before must be reconfigured to benefit from the more
+    /// accurate value, e.g. to correct USART baud rates.
+    ///
+    /// Returns `Err(ImplError::InvalidConfiguration)` when the LFXO is not
+    /// running, see [`Cmu::use_lfxo()`], and `Err(ImplError::Internal)` when
+    /// the counter overflowed, which requires an HFRCO above 130 MHz.
+    pub fn measure_hfrco_against_lfxo(&mut self) -> Result<Hertz, ImplError> {
+        const REFERENCE_CYCLES: u32 = 256;
+
+        if self.raw.status.read().lfxordy().bit_is_clear() {
+            return Err(ImplError::InvalidConfiguration);
+        }
+
+        // The down counter runs for `CALCNT + 1` reference cycles, the up
+        // counter counts the HFRCO cycles meanwhile.
+        self.raw.calctrl.write(|w| {
+            w.upsel()
+                .variant(UPSEL_A::HFRCO)
+                .downsel()
+                .variant(DOWNSEL_A::LFXO)
+        });
+        self.raw
+            .calcnt
+            .write(|w| unsafe { w.calcnt().bits(REFERENCE_CYCLES - 1) });
+        self.raw
+            .ifc
+            .write(|w| w.calrdy().set_bit().calof().set_bit());
+        self.raw.cmd.write(|w| w.calstart().set_bit());
+        while self.raw.if_.read().calrdy().bit_is_clear() {}
+
+        if self.raw.if_.read().calof().bit_is_set() {
+            return Err(ImplError::Internal);
+        }
+        let cycles = self.raw.calcnt.read().calcnt().bits();
+        let frequency = Hertz((cycles as u64 * 32_768 / REFERENCE_CYCLES as u64) as u32);
+        debug!("CMU: HFRCO measured at {=u32} Hz", frequency.0);
+
+        self.hfrco = frequency;
+        if self.hfclk_requested == HfClkSource::HFRCO {
+            self.hfclk = frequency;
+            self.update_frequencies();
+        }
+        Ok(frequency)
+    }
+
+    /// Starts the high frequency crystal oscillator (HFXO) and selects it
+    /// for HFCLK.
+    ///
+    /// `frequency` is the frequency of the crystal, e.g. 40 MHz on the starter
+    /// kit, and replaces the assumed HFCLK frequency. The flash wait states
+    /// and the clock divider of the low energy peripheral interface are
+    /// adjusted to the new frequency. Peripherals configured before the
+    /// change keep their old dividers and must be reconfigured.
+    ///
+    /// Returns `Err(ImplError::TimedOut)` when the crystal does not become
+    /// ready within roughly one to two seconds. HFCLK is not changed in that
+    /// case.
+    pub fn use_hfxo(&mut self, frequency: Hertz) -> Result<(), ImplError> {
+        self.raw.oscencmd.write(|w| w.hfxoen().set_bit());
+
+        // Busy loop of a few cycles per iteration, independent of any timer.
+        let mut remaining = self.hfcoreclk.0 / 4;
+        while self.raw.status.read().hfxordy().bit_is_clear() {
+            if remaining == 0 {
+                self.raw.oscencmd.write(|w| w.hfxodis().set_bit());
+                warn!("CMU: HFXO not ready");
+                return Err(ImplError::TimedOut);
+            }
+            remaining -= 1;
+        }
+
+        self.select_hfclk(HfClkSource::HFXO, frequency);
+        Ok(())
+    }
+
+    /// Selects the HFRCO for HFCLK, e.g. to return from the HFXO, and stops
+    /// the HFXO.
+    ///
+    /// The HFRCO runs at the frequency it had before, 19 MHz after reset, the
+    /// band selected with [`Cmu::set_hfrco_band()`] or the value of the last
+    /// [`Cmu::measure_hfrco_against_lfxo()`].
+    /// Peripherals configured before the change keep their old dividers and
+    /// must be reconfigured.
+    pub fn use_hfrco(&mut self) {
+        self.raw.oscencmd.write(|w| w.hfrcoen().set_bit());
+        while self.raw.status.read().hfrcordy().bit_is_clear() {}
+
+        self.select_hfclk(HfClkSource::HFRCO, self.hfrco);
+        self.raw.oscencmd.write(|w| w.hfxodis().set_bit());
+    }
+
+    /// Tunes the HFRCO to `band` with its factory calibration from the
+    /// DEVINFO page.
+    ///
+    /// When the HFRCO drives HFCLK, the assumed HFCLK frequency and the flash
+    /// wait states follow. Peripherals configured before the change keep
+    /// their old dividers and must be reconfigured.
+    pub fn set_hfrco_band(&mut self, band: HfrcoBand) {
+        let frequency = band.frequency();
+        let tuning = band.control_value(devinfo::hfrcocal(band.calibration_index()));
+        let drives_hfclk = self.hfclk_requested == HfClkSource::HFRCO;
+
+        if drives_hfclk {
+            self.configure_for(Hertz(frequency.0.max(self.hfclk.0)));
+        }
+        while self.raw.syncbusy.read().hfrcobsy().bit_is_set() {}
+        self.raw.hfrcoctrl.write(|w| unsafe { w.bits(tuning) });
+        while self.raw.syncbusy.read().hfrcobsy().bit_is_set() {}
+
+        self.hfrco = frequency;
+        if drives_hfclk {
+            self.configure_for(frequency);
+            self.hfclk = frequency;
+            self.update_frequencies();
+        }
+        debug!("CMU: HFRCO at {=u32} Hz", frequency.0);
+    }
+
+    // Switches HFCLK to a running oscillator. The flash and the low energy
+    // interface must be configured for the higher of both frequencies while
+    // switching.
+    fn select_hfclk(&mut self, source: HfClkSource, frequency: Hertz) {
+        let transition = Hertz(frequency.0.max(self.hfclk.0));
+        self.configure_for(transition);
+        let select = match source {
+            HfClkSource::HFRCO => HF_AW::HFRCO,
+            HfClkSource::HFXO => HF_AW::HFXO,
+            HfClkSource::LFRCO => HF_AW::LFRCO,
+            HfClkSource::LFXO => HF_AW::LFXO,
+            HfClkSource::HFRCODIV2 => HF_AW::HFRCODIV2,
+            HfClkSource::CLKIN0 => HF_AW::CLKIN0,
+        };
+        self.raw.hfclksel.write(|w| w.hf().variant(select));
+        while self.hfclk_source() != Some(source) {}
+        self.configure_for(frequency);
+
+        self.hfclk = frequency;
+        self.hfclk_requested = source;
+        self.update_frequencies();
+        debug!(
+            "CMU: HFCLK source {=u8} at {=u32} Hz",
+            u8::from(source),
+            frequency.0
+        );
+    }
+
+    // Sets the flash wait states and the low energy interface divider for an
+    // HFCLK of `frequency`.
+    fn configure_for(&mut self, frequency: Hertz) {
+        // The MSC is not owned by any driver, only the wait states change.
+        let msc = unsafe { &*MSC::ptr() };
+        msc.readctrl.modify(|_, w| {
+            w.mode().variant(match frequency.0 > MAX_FREQ_0WS {
+                true => MODE_A::WS1,
+                false => MODE_A::WS0,
+            })
+        });
+        self.raw.hfpresc.modify(|_, w| {
+            w.hfclklepresc().variant(match frequency.0 > MAX_FREQ_HFLE {
+                true => HFCLKLEPRESC_A::DIV4,
+                false => HFCLKLEPRESC_A::DIV2,
+            })
+        });
+    }
+
+    /// Starts the low frequency RC oscillator (LFRCO, 32768 Hz) and waits
+    /// until it is ready.
+    pub fn enable_lfrco(&mut self) {
+        self.raw.oscencmd.write(|w| w.lfrcoen().set_bit());
+        while self.raw.status.read().lfrcordy().bit_is_clear() {}
+    }
+
+    /// Starts the low frequency crystal oscillator (LFXO, 32768 Hz) and waits
+    /// until it is ready, without selecting it for any LF clock.
+    ///
+    /// Returns `Err(ImplError::TimedOut)` and stops the oscillator again when
+    /// the crystal does not become ready within roughly one to two seconds.
+    pub fn enable_lfxo(&mut self) -> Result<(), ImplError> {
+        self.raw.oscencmd.write(|w| w.lfxoen().set_bit());
+
+        // Busy loop of a few cycles per iteration, independent of any timer.
+        let mut remaining = self.hfcoreclk.0 / 4;
+        while self.raw.status.read().lfxordy().bit_is_clear() {
+            if remaining == 0 {
+                self.raw.oscencmd.write(|w| w.lfxodis().set_bit());
+                warn!("CMU: LFXO not ready");
+                return Err(ImplError::TimedOut);
+            }
+            remaining -= 1;
+        }
+        Ok(())
+    }
+
+    /// Starts the LFXO and selects it for LFACLK, LFBCLK and LFECLK.
+    ///
+    /// Returns `Err(ImplError::TimedOut)` when the crystal does not become
+    /// ready, see [`Cmu::enable_lfxo()`]. The LF clock selection is not
+    /// changed in that case.
+    pub fn use_lfxo(&mut self) -> Result<(), ImplError> {
+        self.enable_lfxo()?;
+        self.select_lfclk(LfClkSource::LFXO);
+        Ok(())
+    }
+
+    /// Same as [`Cmu::use_lfxo()`] but selects the ULFRCO when the crystal
+    /// fails to start. Returns the selected source.
+    ///
+    /// The ULFRCO is always running but only has a resolution of 1 ms and a
+    /// tolerance in the order of ±20 % over temperature and supply voltage,
+    /// compared to a few ppm for a crystal. Low energy peripherals keep
+    /// working, while timekeeping and LEUART baud rates become inaccurate.
+    /// Check [`Cmu::lfaclk()`] when configuring dependent drivers.
+    pub fn use_lfxo_or_fallback(&mut self) -> LfClkSource {
+        if self.use_lfxo().is_ok() {
+            return LfClkSource::LFXO;
+        }
+        self.select_lfclk(LfClkSource::ULFRCO);
+        LfClkSource::ULFRCO
+    }
+
+    fn select_lfclk(&mut self, source: LfClkSource) {
+        self.set_lfa_source(source);
+        self.set_lfb_source(source);
+        self.set_lfe_source(source);
+    }
+
+    /// Selects the source of LFACLK, which clocks e.g. the LETIMER.
+    ///
+    /// The LFRCO and the LFXO must be running, see [`Cmu::enable_lfrco()`]
+    /// and [`Cmu::enable_lfxo()`]. The ULFRCO is always running. Peripherals
+    /// on LFACLK configured before the change must be reconfigured.
+    pub fn set_lfa_source(&mut self, source: LfClkSource) {
+        self.raw
+            .lfaclksel
+            .write(|w| unsafe { w.lfa().bits(u8::from(source)) });
+        self.lfaclk = lf_frequency(source);
+        debug!("CMU: LFACLK at {=u32} Hz", self.lfaclk.0);
+    }
+
+    /// Selects the source of LFBCLK, which clocks e.g. the LEUART, see
+    /// [`Cmu::set_lfa_source()`].
+    pub fn set_lfb_source(&mut self, source: LfClkSource) {
+        // The encoding matches LFACLKSEL, with HFCLKLE as additional source.
+        self.raw
+            .lfbclksel
+            .write(|w| unsafe { w.lfb().bits(u8::from(source)) });
+        self.lfbclk = lf_frequency(source);
+        debug!("CMU: LFBCLK at {=u32} Hz", self.lfbclk.0);
+    }
+
+    /// Selects the source of LFECLK, which clocks the RTCC, see
+    /// [`Cmu::set_lfa_source()`].
+    pub fn set_lfe_source(&mut self, source: LfClkSource) {
+        self.raw
+            .lfeclksel
+            .write(|w| unsafe { w.lfe().bits(u8::from(source)) });
+        self.lfeclk = lf_frequency(source);
+        debug!("CMU: LFECLK at {=u32} Hz", self.lfeclk.0);
+    }
+
+    // Divides LFACLK for LETIMER0.
+    pub(crate) fn set_letimer_prescaler(&mut self, prescaler: LetimerPrescaler) {
+        while self.raw.syncbusy.read().lfapresc0().bit_is_set() {}
+        self.raw
+            .lfapresc0
+            .modify(|_, w| w.letimer0().variant(prescaler));
+    }
+
+    /// Frequency of the low frequency clock A (LFACLK).
+    ///
+    /// Zero until a source was selected, e.g. with [`Cmu::use_lfxo()`] or
+    /// [`Cmu::set_lfa_source()`].
+    pub fn lfaclk(&self) -> Hertz {
+        self.lfaclk
+    }
+
+    /// Frequency of the low frequency clock B (LFBCLK).
+    pub fn lfbclk(&self) -> Hertz {
+        self.lfbclk
+    }
+
+    /// Frequency of the low frequency clock E (LFECLK).
+    pub fn lfeclk(&self) -> Hertz {
+        self.lfeclk
+    }
+
+    /// Returns the current clock frequencies.
+    ///
+    /// Drivers that only need a frequency, e.g. [`Delay`](crate::delay::Delay),
+    /// take the returned value instead of the `Cmu`, so frequencies can be
+    /// passed around while the `Cmu` is borrowed elsewhere. Like the
+    /// frequencies of the `Cmu` itself the snapshot is not updated when the
+    /// clock configuration changes later, get a new one in that case.
+    pub fn clocks(&self) -> Clocks {
+        Clocks {
+            hfclk: self.hfclk,
+            hfcoreclk: self.hfcoreclk,
+            hfperclk: self.hfperclk,
+            lfaclk: self.lfaclk,
+            lfbclk: self.lfbclk,
+            lfeclk: self.lfeclk,
+        }
+    }
+
+    /// Returns a snapshot of the clock tree for diagnostics.
+    ///
+    /// The clock sources and clock gates are read from the registers at the
+    /// time of the call and do not change when the configuration changes
+    /// later. The frequencies are the values assumed by the HAL, see
+    /// [`Cmu::hfclk()`].
+    pub fn clock_tree(&self) -> ClockTree {
+        let lf_source = |bits: u8| match bits {
+            1 => LfClkSource::LFRCO,
+            2 => LfClkSource::LFXO,
+            4 => LfClkSource::ULFRCO,
+            _ => LfClkSource::DISABLED,
+        };
+
+        ClockTree {
+            hfclk_source: self.hfclk_source(),
+            requested_hfclk_source: self.hfclk_requested,
+            hfclk: self.hfclk(),
+            hfcoreclk: self.hfcoreclk(),
+            hfbusclk: self.hfbusclk(),
+            hfperclk: self.hfperclk(),
+            lfaclk_source: lf_source(self.raw.lfaclksel.read().lfa().bits()),
+            lfbclk_source: match self.raw.lfbclksel.read().lfb().variant() {
+                Variant::Val(source) => source,
+                Variant::Res(_) => LfbClkSource::DISABLED,
+            },
+            lfeclk_source: lf_source(self.raw.lfeclksel.read().lfe().bits()),
+            lfaclk: self.lfaclk,
+            lfbclk: self.lfbclk,
+            lfeclk: self.lfeclk,
+            hfbusclken0: self.raw.hfbusclken0.read().bits(),
+            hfperclken0: self.raw.hfperclken0.read().bits(),
+            lfaclken0: self.raw.lfaclken0.read().bits(),
+            lfbclken0: self.raw.lfbclken0.read().bits(),
+            lfeclken0: self.raw.lfeclken0.read().bits(),
+        }
     }
 
     /// Enables all clocks required to use a peripheral.
@@ -37,14 +510,293 @@ impl Cmu {
         peripheral.enable_clock(self);
     }
 
+    /// Disables the clocks enabled by [`Cmu::enable_clock()`] to save power.
+    ///
+    /// The peripheral keeps its register contents but must not be accessed
+    /// until its clock is enabled again. All low energy peripherals share the
+    /// HFBUSCLKLE clock of their register interface, which is only disabled
+    /// together with the last LF peripheral clock in `LFACLKEN0`, `LFBCLKEN0`
+    /// and `LFECLKEN0`, and only while no watchdog is enabled.
+    pub fn disable_clock(&mut self, peripheral: &impl ClockControlExt) {
+        peripheral.disable_clock(self);
+    }
+
+    // Disables the LE register interface clock when no LF peripheral clock is
+    // enabled anymore and no watchdog runs, which must stay feedable.
+    fn disable_unused_le(&mut self) {
+        let lf_gates = self.raw.lfaclken0.read().bits()
+            | self.raw.lfbclken0.read().bits()
+            | self.raw.lfeclken0.read().bits();
+        if lf_gates != 0 || self.raw.hfbusclken0.read().le().bit_is_clear() {
+            return;
+        }
+        // The LE clock is still enabled here, so the watchdogs can be read.
+        let wdog_running = unsafe {
+            (*WDOG0::ptr()).ctrl.read().en().bit_is_set()
+                || (*WDOG1::ptr()).ctrl.read().en().bit_is_set()
+        };
+        if !wdog_running {
+            self.raw.hfbusclken0.modify(|_, w| w.le().clear_bit());
+        }
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// the cached clock frequencies, which are not updated by raw writes.
+    pub unsafe fn raw(&self) -> &crate::pac::cmu::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Cmu::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut CMU {
+        &mut self.raw
+    }
+
     /// Return the raw interface to the underlying peripheral.
     pub fn release(self) -> CMU {
         self.raw
     }
+
+    /// Same as [`Cmu::release()`].
+    pub fn free(self) -> CMU {
+        self.release()
+    }
+}
+
+// Nominal frequency of an LF oscillator.
+fn lf_frequency(source: LfClkSource) -> Hertz {
+    Hertz(match source {
+        LfClkSource::DISABLED => 0,
+        LfClkSource::LFRCO | LfClkSource::LFXO => 32_768,
+        LfClkSource::ULFRCO => 1_000,
+    })
+}
+
+/// Calibrated frequency bands of the HFRCO, see [`Cmu::set_hfrco_band()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HfrcoBand {
+    /// 1 MHz
+    Mhz1,
+    /// 7 MHz
+    Mhz7,
+    /// 13 MHz
+    Mhz13,
+    /// 16 MHz
+    Mhz16,
+    /// 19 MHz, the reset default.
+    Mhz19,
+    /// 26 MHz
+    Mhz26,
+    /// 32 MHz
+    Mhz32,
+    /// 38 MHz
+    Mhz38,
+}
+
+impl HfrcoBand {
+    /// Nominal frequency of the band.
+    pub fn frequency(self) -> Hertz {
+        Hertz(match self {
+            HfrcoBand::Mhz1 => 1_000_000,
+            HfrcoBand::Mhz7 => 7_000_000,
+            HfrcoBand::Mhz13 => 13_000_000,
+            HfrcoBand::Mhz16 => 16_000_000,
+            HfrcoBand::Mhz19 => 19_000_000,
+            HfrcoBand::Mhz26 => 26_000_000,
+            HfrcoBand::Mhz32 => 32_000_000,
+            HfrcoBand::Mhz38 => 38_000_000,
+        })
+    }
+
+    // Index of the `HFRCOCALn` word in the DEVINFO page, which holds the
+    // `HFRCOCTRL` value of the band. There is no word for 1 MHz, that band is
+    // the 4 MHz band divided by 4.
+    fn calibration_index(self) -> usize {
+        match self {
+            HfrcoBand::Mhz1 => 0,
+            HfrcoBand::Mhz7 => 3,
+            HfrcoBand::Mhz13 => 6,
+            HfrcoBand::Mhz16 => 7,
+            HfrcoBand::Mhz19 => 8,
+            HfrcoBand::Mhz26 => 10,
+            HfrcoBand::Mhz32 => 11,
+            HfrcoBand::Mhz38 => 12,
+        }
+    }
+
+    // `HFRCOCTRL` value of the band from its calibration word.
+    fn control_value(self, calibration: u32) -> u32 {
+        match self {
+            HfrcoBand::Mhz1 => (calibration & !HFRCOCTRL_CLKDIV_MASK) | HFRCOCTRL_CLKDIV_DIV4,
+            _ => calibration,
+        }
+    }
+}
+
+/// Clock frequencies, created by [`Cmu::clocks()`].
+///
+/// The getters mirror those of [`Cmu`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clocks {
+    hfclk: Hertz,
+    hfcoreclk: Hertz,
+    hfperclk: Hertz,
+    lfaclk: Hertz,
+    lfbclk: Hertz,
+    lfeclk: Hertz,
+}
+
+impl Clocks {
+    /// Same as [`Cmu::hfclk()`].
+    pub fn hfclk(&self) -> Hertz {
+        self.hfclk
+    }
+
+    /// Same as [`Cmu::hfcoreclk()`].
+    pub fn hfcoreclk(&self) -> Hertz {
+        self.hfcoreclk
+    }
+
+    /// Same as [`Cmu::hfbusclk()`].
+    pub fn hfbusclk(&self) -> Hertz {
+        self.hfclk
+    }
+
+    /// Same as [`Cmu::hfperclk()`].
+    pub fn hfperclk(&self) -> Hertz {
+        self.hfperclk
+    }
+
+    /// Same as [`Cmu::lfaclk()`].
+    pub fn lfaclk(&self) -> Hertz {
+        self.lfaclk
+    }
+
+    /// Same as [`Cmu::lfbclk()`].
+    pub fn lfbclk(&self) -> Hertz {
+        self.lfbclk
+    }
+
+    /// Same as [`Cmu::lfeclk()`].
+    pub fn lfeclk(&self) -> Hertz {
+        self.lfeclk
+    }
 }
 
+/// Snapshot of the clock tree, created by [`Cmu::clock_tree()`].
+///
+/// The clock gate fields hold the raw contents of the clock enable registers,
+/// one bit per peripheral as listed in the reference manual, e.g. bit 3 of
+/// `hfbusclken0` is set while the GPIO clock is enabled.
+#[derive(Clone, Copy, Debug)]
+pub struct ClockTree {
+    /// Source selected for HFCLK, see [`Cmu::hfclk_source()`].
+    pub hfclk_source: Option<HfClkSource>,
+    /// Source requested for HFCLK, see [`Cmu::requested_hfclk_source()`].
+    pub requested_hfclk_source: HfClkSource,
+    /// Frequency of HFCLK, see [`Cmu::hfclk()`].
+    pub hfclk: Hertz,
+    /// Frequency of HFCORECLK, see [`Cmu::hfcoreclk()`].
+    pub hfcoreclk: Hertz,
+    /// Frequency of HFBUSCLK, see [`Cmu::hfbusclk()`].
+    pub hfbusclk: Hertz,
+    /// Frequency of HFPERCLK, see [`Cmu::hfperclk()`].
+    pub hfperclk: Hertz,
+    /// Source selected for LFACLK.
+    pub lfaclk_source: LfClkSource,
+    /// Source selected for LFBCLK.
+    pub lfbclk_source: LfbClkSource,
+    /// Source selected for LFECLK.
+    pub lfeclk_source: LfClkSource,
+    /// Frequency of LFACLK, see [`Cmu::lfaclk()`].
+    pub lfaclk: Hertz,
+    /// Frequency of LFBCLK, see [`Cmu::lfbclk()`].
+    pub lfbclk: Hertz,
+    /// Frequency of LFECLK, see [`Cmu::lfeclk()`].
+    pub lfeclk: Hertz,
+    /// Contents of `CMU.HFBUSCLKEN0`.
+    pub hfbusclken0: u32,
+    /// Contents of `CMU.HFPERCLKEN0`.
+    pub hfperclken0: u32,
+    /// Contents of `CMU.LFACLKEN0`.
+    pub lfaclken0: u32,
+    /// Contents of `CMU.LFBCLKEN0`.
+    pub lfbclken0: u32,
+    /// Contents of `CMU.LFECLKEN0`.
+    pub lfeclken0: u32,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ClockTree {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "HFCLK source {=?} (requested {=u8}) at {=u32} Hz, HFCORECLK {=u32} Hz, HFBUSCLK {=u32} Hz, HFPERCLK {=u32} Hz, ",
+            self.hfclk_source.map(u8::from),
+            u8::from(self.requested_hfclk_source),
+            self.hfclk.0,
+            self.hfcoreclk.0,
+            self.hfbusclk.0,
+            self.hfperclk.0,
+        );
+        defmt::write!(
+            f,
+            "LFA/LFB/LFE sources {=u8}/{=u8}/{=u8} at {=u32}/{=u32}/{=u32} Hz, ",
+            u8::from(self.lfaclk_source),
+            u8::from(self.lfbclk_source),
+            u8::from(self.lfeclk_source),
+            self.lfaclk.0,
+            self.lfbclk.0,
+            self.lfeclk.0,
+        );
+        defmt::write!(
+            f,
+            "gates HFBUS {=u32:#x} HFPER {=u32:#x} LFA {=u32:#x} LFB {=u32:#x} LFE {=u32:#x}",
+            self.hfbusclken0,
+            self.hfperclken0,
+            self.lfaclken0,
+            self.lfbclken0,
+            self.lfeclken0,
+        );
+    }
+}
+
+/// Extension trait to create the CMU HAL instance from the raw peripheral.
+pub trait CmuExt {
+    /// Same as [`Cmu::new()`].
+    fn constrain(self) -> Cmu;
+
+    /// Same as [`Cmu::new()`].
+    fn freeze(self) -> Cmu;
+}
+
+impl CmuExt for CMU {
+    fn constrain(self) -> Cmu {
+        Cmu::new(self)
+    }
+
+    fn freeze(self) -> Cmu {
+        Cmu::new(self)
+    }
+}
+
+/// Extension trait to gate the clock of a raw peripheral.
+///
+/// The drivers enable the clock of their peripheral in the constructor, this
+/// is only needed for peripherals used through the PAC.
 pub trait ClockControlExt {
+    /// Enables the bus or peripheral clock of the peripheral.
     fn enable_clock(&self, clocks: &mut Cmu);
+    /// Disables the bus or peripheral clock of the peripheral.
+    fn disable_clock(&self, clocks: &mut Cmu);
 }
 
 macro_rules! impl_clock_control_ext {
@@ -53,6 +805,10 @@ macro_rules! impl_clock_control_ext {
             fn enable_clock(&self, clocks: &mut Cmu) {
                 clocks.raw.$reg.modify(|_, w| w.$bit().set_bit());
             }
+
+            fn disable_clock(&self, clocks: &mut Cmu) {
+                clocks.raw.$reg.modify(|_, w| w.$bit().clear_bit());
+            }
         }
     };
 }
@@ -90,6 +846,11 @@ macro_rules! impl_lf_clock_control_ext {
                 clocks.raw.hfbusclken0.modify(|_, w| w.le().set_bit());
                 clocks.raw.$reg.modify(|_, w| w.$bit().set_bit());
             }
+
+            fn disable_clock(&self, clocks: &mut Cmu) {
+                clocks.raw.$reg.modify(|_, w| w.$bit().clear_bit());
+                clocks.disable_unused_le();
+            }
         }
     };
 }
@@ -100,10 +861,58 @@ impl_lf_clock_control_ext!(SYST, lfbclken0, systick);
 impl_lf_clock_control_ext!(LEUART0, lfbclken0, leuart0);
 impl_lf_clock_control_ext!(RTCC, lfeclken0, rtcc);
 
+// The watchdogs are clocked directly by their selected oscillator and only
+// need the LE register interface clock.
+macro_rules! impl_wdog_clock_control_ext {
+    ($type:ty) => {
+        impl ClockControlExt for $type {
+            fn enable_clock(&self, clocks: &mut Cmu) {
+                clocks.raw.hfbusclken0.modify(|_, w| w.le().set_bit());
+            }
+
+            fn disable_clock(&self, clocks: &mut Cmu) {
+                clocks.disable_unused_le();
+            }
+        }
+    };
+}
+
+impl_wdog_clock_control_ext!(WDOG0);
+impl_wdog_clock_control_ext!(WDOG1);
+
 // The CSEN peripheral is special because it uses the HF and LF clock domain.
 impl ClockControlExt for CSEN {
     fn enable_clock(&self, cmu: &mut Cmu) {
         cmu.raw.hfperclken0.modify(|_, w| w.csen().set_bit());
         cmu.raw.lfbclken0.modify(|_, w| w.csen().set_bit());
     }
+
+    fn disable_clock(&self, cmu: &mut Cmu) {
+        cmu.raw.hfperclken0.modify(|_, w| w.csen().clear_bit());
+        cmu.raw.lfbclken0.modify(|_, w| w.csen().clear_bit());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hfrco_1mhz_divides_the_4mhz_band() {
+        // The 4 MHz word as written by the factory, with CLKDIV = DIV1.
+        let calibration = 0x8000_0000 | 0x1F << 8 | 0x3A;
+        let value = HfrcoBand::Mhz1.control_value(calibration);
+        assert_eq!(value & HFRCOCTRL_CLKDIV_MASK, HFRCOCTRL_CLKDIV_DIV4);
+        assert_eq!(value & !HFRCOCTRL_CLKDIV_MASK, calibration);
+
+        // A stray divider in the word is replaced, not combined.
+        let value = HfrcoBand::Mhz1.control_value(calibration | 0x1 << 25);
+        assert_eq!(value & HFRCOCTRL_CLKDIV_MASK, HFRCOCTRL_CLKDIV_DIV4);
+    }
+
+    #[test]
+    fn hfrco_bands_use_the_calibration_word_unchanged() {
+        let calibration = 0x8000_1F3A;
+        assert_eq!(HfrcoBand::Mhz19.control_value(calibration), calibration);
+    }
 }
@@ -0,0 +1,306 @@
+//! Ultra low energy timer/counter (CRYOTIMER) API
+//!
+//! The CRYOTIMER is a free running 32-bit counter clocked by one of the low
+//! frequency oscillators. It keeps counting in all energy modes and is not
+//! affected by changes of the high frequency clocks, which makes it a cheap
+//! time base for software timeouts:
+//!
+//! ```ignore
+//! let deadline = cryotimer.deadline(50.ms());
+//! while !deadline.expired() {
+//!     // poll the sensor
+//! }
+//! ```
+//!
+//! The resolution is one tick of the prescaled oscillator, i.e. 1 ms with the
+//! ULFRCO (1 kHz) and 30.5 µs with LFRCO or LFXO (32768 Hz) without
+//! prescaler.
+pub use crate::pac::cryotimer::ctrl::{OSCSEL_A as Oscillator, PRESC_A as Prescaler};
+
+use crate::{
+    cmu::Cmu,
+    hal::timer::CountDown,
+    pac::{Interrupt, CRYOTIMER, EMU},
+    time::{Hertz, MilliSeconds},
+};
+use void::Void;
+
+/// CRYOTIMER configuration.
+///
+/// Defaults to the ULFRCO, which is always running, without prescaler.
+pub struct Config {
+    /// Clock source of the counter.
+    ///
+    /// The LFRCO is started by [`Cryotimer::new()`]. The LFXO must already be
+    /// running.
+    pub oscillator: Oscillator,
+    /// Divides the oscillator clock to derive the counter clock.
+    pub prescaler: Prescaler,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            oscillator: Oscillator::ULFRCO,
+            prescaler: Prescaler::DIV1,
+        }
+    }
+}
+
+/// CRYOTIMER API
+pub struct Cryotimer {
+    raw: CRYOTIMER,
+    tick_frequency: Hertz,
+    timeout: Option<Deadline>,
+}
+
+impl Cryotimer {
+    /// Creates the HAL instance and starts the counter.
+    ///
+    /// Panics when `config.oscillator` is [`Oscillator::DISABLED`].
+    pub fn new(cryotimer: CRYOTIMER, config: &Config, cmu: &mut Cmu) -> Self {
+        let oscillator_frequency = match config.oscillator {
+            Oscillator::DISABLED => panic!("CRYOTIMER oscillator disabled"),
+            Oscillator::LFRCO => {
+                cmu.enable_lfrco();
+                32_768
+            }
+            Oscillator::LFXO => 32_768,
+            Oscillator::ULFRCO => 1_000,
+        };
+
+        cmu.enable_clock(&cryotimer);
+
+        cryotimer.ctrl.write(|w| {
+            w.oscsel()
+                .variant(config.oscillator)
+                .presc()
+                .variant(config.prescaler)
+                .en()
+                .set_bit()
+        });
+
+        Cryotimer {
+            raw: cryotimer,
+            tick_frequency: Hertz(oscillator_frequency >> u8::from(config.prescaler)),
+            timeout: None,
+        }
+    }
+
+    /// Frequency of the counter ticks.
+    pub fn tick_frequency(&self) -> Hertz {
+        self.tick_frequency
+    }
+
+    /// Current value of the free running counter.
+    pub fn count(&self) -> u32 {
+        self.raw.cnt.read().bits()
+    }
+
+    /// Returns a deadline which expires after at least `duration`.
+    ///
+    /// The duration is rounded up to full ticks plus one tick, because the
+    /// counter may be just about to increment. It must be shorter than one
+    /// period of the counter, i.e. `2^32` ticks.
+    pub fn deadline(&self, duration: MilliSeconds) -> Deadline {
+        let ticks = (duration.0 as u64 * self.tick_frequency.0 as u64).div_ceil(1000) + 1;
+        Deadline {
+            start: self.count(),
+            ticks: ticks.min(u32::MAX as u64) as u32,
+        }
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// a changed prescaler invalidates the tick frequency and pending deadlines.
+    pub unsafe fn raw(&self) -> &crate::pac::cryotimer::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Cryotimer::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut CRYOTIMER {
+        &mut self.raw
+    }
+
+    /// Raises the `CRYOTIMER` interrupt periodically with the period closest
+    /// to `interval`, see [`PeriodicTick`].
+    pub fn into_periodic(self, interval: MilliSeconds) -> PeriodicTick {
+        let ticks = (interval.0 as u64 * self.tick_frequency.0 as u64 / 1000).max(1);
+        // The period is a power of two ticks, round to the nearest one.
+        let mut periodsel = 63 - ticks.leading_zeros();
+        if periodsel < 63 && ticks - (1 << periodsel) > (2 << periodsel) - ticks {
+            periodsel += 1;
+        }
+        let periodsel = periodsel.min(31) as u8;
+
+        self.raw
+            .periodsel
+            .write(|w| unsafe { w.periodsel().bits(periodsel) });
+        self.raw.ifc.write(|w| w.period().set_bit());
+        self.raw.ien.write(|w| w.period().set_bit());
+        debug!("CRYOTIMER: PERIODSEL {=u8}", periodsel);
+
+        PeriodicTick {
+            cryotimer: self,
+            ticks: 0,
+        }
+    }
+
+    /// Stops the counter and returns the raw interface to the underlying
+    /// peripheral.
+    pub fn release(self) -> CRYOTIMER {
+        self.raw.ctrl.reset();
+        self.raw
+    }
+}
+
+/// Periodic interrupt as low power system tick, created by
+/// [`Cryotimer::into_periodic()`].
+///
+/// The period is a power of two counter ticks, e.g. 1024 ms with the ULFRCO
+/// for an interval of one second, or exactly one second with the LFXO. Call
+/// [`PeriodicTick::on_interrupt()`] from the `CRYOTIMER` handler, which must
+/// be unmasked in the NVIC by the application.
+///
+/// The ticks do not drift against the counter because the hardware raises the
+/// interrupt whenever the selected counter bit toggles, independent of when
+/// the handler ran. Each tick is delayed by the interrupt latency, including
+/// the wake-up time from EM2 or EM3 of a few microseconds, and its length
+/// follows the accuracy of the oscillator: the LFXO is crystal accurate, the
+/// LFRCO and the ULFRCO vary by several percent with temperature and supply
+/// voltage. The first tick comes after at most one period.
+///
+/// The interrupt wakes the core from EM1 to EM3. The LFRCO and the LFXO run
+/// down to EM2, only the ULFRCO runs in EM3. To wake up from EM4, see
+/// [`PeriodicTick::enable_em4_wakeup()`]. EM4 is left through a reset, which
+/// clears the tick count.
+pub struct PeriodicTick {
+    cryotimer: Cryotimer,
+    ticks: u64,
+}
+
+impl PeriodicTick {
+    /// Counts a tick when the period elapsed. Call this from the `CRYOTIMER`
+    /// interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        let raw = &self.cryotimer.raw;
+        if raw.if_.read().period().bit_is_set() {
+            raw.ifc.write(|w| w.period().set_bit());
+            self.ticks += 1;
+        }
+    }
+
+    /// Returns the NVIC line of the periodic interrupt, which the application
+    /// unmasks.
+    pub fn interrupt(&self) -> Interrupt {
+        Interrupt::CRYOTIMER
+    }
+
+    /// Number of ticks since [`Cryotimer::into_periodic()`].
+    ///
+    /// A 64-bit count does not overflow in practice, even with a period of
+    /// one counter tick.
+    pub fn tick_count(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Lets the periodic interrupt wake the device from EM4 as well.
+    ///
+    /// Also retains the oscillator of the counter during EM4 in
+    /// `EMU.EM4CTRL`, which otherwise stops the counter. The ULFRCO keeps the
+    /// counter running in EM4S (shutoff) and EM4H (hibernate), the LFRCO and
+    /// the LFXO in EM4H only. The wake-up is a reset, software starts from
+    /// the beginning.
+    pub fn enable_em4_wakeup(&mut self, emu: &EMU) {
+        let raw = &self.cryotimer.raw;
+        match raw.ctrl.read().oscsel().variant() {
+            Oscillator::LFRCO => emu.em4ctrl.modify(|_, w| w.retainlfrco().set_bit()),
+            Oscillator::LFXO => emu.em4ctrl.modify(|_, w| w.retainlfxo().set_bit()),
+            Oscillator::ULFRCO => emu.em4ctrl.modify(|_, w| w.retainulfrco().set_bit()),
+            Oscillator::DISABLED => {}
+        }
+        raw.em4wuen.write(|w| w.em4wu().set_bit());
+    }
+
+    /// Stops the periodic interrupt from waking the device from EM4.
+    ///
+    /// The oscillator stays retained during EM4, other drivers may rely on
+    /// it.
+    pub fn disable_em4_wakeup(&mut self) {
+        self.cryotimer.raw.em4wuen.reset();
+    }
+
+    /// Length of one period in counter ticks.
+    pub fn period_ticks(&self) -> u32 {
+        1 << self.cryotimer.raw.periodsel.read().periodsel().bits()
+    }
+
+    /// Returns the counter for deadlines in between ticks.
+    pub fn cryotimer(&self) -> &Cryotimer {
+        &self.cryotimer
+    }
+
+    /// Disables the periodic interrupt and the EM4 wake-up and returns the
+    /// counter, which keeps running.
+    pub fn free(self) -> Cryotimer {
+        let raw = &self.cryotimer.raw;
+        raw.em4wuen.reset();
+        raw.ien.reset();
+        raw.ifc.write(|w| w.period().set_bit());
+        raw.periodsel.reset();
+        self.cryotimer
+    }
+}
+
+/// Point in time created by [`Cryotimer::deadline()`].
+///
+/// Does not borrow the [`Cryotimer`] so that it can be passed to other
+/// drivers. It only reads the counter and becomes meaningless when the
+/// CRYOTIMER is stopped or reconfigured.
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    start: u32,
+    ticks: u32,
+}
+
+impl Deadline {
+    /// Returns `true` once the duration has passed.
+    ///
+    /// The elapsed time is computed with wrapping arithmetic, so that an
+    /// overflow of the counter in between is handled correctly. A deadline
+    /// reads as not expired again after a full period of the counter has
+    /// passed, e.g. after 49.7 days with the ULFRCO.
+    pub fn expired(&self) -> bool {
+        let count = unsafe { (*CRYOTIMER::ptr()).cnt.read().bits() };
+        count.wrapping_sub(self.start) >= self.ticks
+    }
+}
+
+impl CountDown for Cryotimer {
+    type Time = MilliSeconds;
+
+    fn start<T>(&mut self, count: T)
+    where
+        T: Into<MilliSeconds>,
+    {
+        self.timeout = Some(self.deadline(count.into()));
+    }
+
+    fn wait(&mut self) -> nb::Result<(), Void> {
+        match self.timeout {
+            Some(deadline) if deadline.expired() => {
+                self.timeout = None;
+                Ok(())
+            }
+            _ => Err(nb::Error::WouldBlock),
+        }
+    }
+}
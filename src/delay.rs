@@ -0,0 +1,99 @@
+//! Blocking delays based on the SysTick timer
+//!
+//! [`Delay`] counts HFCORECLK cycles with the 24-bit SysTick timer of the
+//! Cortex-M core. Delays longer than one SysTick period (about 873 ms at
+//! 19 MHz) are split into several periods, so any `u32` number of
+//! milliseconds can be waited for.
+//!
+//! The HFCORECLK frequency is taken from the [`Clocks`] when the delay is
+//! created. Create a new instance after changing the core clock.
+use crate::{
+    cmu::Clocks,
+    hal::blocking::delay::{DelayMs, DelayUs},
+};
+use cortex_m::peripheral::{syst::SystClkSource, SYST};
+
+// SysTick runs from the core clock and has a 24-bit counter.
+const MAX_RELOAD: u32 = 0x00FF_FFFF;
+
+/// SysTick based delay provider.
+pub struct Delay {
+    syst: SYST,
+    hfcoreclk: u32,
+}
+
+impl Delay {
+    /// Configures SysTick to count HFCORECLK cycles.
+    pub fn new(mut syst: SYST, clocks: &Clocks) -> Self {
+        syst.set_clock_source(SystClkSource::Core);
+        Delay {
+            syst,
+            hfcoreclk: clocks.hfcoreclk().0,
+        }
+    }
+
+    // Waits for `ticks` core clock cycles, one SysTick period at a time.
+    fn delay_ticks(&mut self, mut ticks: u64) {
+        while ticks != 0 {
+            let reload = ticks.min(MAX_RELOAD as u64) as u32;
+            ticks -= reload as u64;
+
+            // A reload value of 0 does not start the counter.
+            self.syst.set_reload(reload.max(1));
+            self.syst.clear_current();
+            self.syst.enable_counter();
+            while !self.syst.has_wrapped() {}
+            self.syst.disable_counter();
+        }
+    }
+
+    /// Stops SysTick and returns the raw peripheral.
+    pub fn release(mut self) -> SYST {
+        self.syst.disable_counter();
+        self.syst
+    }
+}
+
+impl DelayUs<u32> for Delay {
+    fn delay_us(&mut self, us: u32) {
+        self.delay_ticks((self.hfcoreclk as u64 * us as u64).div_ceil(1_000_000));
+    }
+}
+
+impl DelayUs<u16> for Delay {
+    fn delay_us(&mut self, us: u16) {
+        self.delay_us(us as u32);
+    }
+}
+
+impl DelayUs<u8> for Delay {
+    fn delay_us(&mut self, us: u8) {
+        self.delay_us(us as u32);
+    }
+}
+
+impl DelayMs<u32> for Delay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_ticks((self.hfcoreclk as u64 * ms as u64).div_ceil(1_000));
+    }
+}
+
+impl DelayMs<u16> for Delay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(ms as u32);
+    }
+}
+
+impl DelayMs<u8> for Delay {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(ms as u32);
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::delay::DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        // Round up, a delay must never be shorter than requested.
+        self.delay_ticks((self.hfcoreclk as u64 * ns as u64).div_ceil(1_000_000_000));
+    }
+}
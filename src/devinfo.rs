@@ -0,0 +1,120 @@
+//! Device information (DEVINFO) page
+//!
+//! Read-only factory data like calibration values, the unique device
+//! identifier, the memory sizes and the part number. The PAC does not provide
+//! an accessor for this flash page, so the words are read by address.
+
+/// Base address of the DEVINFO page.
+const BASE: usize = 0x0FE0_81B0;
+
+/// Reads the DEVINFO word at `offset` bytes from the base address.
+fn read(offset: usize) -> u32 {
+    // Safety: The DEVINFO page is always mapped and read-only.
+    unsafe { core::ptr::read_volatile((BASE + offset) as *const u32) }
+}
+
+/// ADC0 calibration word `n` (`ADC0CAL0` to `ADC0CAL3`).
+pub(crate) fn adc0_cal(n: usize) -> u32 {
+    assert!(n < 4);
+    read(0x60 + 4 * n)
+}
+
+/// HFRCO tuning word `n` (`HFRCOCAL0` to `HFRCOCAL12`), not every index is
+/// populated on this device.
+pub(crate) fn hfrcocal(n: usize) -> u32 {
+    assert!(n <= 12);
+    read(0x80 + 4 * n)
+}
+
+/// Returns the 64-bit unique device identifier (`UNIQUEH:UNIQUEL`).
+pub fn unique_id() -> u64 {
+    (read(0x44) as u64) << 32 | read(0x40) as u64
+}
+
+/// Returns the EUI-64 of the device, most significant byte first.
+///
+/// The unique device identifier is an EUI-64 assigned by Silicon Labs: the
+/// upper three bytes are the Silicon Labs OUI, the lower five bytes a serial
+/// number.
+pub fn eui64() -> [u8; 8] {
+    unique_id().to_be_bytes()
+}
+
+/// Returns an EUI-48, e.g. for use as MAC address, most significant byte
+/// first.
+///
+/// Parts with a radio store an EUI-48 in the `EUI48L`/`EUI48H` words, which
+/// is returned when present. On the EFM32PG12 these words are usually not
+/// programmed. A locally administered unicast address is derived from the
+/// unique identifier instead: the first byte is `0x02` and the remaining
+/// five bytes are the serial number part of the [`eui64()`]. The address is
+/// the same on every call and unique among devices sharing the OUI.
+pub fn eui48() -> [u8; 6] {
+    let low = read(0x28);
+    let high = read(0x2C) & 0xFFFF;
+    if low == 0xFFFF_FFFF && high == 0xFFFF {
+        derive_eui48(eui64())
+    } else {
+        let eui = (high as u64) << 32 | low as u64;
+        let bytes = eui.to_be_bytes();
+        [bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]
+    }
+}
+
+// Sets the locally administered bit and clears the multicast bit of the first
+// byte, followed by the serial number of the EUI-64.
+fn derive_eui48(eui64: [u8; 8]) -> [u8; 6] {
+    [0x02, eui64[3], eui64[4], eui64[5], eui64[6], eui64[7]]
+}
+
+/// Returns the size of the flash memory in kB (`MSIZE.FLASH`).
+pub fn flash_size_kb() -> u16 {
+    read(0x48) as u16
+}
+
+/// Returns the size of the RAM in kB (`MSIZE.SRAM`).
+pub fn ram_size_kb() -> u16 {
+    (read(0x48) >> 16) as u16
+}
+
+/// Part number of the device as stored in the `PART` word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartNumber {
+    /// Device family code (`DEVICE_FAMILY`), see the reference manual for
+    /// the list of codes.
+    pub family: u8,
+    /// Number of the part within the family (`DEVICE_NUMBER`), e.g. 500 for
+    /// the EFM32PG12B500F1024GL125.
+    pub device_number: u16,
+    /// Production revision (`PROD_REV`).
+    pub revision: u8,
+}
+
+/// Returns the part number of the device.
+pub fn part_number() -> PartNumber {
+    let part = read(0x4C);
+    PartNumber {
+        family: (part >> 16) as u8,
+        device_number: part as u16,
+        revision: (part >> 24) as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_eui48_from_known_eui64() {
+        let eui64 = 0x000B_57FF_FE12_3456u64.to_be_bytes();
+        assert_eq!(derive_eui48(eui64), [0x02, 0xFF, 0xFE, 0x12, 0x34, 0x56]);
+    }
+
+    #[test]
+    fn derive_eui48_is_local_unicast() {
+        let eui48 = derive_eui48([0xFF; 8]);
+        assert_eq!(eui48[0] & 0x02, 0x02);
+        assert_eq!(eui48[0] & 0x01, 0x00);
+        assert_eq!(eui48, derive_eui48([0xFF; 8]));
+    }
+}
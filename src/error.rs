@@ -0,0 +1,176 @@
+//! Formatting of the error types returned by the drivers
+//!
+//! The serial, SPI and I2C drivers return the error types of the `embedded-error`
+//! crate, which implement neither `core::fmt::Display` nor `defmt::Format`.
+//! Wrap them in [`Decoded`] to print a short description:
+//!
+//! ```ignore
+//! if let Err(nb::Error::Other(e)) = rx.read() {
+//!     rprintln!("RX error: {}", Decoded(e));
+//! }
+//! ```
+use crate::usart::HalfDuplexError;
+use core::fmt;
+use embedded_error::{I2cError, ImplError, SerialError, SpiError};
+
+/// Wrapper that implements `Display` (and `defmt::Format` with the `defmt`
+/// feature) for a driver error.
+///
+/// With the `embedded-hal-1` feature it also implements the embedded-hal 1.0
+/// error traits, so that generic drivers can match on the error kind.
+#[derive(Debug, Clone)]
+pub struct Decoded<E>(pub E);
+
+/// Implemented by error types that have a short description.
+pub trait Describe {
+    /// Returns a short description of the error.
+    fn describe(&self) -> &'static str;
+}
+
+impl Describe for ImplError {
+    fn describe(&self) -> &'static str {
+        match self {
+            ImplError::Internal => "internal driver error",
+            ImplError::Disconnected => "connection lost",
+            ImplError::OutOfMemory => "out of memory",
+            ImplError::TimedOut => "timed out",
+            ImplError::Asleep => "peripheral asleep",
+            ImplError::PowerDown => "peripheral powered down",
+            ImplError::InvalidConfiguration => "invalid configuration",
+            ImplError::CouldNotOpen => "could not open peripheral",
+            ImplError::PermissionDenied => "permission denied",
+            _ => "unknown implementation error",
+        }
+    }
+}
+
+impl Describe for SerialError {
+    fn describe(&self) -> &'static str {
+        match self {
+            SerialError::Overrun => "receive buffer overrun",
+            SerialError::FrameFormat => "framing error (missing stop bit)",
+            SerialError::Parity => "parity error",
+            SerialError::Noise => "noise detected",
+            SerialError::Impl(e) => e.describe(),
+            _ => "unknown serial error",
+        }
+    }
+}
+
+impl Describe for SpiError {
+    fn describe(&self) -> &'static str {
+        match self {
+            SpiError::Overrun => "receive buffer overrun",
+            SpiError::ModeFault => "mode fault",
+            SpiError::CRCError => "CRC mismatch",
+            SpiError::FrameFormat => "frame format error",
+            SpiError::Impl(e) => e.describe(),
+            _ => "unknown SPI error",
+        }
+    }
+}
+
+impl Describe for I2cError {
+    fn describe(&self) -> &'static str {
+        match self {
+            I2cError::Bus => "bus error",
+            I2cError::ArbitrationLoss => "arbitration lost",
+            I2cError::NACK => "not acknowledged",
+            I2cError::Overrun => "receive buffer overrun",
+            I2cError::Underrun => "transmit buffer underrun",
+            I2cError::PacketErrorChecking => "packet error checking mismatch",
+            I2cError::Timeout => "bus timeout",
+            I2cError::Alert => "SMBus alert",
+            I2cError::Impl(e) => e.describe(),
+            _ => "unknown I2C error",
+        }
+    }
+}
+
+impl Describe for HalfDuplexError {
+    fn describe(&self) -> &'static str {
+        match self {
+            HalfDuplexError::Collision => "bus collision",
+            HalfDuplexError::Serial(e) => e.describe(),
+        }
+    }
+}
+
+impl<E: Describe> fmt::Display for Decoded<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.describe())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<E: Describe> defmt::Format for Decoded<E> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=str}", self.0.describe())
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::i2c::Error for Decoded<I2cError> {
+    fn kind(&self) -> embedded_hal_1::i2c::ErrorKind {
+        use embedded_hal_1::i2c::{ErrorKind, NoAcknowledgeSource};
+        match self.0 {
+            I2cError::Bus => ErrorKind::Bus,
+            I2cError::ArbitrationLoss => ErrorKind::ArbitrationLoss,
+            // The driver does not track whether the address or a data byte
+            // was rejected.
+            I2cError::NACK => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            I2cError::Overrun => ErrorKind::Overrun,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::spi::Error for Decoded<SpiError> {
+    fn kind(&self) -> embedded_hal_1::spi::ErrorKind {
+        use embedded_hal_1::spi::ErrorKind;
+        match self.0 {
+            SpiError::Overrun => ErrorKind::Overrun,
+            SpiError::ModeFault => ErrorKind::ModeFault,
+            SpiError::FrameFormat => ErrorKind::FrameFormat,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "embedded-hal-1"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn i2c_error_kind() {
+        use embedded_hal_1::i2c::{Error, ErrorKind, NoAcknowledgeSource};
+        let kind = |e| Decoded(e).kind();
+        assert_eq!(kind(I2cError::Bus), ErrorKind::Bus);
+        assert_eq!(kind(I2cError::ArbitrationLoss), ErrorKind::ArbitrationLoss);
+        assert_eq!(
+            kind(I2cError::NACK),
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)
+        );
+        assert_eq!(kind(I2cError::Overrun), ErrorKind::Overrun);
+        assert_eq!(kind(I2cError::Underrun), ErrorKind::Other);
+        assert_eq!(kind(I2cError::PacketErrorChecking), ErrorKind::Other);
+        assert_eq!(kind(I2cError::Timeout), ErrorKind::Other);
+        assert_eq!(kind(I2cError::Alert), ErrorKind::Other);
+        assert_eq!(kind(I2cError::Impl(ImplError::TimedOut)), ErrorKind::Other);
+    }
+
+    #[test]
+    fn spi_error_kind() {
+        use embedded_hal_1::spi::{Error, ErrorKind};
+        let kind = |e| Decoded(e).kind();
+        assert_eq!(kind(SpiError::Overrun), ErrorKind::Overrun);
+        assert_eq!(kind(SpiError::ModeFault), ErrorKind::ModeFault);
+        assert_eq!(kind(SpiError::FrameFormat), ErrorKind::FrameFormat);
+        assert_eq!(kind(SpiError::CRCError), ErrorKind::Other);
+        assert_eq!(
+            kind(SpiError::Impl(ImplError::InvalidConfiguration)),
+            ErrorKind::Other
+        );
+    }
+}
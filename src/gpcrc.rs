@@ -0,0 +1,271 @@
+//! General purpose cyclic redundancy check (GPCRC) API
+//!
+//! The GPCRC processes each input byte least significant bit first, which
+//! matches the reflected CRC algorithms of the [`Preset`]s. Other algorithms
+//! are described by a [`Config`]: the hardware reverses the bits of each
+//! input byte for algorithms that process the most significant bit first and
+//! the result is read bit reversed when the output is not reflected. The
+//! polynomial is fixed to `0x04C11DB7` for 32-bit CRCs and programmable for
+//! 16-bit CRCs. [`Gpcrc::feed()`] writes whole words where possible, the
+//! hardware processes one byte per clock cycle.
+//!
+//! CRC values are transmitted least significant byte first, e.g. appended to
+//! a Modbus RTU frame. Feeding a frame including its appended CRC leaves a
+//! fixed residue, which [`Gpcrc::is_valid()`] checks.
+use crate::{cmu::Cmu, pac::GPCRC};
+
+/// CRC algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// CRC-32 as used by Ethernet and zlib (polynomial `0x04C11DB7`, initial
+    /// value `0xFFFFFFFF`, final XOR `0xFFFFFFFF`).
+    Crc32,
+    /// CRC-16/MODBUS (polynomial `0x8005`, initial value `0xFFFF`, no final
+    /// XOR).
+    Crc16Modbus,
+}
+
+impl Preset {
+    /// Number of bytes of the CRC value.
+    pub fn width(self) -> usize {
+        match self {
+            Preset::Crc32 => 4,
+            Preset::Crc16Modbus => 2,
+        }
+    }
+
+    fn mask(self) -> u32 {
+        match self {
+            Preset::Crc32 => 0xFFFF_FFFF,
+            Preset::Crc16Modbus => 0xFFFF,
+        }
+    }
+
+    // Register contents after feeding data followed by its CRC.
+    fn residue(self) -> u32 {
+        match self {
+            Preset::Crc32 => 0xDEBB_20E3,
+            Preset::Crc16Modbus => 0,
+        }
+    }
+}
+
+/// Width and polynomial of a CRC algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Polynomial {
+    /// 32-bit CRC with the polynomial `0x04C11DB7`.
+    Crc32,
+    /// 16-bit CRC with the given polynomial in normal notation, e.g. `0x1021`
+    /// for CRC-16/CCITT.
+    Crc16(u16),
+}
+
+/// Parameters of a CRC algorithm, as listed in CRC catalogues.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Width and polynomial.
+    pub polynomial: Polynomial,
+    /// Initial value of the calculation.
+    pub init: u32,
+    /// Processes each input byte least significant bit first.
+    pub reflect_input: bool,
+    /// Reflects the result before the final XOR.
+    pub reflect_output: bool,
+    /// Value XORed with the result.
+    pub final_xor: u32,
+}
+
+impl Config {
+    /// CRC-32 as used by Ethernet and zlib, same as [`Preset::Crc32`].
+    pub const fn crc32() -> Self {
+        Config {
+            polynomial: Polynomial::Crc32,
+            init: 0xFFFF_FFFF,
+            reflect_input: true,
+            reflect_output: true,
+            final_xor: 0xFFFF_FFFF,
+        }
+    }
+
+    /// CRC-16/CCITT-FALSE (polynomial `0x1021`, initial value `0xFFFF`, most
+    /// significant bit first, no final XOR), e.g. for XMODEM style framing.
+    pub const fn crc16_ccitt() -> Self {
+        Config {
+            polynomial: Polynomial::Crc16(0x1021),
+            init: 0xFFFF,
+            reflect_input: false,
+            reflect_output: false,
+            final_xor: 0,
+        }
+    }
+
+    fn width(&self) -> usize {
+        match self.polynomial {
+            Polynomial::Crc32 => 4,
+            Polynomial::Crc16(_) => 2,
+        }
+    }
+
+    fn mask(&self) -> u32 {
+        match self.polynomial {
+            Polynomial::Crc32 => 0xFFFF_FFFF,
+            Polynomial::Crc16(_) => 0xFFFF,
+        }
+    }
+}
+
+impl From<Preset> for Config {
+    fn from(preset: Preset) -> Self {
+        match preset {
+            Preset::Crc32 => Config::crc32(),
+            Preset::Crc16Modbus => Config {
+                polynomial: Polynomial::Crc16(0x8005),
+                init: 0xFFFF,
+                reflect_input: true,
+                reflect_output: true,
+                final_xor: 0,
+            },
+        }
+    }
+}
+
+/// GPCRC API
+pub struct Gpcrc {
+    raw: GPCRC,
+    config: Config,
+    preset: Option<Preset>,
+}
+
+impl Gpcrc {
+    /// Configures the GPCRC for `preset` and starts a new calculation.
+    pub fn new(gpcrc: GPCRC, preset: Preset, cmu: &mut Cmu) -> Self {
+        let mut gpcrc = Self::with_config(gpcrc, &preset.into(), cmu);
+        gpcrc.preset = Some(preset);
+        gpcrc
+    }
+
+    /// Configures the GPCRC for the algorithm described by `config` and
+    /// starts a new calculation.
+    pub fn with_config(gpcrc: GPCRC, config: &Config, cmu: &mut Cmu) -> Self {
+        cmu.enable_clock(&gpcrc);
+
+        gpcrc.ctrl.write(|w| {
+            w.en()
+                .set_bit()
+                .polysel()
+                .bit(matches!(config.polynomial, Polynomial::Crc16(_)))
+                .bitreverse()
+                .bit(!config.reflect_input)
+        });
+        if let Polynomial::Crc16(poly) = config.polynomial {
+            // The register expects the polynomial in reversed bit order.
+            gpcrc
+                .poly
+                .write(|w| unsafe { w.poly().bits(poly.reverse_bits()) });
+        }
+        // The register holds the reflected state of the calculation.
+        let init = match config.reflect_input {
+            true => config.init,
+            false => config.init.reverse_bits() >> (32 - 8 * config.width()),
+        };
+        gpcrc.init.write(|w| unsafe { w.init().bits(init) });
+
+        let mut gpcrc = Gpcrc {
+            raw: gpcrc,
+            config: *config,
+            preset: None,
+        };
+        gpcrc.reset();
+        gpcrc
+    }
+
+    /// Returns the configured preset, `None` when created from a [`Config`].
+    pub fn preset(&self) -> Option<Preset> {
+        self.preset
+    }
+
+    /// Returns the configured algorithm.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Number of bytes of the CRC value.
+    pub fn width(&self) -> usize {
+        self.config.width()
+    }
+
+    /// Starts a new calculation.
+    pub fn reset(&mut self) {
+        self.raw.cmd.write(|w| w.init().set_bit());
+    }
+
+    /// Adds a single byte to the calculation.
+    pub fn feed_byte(&mut self, byte: u8) {
+        self.raw
+            .inputdatabyte
+            .write(|w| unsafe { w.inputdatabyte().bits(byte) });
+    }
+
+    /// Adds all bytes of `data` to the calculation.
+    pub fn feed(&mut self, data: &[u8]) {
+        let mut words = data.chunks_exact(4);
+        for word in &mut words {
+            // A word is processed least significant byte first.
+            let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+            self.raw
+                .inputdata
+                .write(|w| unsafe { w.inputdata().bits(word) });
+        }
+        for &byte in words.remainder() {
+            self.feed_byte(byte);
+        }
+    }
+
+    /// Returns the CRC of the bytes fed since the last reset.
+    pub fn value(&self) -> u32 {
+        let value = match self.config.reflect_output {
+            true => self.raw.data.read().bits(),
+            false => self.raw.datarev.read().bits() >> (32 - 8 * self.width()),
+        };
+        (value & self.config.mask()) ^ self.config.final_xor
+    }
+
+    /// Returns `true` when the bytes fed since the last reset end with their
+    /// own CRC, least significant byte first.
+    ///
+    /// Only available for the [`Preset`]s, always `false` for a GPCRC created
+    /// from a [`Config`].
+    pub fn is_valid(&self) -> bool {
+        match self.preset {
+            Some(preset) => self.raw.data.read().bits() & preset.mask() == preset.residue(),
+            None => false,
+        }
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// a different polynomial makes `value()` and `is_valid()` meaningless.
+    pub unsafe fn raw(&self) -> &crate::pac::gpcrc::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Gpcrc::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut GPCRC {
+        &mut self.raw
+    }
+
+    /// Disables the GPCRC and returns the raw interface to the underlying
+    /// peripheral.
+    pub fn release(self) -> GPCRC {
+        self.raw.ctrl.reset();
+        self.raw
+    }
+}
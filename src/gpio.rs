@@ -1,4 +1,26 @@
 //! General purpose I/O (GPIO) pin API
+//!
+//! # Peripheral outputs
+//!
+//! A peripheral with an enabled route (`ROUTEPEN`) overrides the output value
+//! of the pin, the `DOUT` bit of the GPIO is ignored. The pin mode still
+//! applies, so a pin routed to a peripheral must be configured as output.
+//! When the route is disabled, e.g. after the peripheral driver released the
+//! peripheral, the pin falls back to `DOUT`. The initial state passed to the
+//! output builder methods therefore defines the idle level of a bus line
+//! while no peripheral drives it, e.g. `push_pull_output(PinState::High)` for
+//! an UART TX line or an SPI chip select. Lines left in disabled mode float
+//! instead. The builder methods take a [`PinState`] or a `bool`, where `true`
+//! means high.
+//!
+//! The output modes with the `alt` suffix, e.g.
+//! [`PinBuilder::push_pull_alt_output()`], use the alternate drive settings
+//! of the port, see [`set_port_drive()`] or [`PinBuilder::drive_strength()`]
+//! and [`PinBuilder::slew_rate()`]. This allows e.g. a weak driver with
+//! slow edges for a long cable next to fast SPI lines on the same port.
+pub use crate::hal::digital::v2::PinState;
+pub use crate::pac::gpio::pa_model::MODE0_A as PinMode;
+
 use crate::{
     cmu::Cmu,
     hal::digital::v2::{InputPin, OutputPin, StatefulOutputPin, ToggleableOutputPin},
@@ -6,27 +28,84 @@ use crate::{
         // Each GPIO has it’s own enum for the mode field even though the values are
         // shared by all GPIOs. Import the first to have nice names for the numeric
         // constants and use the provided u8 conversion to erase type information.
-        gpio::pa_model::MODE0_A as MODE,
+        emu::em4ctrl::EM4IORETMODE_A,
+        gpio::{pa_model::MODE0_A as MODE, PA_CTRL},
+        Interrupt,
+        CMU,
+        EMU,
         GPIO,
     },
+    prs::{self, PrsSource},
     util::PeripheralClearSetExt,
 };
-use core::{convert::Infallible, marker::PhantomData};
+use core::{
+    convert::Infallible,
+    marker::PhantomData,
+    sync::atomic::{AtomicU16, Ordering},
+};
 
 /// Internal trait to abstract away raw register manipulation.
 /// Leaked because it is used as trait bound. Not relevant for the user.
 pub trait PinTrait {
     fn clear_mode(&mut self);
     fn set_mode(&mut self, mode: MODE);
+    fn read_mode(&self) -> MODE;
     fn clear_dout_bit(&mut self);
     fn set_dout_bit(&mut self);
     fn read_dout_bit(&self) -> bool;
     fn write_douttgl_bit(&mut self);
     fn read_din_bit(&self) -> bool;
+    fn port_index(&self) -> usize;
+    fn pin_nr(&self) -> u8;
+}
+
+// Distance between the register sets of two ports.
+const PORT_STRIDE: usize = 0x30;
+// Offsets of the mode registers within the register set of a port, for pins
+// 0 to 7 and 8 to 15.
+const MODEL_OFFSET: usize = 0x04;
+const MODEH_OFFSET: usize = 0x08;
+// Offsets of the data out registers within the register set of a port.
+const DOUT_OFFSET: usize = 0x0C;
+const DOUTTGL_OFFSET: usize = 0x18;
+const DIN_OFFSET: usize = 0x1C;
+
+// Catches pin operations while the GPIO clock is disabled, which happens when
+// pins are created from a stolen peripheral without `Gpio::new()`. Register
+// writes are silently ignored in that state. Compiled out in release builds.
+#[inline(always)]
+fn debug_assert_clock_enabled() {
+    debug_assert!(
+        unsafe { (*CMU::ptr()).hfbusclken0.read().gpio().bit_is_set() },
+        "GPIO clock disabled (CMU.HFBUSCLKEN0.GPIO), create the pins with `Gpio::new()` or `GpioExt::split()`"
+    );
+}
+
+// Converts the raw value of a mode field back to the shared enum.
+fn mode_from_bits(bits: u8) -> MODE {
+    match bits & 0xF {
+        0 => MODE::DISABLED,
+        1 => MODE::INPUT,
+        2 => MODE::INPUTPULL,
+        3 => MODE::INPUTPULLFILTER,
+        4 => MODE::PUSHPULL,
+        5 => MODE::PUSHPULLALT,
+        6 => MODE::WIREDOR,
+        7 => MODE::WIREDORPULLDOWN,
+        8 => MODE::WIREDAND,
+        9 => MODE::WIREDANDFILTER,
+        10 => MODE::WIREDANDPULLUP,
+        11 => MODE::WIREDANDPULLUPFILTER,
+        12 => MODE::WIREDANDALT,
+        13 => MODE::WIREDANDALTFILTER,
+        14 => MODE::WIREDANDALTPULLUP,
+        _ => MODE::WIREDANDALTPULLUPFILTER,
+    }
 }
 
 macro_rules! gpios {
     ($(
+        $(#[$attr:meta])*
         $field:ident,
         $type:ident,
         $mode_reg:ident,
@@ -37,8 +116,22 @@ macro_rules! gpios {
         $pin_nr:expr;
     )*) => {
         /// Contains a field for each individual GPIO pin.
+        ///
+        /// Created with [`Gpio::new()`] or, equivalently, with
+        /// [`GpioExt::split()`] on the raw peripheral.
+        ///
+        /// Pins not bonded out on the package selected by the crate features
+        /// are omitted.
+        ///
+        /// In debug builds all pin operations panic when the GPIO clock is
+        /// disabled, e.g. because the pins were obtained from a stolen
+        /// peripheral and the clock was never enabled. The check is omitted
+        /// in release builds.
         pub struct Gpio {
-            $(pub $field: PinBuilder<$type, Floating, NoFilter>,)*
+            $(
+                $(#[$attr])*
+                pub $field: PinBuilder<$type, Floating, NoFilter>,
+            )*
         }
 
         impl Gpio {
@@ -48,6 +141,7 @@ macro_rules! gpios {
 
                 Gpio {
                     $(
+                        $(#[$attr])*
                         $field: PinBuilder {
                             ty: $type,
                             _pull: PhantomData,
@@ -66,39 +160,61 @@ macro_rules! gpios {
 
             impl PinTrait for $type {
                 fn clear_mode(&mut self) {
+                    debug_assert_clock_enabled();
                     let mode_clear = unsafe { &(*GPIO::ptr_clear()).$mode_reg };
                     mode_clear.write_with_zero(|w| w.$mode_field().bits(!0));
                 }
 
                 fn set_mode(&mut self, mode: MODE) {
+                    debug_assert_clock_enabled();
                     let mode_set = unsafe { &(*GPIO::ptr_set()).$mode_reg };
                     mode_set.write_with_zero(|w| w.$mode_field().bits(mode.into()));
                 }
 
+                fn read_mode(&self) -> MODE {
+                    debug_assert_clock_enabled();
+                    let mode_reg = unsafe { &(*GPIO::ptr()).$mode_reg };
+                    mode_from_bits(mode_reg.read().$mode_field().bits())
+                }
+
                 fn clear_dout_bit(&mut self) {
+                    debug_assert_clock_enabled();
                     let dout_clear = unsafe { &(*GPIO::ptr_clear()).$dout_reg };
                     dout_clear.write_with_zero(|w| unsafe { w.bits(1 << $pin_nr) });
                 }
 
                 fn set_dout_bit(&mut self) {
+                    debug_assert_clock_enabled();
                     let dout_set = unsafe { &(*GPIO::ptr_set()).$dout_reg };
                     dout_set.write_with_zero(|w| unsafe { w.bits(1 << $pin_nr) });
                 }
 
                 fn read_dout_bit(&self) -> bool {
+                    debug_assert_clock_enabled();
                     let dout_reg = unsafe { &(*GPIO::ptr()).$dout_reg };
                     dout_reg.read().bits() & (1 << $pin_nr) == 1 << $pin_nr
                 }
 
                 fn write_douttgl_bit(&mut self) {
+                    debug_assert_clock_enabled();
                     let douttgl = unsafe { &(*GPIO::ptr()).$douttgl_reg };
                     douttgl.write(|w| unsafe { w.bits(1 << $pin_nr) });
                 }
 
                 fn read_din_bit(&self) -> bool {
+                    debug_assert_clock_enabled();
                     let din_reg = unsafe { &(*GPIO::ptr()).$din_reg };
                     din_reg.read().bits() & (1 << $pin_nr) == 1 << $pin_nr
                 }
+
+                fn port_index(&self) -> usize {
+                    let dout_reg = unsafe { &(*GPIO::ptr()).$dout_reg };
+                    (dout_reg as *const _ as usize - GPIO::ptr() as usize) / PORT_STRIDE
+                }
+
+                fn pin_nr(&self) -> u8 {
+                    $pin_nr
+                }
             }
         )*
     }
@@ -111,15 +227,24 @@ gpios!(
     pa3, PA3, pa_model, mode3, pa_dout, pa_douttgl, pa_din, 3;
     pa4, PA4, pa_model, mode4, pa_dout, pa_douttgl, pa_din, 4;
     pa5, PA5, pa_model, mode5, pa_dout, pa_douttgl, pa_din, 5;
+    #[cfg(feature = "bga125")]
     pa6, PA6, pa_model, mode6, pa_dout, pa_douttgl, pa_din, 6;
+    #[cfg(feature = "bga125")]
     pa7, PA7, pa_model, mode7, pa_dout, pa_douttgl, pa_din, 7;
+    #[cfg(feature = "bga125")]
     pa8, PA8, pa_modeh, mode8, pa_dout, pa_douttgl, pa_din, 8;
+    #[cfg(feature = "bga125")]
     pa9, PA9, pa_modeh, mode9, pa_dout, pa_douttgl, pa_din, 9;
 
+    #[cfg(feature = "bga125")]
     pb6, PB6, pb_model, mode6, pb_dout, pb_douttgl, pb_din, 6;
+    #[cfg(feature = "bga125")]
     pb7, PB7, pb_model, mode7, pb_dout, pb_douttgl, pb_din, 7;
+    #[cfg(feature = "bga125")]
     pb8, PB8, pb_modeh, mode8, pb_dout, pb_douttgl, pb_din, 8;
+    #[cfg(feature = "bga125")]
     pb9, PB9, pb_modeh, mode9, pb_dout, pb_douttgl, pb_din, 9;
+    #[cfg(feature = "bga125")]
     pb10, PB10, pb_modeh, mode10, pb_dout, pb_douttgl, pb_din, 10;
     pb11, PB11, pb_modeh, mode11, pb_dout, pb_douttgl, pb_din, 11;
     pb12, PB12, pb_modeh, mode12, pb_dout, pb_douttgl, pb_din, 12;
@@ -127,11 +252,17 @@ gpios!(
     pb14, PB14, pb_modeh, mode14, pb_dout, pb_douttgl, pb_din, 14;
     pb15, PB15, pb_modeh, mode15, pb_dout, pb_douttgl, pb_din, 15;
 
+    #[cfg(feature = "bga125")]
     pc0, PC0, pc_model, mode0, pc_dout, pc_douttgl, pc_din, 0;
+    #[cfg(feature = "bga125")]
     pc1, PC1, pc_model, mode1, pc_dout, pc_douttgl, pc_din, 1;
+    #[cfg(feature = "bga125")]
     pc2, PC2, pc_model, mode2, pc_dout, pc_douttgl, pc_din, 2;
+    #[cfg(feature = "bga125")]
     pc3, PC3, pc_model, mode3, pc_dout, pc_douttgl, pc_din, 3;
+    #[cfg(feature = "bga125")]
     pc4, PC4, pc_model, mode4, pc_dout, pc_douttgl, pc_din, 4;
+    #[cfg(feature = "bga125")]
     pc5, PC5, pc_model, mode5, pc_dout, pc_douttgl, pc_din, 5;
     pc6, PC6, pc_model, mode6, pc_dout, pc_douttgl, pc_din, 6;
     pc7, PC7, pc_model, mode7, pc_dout, pc_douttgl, pc_din, 7;
@@ -140,6 +271,7 @@ gpios!(
     pc10, PC10, pc_modeh, mode10, pc_dout, pc_douttgl, pc_din, 10;
     pc11, PC11, pc_modeh, mode11, pc_dout, pc_douttgl, pc_din, 11;
 
+    #[cfg(feature = "bga125")]
     pd8, PD8, pd_modeh, mode8, pd_dout, pd_douttgl, pd_din, 8;
     pd9, PD9, pd_modeh, mode9, pd_dout, pd_douttgl, pd_din, 9;
     pd10, PD10, pd_modeh, mode10, pd_dout, pd_douttgl, pd_din, 10;
@@ -162,28 +294,274 @@ gpios!(
     pf5, PF5, pf_model, mode5, pf_dout, pf_douttgl, pf_din, 5;
     pf6, PF6, pf_model, mode6, pf_dout, pf_douttgl, pf_din, 6;
     pf7, PF7, pf_model, mode7, pf_dout, pf_douttgl, pf_din, 7;
+    #[cfg(feature = "bga125")]
     pf8, PF8, pf_modeh, mode8, pf_dout, pf_douttgl, pf_din, 8;
+    #[cfg(feature = "bga125")]
     pf9, PF9, pf_modeh, mode9, pf_dout, pf_douttgl, pf_din, 9;
+    #[cfg(feature = "bga125")]
     pf10, PF10, pf_modeh, mode10, pf_dout, pf_douttgl, pf_din, 10;
+    #[cfg(feature = "bga125")]
     pf11, PF11, pf_modeh, mode11, pf_dout, pf_douttgl, pf_din, 11;
+    #[cfg(feature = "bga125")]
     pf12, PF12, pf_modeh, mode12, pf_dout, pf_douttgl, pf_din, 12;
+    #[cfg(feature = "bga125")]
     pf13, PF13, pf_modeh, mode13, pf_dout, pf_douttgl, pf_din, 13;
+    #[cfg(feature = "bga125")]
     pf14, PF14, pf_modeh, mode14, pf_dout, pf_douttgl, pf_din, 14;
+    #[cfg(feature = "bga125")]
     pf15, PF15, pf_modeh, mode15, pf_dout, pf_douttgl, pf_din, 15;
 
+    #[cfg(feature = "bga125")]
     pi0, PI0, pi_model, mode0, pi_dout, pi_douttgl, pi_din, 0;
+    #[cfg(feature = "bga125")]
     pi1, PI1, pi_model, mode1, pi_dout, pi_douttgl, pi_din, 1;
+    #[cfg(feature = "bga125")]
     pi2, PI2, pi_model, mode2, pi_dout, pi_douttgl, pi_din, 2;
+    #[cfg(feature = "bga125")]
     pi3, PI3, pi_model, mode3, pi_dout, pi_douttgl, pi_din, 3;
 
+    #[cfg(feature = "bga125")]
     pj14, PJ14, pj_modeh, mode14, pj_dout, pj_douttgl, pj_din, 14;
+    #[cfg(feature = "bga125")]
     pj15, PJ15, pj_modeh, mode15, pj_dout, pj_douttgl, pj_din, 15;
 
+    #[cfg(feature = "bga125")]
     pk0, PK0, pk_model, mode0, pk_dout, pk_douttgl, pk_din, 0;
+    #[cfg(feature = "bga125")]
     pk1, PK1, pk_model, mode1, pk_dout, pk_douttgl, pk_din, 1;
+    #[cfg(feature = "bga125")]
     pk2, PK2, pk_model, mode2, pk_dout, pk_douttgl, pk_din, 2;
 );
 
+/// GPIO port
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Port {
+    A,
+    B,
+    C,
+    D,
+    F,
+    I,
+    J,
+    K,
+}
+
+impl Port {
+    // Position of the register set of the port, ports E, G and H do not
+    // exist on this device.
+    fn index(self) -> usize {
+        match self {
+            Port::A => 0,
+            Port::B => 1,
+            Port::C => 2,
+            Port::D => 3,
+            Port::F => 5,
+            Port::I => 8,
+            Port::J => 9,
+            Port::K => 10,
+        }
+    }
+}
+
+/// Reads the input data register (`DIN`) of a port in a single access.
+///
+/// All 16 pins are sampled at the same instant. The result contains the level
+/// of every pin of the port, regardless of its mode or which part of the
+/// application owns it. Bits of disabled pins or pins not available on the
+/// package read as zero.
+pub fn read_port(port: Port) -> u16 {
+    debug_assert_clock_enabled();
+    let gpio = unsafe { &*GPIO::ptr() };
+    let din = match port {
+        Port::A => gpio.pa_din.read().bits(),
+        Port::B => gpio.pb_din.read().bits(),
+        Port::C => gpio.pc_din.read().bits(),
+        Port::D => gpio.pd_din.read().bits(),
+        Port::F => gpio.pf_din.read().bits(),
+        Port::I => gpio.pi_din.read().bits(),
+        Port::J => gpio.pj_din.read().bits(),
+        Port::K => gpio.pk_din.read().bits(),
+    };
+    din as u16
+}
+
+/// Reads the pins selected by `mask` of a port in a single access and shifts
+/// the result so that the lowest selected pin ends up in bit 0.
+///
+/// For example a data bus on `PC4`..`PC11` is read with
+/// `read_port_masked(Port::C, 0x0FF0)` and returns a value in the range
+/// `0..=0xFF`.
+pub fn read_port_masked(port: Port, mask: u16) -> u16 {
+    if mask == 0 {
+        return 0;
+    }
+    (read_port(port) & mask) >> mask.trailing_zeros()
+}
+
+/// Sets the pins selected by `mask` of a port to the bits of `value`, the
+/// counterpart of [`read_port_masked()`].
+///
+/// `value` is shifted like the result of [`read_port_masked()`], i.e. bit 0
+/// of `value` goes to the lowest selected pin. For example
+/// `write_port_masked(Port::C, 0x0FF0, byte)` puts a byte on a data bus on
+/// `PC4`..`PC11`.
+///
+/// The pins are changed through the set and clear aliases of `DOUT`, so pins
+/// outside of `mask` are never written, even when an interrupt handler
+/// changes them concurrently. The pins to be set change with the first
+/// write, the pins to be cleared with the second one, one bus cycle later.
+///
+/// # Safety
+///
+/// The pins selected by `mask` must not be used through their [`Pin`] at the
+/// same time, e.g. by owning them in a struct that only calls this function.
+/// Pins that are not configured as outputs only change their pull direction
+/// or filter setting.
+pub unsafe fn write_port_masked(port: Port, mask: u16, value: u16) {
+    if mask == 0 {
+        return;
+    }
+    debug_assert_clock_enabled();
+    let value = (value << mask.trailing_zeros()) & mask;
+    let offset = port.index() * PORT_STRIDE + DOUT_OFFSET;
+    let set = GPIO::ptr_set() as usize + offset;
+    let clear = GPIO::ptr_clear() as usize + offset;
+    core::ptr::write_volatile(set as *mut u32, value as u32);
+    core::ptr::write_volatile(clear as *mut u32, (!value & mask) as u32);
+}
+
+// Value of `GPIO_LOCK` that unlocks the configuration, any other value locks.
+const UNLOCK_KEY: u16 = 0xA534;
+
+/// Locks the GPIO configuration registers against writes.
+///
+/// Protects the pin modes (`Px_MODEL`, `Px_MODEH`), the port control
+/// registers, the external interrupt pin selection and the debug pin routing
+/// against a runaway pointer. Writes to those registers are ignored while
+/// locked, so [`Pin::reset()`], the [`PinBuilder`] finalization methods,
+/// [`set_port_drive()`], [`PinBuilder::drive_strength()`] and
+/// [`PinBuilder::slew_rate()`] silently no longer take effect. Output levels
+/// (`DOUT`) are not locked and keep working.
+///
+/// The lock holds until [`unlock_configuration()`] or the next reset.
+pub fn lock_configuration() {
+    debug_assert_clock_enabled();
+    let gpio = unsafe { &*GPIO::ptr() };
+    gpio.lock.write(|w| w.lockkey().locked());
+}
+
+/// Unlocks the GPIO configuration registers, see [`lock_configuration()`].
+pub fn unlock_configuration() {
+    debug_assert_clock_enabled();
+    let gpio = unsafe { &*GPIO::ptr() };
+    gpio.lock.write(|w| unsafe { w.lockkey().bits(UNLOCK_KEY) });
+}
+
+/// Returns `true` while the GPIO configuration registers are locked.
+pub fn is_configuration_locked() -> bool {
+    let gpio = unsafe { &*GPIO::ptr() };
+    gpio.lock.read().lockkey().bits() != 0
+}
+
+/// Selects which of the two drive settings of a port to configure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DriveSet {
+    /// Used by the regular output modes.
+    Primary,
+    /// Used by the output modes with the `alt` suffix.
+    Alternate,
+}
+
+/// Output driver settings of a port.
+///
+/// Defaults to the reset values: strong drive strength, slew rate 5 and
+/// digital input enabled.
+pub struct DriveConfig {
+    /// Limits the drive current to about 1 mA instead of 10 mA.
+    pub weak: bool,
+    /// Slew rate of the output edges, `0..=7`. Higher values give faster
+    /// edges.
+    pub slew_rate: u8,
+    /// Disables the digital input of the pins using this set, e.g. to save
+    /// power on pins only used as outputs.
+    pub input_disabled: bool,
+}
+
+impl Default for DriveConfig {
+    fn default() -> Self {
+        Self {
+            weak: false,
+            slew_rate: 5,
+            input_disabled: false,
+        }
+    }
+}
+
+/// Configures the primary or alternate drive settings of a port
+/// (`GPIO_Px_CTRL`).
+///
+/// The settings are shared by all pins of the port that use the respective
+/// set, regardless of which part of the application owns them.
+///
+/// Panics when the slew rate is out of range.
+pub fn set_port_drive(port: Port, set: DriveSet, config: &DriveConfig) {
+    assert!(config.slew_rate <= 7);
+    debug_assert_clock_enabled();
+
+    port_ctrl(port.index()).modify(|_, w| unsafe {
+        match set {
+            DriveSet::Primary => w
+                .drivestrength()
+                .bit(config.weak)
+                .slewrate()
+                .bits(config.slew_rate)
+                .dindis()
+                .bit(config.input_disabled),
+            DriveSet::Alternate => w
+                .drivestrengthalt()
+                .bit(config.weak)
+                .slewratealt()
+                .bits(config.slew_rate)
+                .dindisalt()
+                .bit(config.input_disabled),
+        }
+    });
+}
+
+// The control register is the first register of each port.
+fn port_ctrl(index: usize) -> &'static PA_CTRL {
+    unsafe { &*((GPIO::ptr() as usize + index * PORT_STRIDE) as *const PA_CTRL) }
+}
+
+/// Drive strength of one of the two drive sets of a port, see
+/// [`PinBuilder::drive_strength()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DriveStrength {
+    /// About 10 mA for the regular output modes, the reset value.
+    Strong,
+    /// About 1 mA for the regular output modes.
+    Weak,
+    /// About 10 mA for the output modes with the `alt` suffix, the reset
+    /// value.
+    StrongAlt,
+    /// About 1 mA for the output modes with the `alt` suffix.
+    WeakAlt,
+}
+
+/// Extension trait to split the GPIO peripheral into individual pins.
+pub trait GpioExt {
+    /// Enables the GPIO clock and returns a field for each pin.
+    ///
+    /// Same as [`Gpio::new()`].
+    fn split(self, cmu: &mut Cmu) -> Gpio;
+}
+
+impl GpioExt for GPIO {
+    fn split(self, cmu: &mut Cmu) -> Gpio {
+        Gpio::new(self, cmu)
+    }
+}
+
 /// Implemented by types that indicate an GPIO mode.
 ///
 /// Used as trait bound by the [`Pin`] type.
@@ -212,6 +590,15 @@ pub struct Pin<T: PinTrait, M: Mode> {
 }
 
 impl<T: PinTrait, M: Mode> Pin<T, M> {
+    /// Reads back the mode currently configured in hardware.
+    ///
+    /// Reflects the actual register contents independent of the type state
+    /// `M`. Both should always agree, this method is meant to diagnose cases
+    /// where they do not.
+    pub fn mode(&self) -> PinMode {
+        self.ty.read_mode()
+    }
+
     /// Disables the pin and returns a builder.
     pub fn reset(mut self) -> PinBuilder<T, Floating, NoFilter> {
         self.ty.clear_mode();
@@ -230,8 +617,9 @@ impl<T: PinTrait, M: Mode> Pin<T, M> {
     }
 }
 
-// Use a private module to hide those types from the documentation.
-use builder_types::*;
+// Use a private module to hide those types from the documentation. The
+// drivers that finalize builders themselves name them within the crate.
+pub(crate) use builder_types::*;
 mod builder_types {
     /// Internal trait implemented by types that indicate a pull-up or pull-down
     /// resistor configuration.
@@ -261,6 +649,29 @@ mod builder_types {
 ///
 /// Finalization methods like `input()` or `PinBuilder::push_pull_output()` are
 /// only implemented for configurations that are supported by the hardware.
+/// Calling one in any other builder state fails with "method not found". The
+/// modes exist in these combinations:
+///
+/// | Mode            | Floating | Pull-up | Pull-down | Filter | Pull-up and filter | Pull-down and filter |
+/// |-----------------|:--------:|:-------:|:---------:|:------:|:------------------:|:--------------------:|
+/// | disabled        | ✓        | ✓       |           |        |                    |                      |
+/// | input           | ✓        | ✓       | ✓         | ✓      | ✓                  | ✓                    |
+/// | push-pull       | ✓        |         |           |        |                    |                      |
+/// | open-drain      | ✓        | ✓       |           | ✓      | ✓                  |                      |
+/// | open-source     | ✓        |         | ✓         |        |                    |                      |
+///
+/// The disabled mode (`DISABLED`) turns off the input buffer, so there is
+/// nothing to filter, and the `DOUT` bit only switches a pull-up on. A pin
+/// that must be held low while disabled, e.g. during EM4 with the pin
+/// retention, needs the pull-down input mode instead. The filter of the
+/// input modes is available with any pull configuration.
+///
+/// Open-drain (wired-AND) outputs only drive low, their pull resistor and
+/// filter therefore hold and clean up the released high level. Open-source
+/// (wired-OR) outputs only drive high and the hardware mirrors this with a
+/// pull-down as the only option. It has no filtered open-source mode and a
+/// pull-up would keep a released open-source output high, so these builder
+/// states have no `open_source_output()`.
 ///
 /// This can be obtained by accessing a field of [`Gpio`] or by calling
 /// [`Pin::reset()`] on an existing pin.
@@ -301,6 +712,21 @@ impl<T: PinTrait, P: PullTrait, F: FilterTrait> PinBuilder<T, P, F> {
     }
 
     /// Enables the glitch filter on the input circuitry.
+    ///
+    /// The filter is an analog circuit in the pad with a fixed width of a few
+    /// tens of nanoseconds, see the pulse width of the glitch suppression
+    /// filter in the datasheet. Unlike on other EFM32 families there is no
+    /// filter clock or duration setting on this device, neither per pin nor
+    /// per port (`GPIO_Px_CTRL` only holds drive strength, slew rate and
+    /// input disable settings). It rejects noise spikes, but mechanical
+    /// contacts bounce for milliseconds and require debouncing in software or
+    /// with a timer.
+    ///
+    /// The filter sits in front of the `DIN` register, so [`InputPin`] reads
+    /// of a filtered pin return the filtered level. The hardware has no
+    /// separate register with the unfiltered level. To check whether a
+    /// filtered input sees spikes, reconfigure it without the filter through
+    /// [`Pin::reset()`] and compare.
     pub fn filter(self) -> PinBuilder<T, P, Filter> {
         PinBuilder {
             ty: self.ty,
@@ -317,6 +743,51 @@ impl<T: PinTrait, P: PullTrait, F: FilterTrait> PinBuilder<T, P, F> {
             _filter: PhantomData,
         }
     }
+
+    /// Sets the drive strength of the port of this pin.
+    ///
+    /// The drive strength is a port setting, not a pin setting: `Strong` and
+    /// `Weak` apply to all pins of the port in the regular output modes,
+    /// `StrongAlt` and `WeakAlt` to all pins of the port in the output modes
+    /// with the `alt` suffix, e.g. [`PinBuilder::push_pull_alt_output()`].
+    /// Pins of the port configured before or by other parts of the
+    /// application change their drive strength as well. Set it once per port
+    /// during initialization, or use [`set_port_drive()`] for all drive
+    /// settings at once.
+    pub fn drive_strength(self, strength: DriveStrength) -> Self {
+        debug_assert_clock_enabled();
+        port_ctrl(self.ty.port_index()).modify(|_, w| match strength {
+            DriveStrength::Strong => w.drivestrength().clear_bit(),
+            DriveStrength::Weak => w.drivestrength().set_bit(),
+            DriveStrength::StrongAlt => w.drivestrengthalt().clear_bit(),
+            DriveStrength::WeakAlt => w.drivestrengthalt().set_bit(),
+        });
+        self
+    }
+
+    /// Sets the slew rate of the port of this pin, `0..=7` (`SLEWRATE` or
+    /// `SLEWRATEALT` in `GPIO_Px_CTRL`).
+    ///
+    /// Lower values give slower edges, which reduces emissions and ringing on
+    /// long traces at the cost of the maximum toggle rate. The reset value is
+    /// 5. Like the drive strength this is a port setting: `DriveSet::Primary`
+    /// applies to all pins of the port in the regular output modes,
+    /// `DriveSet::Alternate` to all pins of the port in the output modes with
+    /// the `alt` suffix, including pins configured by other parts of the
+    /// application.
+    ///
+    /// Panics when the slew rate is out of range.
+    pub fn slew_rate(self, set: DriveSet, slew_rate: u8) -> Self {
+        assert!(slew_rate <= 7);
+        debug_assert_clock_enabled();
+        port_ctrl(self.ty.port_index()).modify(|_, w| unsafe {
+            match set {
+                DriveSet::Primary => w.slewrate().bits(slew_rate),
+                DriveSet::Alternate => w.slewratealt().bits(slew_rate),
+            }
+        });
+        self
+    }
 }
 
 impl<T: PinTrait> PinBuilder<T, Floating, NoFilter> {
@@ -330,7 +801,8 @@ impl<T: PinTrait> PinBuilder<T, Floating, NoFilter> {
 }
 
 impl<T: PinTrait> PinBuilder<T, PullUp, NoFilter> {
-    /// Disables the digital input and output circuitry for this pin.
+    /// Disables the digital input and output circuitry for this pin and keeps
+    /// the pull-up resistor enabled.
     pub fn disabled(mut self) -> Pin<T, Disabled> {
         self.ty.set_dout_bit();
 
@@ -420,8 +892,8 @@ impl<T: PinTrait> PinBuilder<T, PullUp, Filter> {
 
 impl<T: PinTrait> PinBuilder<T, Floating, NoFilter> {
     /// Configures this pin as push-pull output.
-    pub fn push_pull_output(mut self, state: bool) -> Pin<T, Output> {
-        if state {
+    pub fn push_pull_output(mut self, state: impl Into<PinState>) -> Pin<T, Output> {
+        if state.into() == PinState::High {
             self.ty.set_dout_bit();
         }
         self.ty.set_mode(MODE::PUSHPULL);
@@ -434,10 +906,27 @@ impl<T: PinTrait> PinBuilder<T, Floating, NoFilter> {
 }
 
 impl<T: PinTrait> PinBuilder<T, Floating, NoFilter> {
-    /// Configures this pin as open-source output.
-    pub fn open_source_output(mut self, state: bool) -> Pin<T, Output> {
+    /// Configures this pin as push-pull output with the alternate drive
+    /// settings of the port.
+    pub fn push_pull_alt_output(mut self, state: impl Into<PinState>) -> Pin<T, Output> {
+        if state.into() == PinState::High {
+            self.ty.set_dout_bit();
+        }
+        self.ty.set_mode(MODE::PUSHPULLALT);
+
+        Pin {
+            ty: self.ty,
+            _mode: PhantomData,
+        }
+    }
+}
+
+impl<T: PinTrait> PinBuilder<T, Floating, NoFilter> {
+    /// Configures this pin as open-source output, which drives high and
+    /// floats when low.
+    pub fn open_source_output(mut self, state: impl Into<PinState>) -> Pin<T, Output> {
         self.ty.set_mode(MODE::WIREDOR);
-        if state {
+        if state.into() == PinState::High {
             self.ty.set_dout_bit();
         }
 
@@ -449,10 +938,11 @@ impl<T: PinTrait> PinBuilder<T, Floating, NoFilter> {
 }
 
 impl<T: PinTrait> PinBuilder<T, PullDown, NoFilter> {
-    /// Configures this pin as open-source output.
-    pub fn open_source_output(mut self, state: bool) -> Pin<T, Output> {
+    /// Configures this pin as open-source output, which drives high and is
+    /// pulled down when low.
+    pub fn open_source_output(mut self, state: impl Into<PinState>) -> Pin<T, Output> {
         self.ty.set_mode(MODE::WIREDORPULLDOWN);
-        if state {
+        if state.into() == PinState::High {
             self.ty.set_dout_bit();
         }
 
@@ -465,8 +955,8 @@ impl<T: PinTrait> PinBuilder<T, PullDown, NoFilter> {
 
 impl<T: PinTrait> PinBuilder<T, Floating, NoFilter> {
     /// Configures this pin as open-drain output.
-    pub fn open_drain_output(mut self, state: bool) -> Pin<T, Output> {
-        if state {
+    pub fn open_drain_output(mut self, state: impl Into<PinState>) -> Pin<T, Output> {
+        if state.into() == PinState::High {
             self.ty.set_dout_bit();
         }
         self.ty.set_mode(MODE::WIREDAND);
@@ -480,8 +970,8 @@ impl<T: PinTrait> PinBuilder<T, Floating, NoFilter> {
 
 impl<T: PinTrait> PinBuilder<T, Floating, Filter> {
     /// Configures this pin as open-drain output.
-    pub fn open_drain_output(mut self, state: bool) -> Pin<T, Output> {
-        if state {
+    pub fn open_drain_output(mut self, state: impl Into<PinState>) -> Pin<T, Output> {
+        if state.into() == PinState::High {
             self.ty.set_dout_bit();
         }
         self.ty.set_mode(MODE::WIREDANDFILTER);
@@ -495,8 +985,8 @@ impl<T: PinTrait> PinBuilder<T, Floating, Filter> {
 
 impl<T: PinTrait> PinBuilder<T, PullUp, NoFilter> {
     /// Configures this pin as open-drain output.
-    pub fn open_drain_output(mut self, state: bool) -> Pin<T, Output> {
-        if state {
+    pub fn open_drain_output(mut self, state: impl Into<PinState>) -> Pin<T, Output> {
+        if state.into() == PinState::High {
             self.ty.set_dout_bit();
         }
         self.ty.set_mode(MODE::WIREDANDPULLUP);
@@ -510,8 +1000,8 @@ impl<T: PinTrait> PinBuilder<T, PullUp, NoFilter> {
 
 impl<T: PinTrait> PinBuilder<T, PullUp, Filter> {
     /// Configures this pin as open-drain output.
-    pub fn open_drain_output(mut self, state: bool) -> Pin<T, Output> {
-        if state {
+    pub fn open_drain_output(mut self, state: impl Into<PinState>) -> Pin<T, Output> {
+        if state.into() == PinState::High {
             self.ty.set_dout_bit();
         }
         self.ty.set_mode(MODE::WIREDANDPULLUPFILTER);
@@ -529,6 +1019,24 @@ pub trait InputAvailable {}
 impl InputAvailable for Input {}
 impl InputAvailable for Output {}
 
+/// Reads the `DIN` register, which reflects the level after the glitch
+/// filter when the filter is enabled, see [`PinBuilder::filter()`].
+///
+/// For an output this is the physical level of the line, not the value
+/// written to `DOUT` that [`StatefulOutputPin`] returns. Both differ when
+/// another device pulls a wired-AND line low while an open-drain output
+/// releases it, which detects bus collisions and clock stretching:
+///
+/// ```ignore
+/// let mut line = gpio.pa2.pull_up().open_drain_output(PinState::High);
+/// line.set_high()?;
+/// if line.is_low()? {
+///     // Another device holds the line low.
+/// }
+/// ```
+///
+/// Reads return low while the digital input of the pins is disabled with
+/// [`DriveConfig::input_disabled`].
 impl<T, M> InputPin for Pin<T, M>
 where
     T: PinTrait,
@@ -557,8 +1065,18 @@ impl<T: PinTrait> OutputPin for Pin<T, Output> {
         self.ty.set_dout_bit();
         Ok(())
     }
+
+    fn set_state(&mut self, state: PinState) -> Result<(), Self::Error> {
+        match state {
+            PinState::Low => self.ty.clear_dout_bit(),
+            PinState::High => self.ty.set_dout_bit(),
+        }
+        Ok(())
+    }
 }
 
+/// Reads the `DOUT` register, i.e. the level the pin is set to, see
+/// [`InputPin`] for the physical level.
 impl<T: PinTrait> StatefulOutputPin for Pin<T, Output> {
     fn is_set_low(&self) -> Result<bool, Self::Error> {
         Ok(!self.ty.read_dout_bit())
@@ -578,6 +1096,719 @@ impl<T: PinTrait> ToggleableOutputPin for Pin<T, Output> {
     }
 }
 
+impl<T: PinTrait, M: Mode> Pin<T, M> {
+    // Gives drivers that switch the pin mode at runtime, like the ACMP touch
+    // sensor, access to the registers. The pin has to be back in mode `M`
+    // before it is handed out again.
+    pub(crate) fn pin_trait_mut(&mut self) -> &mut T {
+        &mut self.ty
+    }
+}
+
+impl<T: PinTrait> Pin<T, Output> {
+    /// Latches the output level of all GPIO pins when entering EM4.
+    ///
+    /// Sets `EMU.EM4CTRL.EM4IORETMODE` to `SWUNLATCH`. The pads keep their
+    /// state through EM4 and after the wake-up reset until the retention is
+    /// removed with [`em4_unlatch()`]. Retention is a global setting and
+    /// affects every pin, not only this one. The held output draws the same
+    /// current as in active mode, e.g. through a pull-up or a driven load.
+    pub fn retain_in_em4(self, emu: &EMU) -> RetainedPin<T> {
+        emu.em4ctrl
+            .modify(|_, w| w.em4ioretmode().variant(EM4IORETMODE_A::SWUNLATCH));
+        RetainedPin { pin: self }
+    }
+}
+
+/// Output pin which holds its level through EM4.
+///
+/// Does not provide any methods to change the pin configuration to prevent
+/// glitches on the held line.
+pub struct RetainedPin<T: PinTrait> {
+    pin: Pin<T, Output>,
+}
+
+impl<T: PinTrait> RetainedPin<T> {
+    /// Disables EM4 retention and returns the output pin.
+    pub fn release(self, emu: &EMU) -> Pin<T, Output> {
+        emu.em4ctrl
+            .modify(|_, w| w.em4ioretmode().variant(EM4IORETMODE_A::DISABLE));
+        em4_unlatch(emu);
+        self.pin
+    }
+}
+
+impl<T: PinTrait> Pin<T, Output> {
+    /// Erases the pin type, e.g. to store pins of different ports in an
+    /// array.
+    ///
+    /// The mode of the pin is kept. There is no way back to the typed pin.
+    pub fn downgrade(self) -> AnyOutputPin {
+        AnyOutputPin {
+            pin: ErasedPin::new(&self.ty),
+        }
+    }
+}
+
+impl<T: PinTrait> Pin<T, Input> {
+    /// Erases the pin type, e.g. to store pins of different ports in an
+    /// array.
+    ///
+    /// The mode of the pin is kept. There is no way back to the typed pin.
+    pub fn downgrade(self) -> AnyInputPin {
+        AnyInputPin {
+            pin: ErasedPin::new(&self.ty),
+        }
+    }
+}
+
+// Port and number of a pin as runtime values. Accesses the same registers as
+// the typed pins, the output level is changed through the set, clear and
+// toggle aliases so that other pins of the port are never affected.
+#[derive(Debug)]
+struct ErasedPin {
+    port_index: u8,
+    pin_nr: u8,
+}
+
+impl ErasedPin {
+    fn new(pin: &impl PinTrait) -> Self {
+        ErasedPin {
+            port_index: pin.port_index() as u8,
+            pin_nr: pin.pin_nr(),
+        }
+    }
+
+    fn register(&self, base: *const crate::pac::gpio::RegisterBlock, offset: usize) -> *mut u32 {
+        debug_assert_clock_enabled();
+        (base as usize + self.port_index as usize * PORT_STRIDE + offset) as *mut u32
+    }
+
+    fn bit(&self) -> u32 {
+        1 << self.pin_nr
+    }
+
+    fn read_mode(&self) -> MODE {
+        let (offset, shift) = match self.pin_nr {
+            0..=7 => (MODEL_OFFSET, 4 * self.pin_nr),
+            _ => (MODEH_OFFSET, 4 * (self.pin_nr - 8)),
+        };
+        let mode = self.register(GPIO::ptr(), offset);
+        mode_from_bits((unsafe { core::ptr::read_volatile(mode) } >> shift) as u8)
+    }
+
+    fn read_din_bit(&self) -> bool {
+        let din = self.register(GPIO::ptr(), DIN_OFFSET);
+        unsafe { core::ptr::read_volatile(din) & self.bit() != 0 }
+    }
+
+    fn read_dout_bit(&self) -> bool {
+        let dout = self.register(GPIO::ptr(), DOUT_OFFSET);
+        unsafe { core::ptr::read_volatile(dout) & self.bit() != 0 }
+    }
+
+    fn set_dout_bit(&mut self) {
+        let dout_set = self.register(GPIO::ptr_set(), DOUT_OFFSET);
+        unsafe { core::ptr::write_volatile(dout_set, self.bit()) };
+    }
+
+    fn clear_dout_bit(&mut self) {
+        let dout_clear = self.register(GPIO::ptr_clear(), DOUT_OFFSET);
+        unsafe { core::ptr::write_volatile(dout_clear, self.bit()) };
+    }
+
+    fn write_douttgl_bit(&mut self) {
+        let douttgl = self.register(GPIO::ptr(), DOUTTGL_OFFSET);
+        unsafe { core::ptr::write_volatile(douttgl, self.bit()) };
+    }
+}
+
+/// Output pin with its port and number stored at runtime.
+///
+/// Created by [`Pin::downgrade()`]. Slightly slower than the typed pin,
+/// which resolves the registers at compile time.
+#[derive(Debug)]
+pub struct AnyOutputPin {
+    pin: ErasedPin,
+}
+
+impl AnyOutputPin {
+    /// Returns the pin number within the port (`0..=15`).
+    pub fn pin_nr(&self) -> u8 {
+        self.pin.pin_nr
+    }
+
+    /// Reads back the mode currently configured in hardware, see
+    /// [`Pin::mode()`].
+    pub fn mode(&self) -> PinMode {
+        self.pin.read_mode()
+    }
+}
+
+/// Reads the `DIN` register, i.e. the physical level like [`Pin`].
+impl InputPin for AnyOutputPin {
+    type Error = Infallible;
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.pin.read_din_bit())
+    }
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.read_din_bit())
+    }
+}
+
+impl OutputPin for AnyOutputPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin.clear_dout_bit();
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_dout_bit();
+        Ok(())
+    }
+}
+
+/// Reads the `DOUT` register like [`Pin`].
+impl StatefulOutputPin for AnyOutputPin {
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.pin.read_dout_bit())
+    }
+
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.read_dout_bit())
+    }
+}
+
+impl ToggleableOutputPin for AnyOutputPin {
+    type Error = Infallible;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        self.pin.write_douttgl_bit();
+        Ok(())
+    }
+}
+
+/// Input pin with its port and number stored at runtime.
+///
+/// Created by [`Pin::downgrade()`].
+#[derive(Debug)]
+pub struct AnyInputPin {
+    pin: ErasedPin,
+}
+
+impl AnyInputPin {
+    /// Returns the pin number within the port (`0..=15`).
+    pub fn pin_nr(&self) -> u8 {
+        self.pin.pin_nr
+    }
+
+    /// Reads back the mode currently configured in hardware, see
+    /// [`Pin::mode()`].
+    pub fn mode(&self) -> PinMode {
+        self.pin.read_mode()
+    }
+}
+
+impl InputPin for AnyInputPin {
+    type Error = Infallible;
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.pin.read_din_bit())
+    }
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.read_din_bit())
+    }
+}
+
+macro_rules! logic_level_adapters {
+    ($(
+        $(#[$attr:meta])*
+        $name:ident, $invert:expr;
+    )*) => {
+        $(
+            $(#[$attr])*
+            pub struct $name<P> {
+                pin: P,
+            }
+
+            impl<P> $name<P> {
+                /// Wraps the pin.
+                pub fn new(pin: P) -> Self {
+                    $name { pin }
+                }
+
+                /// Returns the wrapped pin.
+                pub fn free(self) -> P {
+                    self.pin
+                }
+            }
+
+            impl<P: InputPin> InputPin for $name<P> {
+                type Error = P::Error;
+
+                fn is_high(&self) -> Result<bool, Self::Error> {
+                    self.pin.is_high().map(|high| high != $invert)
+                }
+
+                fn is_low(&self) -> Result<bool, Self::Error> {
+                    self.is_high().map(|high| !high)
+                }
+            }
+
+            impl<P: OutputPin> OutputPin for $name<P> {
+                type Error = P::Error;
+
+                fn set_high(&mut self) -> Result<(), Self::Error> {
+                    if $invert {
+                        self.pin.set_low()
+                    } else {
+                        self.pin.set_high()
+                    }
+                }
+
+                fn set_low(&mut self) -> Result<(), Self::Error> {
+                    if $invert {
+                        self.pin.set_high()
+                    } else {
+                        self.pin.set_low()
+                    }
+                }
+            }
+
+            impl<P: StatefulOutputPin> StatefulOutputPin for $name<P> {
+                fn is_set_high(&self) -> Result<bool, Self::Error> {
+                    self.pin.is_set_high().map(|high| high != $invert)
+                }
+
+                fn is_set_low(&self) -> Result<bool, Self::Error> {
+                    self.is_set_high().map(|high| !high)
+                }
+            }
+
+            impl<P: ToggleableOutputPin> ToggleableOutputPin for $name<P> {
+                type Error = P::Error;
+
+                fn toggle(&mut self) -> Result<(), Self::Error> {
+                    self.pin.toggle()
+                }
+            }
+        )*
+    };
+}
+
+logic_level_adapters!(
+    /// Input or output pin with inverted logic, e.g. for a LED or button
+    /// connected to the supply voltage.
+    ///
+    /// `set_high()` drives the pin low and `is_high()` returns `true` while
+    /// the pin is low, so that application code reads naturally, e.g.
+    /// `led.set_high()` turns the LED on. Only the logical sense is inverted,
+    /// the electrical configuration of the wrapped pin (pull resistors,
+    /// output mode, initial level) is not changed.
+    ActiveLow, true;
+
+    /// Input or output pin with regular logic.
+    ///
+    /// Counterpart of [`ActiveLow`] which passes all levels through
+    /// unchanged. Makes the active level of a signal explicit in its type.
+    ActiveHigh, false;
+);
+
+/// Removes the latched EM4 pad state after waking up from EM4.
+///
+/// Configure all retained pins to their previous state before calling this
+/// function to avoid glitches. Returns without effect when retention is not
+/// active (`EMU.STATUS.EM4IORET` is clear).
+pub fn em4_unlatch(emu: &EMU) {
+    if emu.status.read().em4ioret().bit_is_set() {
+        emu.cmd.write(|w| w.em4unlatch().set_bit());
+    }
+}
+
+/// Edges of the pin level that set the flag of an external interrupt line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterruptEdge {
+    Rising,
+    Falling,
+    Both,
+}
+
+// External interrupt lines in use by an `ExtiPin`.
+static CLAIMED_LINES: AtomicU16 = AtomicU16::new(0);
+
+impl<T: PinTrait> Pin<T, Input> {
+    /// Routes the pin to the external interrupt line with the same number,
+    /// e.g. PC9 to line 9, and sets its interrupt flag on `edge`.
+    ///
+    /// The interrupt itself stays disabled until
+    /// [`ExtiPin::enable_interrupt()`] is called. Each line serves a single
+    /// pin at a time: pins with the same number on different ports, e.g. PA1
+    /// and PF1, share line 1.
+    ///
+    /// Panics when the line is already used by another [`ExtiPin`], see
+    /// [`Pin::try_into_exti()`].
+    pub fn into_exti(self, edge: InterruptEdge) -> ExtiPin<T> {
+        match self.try_into_exti(edge) {
+            Ok(pin) => pin,
+            Err(_) => panic!("external interrupt line already in use"),
+        }
+    }
+
+    /// Same as [`Pin::into_exti()`] but returns the pin as `Err` when the
+    /// line is already used by another [`ExtiPin`]. The line becomes
+    /// available again with [`ExtiPin::free()`].
+    pub fn try_into_exti(self, edge: InterruptEdge) -> Result<ExtiPin<T>, Self> {
+        let line = self.ty.pin_nr();
+        let bit = 1u32 << line;
+        if CLAIMED_LINES.fetch_or(bit as u16, Ordering::Relaxed) & bit as u16 != 0 {
+            return Err(self);
+        }
+
+        let gpio = unsafe { &*GPIO::ptr() };
+        let shift = (line % 8) * 4;
+        let field = 0xFu32 << shift;
+
+        // Select the port for the line and map the line to the pin with the
+        // same number within its group of four pins.
+        let port = (self.ty.port_index() as u32) << shift;
+        let pin = ((line % 4) as u32) << shift;
+        if line < 8 {
+            gpio.extipsell
+                .modify(|r, w| unsafe { w.bits((r.bits() & !field) | port) });
+            gpio.extipinsell
+                .modify(|r, w| unsafe { w.bits((r.bits() & !field) | pin) });
+        } else {
+            gpio.extipselh
+                .modify(|r, w| unsafe { w.bits((r.bits() & !field) | port) });
+            gpio.extipinselh
+                .modify(|r, w| unsafe { w.bits((r.bits() & !field) | pin) });
+        }
+
+        let rise = edge != InterruptEdge::Falling;
+        let fall = edge != InterruptEdge::Rising;
+        gpio.extirise.modify(|r, w| unsafe {
+            w.bits(if rise {
+                r.bits() | bit
+            } else {
+                r.bits() & !bit
+            })
+        });
+        gpio.extifall.modify(|r, w| unsafe {
+            w.bits(if fall {
+                r.bits() | bit
+            } else {
+                r.bits() & !bit
+            })
+        });
+        gpio.ifc.write(|w| unsafe { w.ext().bits(bit as u16) });
+
+        Ok(ExtiPin { pin: self })
+    }
+}
+
+/// Input pin routed to an external interrupt line.
+///
+/// Created by [`Pin::into_exti()`].
+pub struct ExtiPin<T: PinTrait> {
+    pin: Pin<T, Input>,
+}
+
+impl<T: PinTrait> ExtiPin<T> {
+    /// Returns the external interrupt line number (`0..=15`).
+    pub fn line(&self) -> u8 {
+        self.pin.ty.pin_nr()
+    }
+
+    /// Returns the interrupt group which raises the interrupt of this line.
+    pub fn group(&self) -> InterruptGroup {
+        if self.line().is_multiple_of(2) {
+            InterruptGroup::Even
+        } else {
+            InterruptGroup::Odd
+        }
+    }
+
+    /// Returns the NVIC line of the interrupt group of this line.
+    ///
+    /// [`ExtiPin::enable_interrupt()`] only enables the line in the GPIO
+    /// peripheral, the NVIC line must be unmasked as well, e.g. with
+    /// `cortex_m::peripheral::NVIC::unmask(pin.interrupt())`.
+    pub fn interrupt(&self) -> Interrupt {
+        self.group().interrupt()
+    }
+
+    /// Returns `true` while the interrupt flag of the line is set, also when
+    /// the interrupt is disabled.
+    pub fn is_pending(&self) -> bool {
+        let gpio = unsafe { &*GPIO::ptr() };
+        gpio.if_.read().ext().bits() & (1 << self.line()) != 0
+    }
+
+    /// Clears the interrupt flag of the line without affecting other lines.
+    pub fn clear_pending(&mut self) {
+        let gpio = unsafe { &*GPIO::ptr() };
+        gpio.ifc
+            .write(|w| unsafe { w.ext().bits(1 << self.line()) });
+    }
+
+    /// Enables the interrupt of the line.
+    ///
+    /// The `GPIO_EVEN` or `GPIO_ODD` handler must acknowledge the line, see
+    /// [`pending_interrupts()`].
+    pub fn enable_interrupt(&mut self) {
+        let gpio_set = unsafe { &*GPIO::ptr_set() };
+        gpio_set
+            .ien
+            .write(|w| unsafe { w.ext().bits(1 << self.line()) });
+    }
+
+    /// Disables the interrupt of the line.
+    pub fn disable_interrupt(&mut self) {
+        let gpio_clear = unsafe { &*GPIO::ptr_clear() };
+        gpio_clear
+            .ien
+            .write(|w| unsafe { w.ext().bits(1 << self.line()) });
+    }
+
+    /// Returns the PRS producer of the line, to be passed to
+    /// [`prs::Channel::connect()`](crate::prs::Channel::connect).
+    ///
+    /// The PRS signal follows the level of the pin, edges are detected by the
+    /// PRS channel or by the consumer. It does not depend on the interrupt
+    /// edge selection or on the interrupt being enabled.
+    ///
+    /// Lines 0 to 7 are signals 0 to 7 of the `GPIOL` source, lines 8 to 15
+    /// are signals 0 to 7 of the `GPIOH` source. This split is unrelated to
+    /// the even/odd [`InterruptGroup`] of the line, e.g. line 3 and line 4
+    /// both belong to `GPIOL` but raise different interrupts.
+    pub fn prs_source(&self) -> PrsSource {
+        let line = self.line();
+        if line < 8 {
+            PrsSource::new(prs::Source::GPIOL, line)
+        } else {
+            PrsSource::new(prs::Source::GPIOH, line - 8)
+        }
+    }
+
+    /// Disables the interrupt and both edges of the line, releases the line
+    /// and returns the input pin.
+    pub fn free(mut self) -> Pin<T, Input> {
+        self.disable_interrupt();
+        let gpio = unsafe { &*GPIO::ptr() };
+        let mask = !(1u32 << self.line());
+        gpio.extirise
+            .modify(|r, w| unsafe { w.bits(r.bits() & mask) });
+        gpio.extifall
+            .modify(|r, w| unsafe { w.bits(r.bits() & mask) });
+        CLAIMED_LINES.fetch_and(mask as u16, Ordering::Relaxed);
+        self.pin
+    }
+}
+
+impl<T: PinTrait> InputPin for ExtiPin<T> {
+    type Error = Infallible;
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.pin.is_low()
+    }
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.pin.is_high()
+    }
+}
+
+/// Interrupt group of the external interrupt lines.
+///
+/// Even lines raise `GPIO_EVEN`, odd lines raise `GPIO_ODD`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterruptGroup {
+    Even,
+    Odd,
+}
+
+impl InterruptGroup {
+    /// Returns the NVIC line of the group.
+    pub fn interrupt(self) -> Interrupt {
+        match self {
+            InterruptGroup::Even => Interrupt::GPIO_EVEN,
+            InterruptGroup::Odd => Interrupt::GPIO_ODD,
+        }
+    }
+
+    fn mask(self) -> u16 {
+        match self {
+            InterruptGroup::Even => 0x5555,
+            InterruptGroup::Odd => 0xAAAA,
+        }
+    }
+}
+
+/// Pending external interrupt line handed to an interrupt handler.
+///
+/// Obtained from [`pending_interrupts()`] and only for lines whose flag was
+/// set and enabled. Clearing consumes the token so that a handler can only
+/// acknowledge the lines it was given.
+#[derive(Debug, PartialEq, Eq)]
+pub struct InterruptLine {
+    line: u8,
+}
+
+impl InterruptLine {
+    /// Returns the line number (`0..=15`).
+    pub fn number(&self) -> u8 {
+        self.line
+    }
+
+    /// Returns `true` while the interrupt flag of this line is set.
+    pub fn is_pending(&self) -> bool {
+        let gpio = unsafe { &*GPIO::ptr() };
+        gpio.if_.read().ext().bits() & (1 << self.line) != 0
+    }
+
+    /// Clears the interrupt flag of this line.
+    ///
+    /// `GPIO.IFC` is write-1-to-clear: bits written as zero leave the
+    /// corresponding flags untouched. Only the bit of this line is written, so
+    /// flags of other lines set concurrently, e.g. while a higher priority
+    /// handler runs, are never lost.
+    pub fn clear(self) {
+        let gpio = unsafe { &*GPIO::ptr() };
+        gpio.ifc.write(|w| unsafe { w.ext().bits(1 << self.line) });
+    }
+}
+
+/// Iterator over the pending lines of an [`InterruptGroup`].
+///
+/// Created by [`pending_interrupts()`].
+pub struct PendingInterrupts {
+    pending: u16,
+}
+
+impl Iterator for PendingInterrupts {
+    type Item = InterruptLine;
+
+    fn next(&mut self) -> Option<InterruptLine> {
+        if self.pending == 0 {
+            return None;
+        }
+        let line = self.pending.trailing_zeros() as u8;
+        self.pending &= !(1 << line);
+        Some(InterruptLine { line })
+    }
+}
+
+/// Returns the enabled and pending external interrupt lines of `group`.
+///
+/// Intended to be called from the `GPIO_EVEN` or `GPIO_ODD` interrupt handler.
+/// The flags are sampled once; lines becoming pending afterwards raise the
+/// interrupt again because their flags are not cleared by this iterator.
+///
+/// ```ignore
+/// #[interrupt]
+/// fn GPIO_EVEN() {
+///     for line in gpio::pending_interrupts(InterruptGroup::Even) {
+///         // handle line.number()
+///         line.clear();
+///     }
+/// }
+/// ```
+pub fn pending_interrupts(group: InterruptGroup) -> PendingInterrupts {
+    let gpio = unsafe { &*GPIO::ptr() };
+    let flags = gpio.if_.read().ext().bits() & gpio.ien.read().ext().bits();
+    PendingInterrupts {
+        pending: flags & group.mask(),
+    }
+}
+
+// Sets the pins selected by `mask` of a port to `value` with a single write.
+//
+// Uses the toggle register so that pins outside of `mask` are not affected,
+// even when they are changed concurrently.
+fn write_port_group(port_index: usize, mask: u16, value: u16) {
+    debug_assert_clock_enabled();
+    let port = GPIO::ptr() as usize + port_index * PORT_STRIDE;
+    unsafe {
+        let dout = core::ptr::read_volatile((port + DOUT_OFFSET) as *const u32) as u16;
+        let toggle = (dout ^ value) & mask;
+        if toggle != 0 {
+            core::ptr::write_volatile((port + DOUTTGL_OFFSET) as *mut u32, toggle as u32);
+        }
+    }
+}
+
+macro_rules! pin_groups {
+    ($(
+        $(#[$attr:meta])*
+        $group:ident, $n:expr, $value:ty, { $($T:ident: $i:tt,)* }
+    )*) => {
+        $(
+            $(#[$attr])*
+            pub struct $group<$($T: PinTrait),*> {
+                pins: ($(Pin<$T, Output>,)*),
+            }
+
+            impl<$($T: PinTrait),*> $group<$($T),*> {
+                /// Groups the output pins. The first pin corresponds to bit 0.
+                pub fn new(pins: ($(Pin<$T, Output>,)*)) -> Self {
+                    Self { pins }
+                }
+
+                /// Sets each pin to the level of the corresponding bit in
+                /// `value`.
+                pub fn write(&mut self, value: $value) {
+                    // Collects the bits per port, at most one port per pin.
+                    let mut ports = [(usize::MAX, 0u16, 0u16); $n];
+                    $(
+                        let port = self.pins.$i.ty.port_index();
+                        let bit = 1u16 << self.pins.$i.ty.pin_nr();
+                        let level = if value & (1 << $i) != 0 { bit } else { 0 };
+                        for entry in ports.iter_mut() {
+                            if entry.0 == usize::MAX || entry.0 == port {
+                                *entry = (port, entry.1 | bit, entry.2 | level);
+                                break;
+                            }
+                        }
+                    )*
+
+                    for &(port, mask, level) in ports.iter() {
+                        if port == usize::MAX {
+                            break;
+                        }
+                        write_port_group(port, mask, level);
+                    }
+                }
+
+                /// Returns the individual pins.
+                pub fn free(self) -> ($(Pin<$T, Output>,)*) {
+                    self.pins
+                }
+            }
+        )*
+    };
+}
+
+pin_groups!(
+    /// Group of 4 output pins driven as a unit, e.g. the data bus of a
+    /// HD44780 display in 4-bit mode.
+    ///
+    /// Pins on the same port change simultaneously with a single register
+    /// write. Pins on different ports change one port after another, in the
+    /// order of their first appearance in the group.
+    Pins4, 4, u8, { T0: 0, T1: 1, T2: 2, T3: 3, }
+
+    /// Group of 8 output pins driven as a unit, e.g. a parallel data bus.
+    ///
+    /// Pins on the same port change simultaneously with a single register
+    /// write. Pins on different ports change one port after another, in the
+    /// order of their first appearance in the group.
+    Pins8, 8, u8, { T0: 0, T1: 1, T2: 2, T3: 3, T4: 4, T5: 5, T6: 6, T7: 7, }
+);
+
 /// Implemented by pin types that can be mapped to a specific peripheral.
 pub trait PinLocation<Peripheral: ?Sized, PinType> {
     const LOCATION: u8;
@@ -593,3 +1824,64 @@ macro_rules! impl_pin_locations {
         )*
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pin with separate output latch and line level, like an open-drain
+    // output on a wired-AND line.
+    struct Line {
+        dout: bool,
+        din: bool,
+    }
+
+    impl PinTrait for Line {
+        fn clear_mode(&mut self) {}
+        fn set_mode(&mut self, _mode: MODE) {}
+        fn read_mode(&self) -> MODE {
+            MODE::WIREDAND
+        }
+        fn clear_dout_bit(&mut self) {
+            self.dout = false;
+        }
+        fn set_dout_bit(&mut self) {
+            self.dout = true;
+        }
+        fn read_dout_bit(&self) -> bool {
+            self.dout
+        }
+        fn write_douttgl_bit(&mut self) {
+            self.dout = !self.dout;
+        }
+        fn read_din_bit(&self) -> bool {
+            self.din
+        }
+        fn port_index(&self) -> usize {
+            0
+        }
+        fn pin_nr(&self) -> u8 {
+            0
+        }
+    }
+
+    #[test]
+    fn output_reads_back_line_level() {
+        let mut pin: Pin<Line, Output> = Pin {
+            ty: Line {
+                dout: false,
+                din: false,
+            },
+            _mode: PhantomData,
+        };
+
+        // Another device holds the line low.
+        pin.set_high().unwrap();
+        assert!(pin.is_set_high().unwrap());
+        assert!(pin.is_low().unwrap());
+
+        // The line follows once it is released.
+        pin.ty.din = true;
+        assert!(pin.is_high().unwrap());
+    }
+}
@@ -1,47 +1,217 @@
+//! I2C master API, see [`slave`] for the slave mode
+//!
+//! # Multiple masters
+//!
+//! A master that starts at the same time as another one can lose the
+//! arbitration while sending the address or data, the peripheral then
+//! releases the bus and sets `IF.ARBLOST`. The blocking transfers detect the
+//! flag, abort the transfer, clear the flag and return
+//! `Err(Error::ArbitrationLoss)`. No stop condition is sent, the bus belongs
+//! to the winning master.
+//!
+//! The transfer was not completed and must be repeated as a whole once the
+//! other master is done, i.e. when [`I2c::lock()`] no longer returns
+//! `Err(Error::Bus)`. Writes that were interrupted after some data bytes
+//! were acknowledged, e.g. a register pointer, leave the device in an
+//! intermediate state that the repeated transfer overwrites. A few retries
+//! with a delay of a couple of transfer durations resolve the contention
+//! between well-behaved masters. [`retry::RetryI2c`] only retries NACKs.
+//!
+//! A misplaced start or stop condition on the bus, e.g. caused by a glitch or
+//! by a master that ignores the arbitration, sets `IF.BUSERR`. The peripheral
+//! goes idle, the blocking transfers abort and return `Err(Error::Bus)` like
+//! for a busy bus. Repeat the transfer once [`I2c::lock()`] succeeds again.
+pub use crate::pac::i2c0::ctrl::{
+    BITO_A as BusIdleTimeout, CLHR_A as ClockRatio, CLTO_A as ClockLowTimeout,
+};
 pub use embedded_error::I2cError as Error;
 
 use crate::{
-    cmu::{ClockControlExt, Cmu},
+    cmu::{ClockControlExt, Clocks, Cmu},
     gpio::*,
     hal::blocking::i2c::{Read, Write, WriteRead},
-    pac::{i2c0::RegisterBlock, I2C0, I2C1},
+    ldma::{self, Channel},
+    pac::{generic::Variant, i2c0::RegisterBlock, Interrupt, I2C0, I2C1},
+    time::Hertz,
 };
 use core::ops::Deref;
 use embedded_error::ImplError;
 
+pub mod nonblocking;
+pub mod retry;
+pub mod slave;
+
+/// I2C configuration.
+///
+/// The SCL high and low periods, and with them the setup and hold times of the
+/// start, stop and data conditions, follow from the clock divider. This
+/// peripheral has no separate setup or hold time registers. The hardware
+/// timeouts below are counted in periods of the prescaled clock (PCC) and
+/// help to detect and recover from stuck or marginal buses.
+///
+/// Defaults to standard mode (100 kHz), a software timeout of 10000 SCL
+/// periods and all hardware timeouts disabled.
+pub struct Config {
+    /// SCL frequency and low to high ratio.
+    pub speed: Speed,
+    /// Considers the bus idle after SCL and SDA were high for this time.
+    /// Sets the `BITO` interrupt flag.
+    pub bus_idle_timeout: BusIdleTimeout,
+    /// Releases the bus when the bus idle timeout triggers, e.g. when the
+    /// master lost the stop condition of another master.
+    pub go_idle_on_timeout: bool,
+    /// Sets the `CLTO` interrupt flag when a device holds SCL low for longer
+    /// than this time.
+    pub clock_low_timeout: ClockLowTimeout,
+    /// Gives up waiting for the response to a byte after roughly this many
+    /// SCL periods, `None` waits forever.
+    ///
+    /// Covers a missing ACK or NACK and a device that stretches the clock
+    /// for too long, which otherwise blocks the transfer functions forever.
+    /// The default of 10000 periods, i.e. 100 ms at 100 kHz, leaves room for
+    /// devices that stretch the clock during a measurement. The timeout is a
+    /// busy loop counter and not exact, it is scaled with the HFCORECLK and
+    /// the SCL frequency.
+    pub timeout: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            speed: Speed::Standard100k,
+            bus_idle_timeout: BusIdleTimeout::OFF,
+            go_idle_on_timeout: false,
+            clock_low_timeout: ClockLowTimeout::OFF,
+            timeout: Some(10_000),
+        }
+    }
+}
+
+/// Bus speed mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    /// Standard-mode, 100 kHz with the standard (4:4) SCL low to high ratio.
+    Standard100k,
+    /// Fast-mode, 400 kHz with the asymmetric (6:3) ratio.
+    Fast400k,
+    /// Fast-mode Plus, 1 MHz with the fast (11:6) ratio. Needs an HFPERCLK of
+    /// at least 25 MHz for the full 1 MHz and strong pull-ups.
+    FastPlus1M,
+}
+
+impl Speed {
+    /// Maximum SCL frequency of the mode.
+    pub fn frequency(self) -> Hertz {
+        match self {
+            Speed::Standard100k => Hertz(100_000),
+            Speed::Fast400k => Hertz(400_000),
+            Speed::FastPlus1M => Hertz(1_000_000),
+        }
+    }
+
+    /// SCL low to high ratio of the mode.
+    pub fn clock_ratio(self) -> ClockRatio {
+        match self {
+            Speed::Standard100k => ClockRatio::STANDARD,
+            Speed::Fast400k => ClockRatio::ASYMMETRIC,
+            Speed::FastPlus1M => ClockRatio::FAST,
+        }
+    }
+}
+
 /// I2C API
-pub struct I2c<I> {
+///
+/// Owns the SCL and SDA pins as `P`, so they can be handed to a different
+/// peripheral or used as GPIO after [`I2c::release_with_pins()`].
+pub struct I2c<I, P = ()> {
     raw: I,
+    pins: P,
+    timeout_periods: Option<u32>,
+    // Busy loop iterations per byte, derived from `timeout_periods`.
+    timeout: Option<u32>,
+    // Busy loop iterations for a pending stop condition to go out.
+    stop_polls: u32,
 }
 
-impl<I: I2CX> I2c<I> {
-    pub fn new<SCL, SDA>(
-        i2c: I,
-        _scl: SCL,
-        _sda: SDA,
-        //config: &Config,
-        cmu: &mut Cmu,
-    ) -> Self
+// SCL periods granted to a pending stop condition on release.
+const STOP_PERIODS: u32 = 16;
+
+// Remaining iterations of a busy wait.
+struct Deadline(Option<u32>);
+
+impl Deadline {
+    fn expired(&mut self) -> bool {
+        match &mut self.0 {
+            None => false,
+            Some(0) => true,
+            Some(n) => {
+                *n -= 1;
+                false
+            }
+        }
+    }
+}
+
+impl<I: I2CX, SCL, SDA> I2c<I, (SCL, SDA)> {
+    /// Enables the peripheral at the configured speed and routes it to the
+    /// pins.
+    ///
+    /// Each pin is checked against the location table of its signal, a pin
+    /// without a location for the signal is rejected at compile time. Both
+    /// tables of a peripheral cover the same pins at different locations:
+    ///
+    /// ```no_run
+    /// # use efm32pg12_hal::{cmu::Cmu, gpio::Gpio, i2c::{Config, I2c}, pac::Peripherals};
+    /// # let p = Peripherals::take().unwrap();
+    /// # let mut cmu = Cmu::new(p.CMU);
+    /// # let gpio = Gpio::new(p.GPIO, &mut cmu);
+    /// let scl = gpio.pc11.open_drain_output(true);
+    /// let sda = gpio.pc10.open_drain_output(true);
+    /// let i2c = I2c::new(p.I2C0, scl, sda, &Config::default(), &mut cmu);
+    /// ```
+    ///
+    /// ```compile_fail,E0277
+    /// # use efm32pg12_hal::{cmu::Cmu, gpio::Gpio, i2c::{Config, I2c}, pac::Peripherals};
+    /// # let p = Peripherals::take().unwrap();
+    /// # let mut cmu = Cmu::new(p.CMU);
+    /// # let gpio = Gpio::new(p.GPIO, &mut cmu);
+    /// // PA6 has no location on I2C0, only on I2C1. Every I2C0 pin has both an
+    /// // SCL and an SDA location, the tests check that SDA uses its own table.
+    /// let scl = gpio.pc11.open_drain_output(true);
+    /// let sda = gpio.pa6.open_drain_output(true);
+    /// let i2c = I2c::new(p.I2C0, scl, sda, &Config::default(), &mut cmu);
+    /// ```
+    ///
+    /// Returns `Err(Error::Impl(ImplError::InvalidConfiguration))` when the
+    /// speed cannot be reached with the HFPERCLK frequency or the SCL timing
+    /// would violate the minimum periods of the mode, see [`compute_div()`].
+    /// The peripheral is not touched in that case. The SCL frequency can be
+    /// lower than the one of the speed, see [`I2c::actual_frequency()`].
+    pub fn new(i2c: I, scl: SCL, sda: SDA, config: &Config, cmu: &mut Cmu) -> Result<Self, Error>
     where
         SCL: PinLocation<I, SclPin>,
-        SDA: PinLocation<I, SclPin>,
+        SDA: PinLocation<I, SdaPin>,
     {
-        cmu.enable_clock(&i2c);
-
-        let hfperclk = cmu.hfperclk();
+        let hfperclk = cmu.hfperclk().0;
+        let clock_ratio = config.speed.clock_ratio();
+        let div = compute_div(hfperclk, config.speed.frequency().0, clock_ratio)?;
+        debug!("I2C: HFPERCLK {=u32} Hz, CLKDIV {=u16}", hfperclk, div);
 
-        // Configure I2C standard mode
-        assert!(hfperclk >= 2_000_000);
-        let freq_scl = 100_000;
-        let n_high = 4;
-        let n_low = 4;
-        let div = (hfperclk - (8 * freq_scl)) / ((n_high + n_low) * freq_scl) - 1;
-        assert!(div < 512);
-
-        i2c.clkdiv
-            .modify(|_, w| unsafe { w.div().bits(div as u16) });
+        cmu.enable_clock(&i2c);
+        i2c.clkdiv.modify(|_, w| unsafe { w.div().bits(div) });
 
-        i2c.ctrl.write(|w| w.en().set_bit());
+        i2c.ctrl.write(|w| {
+            w.clhr()
+                .variant(clock_ratio)
+                .bito()
+                .variant(config.bus_idle_timeout)
+                .gibito()
+                .bit(config.go_idle_on_timeout)
+                .clto()
+                .variant(config.clock_low_timeout)
+                .en()
+                .set_bit()
+        });
 
         // Busy flag is set after reset, use the ABORT command to clear it.
         if i2c.state.read().busy().bit_is_set() {
@@ -51,19 +221,192 @@ impl<I: I2CX> I2c<I> {
         // Clear pendig commands and the TX buffers.
         i2c.cmd.write(|w| w.clearpc().set_bit().cleartx().set_bit());
 
-        // Route peripheral to pins.
-        i2c.routeloc0
-            .write(|w| unsafe { w.sclloc().bits(SCL::LOCATION).sdaloc().bits(SDA::LOCATION) });
-        i2c.routepen
+        route::<I, SCL, SDA>(&i2c);
+
+        let mut i2c = Self {
+            raw: i2c,
+            pins: (scl, sda),
+            timeout_periods: config.timeout,
+            timeout: None,
+            stop_polls: 0,
+        };
+        i2c.update_timeout(&cmu.clocks());
+        Ok(i2c)
+    }
+
+    /// Disables the peripheral and its pin routes and returns the
+    /// peripheral and the pins.
+    ///
+    /// Waits until a pending stop condition went out, at most a few SCL
+    /// periods. A transfer that still holds the bus after that, e.g. because
+    /// a device holds SCL low, is aborted (`CMD.ABORT`) instead of blocking
+    /// the release. The route is disabled first, the pins fall back to their
+    /// GPIO output level, which is high (released) for the open-drain outputs
+    /// created with `open_drain_output(true)`. No edge appears on the bus
+    /// until the pins are reconfigured.
+    ///
+    /// To use a pin as GPIO, go through [`Pin::reset()`], which disables it
+    /// before the new mode is applied:
+    ///
+    /// ```ignore
+    /// let (i2c0, scl, sda) = i2c.release_with_pins();
+    /// let mut sda = sda.reset().push_pull_output(true);
+    /// // ...
+    /// let sda = sda.reset().filter().open_drain_output(true);
+    /// let i2c = I2c::new(i2c0, scl, sda, &Config::default(), &mut cmu)?;
+    /// ```
+    ///
+    /// Set the GPIO to the idle level of the bus, i.e. high, before handing
+    /// the pin back, so the new route does not start with a low level that
+    /// devices see as a start condition.
+    pub fn release_with_pins(mut self) -> (I, SCL, SDA) {
+        self.disable();
+        let (scl, sda) = self.pins;
+        (self.raw, scl, sda)
+    }
+}
+
+impl<I: I2CX, SCL: PinTrait, SDA: PinTrait> I2c<I, (Pin<SCL, Output>, Pin<SDA, Output>)> {
+    /// Frees a device that holds SDA low, e.g. because the MCU was reset or
+    /// halted by a debugger in the middle of a read.
+    ///
+    /// Takes the pins over as GPIO and clocks SCL at roughly 100 kHz up to
+    /// nine times until the device releases SDA, which finishes the byte it
+    /// was sending. A stop condition then resets the state machine of every
+    /// device on the bus. Afterwards the pins are routed to the peripheral
+    /// again and its state machine is reset as well.
+    ///
+    /// Requires open-drain outputs with the input enabled, as created with
+    /// `open_drain_output(true)`. Call it after an error or a timeout, or
+    /// right after [`I2c::new()`] when a device may be stuck since before the
+    /// reset. Returns `Err(Error::Bus)` when SDA stays low, e.g. because it
+    /// is shorted to ground.
+    pub fn recover(&mut self, clocks: &Clocks) -> Result<(), Error> {
+        // Busy loop of a few cycles per iteration for half an SCL period.
+        let half_period = clocks.hfcoreclk().0 / 200_000;
+        let delay = |raw: &I| {
+            for _ in 0..half_period {
+                raw.state.read();
+            }
+        };
+
+        let (scl, sda) = &mut self.pins;
+        let scl = scl.pin_trait_mut();
+        let sda = sda.pin_trait_mut();
+
+        // Release both lines before the route is disabled so that the GPIO
+        // takes over without an edge.
+        scl.set_dout_bit();
+        sda.set_dout_bit();
+        self.raw.routepen.reset();
+        delay(&self.raw);
+
+        let mut clocks = 0;
+        while !sda.read_din_bit() && clocks < 9 {
+            scl.clear_dout_bit();
+            delay(&self.raw);
+            scl.set_dout_bit();
+            delay(&self.raw);
+            clocks += 1;
+        }
+        trace!("I2C: recovery took {=u8} clocks", clocks);
+
+        // Stop condition: SDA rises while SCL is high.
+        scl.clear_dout_bit();
+        delay(&self.raw);
+        sda.clear_dout_bit();
+        delay(&self.raw);
+        scl.set_dout_bit();
+        delay(&self.raw);
+        sda.set_dout_bit();
+        delay(&self.raw);
+        let released = sda.read_din_bit();
+
+        self.raw
+            .routepen
             .write(|w| w.sclpen().set_bit().sdapen().set_bit());
+        self.raw
+            .cmd
+            .write(|w| w.abort().set_bit().clearpc().set_bit().cleartx().set_bit());
+
+        if !released {
+            warn!("I2C: SDA still low after recovery");
+            return Err(Error::Bus);
+        }
+        Ok(())
+    }
+}
+
+impl<I: I2CX, P> I2c<I, P> {
+    /// Changes the timeout, see [`Config::timeout`]. Pass new
+    /// [`Cmu::clocks()`] when the HFCORECLK frequency changed since
+    /// construction.
+    pub fn set_timeout(&mut self, timeout: Option<u32>, clocks: &Clocks) {
+        self.timeout_periods = timeout;
+        self.update_timeout(clocks);
+    }
+
+    // Converts the timeout to busy loop iterations for the current SCL
+    // frequency. An iteration polls a register and takes a few cycles.
+    fn update_timeout(&mut self, clocks: &Clocks) {
+        let (n_low, n_high) = scl_cycles(self.clock_ratio());
+        let n = (n_low + n_high) as u64;
+        let div = self.raw.clkdiv.read().div().bits() as u64;
+        // SCL period in HFCORECLK cycles.
+        let period = (n * (div + 1) + 8) * clocks.hfcoreclk().0 as u64 / clocks.hfperclk().0 as u64;
+        self.timeout = self
+            .timeout_periods
+            .map(|periods| (periods as u64 * period / 4).clamp(1, u32::MAX as u64) as u32);
+        self.stop_polls = (STOP_PERIODS as u64 * period / 4).clamp(1, u32::MAX as u64) as u32;
+    }
+
+    // Gives a pending stop condition a few SCL periods to go out, then aborts
+    // whatever still holds the bus, e.g. a device that holds SCL low, and
+    // disables the peripheral and its pin routes.
+    fn disable(&mut self) {
+        let mut deadline = Deadline(Some(self.stop_polls));
+        while self.raw.state.read().master().bit_is_set() {
+            if deadline.expired() {
+                self.raw.cmd.write(|w| w.abort().set_bit());
+                warn!("I2C: transfer aborted on release");
+                break;
+            }
+        }
+        self.raw.routepen.reset();
+        self.raw.routeloc0.reset();
+        self.raw.ctrl.modify(|_, w| w.en().clear_bit());
+    }
 
-        Self { raw: i2c }
+    // Starts the timeout of a wait for `bytes` bytes.
+    fn deadline(&self, bytes: usize) -> Deadline {
+        Deadline(self.timeout.map(|t| t.saturating_mul(bytes as u32)))
+    }
+
+    // Resets the state machine after a device did not respond in time. The
+    // stop condition cannot be sent while a device holds SCL low, see
+    // `I2c::recover()` for a device that holds SDA low.
+    fn timed_out(&mut self) -> Error {
+        self.raw
+            .cmd
+            .write(|w| w.abort().set_bit().clearpc().set_bit().cleartx().set_bit());
+        self.raw
+            .ifc
+            .write(|w| w.ack().set_bit().nack().set_bit().arblost().set_bit());
+        warn!("I2C: timeout");
+        Error::Impl(ImplError::TimedOut)
     }
 
     // Waits for an ACK or NACK of address or data byte.
     fn wait_for_ack(&mut self) -> Result<(), Error> {
+        let mut deadline = self.deadline(1);
         loop {
             let if_ = self.raw.if_.read();
+            if if_.arblost().bit_is_set() {
+                return Err(self.arbitration_lost());
+            }
+            if if_.buserr().bit_is_set() {
+                return Err(self.bus_error());
+            }
             if if_.nack().bit_is_set() {
                 self.raw.ifc.write(|w| w.nack().set_bit());
                 self.raw.cmd.write(|w| w.stop().set_bit());
@@ -73,36 +416,638 @@ impl<I: I2CX> I2c<I> {
                 self.raw.ifc.write(|w| w.ack().set_bit());
                 return Ok(());
             }
+            if deadline.expired() {
+                return Err(self.timed_out());
+            }
         }
     }
 
-    fn write_no_stop(&mut self, address: u8, buffer: &[u8]) -> Result<(), Error> {
+    // Resets the state machine after another master won the arbitration.
+    // The peripheral already released the bus, the abort drops pending
+    // commands and the transmit buffer so that the next transfer starts from
+    // idle.
+    fn arbitration_lost(&mut self) -> Error {
+        self.raw
+            .cmd
+            .write(|w| w.abort().set_bit().cleartx().set_bit());
         self.raw
-            .txdata
-            .write(|w| unsafe { w.txdata().bits(address << 1) });
-        self.raw.cmd.write(|w| w.start().set_bit());
+            .ifc
+            .write(|w| w.arblost().set_bit().ack().set_bit().nack().set_bit());
+        debug!("I2C: arbitration lost");
+        Error::ArbitrationLoss
+    }
+
+    // Resets the state machine after a misplaced start or stop condition,
+    // e.g. a glitch on the bus or a master that does not arbitrate. The
+    // peripheral already went idle, the abort drops pending commands and the
+    // transmit buffer like after a lost arbitration.
+    fn bus_error(&mut self) -> Error {
+        self.raw
+            .cmd
+            .write(|w| w.abort().set_bit().clearpc().set_bit().cleartx().set_bit());
+        self.raw.ifc.write(|w| {
+            w.buserr()
+                .set_bit()
+                .arblost()
+                .set_bit()
+                .ack()
+                .set_bit()
+                .nack()
+                .set_bit()
+        });
+        warn!("I2C: bus error");
+        Error::Bus
+    }
+
+    // Sends a (repeated) start condition followed by the address byte.
+    //
+    // On an idle bus the address is loaded into the transmit buffer before
+    // the START command is issued. This way the peripheral clocks out the
+    // address right after the start condition instead of stretching SCL
+    // until software provides the data. While the bus is held
+    // (`STATE.MASTER`), e.g. for the repeated start of `write_read()`, a byte
+    // in the transmit buffer would be sent as data before the repeated start,
+    // so the START command goes first and the address follows right after.
+    //
+    // The `profile_i2c` example measures the duration of a register read
+    // with a repeated start, compare it to the 38 SCL periods the bus needs
+    // for it to get the software overhead.
+    fn start(&mut self, address_byte: u8) -> Result<(), Error> {
+        // Stale flags of an earlier transfer would be taken as the response
+        // to this address byte.
+        self.raw.ifc.write(|w| {
+            w.ack()
+                .set_bit()
+                .nack()
+                .set_bit()
+                .arblost()
+                .set_bit()
+                .buserr()
+                .set_bit()
+        });
+        if self.raw.state.read().master().bit_is_set() {
+            self.raw.cmd.write(|w| w.start().set_bit());
+            self.raw
+                .txdata
+                .write(|w| unsafe { w.txdata().bits(address_byte) });
+        } else {
+            self.raw
+                .txdata
+                .write(|w| unsafe { w.txdata().bits(address_byte) });
+            self.raw.cmd.write(|w| w.start().set_bit());
+        }
+
+        let result = self.wait_for_ack();
+        if result.is_err() {
+            debug!(
+                "I2C: address byte {=u8:#04x} not acknowledged",
+                address_byte
+            );
+        }
+        result
+    }
 
-        self.wait_for_ack()?;
+    fn write_no_stop(&mut self, address: u8, buffer: &[u8]) -> Result<(), Error> {
+        if self.write_acked(address, buffer)? < buffer.len() {
+            return Err(Error::NACK);
+        }
+        Ok(())
+    }
+
+    // Writes bytes until one is not acknowledged and returns the number of
+    // acknowledged bytes. The stop condition is only sent on a NACK.
+    fn write_acked(&mut self, address: u8, buffer: &[u8]) -> Result<usize, Error> {
+        check_address(address)?;
+        self.start(address << 1)?;
 
-        for &b in buffer {
+        for (i, &b) in buffer.iter().enumerate() {
             self.raw.txdata.write(|w| unsafe { w.txdata().bits(b) });
 
-            self.wait_for_ack()?;
+            match self.wait_for_ack() {
+                Ok(()) => {}
+                Err(Error::NACK) => return Ok(i),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(buffer.len())
+    }
+
+    /// Writes `buffer` and returns the number of bytes acknowledged by the
+    /// device.
+    ///
+    /// Stops at the first byte which is not acknowledged, e.g. because a
+    /// flash memory is busy, so that the transfer can be resumed later. The
+    /// not acknowledged byte is not counted. Returns `Err(Error::NACK)` when
+    /// the address itself is not acknowledged, no byte was written in that
+    /// case.
+    pub fn write_partial(&mut self, address: u8, buffer: &[u8]) -> Result<usize, Error> {
+        let n = self.write_acked(address, buffer)?;
+        if n == buffer.len() {
+            self.raw.cmd.write(|w| w.stop().set_bit());
+        }
+        Ok(n)
+    }
+
+    /// Reads into `buffer` from the 16-bit register `register` of the device
+    /// at `address`.
+    ///
+    /// Writes the register pointer most significant byte first and reads with
+    /// a repeated start in between, see [`WriteRead::write_read()`]. Devices
+    /// with the least significant byte first need
+    /// `write_read(address, &register.to_le_bytes(), buffer)` instead.
+    pub fn read_register(
+        &mut self,
+        address: u8,
+        register: u16,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        self.write_read(address, &register.to_be_bytes(), buffer)
+    }
+
+    /// Probes all non-reserved 7-bit addresses (`0x08` to `0x77`) and yields
+    /// the addresses of devices which acknowledged.
+    ///
+    /// Each address is probed with a start condition, the address byte in
+    /// write direction and an immediate stop condition. No data is written to
+    /// the devices.
+    pub fn scan(&mut self) -> Scan<'_, I, P> {
+        // Recover from a transfer that was not finished properly.
+        if self.raw.state.read().busy().bit_is_set() {
+            self.raw.cmd.write(|w| w.abort().set_bit());
+        }
+        Scan {
+            i2c: self,
+            address: 0x08,
+        }
+    }
+
+    /// Changes the SCL frequency to at most `frequency`.
+    ///
+    /// Selects the standard (4:4) SCL low to high ratio up to 100 kHz, the
+    /// asymmetric (6:3) ratio up to 400 kHz and the fast (11:6) ratio for
+    /// higher frequencies up to 1 MHz. The HFPERCLK frequency is taken from
+    /// `clocks`.
+    ///
+    /// Returns `Err(Error::Bus)` when the bus is busy, because the change
+    /// would corrupt a transfer in progress. Returns
+    /// `Err(Error::Impl(ImplError::InvalidConfiguration))` when the frequency
+    /// cannot be reached with the HFPERCLK frequency.
+    pub fn set_frequency(&mut self, frequency: Hertz, clocks: &Clocks) -> Result<(), Error> {
+        if self.raw.state.read().busy().bit_is_set() {
+            return Err(Error::Bus);
+        }
+
+        let (clock_ratio, div) = clock_divider(clocks.hfperclk().0, frequency.0)
+            .ok_or(Error::Impl(ImplError::InvalidConfiguration))?;
+        debug!("I2C: {=u32} Hz, CLKDIV {=u16}", frequency.0, div);
+
+        self.raw.ctrl.modify(|_, w| w.clhr().variant(clock_ratio));
+        self.raw.clkdiv.write(|w| unsafe { w.div().bits(div) });
+        self.update_timeout(clocks);
+        Ok(())
+    }
+
+    /// Returns the SCL frequency that the current `CLKDIV` value and SCL low
+    /// to high ratio give with the HFPERCLK frequency of `clocks`.
+    ///
+    /// Can be lower than the frequency of the [`Speed`] or the one passed to
+    /// [`I2c::set_frequency()`], because the divider is rounded up. Devices
+    /// that stretch the clock lower it further.
+    pub fn actual_frequency(&self, clocks: &Clocks) -> Hertz {
+        let div = self.raw.clkdiv.read().div().bits();
+        scl_frequency(clocks.hfperclk().0, div, self.clock_ratio())
+    }
+
+    // The reserved value of `CTRL.CLHR` behaves like the fast ratio.
+    fn clock_ratio(&self) -> ClockRatio {
+        match self.raw.ctrl.read().clhr().variant() {
+            Variant::Val(clock_ratio) => clock_ratio,
+            Variant::Res(_) => ClockRatio::FAST,
+        }
+    }
+
+    /// Reads into `buffer` with the LDMA moving the received bytes.
+    ///
+    /// The hardware acknowledges the bytes (`CTRL.AUTOACK`), the CPU only
+    /// handles the address, the final NACK and the stop condition. Blocks
+    /// until the transfer is complete. Setting up the DMA costs about as much
+    /// as receiving a few bytes with [`Read::read()`], use it for transfers of
+    /// more than roughly 8 bytes. At most [`ldma::MAX_TRANSFER_COUNT`] + 1
+    /// bytes can be read at once.
+    ///
+    /// The last byte is NACKed by software after the DMA transfer finished.
+    /// When the driver is delayed by an interrupt for longer than one byte
+    /// period, the device already sent one more byte, which is discarded.
+    pub fn read_dma(
+        &mut self,
+        address: u8,
+        buffer: &mut [u8],
+        channel: &mut Channel,
+    ) -> Result<(), Error> {
+        if buffer.is_empty() || buffer.len() > ldma::MAX_TRANSFER_COUNT + 1 {
+            return Err(Error::Impl(ImplError::InvalidConfiguration));
+        }
+        check_address(address)?;
+
+        let (last, dma_buffer) = buffer.split_last_mut().unwrap();
+        if !dma_buffer.is_empty() {
+            self.raw.ctrl.modify(|_, w| w.autoack().set_bit());
+        }
+        if let Err(e) = self.start((address << 1) | 1) {
+            self.raw.ctrl.modify(|_, w| w.autoack().clear_bit());
+            return Err(e);
+        }
+
+        if !dma_buffer.is_empty() {
+            channel.start_bytes(
+                I::LDMA_SOURCE,
+                I2C_RXDATAV,
+                (&self.raw.rxdata as *const _ as u32, false),
+                (dma_buffer.as_mut_ptr() as u32, true),
+                dma_buffer.len(),
+            );
+            let mut deadline = self.deadline(dma_buffer.len());
+            while !channel.is_done() {
+                let if_ = self.raw.if_.read();
+                if if_.arblost().bit_is_set() {
+                    channel.stop();
+                    self.raw.ctrl.modify(|_, w| w.autoack().clear_bit());
+                    return Err(self.arbitration_lost());
+                }
+                if if_.buserr().bit_is_set() {
+                    channel.stop();
+                    self.raw.ctrl.modify(|_, w| w.autoack().clear_bit());
+                    return Err(self.bus_error());
+                }
+                if deadline.expired() {
+                    channel.stop();
+                    self.raw.ctrl.modify(|_, w| w.autoack().clear_bit());
+                    return Err(self.timed_out());
+                }
+            }
+            self.raw.ctrl.modify(|_, w| w.autoack().clear_bit());
+        }
+
+        self.raw.cmd.write(|w| w.nack().set_bit());
+        self.wait_for_rxdata()?;
+        *last = self.raw.rxdata.read().rxdata().bits();
+
+        self.raw.cmd.write(|w| w.stop().set_bit());
+        Ok(())
+    }
+
+    /// Writes `bytes` with the LDMA feeding the transmit buffer.
+    ///
+    /// Blocks until the transfer is complete. See [`I2c::read_dma()`] for
+    /// when DMA pays off. At most [`ldma::MAX_TRANSFER_COUNT`] bytes can be
+    /// written at once.
+    pub fn write_dma(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        channel: &mut Channel,
+    ) -> Result<(), Error> {
+        if bytes.is_empty() || bytes.len() > ldma::MAX_TRANSFER_COUNT {
+            return Err(Error::Impl(ImplError::InvalidConfiguration));
         }
+        check_address(address)?;
+        self.start(address << 1)?;
+
+        self.raw.ifc.write(|w| w.txc().set_bit());
+        channel.start_bytes(
+            I::LDMA_SOURCE,
+            I2C_TXBL,
+            (bytes.as_ptr() as u32, true),
+            (&self.raw.txdata as *const _ as u32, false),
+            bytes.len(),
+        );
 
+        // The peripheral waits for a command after a NACK, which stalls the
+        // DMA transfer.
+        let mut deadline = self.deadline(bytes.len());
+        loop {
+            let if_ = self.raw.if_.read();
+            if if_.arblost().bit_is_set() {
+                channel.stop();
+                return Err(self.arbitration_lost());
+            }
+            if if_.buserr().bit_is_set() {
+                channel.stop();
+                return Err(self.bus_error());
+            }
+            if if_.nack().bit_is_set() {
+                channel.stop();
+                self.raw.ifc.write(|w| w.nack().set_bit().ack().set_bit());
+                self.raw
+                    .cmd
+                    .write(|w| w.stop().set_bit().cleartx().set_bit());
+                return Err(Error::NACK);
+            }
+            if channel.is_done() && if_.txc().bit_is_set() && if_.ack().bit_is_set() {
+                break;
+            }
+            if deadline.expired() {
+                channel.stop();
+                return Err(self.timed_out());
+            }
+        }
+
+        self.raw.ifc.write(|w| w.ack().set_bit().txc().set_bit());
+        self.raw.cmd.write(|w| w.stop().set_bit());
         Ok(())
     }
 
-    /// Return the raw interface to the underlying peripheral.
-    pub fn release(self) -> I {
+    /// Starts a sequence of transfers that keeps the bus between them.
+    ///
+    /// The transfers of the returned [`Transaction`] are separated by
+    /// repeated start conditions instead of stop conditions. The stop
+    /// condition is sent when the transaction is dropped, also on an early
+    /// return with `?`.
+    ///
+    /// Returns `Err(Error::Bus)` when the bus is busy, e.g. because another
+    /// master is active. With multiple masters a transfer can still lose the
+    /// arbitration against a master which starts at the same time. The bus is
+    /// only held exclusively after the first address byte was sent
+    /// successfully.
+    pub fn lock(&mut self) -> Result<Transaction<'_, I, P>, Error> {
+        if self.raw.state.read().busy().bit_is_set() {
+            return Err(Error::Bus);
+        }
+        Ok(Transaction { i2c: self })
+    }
+
+    /// Starts a sequence of transfers to the device at `address` that keeps
+    /// the bus between them, see [`I2c::lock()`].
+    ///
+    /// Meant for multi-step sequences with a single device, e.g. writing the
+    /// memory address of an EEPROM, polling it and reading a page:
+    ///
+    /// ```ignore
+    /// let mut txn = i2c.transaction(0x50)?;
+    /// txn.write(&[0x00, 0x40])?;
+    /// txn.read(&mut page)?;
+    /// txn.stop();
+    /// ```
+    ///
+    /// Returns `Err(Error::Impl(ImplError::InvalidConfiguration))` when the
+    /// address does not fit into 7 bits and `Err(Error::Bus)` when the bus is
+    /// busy.
+    pub fn transaction(&mut self, address: u8) -> Result<DeviceTransaction<'_, I, P>, Error> {
+        check_address(address)?;
+        Ok(DeviceTransaction {
+            txn: self.lock()?,
+            address,
+        })
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// issuing commands in the middle of a transfer confuses the state tracking
+    /// of the blocking transfer functions.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`I2c::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut I {
+        &mut self.raw
+    }
+
+    /// Disables the peripheral and its pin routes and returns the raw
+    /// interface to the underlying peripheral. The pins are dropped, see
+    /// [`I2c::release_with_pins()`] to keep them.
+    ///
+    /// A pending stop condition is handled like by
+    /// [`I2c::release_with_pins()`].
+    pub fn release(mut self) -> I {
+        self.disable();
         self.raw
     }
 }
 
-impl<I: I2CX> Read for I2c<I> {
-    type Error = Error;
+/// Transfers with repeated start conditions in between, created by
+/// [`I2c::lock()`].
+///
+/// A not acknowledged address or data byte ends the transaction with a stop
+/// condition, further transfers start a new one.
+pub struct Transaction<'a, I: I2CX, P = ()> {
+    i2c: &'a mut I2c<I, P>,
+}
 
-    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+impl<I: I2CX, P> Transaction<'_, I, P> {
+    /// Writes `bytes` to the device at `address`.
+    pub fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.i2c.write_no_stop(address, bytes)
+    }
+
+    /// Reads into `buffer` from the device at `address`.
+    pub fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.i2c.read_no_stop(address, buffer)
+    }
+
+    /// Writes `bytes` and reads into `buffer` with a repeated start in
+    /// between.
+    pub fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        self.i2c.write_no_stop(address, bytes)?;
+        self.i2c.read_no_stop(address, buffer)
+    }
+
+    /// Ends the transaction with a stop condition, same as dropping it.
+    pub fn stop(self) {}
+}
+
+impl<I: I2CX, P> Drop for Transaction<'_, I, P> {
+    fn drop(&mut self) {
+        // Do not send a stop condition when the bus was already released,
+        // e.g. after a NACK.
+        if self.i2c.raw.state.read().master().bit_is_set() {
+            self.i2c.raw.cmd.write(|w| w.stop().set_bit());
+        }
+    }
+}
+
+/// Transfers to a single device with repeated start conditions in between,
+/// created by [`I2c::transaction()`].
+///
+/// Like [`Transaction`], a not acknowledged address or data byte sends the
+/// stop condition and returns `Err(Error::NACK)`. Further transfers start a
+/// new transaction, e.g. to poll an EEPROM until it acknowledges its address
+/// again after a write cycle.
+pub struct DeviceTransaction<'a, I: I2CX, P = ()> {
+    txn: Transaction<'a, I, P>,
+    address: u8,
+}
+
+impl<I: I2CX, P> DeviceTransaction<'_, I, P> {
+    /// Address of the device.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Writes `bytes` to the device.
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.txn.write(self.address, bytes)
+    }
+
+    /// Reads into `buffer` from the device.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        self.txn.read(self.address, buffer)
+    }
+
+    /// Writes `bytes` and reads into `buffer` with a repeated start in
+    /// between.
+    pub fn write_read(&mut self, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        self.txn.write_read(self.address, bytes, buffer)
+    }
+
+    /// Ends the transaction with a stop condition, same as dropping it.
+    pub fn stop(self) {}
+}
+
+/// Iterator over the addresses of responding devices, created by
+/// [`I2c::scan()`].
+pub struct Scan<'a, I, P = ()> {
+    i2c: &'a mut I2c<I, P>,
+    address: u8,
+}
+
+impl<I: I2CX, P> Iterator for Scan<'_, I, P> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        while self.address <= 0x77 {
+            let address = self.address;
+            self.address += 1;
+
+            if self.i2c.probe(address).is_ok() {
+                return Some(address);
+            }
+        }
+        None
+    }
+}
+
+// Returns the SCL low to high ratio and the value of the `CLKDIV` register
+// for an SCL frequency of `frequency`, or `None` when it is not achievable.
+fn clock_divider(hfperclk: u32, frequency: u32) -> Option<(ClockRatio, u16)> {
+    let clock_ratio = match frequency {
+        0 => return None,
+        1..=100_000 => ClockRatio::STANDARD,
+        100_001..=400_000 => ClockRatio::ASYMMETRIC,
+        _ => ClockRatio::FAST,
+    };
+    let div = compute_div(hfperclk, frequency, clock_ratio).ok()?;
+    Some((clock_ratio, div))
+}
+
+/// Returns the value of the `CLKDIV` register for an SCL frequency of
+/// `frequency` with the SCL low to high ratio `clock_ratio`.
+///
+/// `f_SCL = f_HFPERCLK / (((N_low + N_high) * (DIV + 1)) + 8)` with
+/// `N_low + N_high` being 8 for the standard (4:4), 9 for the asymmetric (6:3)
+/// and 17 for the fast (11:6) ratio. The divider is rounded up, i.e. the SCL
+/// frequency is at most `frequency`, see [`scl_frequency()`] for the actual
+/// value.
+///
+/// The resulting SCL low and high periods are checked against the minimum
+/// periods of the I2C specification for the mode that `frequency` falls
+/// into: 4.7 µs and 4 µs up to 100 kHz (Standard-mode), 1.3 µs and 0.6 µs up
+/// to 400 kHz (Fast-mode) and 0.5 µs and 0.26 µs above (Fast-mode Plus). The
+/// ratios of [`Speed`] always meet them, a standard ratio at 400 kHz does
+/// not.
+///
+/// Returns `Err(Error::Impl(ImplError::InvalidConfiguration))` when the
+/// frequency is zero, above the 1 MHz limit of Fast-mode Plus, too high for
+/// `hfperclk`, too low for the 9-bit divider or when the SCL periods are too
+/// short for the mode.
+///
+/// Pure function without hardware access, used by the constructor and
+/// [`I2c::set_frequency()`].
+pub fn compute_div(hfperclk: u32, frequency: u32, clock_ratio: ClockRatio) -> Result<u16, Error> {
+    const INVALID: Error = Error::Impl(ImplError::InvalidConfiguration);
+
+    if frequency == 0 || frequency > 1_000_000 {
+        return Err(INVALID);
+    }
+    if hfperclk <= 8 * frequency {
+        return Err(INVALID);
+    }
+    let (n_low, n_high) = scl_cycles(clock_ratio);
+    let n = n_low + n_high;
+    // Rounded up, so that the SCL frequency does not exceed `frequency`.
+    let div = (hfperclk - 8 * frequency).div_ceil(n * frequency) - 1;
+    if div >= 512 {
+        return Err(INVALID);
+    }
+
+    // Assumes that the 8 synchronization cycles are split evenly between
+    // both phases.
+    let (min_low, min_high) = min_scl_periods(frequency);
+    let period_ns = |cycles: u32| cycles as u64 * 1_000_000_000 / hfperclk as u64;
+    if period_ns(n_low * (div + 1) + 4) < min_low || period_ns(n_high * (div + 1) + 4) < min_high {
+        debug!(
+            "I2C: SCL low or high period too short for {=u32} Hz",
+            frequency
+        );
+        return Err(INVALID);
+    }
+    Ok(div as u16)
+}
+
+/// Returns the SCL frequency that results from the `CLKDIV` value `div` and
+/// the SCL low to high ratio `clock_ratio`, see [`compute_div()`].
+///
+/// Pure function without hardware access, used by
+/// [`I2c::actual_frequency()`].
+pub fn scl_frequency(hfperclk: u32, div: u16, clock_ratio: ClockRatio) -> Hertz {
+    let (n_low, n_high) = scl_cycles(clock_ratio);
+    Hertz(hfperclk / ((n_low + n_high) * (div as u32 + 1) + 8))
+}
+
+// Returns `N_low` and `N_high` of the SCL low to high ratio.
+fn scl_cycles(clock_ratio: ClockRatio) -> (u32, u32) {
+    match clock_ratio {
+        ClockRatio::STANDARD => (4, 4),
+        ClockRatio::ASYMMETRIC => (6, 3),
+        ClockRatio::FAST => (11, 6),
+    }
+}
+
+// Returns the minimum SCL low and high periods in ns of the I2C specification
+// for the mode that `frequency` falls into.
+fn min_scl_periods(frequency: u32) -> (u64, u64) {
+    match frequency {
+        0..=100_000 => (4700, 4000),
+        100_001..=400_000 => (1300, 600),
+        _ => (500, 260),
+    }
+}
+
+// Rejects addresses which do not fit into 7 bits, e.g. an address which was
+// already shifted to include the R/W bit.
+fn check_address(address: u8) -> Result<(), Error> {
+    if address > 0x7F {
+        return Err(Error::Impl(ImplError::InvalidConfiguration));
+    }
+    Ok(())
+}
+
+impl<I: I2CX, P> I2c<I, P> {
+    fn read_no_stop(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
         // Do not try to read 0 bytes. It is not possible according to the I2C
         // specification, since the slave will always start sending the first
         // byte ACK on an address. The read operation can only be stopped by
@@ -111,49 +1056,112 @@ impl<I: I2CX> Read for I2c<I> {
             return Err(Error::Impl(ImplError::InvalidConfiguration));
         }
 
-        self.raw.cmd.write(|w| w.start().set_bit());
-        self.raw
-            .txdata
-            .write(|w| unsafe { w.txdata().bits((address << 1) | 1) });
+        check_address(address)?;
+        self.start((address << 1) | 1)?;
+        self.read_bytes(buffer, true)
+    }
 
-        self.wait_for_ack()?;
+    // Sends the address byte in write direction followed by a stop
+    // condition. A NACK already sends the stop condition.
+    fn probe(&mut self, address: u8) -> Result<(), Error> {
+        check_address(address)?;
+        self.start(address << 1)?;
+        self.raw.cmd.write(|w| w.stop().set_bit());
+        Ok(())
+    }
 
-        let last_idx = buffer.len() - 1;
+    // Receives bytes and ACKs them. The last byte is NACKed when `nack_last`
+    // is set to end the read transfer.
+    fn read_bytes(&mut self, buffer: &mut [u8], nack_last: bool) -> Result<(), Error> {
+        let last_idx = buffer.len().wrapping_sub(1);
         for (i, b) in buffer.iter_mut().enumerate() {
             // ACK all received bytes but the last.
             // Stop the transfer by sending a NACK.
             self.raw.cmd.write(|w| {
-                if i < last_idx {
+                if i < last_idx || !nack_last {
                     w.ack().set_bit()
                 } else {
                     w.nack().set_bit()
                 }
             });
 
-            // Wait for byte to be received.
-            while self.raw.if_.read().rxdatav().bit_is_clear() {}
+            self.wait_for_rxdata()?;
             *b = self.raw.rxdata.read().rxdata().bits();
         }
+        Ok(())
+    }
 
-        self.raw.cmd.write(|w| w.stop().set_bit());
+    // Waits for a byte to be received.
+    fn wait_for_rxdata(&mut self) -> Result<(), Error> {
+        let mut deadline = self.deadline(1);
+        loop {
+            let if_ = self.raw.if_.read();
+            if if_.arblost().bit_is_set() {
+                return Err(self.arbitration_lost());
+            }
+            if if_.buserr().bit_is_set() {
+                return Err(self.bus_error());
+            }
+            if if_.rxdatav().bit_is_set() {
+                return Ok(());
+            }
+            if deadline.expired() {
+                return Err(self.timed_out());
+            }
+        }
+    }
+}
+
+impl<I: I2CX, P> Read for I2c<I, P> {
+    type Error = Error;
 
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.read_no_stop(address, buffer)?;
+        self.raw.cmd.write(|w| w.stop().set_bit());
         Ok(())
     }
 }
 
-impl<I: I2CX> Write for I2c<I> {
+impl<I: I2CX, P> Write for I2c<I, P> {
     type Error = Error;
 
+    /// Writes `buffer` and sends a stop condition.
+    ///
+    /// An empty `buffer` probes for a device: only the address byte in write
+    /// direction is sent, followed by the stop condition. Returns `Ok(())`
+    /// when the address was acknowledged and `Err(Error::NACK)` when no device
+    /// responded. [`I2c::scan()`] probes the same way.
     fn write(&mut self, address: u8, buffer: &[u8]) -> Result<(), Error> {
+        if buffer.is_empty() {
+            return self.probe(address);
+        }
         self.write_no_stop(address, buffer)?;
         self.raw.cmd.write(|w| w.stop().set_bit());
         Ok(())
     }
 }
 
-impl<I: I2CX> WriteRead for I2c<I> {
+impl<I: I2CX, P> WriteRead for I2c<I, P> {
     type Error = Error;
 
+    /// Writes `bytes` and reads into `buffer` with a repeated start in
+    /// between. The bus is not released between both phases.
+    ///
+    /// Command sequence: `START`, address + W, `bytes`, `START`, address + R,
+    /// `ACK` for all but the last received byte, `NACK`, `STOP`.
+    ///
+    /// No `STOP` is issued between both phases. After the last byte was
+    /// acknowledged, the peripheral keeps the bus and stretches SCL until it
+    /// gets the next command. The second `START` is therefore sent as a
+    /// repeated start condition, which devices need to keep a register
+    /// pointer written in the first phase:
+    ///
+    /// ```text
+    /// S | addr+W | A | bytes... | A | Sr | addr+R | A | data | A ... | data | N | P
+    /// ```
+    ///
+    /// A `NACK` of the write phase sends the `STOP` and returns
+    /// `Err(Error::NACK)` before anything is read.
     fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
         self.write_no_stop(address, bytes)?;
         self.read(address, buffer)?;
@@ -161,10 +1169,40 @@ impl<I: I2CX> WriteRead for I2c<I> {
     }
 }
 
+// Routes the peripheral to the pins, each at the location from the table of
+// its signal.
+fn route<I, SCL, SDA>(i2c: &RegisterBlock)
+where
+    SCL: PinLocation<I, SclPin>,
+    SDA: PinLocation<I, SdaPin>,
+{
+    i2c.routeloc0
+        .write(|w| unsafe { w.sclloc().bits(SCL::LOCATION).sdaloc().bits(SDA::LOCATION) });
+    i2c.routepen
+        .write(|w| w.sclpen().set_bit().sdapen().set_bit());
+}
+
+// LDMA signals of the I2C peripherals.
+const I2C_RXDATAV: u8 = 0;
+const I2C_TXBL: u8 = 1;
+
 /// Internal trait used to implement the I2C API for PAC I2C instances.
-pub trait I2CX: Deref<Target = RegisterBlock> + ClockControlExt {}
-impl I2CX for I2C0 {}
-impl I2CX for I2C1 {}
+pub trait I2CX: Deref<Target = RegisterBlock> + ClockControlExt {
+    /// LDMA request source of the instance.
+    const LDMA_SOURCE: ldma::Source;
+    /// NVIC line of the instance.
+    const INTERRUPT: Interrupt;
+}
+
+impl I2CX for I2C0 {
+    const LDMA_SOURCE: ldma::Source = ldma::Source::I2C0;
+    const INTERRUPT: Interrupt = Interrupt::I2C0;
+}
+
+impl I2CX for I2C1 {
+    const LDMA_SOURCE: ldma::Source = ldma::Source::I2C1;
+    const INTERRUPT: Interrupt = Interrupt::I2C1;
+}
 
 /// Marks a pin that can be used as I2C SCL signal.
 pub struct SclPin;
@@ -313,3 +1351,262 @@ impl_pin_locations!(I2C1, SdaPin, Output, {
     PK1: 30,
     PK2: 31,
 });
+
+#[cfg(feature = "embedded-hal-1")]
+impl<I: I2CX, P> embedded_hal_1::i2c::ErrorType for I2c<I, P> {
+    type Error = crate::error::Decoded<Error>;
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<I: I2CX, P> embedded_hal_1::i2c::I2c for I2c<I, P> {
+    /// Adjacent operations of the same type are merged into one transfer,
+    /// operations of different type are separated by a repeated start. The
+    /// transaction ends with a stop condition, like one of [`I2c::lock()`].
+    ///
+    /// Returns `Err(Error::Impl(ImplError::InvalidConfiguration))` without
+    /// any bus activity when a read buffer is empty.
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal_1::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        use crate::error::Decoded;
+        use embedded_hal_1::i2c::Operation;
+
+        check_address(address).map_err(Decoded)?;
+        // The last byte of a read transfer must be NACKed, which is not
+        // possible when the transfer ends without a byte. Rejected before
+        // the bus is touched, so that no operation is executed partially.
+        if operations
+            .iter()
+            .any(|op| matches!(op, Operation::Read(buffer) if buffer.is_empty()))
+        {
+            return Err(Decoded(Error::Impl(ImplError::InvalidConfiguration)));
+        }
+
+        let txn = self.lock().map_err(Decoded)?;
+        let mut reading = None;
+        let mut ops = operations.iter_mut().peekable();
+        while let Some(op) = ops.next() {
+            let next_is_read = matches!(ops.peek(), Some(Operation::Read(_)));
+            match op {
+                Operation::Write(bytes) => {
+                    if reading != Some(false) {
+                        txn.i2c.start(address << 1).map_err(Decoded)?;
+                        reading = Some(false);
+                    }
+                    for &b in bytes.iter() {
+                        txn.i2c.raw.txdata.write(|w| unsafe { w.txdata().bits(b) });
+                        txn.i2c.wait_for_ack().map_err(Decoded)?;
+                    }
+                }
+                Operation::Read(buffer) => {
+                    if reading != Some(true) {
+                        txn.i2c.start((address << 1) | 1).map_err(Decoded)?;
+                        reading = Some(true);
+                    }
+                    txn.i2c.read_bytes(buffer, !next_is_read).map_err(Decoded)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::{size_of, MaybeUninit};
+
+    // Zeroed memory in place of the I2C registers. Writes to `IFC` and `CMD`
+    // have no effect on the other registers, the tests raise the flags that
+    // the hardware would set.
+    struct Registers(Box<MaybeUninit<RegisterBlock>>);
+
+    impl Deref for Registers {
+        type Target = RegisterBlock;
+
+        fn deref(&self) -> &RegisterBlock {
+            unsafe { &*self.0.as_ptr() }
+        }
+    }
+
+    impl ClockControlExt for Registers {
+        fn enable_clock(&self, _clocks: &mut Cmu) {}
+        fn disable_clock(&self, _clocks: &mut Cmu) {}
+    }
+
+    impl I2CX for Registers {
+        const LDMA_SOURCE: ldma::Source = ldma::Source::I2C0;
+        const INTERRUPT: Interrupt = Interrupt::I2C0;
+    }
+
+    // `CMD` bits.
+    const START: u32 = 1 << 0;
+    const STOP: u32 = 1 << 1;
+    const ABORT: u32 = 1 << 5;
+    const CLEARTX: u32 = 1 << 6;
+
+    // `STATE` bits.
+    const MASTER: u32 = 1 << 1;
+
+    // `IF` bits.
+    const RXDATAV: u32 = 1 << 5;
+    const ACK: u32 = 1 << 6;
+    const NACK: u32 = 1 << 7;
+    const ARBLOST: u32 = 1 << 9;
+
+    fn i2c() -> I2c<Registers> {
+        I2c {
+            raw: Registers(Box::new(MaybeUninit::zeroed())),
+            pins: (),
+            timeout_periods: None,
+            timeout: Some(10),
+            stop_polls: 0,
+        }
+    }
+
+    fn peek<R>(reg: &R) -> u32 {
+        assert_eq!(size_of::<R>(), 4);
+        unsafe { (reg as *const R as *const u32).read_volatile() }
+    }
+
+    fn load<R>(reg: &R, value: u32) {
+        assert_eq!(size_of::<R>(), 4);
+        unsafe { (reg as *const R as *mut u32).write_volatile(value) }
+    }
+
+    // Sets `IF` flags like the hardware would.
+    fn raise(i2c: &I2c<Registers>, flags: u32) {
+        load(&i2c.raw.if_, peek(&i2c.raw.if_) | flags);
+    }
+
+    #[test]
+    fn route_uses_the_table_of_each_signal() {
+        let i2c = i2c();
+        route::<I2C0, Pin<PC11, Output>, Pin<PC10, Output>>(&i2c.raw);
+
+        // PC11 is SCL location 15 and PC10 is SDA location 15. Taken from the
+        // SCL table, PC10 would be location 14.
+        let routeloc0 = i2c.raw.routeloc0.read();
+        assert_eq!(routeloc0.sclloc().bits(), 15);
+        assert_eq!(routeloc0.sdaloc().bits(), 15);
+        assert!(i2c.raw.routepen.read().sclpen().bit_is_set());
+        assert!(i2c.raw.routepen.read().sdapen().bit_is_set());
+    }
+
+    #[test]
+    fn check_address_range() {
+        assert!(check_address(0x00).is_ok());
+        assert!(check_address(0x7F).is_ok());
+        assert!(matches!(
+            check_address(0x80),
+            Err(Error::Impl(ImplError::InvalidConfiguration))
+        ));
+        assert!(matches!(
+            check_address(0xFF),
+            Err(Error::Impl(ImplError::InvalidConfiguration))
+        ));
+    }
+
+    #[test]
+    fn shifted_address_rejected_before_transfer() {
+        let mut i2c = i2c();
+        raise(&i2c, ACK);
+
+        assert!(matches!(
+            i2c.write(0x80, &[0x01]),
+            Err(Error::Impl(ImplError::InvalidConfiguration))
+        ));
+        assert!(matches!(
+            i2c.write(0x90, &[]),
+            Err(Error::Impl(ImplError::InvalidConfiguration))
+        ));
+        assert!(matches!(
+            i2c.read(0xA0, &mut [0]),
+            Err(Error::Impl(ImplError::InvalidConfiguration))
+        ));
+        assert!(matches!(
+            i2c.write_read(0xFE, &[0x01], &mut [0]),
+            Err(Error::Impl(ImplError::InvalidConfiguration))
+        ));
+        assert_eq!(peek(&i2c.raw.txdata), 0);
+        assert_eq!(peek(&i2c.raw.cmd), 0);
+    }
+
+    #[test]
+    fn empty_write_probes_acknowledged_address() {
+        let mut i2c = i2c();
+        raise(&i2c, ACK);
+
+        assert!(i2c.write(0x40, &[]).is_ok());
+        assert_eq!(peek(&i2c.raw.txdata), 0x40 << 1);
+        assert_eq!(peek(&i2c.raw.cmd), STOP);
+    }
+
+    #[test]
+    fn empty_write_reports_missing_device() {
+        let mut i2c = i2c();
+        raise(&i2c, NACK);
+
+        assert!(matches!(i2c.write(0x40, &[]), Err(Error::NACK)));
+        assert_eq!(peek(&i2c.raw.cmd), STOP);
+    }
+
+    #[test]
+    fn arbitration_lost_during_address() {
+        let mut i2c = i2c();
+        raise(&i2c, ARBLOST);
+
+        assert!(matches!(
+            i2c.write(0x40, &[0x01]),
+            Err(Error::ArbitrationLoss)
+        ));
+        // No stop condition, the bus belongs to the other master.
+        assert_eq!(peek(&i2c.raw.cmd), ABORT | CLEARTX);
+    }
+
+    #[test]
+    fn arbitration_lost_mid_transfer() {
+        let mut i2c = i2c();
+        // The address phase is skipped, the flag shows up while waiting for
+        // the data bytes.
+        raise(&i2c, ARBLOST);
+
+        assert!(matches!(
+            i2c.read_bytes(&mut [0; 2], true),
+            Err(Error::ArbitrationLoss)
+        ));
+        assert_eq!(peek(&i2c.raw.cmd), ABORT | CLEARTX);
+
+        assert!(matches!(i2c.wait_for_ack(), Err(Error::ArbitrationLoss)));
+        assert_eq!(peek(&i2c.raw.cmd), ABORT | CLEARTX);
+    }
+
+    #[test]
+    fn write_phase_keeps_the_bus() {
+        let mut i2c = i2c();
+        raise(&i2c, ACK);
+
+        // The last command of the write phase of `write_read()` is the START
+        // of the address, no STOP follows the data bytes.
+        assert!(i2c.write_no_stop(0x40, &[0x12, 0x34]).is_ok());
+        assert_eq!(peek(&i2c.raw.txdata), 0x34);
+        assert_eq!(peek(&i2c.raw.cmd), START);
+    }
+
+    #[test]
+    fn read_register_with_repeated_start() {
+        let mut i2c = i2c();
+        raise(&i2c, ACK | RXDATAV);
+        // The peripheral holds the bus after the write phase.
+        load(&i2c.raw.state, MASTER);
+        load(&i2c.raw.rxdata, 0xAB);
+
+        let mut buffer = [0; 2];
+        assert!(i2c.read_register(0x40, 0x1234, &mut buffer).is_ok());
+        assert_eq!(buffer, [0xAB; 2]);
+        assert_eq!(peek(&i2c.raw.txdata), (0x40 << 1) | 1);
+        assert_eq!(peek(&i2c.raw.cmd), STOP);
+    }
+}
@@ -0,0 +1,314 @@
+//! Interrupt driven I2C transfers
+//!
+//! The transfer functions of [`I2c`] busy-wait for every byte. [`InterruptI2c`]
+//! instead starts a transfer and returns immediately, the I2C interrupt
+//! handler advances it byte by byte. The application polls for the result
+//! with [`InterruptI2c::poll()`], e.g. from its main loop:
+//!
+//! ```ignore
+//! let mut i2c = InterruptI2c::<_, _, 8>::new(i2c);
+//! unsafe { NVIC::unmask(i2c.interrupt()) };
+//! // Move `i2c` into a `Mutex<RefCell<Option<_>>>` shared with the handler,
+//! // which calls `i2c.on_interrupt()`.
+//!
+//! i2c.write_read_nonblocking(0x40, &[0xE3], 2)?;
+//! // ... later on
+//! match i2c.poll() {
+//!     Ok(data) => process(data),
+//!     Err(nb::Error::WouldBlock) => {}
+//!     Err(nb::Error::Other(e)) => report(e),
+//! }
+//! ```
+//!
+//! The bytes to write are copied to and the received bytes are stored in a
+//! buffer of size `N` within the driver, so no buffer has to outlive the
+//! call that starts the transfer.
+//!
+//! There is no software timeout, because nothing runs between two
+//! interrupts. A device that holds SCL low is detected with
+//! [`Config::clock_low_timeout`](super::Config::clock_low_timeout), which ends
+//! the transfer with `Err(Error::Impl(ImplError::TimedOut))`. A device that
+//! never responds at all leaves the transfer running, call
+//! [`InterruptI2c::abort()`] when the application gives up waiting.
+use super::{Error, I2c, I2CX};
+use crate::pac::Interrupt;
+use core::mem;
+use embedded_error::ImplError;
+
+// Progress of the current transfer.
+enum State {
+    Idle,
+    // Waiting for the response to the address byte.
+    Address { read: bool },
+    // Waiting for the response to a data byte.
+    Write,
+    // Waiting for a data byte.
+    Read,
+    Done(Result<(), Error>),
+}
+
+/// I2C master driven by the I2C interrupt with a transfer buffer of size
+/// `N`.
+///
+/// Must be shared between the application and the interrupt handler. Call
+/// [`InterruptI2c::on_interrupt()`] from the `I2Cn` handler. The NVIC line
+/// must be unmasked by the application.
+pub struct InterruptI2c<I: I2CX, P, const N: usize> {
+    i2c: I2c<I, P>,
+    buffer: [u8; N],
+    address: u8,
+    write_len: usize,
+    read_len: usize,
+    // Index of the next byte to write or read.
+    index: usize,
+    state: State,
+}
+
+impl<I: I2CX, P, const N: usize> InterruptI2c<I, P, N> {
+    /// Wraps the blocking driver. Interrupts are only enabled while a
+    /// transfer is running.
+    pub fn new(i2c: I2c<I, P>) -> Self {
+        InterruptI2c {
+            i2c,
+            buffer: [0; N],
+            address: 0,
+            write_len: 0,
+            read_len: 0,
+            index: 0,
+            state: State::Idle,
+        }
+    }
+
+    /// Returns the NVIC line whose handler must call
+    /// [`InterruptI2c::on_interrupt()`]. The application unmasks it.
+    pub fn interrupt(&self) -> Interrupt {
+        I::INTERRUPT
+    }
+
+    /// Starts writing `bytes` to the device at `address`, followed by a stop
+    /// condition.
+    ///
+    /// An empty `bytes` probes for a device, like the blocking
+    /// [`Write::write()`](crate::hal::blocking::i2c::Write::write).
+    pub fn write_nonblocking(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.write_read_nonblocking(address, bytes, 0)
+    }
+
+    /// Starts reading `len` bytes from the device at `address`, followed by a
+    /// stop condition.
+    ///
+    /// The received bytes are returned by [`InterruptI2c::poll()`].
+    pub fn read_nonblocking(&mut self, address: u8, len: usize) -> Result<(), Error> {
+        if len == 0 {
+            return Err(Error::Impl(ImplError::InvalidConfiguration));
+        }
+        self.write_read_nonblocking(address, &[], len)
+    }
+
+    /// Starts writing `bytes` and reading `len` bytes with a repeated start in
+    /// between, followed by a stop condition. `len` may be 0 for a plain
+    /// write.
+    ///
+    /// Returns `Err(Error::Bus)` when the previous transfer is still running
+    /// or the bus is busy, and
+    /// `Err(Error::Impl(ImplError::InvalidConfiguration))` when `bytes` or
+    /// `len` exceed the buffer size `N` or the address does not fit into 7
+    /// bits. The result of a finished transfer that was not polled is
+    /// discarded.
+    pub fn write_read_nonblocking(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        len: usize,
+    ) -> Result<(), Error> {
+        if bytes.len() > N || len > N {
+            return Err(Error::Impl(ImplError::InvalidConfiguration));
+        }
+        super::check_address(address)?;
+        if self.is_busy() || self.i2c.raw.state.read().busy().bit_is_set() {
+            return Err(Error::Bus);
+        }
+
+        self.buffer[..bytes.len()].copy_from_slice(bytes);
+        self.address = address;
+        self.write_len = bytes.len();
+        self.read_len = len;
+        if bytes.is_empty() && len > 0 {
+            self.start(true);
+        } else {
+            self.start(false);
+        }
+        Ok(())
+    }
+
+    /// Returns the received bytes once the transfer is done, or its error.
+    ///
+    /// Returns `WouldBlock` while the transfer is running and
+    /// `Err(Error::Impl(ImplError::InvalidConfiguration))` when no transfer
+    /// was started since the last result. Writes return an empty slice.
+    pub fn poll(&mut self) -> nb::Result<&[u8], Error> {
+        match mem::replace(&mut self.state, State::Idle) {
+            State::Idle => Err(nb::Error::Other(Error::Impl(
+                ImplError::InvalidConfiguration,
+            ))),
+            State::Done(Ok(())) => Ok(&self.buffer[..self.read_len]),
+            State::Done(Err(e)) => Err(nb::Error::Other(e)),
+            state => {
+                self.state = state;
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+
+    /// Returns `true` while a transfer is running.
+    pub fn is_busy(&self) -> bool {
+        !matches!(self.state, State::Idle | State::Done(_))
+    }
+
+    /// Cancels a running transfer without a stop condition and resets the
+    /// state machine of the peripheral, e.g. when a device did not respond
+    /// in time. See [`I2c::recover()`] when a device holds SDA low.
+    pub fn abort(&mut self) {
+        self.disable_interrupts();
+        if self.is_busy() {
+            self.i2c.timed_out();
+            self.state = State::Idle;
+        }
+    }
+
+    /// Advances the transfer on `ACK`, `NACK`, `RXDATAV`, `ARBLOST`, `BUSERR`
+    /// and `CLTO`.
+    ///
+    /// Must be called from the `I2Cn` interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        if !self.is_busy() {
+            return;
+        }
+
+        let if_ = self.i2c.raw.if_.read();
+        if if_.arblost().bit_is_set() {
+            let e = self.i2c.arbitration_lost();
+            self.finish(Err(e));
+            return;
+        }
+        if if_.buserr().bit_is_set() {
+            let e = self.i2c.bus_error();
+            self.finish(Err(e));
+            return;
+        }
+        if if_.clto().bit_is_set() {
+            self.i2c.raw.ifc.write(|w| w.clto().set_bit());
+            let e = self.i2c.timed_out();
+            self.finish(Err(e));
+            return;
+        }
+        if if_.nack().bit_is_set() {
+            self.i2c.raw.ifc.write(|w| w.nack().set_bit());
+            self.i2c.raw.cmd.write(|w| w.stop().set_bit());
+            self.finish(Err(Error::NACK));
+            return;
+        }
+
+        match self.state {
+            State::Address { read: false } | State::Write if if_.ack().bit_is_set() => {
+                self.i2c.raw.ifc.write(|w| w.ack().set_bit());
+                if self.index < self.write_len {
+                    let b = self.buffer[self.index];
+                    self.index += 1;
+                    self.i2c.raw.txdata.write(|w| unsafe { w.txdata().bits(b) });
+                    self.state = State::Write;
+                } else if self.read_len > 0 {
+                    self.start(true);
+                } else {
+                    self.i2c.raw.cmd.write(|w| w.stop().set_bit());
+                    self.finish(Ok(()));
+                }
+            }
+            State::Address { read: true } if if_.ack().bit_is_set() => {
+                self.i2c.raw.ifc.write(|w| w.ack().set_bit());
+                self.acknowledge_next();
+                self.state = State::Read;
+            }
+            State::Read if if_.rxdatav().bit_is_set() => {
+                self.buffer[self.index] = self.i2c.raw.rxdata.read().rxdata().bits();
+                self.index += 1;
+                if self.index < self.read_len {
+                    self.acknowledge_next();
+                } else {
+                    self.i2c.raw.cmd.write(|w| w.stop().set_bit());
+                    self.finish(Ok(()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Sends a (repeated) start condition followed by the address byte, see
+    // `I2c::start()`.
+    fn start(&mut self, read: bool) {
+        self.index = 0;
+        self.state = State::Address { read };
+
+        let raw = &self.i2c.raw;
+        raw.ifc.write(|w| {
+            w.ack()
+                .set_bit()
+                .nack()
+                .set_bit()
+                .arblost()
+                .set_bit()
+                .buserr()
+                .set_bit()
+        });
+        raw.ien.write(|w| {
+            w.ack()
+                .set_bit()
+                .nack()
+                .set_bit()
+                .rxdatav()
+                .set_bit()
+                .arblost()
+                .set_bit()
+                .buserr()
+                .set_bit()
+                .clto()
+                .set_bit()
+        });
+        let address_byte = (self.address << 1) | read as u8;
+        if raw.state.read().master().bit_is_set() {
+            raw.cmd.write(|w| w.start().set_bit());
+            raw.txdata
+                .write(|w| unsafe { w.txdata().bits(address_byte) });
+        } else {
+            raw.txdata
+                .write(|w| unsafe { w.txdata().bits(address_byte) });
+            raw.cmd.write(|w| w.start().set_bit());
+        }
+    }
+
+    // Issues the response to the next received byte in advance. The last
+    // byte is NACKed to end the read transfer.
+    fn acknowledge_next(&mut self) {
+        if self.index + 1 < self.read_len {
+            self.i2c.raw.cmd.write(|w| w.ack().set_bit());
+        } else {
+            self.i2c.raw.cmd.write(|w| w.nack().set_bit());
+        }
+    }
+
+    fn finish(&mut self, result: Result<(), Error>) {
+        self.disable_interrupts();
+        self.state = State::Done(result);
+    }
+
+    fn disable_interrupts(&mut self) {
+        self.i2c.raw.ien.reset();
+    }
+
+    /// Cancels a running transfer, see [`InterruptI2c::abort()`], and returns
+    /// the blocking driver.
+    pub fn free(mut self) -> I2c<I, P> {
+        self.abort();
+        self.i2c
+    }
+}
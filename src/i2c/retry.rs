@@ -0,0 +1,126 @@
+//! Automatic retries for devices that NACK while busy
+//!
+//! Some devices do not acknowledge their address while they are busy, e.g.
+//! the Si7021 during a measurement or an EEPROM during a write cycle.
+//! [`RetryI2c`] wraps any blocking I2C implementation and repeats a transfer
+//! that failed with a NACK, optionally waiting in between attempts.
+//!
+//! Only NACKs are retried. Bus errors, arbitration loss and timeouts are
+//! returned immediately, repeating a transfer does not fix a broken bus.
+use super::Error;
+use crate::hal::blocking::{
+    delay::DelayMs,
+    i2c::{Read, Write, WriteRead},
+};
+
+/// Implemented by I2C error types whose NACK errors can be retried.
+pub trait Nack {
+    /// Returns `true` for errors caused by a not acknowledged byte.
+    fn is_nack(&self) -> bool;
+}
+
+impl Nack for Error {
+    fn is_nack(&self) -> bool {
+        matches!(self, Error::NACK)
+    }
+}
+
+/// Blocking I2C wrapper that retries NACKed transfers.
+///
+/// A transfer is attempted at most `1 + retries` times. Each retry repeats
+/// the whole transfer, so a NACK in the middle of a write sends the leading
+/// bytes again.
+pub struct RetryI2c<I2C, D> {
+    i2c: I2C,
+    delay: D,
+    retries: u8,
+    delay_ms: u32,
+}
+
+impl<I2C, D: DelayMs<u32>> RetryI2c<I2C, D> {
+    /// Wraps `i2c` and waits `delay_ms` milliseconds with `delay` before each
+    /// retry. A delay of 0 retries immediately.
+    pub fn new(i2c: I2C, delay: D, retries: u8, delay_ms: u32) -> Self {
+        RetryI2c {
+            i2c,
+            delay,
+            retries,
+            delay_ms,
+        }
+    }
+
+    /// Sets the number of retries after the first attempt.
+    pub fn set_retries(&mut self, retries: u8) {
+        self.retries = retries;
+    }
+
+    /// Sets the delay before each retry in milliseconds.
+    pub fn set_delay_ms(&mut self, delay_ms: u32) {
+        self.delay_ms = delay_ms;
+    }
+
+    /// Returns the wrapped I2C implementation and the delay.
+    pub fn free(self) -> (I2C, D) {
+        (self.i2c, self.delay)
+    }
+
+    fn retry<E: Nack>(&mut self, mut f: impl FnMut(&mut I2C) -> Result<(), E>) -> Result<(), E> {
+        let mut retries = self.retries;
+        loop {
+            match f(&mut self.i2c) {
+                Err(e) if e.is_nack() && retries > 0 => {
+                    retries -= 1;
+                    if self.delay_ms > 0 {
+                        self.delay.delay_ms(self.delay_ms);
+                    }
+                    trace!("I2C: NACK, {=u8} retries left", retries);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<I2C, D> Read for RetryI2c<I2C, D>
+where
+    I2C: Read,
+    I2C::Error: Nack,
+    D: DelayMs<u32>,
+{
+    type Error = I2C::Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.retry(|i2c| i2c.read(address, buffer))
+    }
+}
+
+impl<I2C, D> Write for RetryI2c<I2C, D>
+where
+    I2C: Write,
+    I2C::Error: Nack,
+    D: DelayMs<u32>,
+{
+    type Error = I2C::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.retry(|i2c| i2c.write(address, bytes))
+    }
+}
+
+impl<I2C, D> WriteRead for RetryI2c<I2C, D>
+where
+    I2C: WriteRead,
+    I2C::Error: Nack,
+    D: DelayMs<u32>,
+{
+    type Error = I2C::Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.retry(|i2c| i2c.write_read(address, bytes, buffer))
+    }
+}
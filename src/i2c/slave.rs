@@ -0,0 +1,258 @@
+//! I2C slave API
+//!
+//! [`I2cSlave`] responds to a master on the bus at its own address. Each step
+//! of a transfer is reported as an [`Event`] by [`I2cSlave::on_interrupt()`].
+//! The peripheral holds SCL low after the address byte, after each received
+//! byte and when the master requests a byte, until the application answered
+//! with [`I2cSlave::ack()`], [`I2cSlave::nack()`] or [`I2cSlave::write()`]:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn I2C0() {
+//!     // `slave` is shared with the application, e.g. in a `Mutex`.
+//!     while let Some(event) = slave.on_interrupt() {
+//!         match event {
+//!             Event::AddressMatch { read: false } => slave.ack(),
+//!             Event::AddressMatch { read: true } => {
+//!                 slave.ack();
+//!                 slave.write(registers[index]);
+//!             }
+//!             Event::DataReceived(byte) => {
+//!                 index = byte as usize;
+//!                 slave.ack();
+//!             }
+//!             Event::DataRequested => {
+//!                 index += 1;
+//!                 slave.write(registers[index]);
+//!             }
+//!             Event::Stop | Event::BusError => {}
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Holding the bus stretches the clock for the master, so the events should be
+//! handled in the interrupt handler rather than polled from a slow main loop.
+use super::{Error, SclPin, SdaPin, I2CX};
+use crate::{
+    cmu::Cmu,
+    gpio::PinLocation,
+    pac::{i2c0::RegisterBlock, Interrupt},
+};
+use embedded_error::ImplError;
+
+/// Step of a transfer addressed to the slave.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A master sent a start condition followed by a matching address.
+    ///
+    /// Answer with [`I2cSlave::ack()`] to accept the transfer or with
+    /// [`I2cSlave::nack()`] to reject it. A read (`read: true`) also needs the
+    /// first byte, write it with [`I2cSlave::write()`] after the ACK.
+    AddressMatch {
+        /// The master reads from the slave.
+        read: bool,
+    },
+    /// The master wrote a byte. Answer with [`I2cSlave::ack()`] to accept
+    /// more bytes or with [`I2cSlave::nack()`] to end the write.
+    DataReceived(u8),
+    /// The master acknowledged the previous byte and reads the next one,
+    /// write it with [`I2cSlave::write()`].
+    DataRequested,
+    /// The master ended the transfer with a stop condition.
+    ///
+    /// A repeated start instead shows up as the next
+    /// [`Event::AddressMatch`].
+    Stop,
+    /// A misplaced start or stop condition was detected. The peripheral
+    /// aborted the transfer and waits for the next address.
+    BusError,
+}
+
+/// I2C slave API
+///
+/// Owns the SCL and SDA pins as `P`, like [`I2c`](super::I2c).
+pub struct I2cSlave<I, P = ()> {
+    raw: I,
+    pins: P,
+}
+
+impl<I: I2CX, SCL, SDA> I2cSlave<I, (SCL, SDA)> {
+    /// Enables the peripheral in slave mode and routes it to the pins.
+    ///
+    /// The slave responds to all 7-bit addresses that equal `address` in the
+    /// bits set in `address_mask`, i.e. `0x7F` for `address` only and `0x7E`
+    /// for `address` and `address | 1`. The `ADDR`, `RXDATAV`, `ACK`,
+    /// `SSTOP` and `BUSERR` interrupts are enabled, the NVIC line must be
+    /// unmasked by the application, see [`I2cSlave::interrupt()`].
+    ///
+    /// Returns `Err(Error::Impl(ImplError::InvalidConfiguration))` when the
+    /// address does not fit into 7 bits. The peripheral is not touched in
+    /// that case.
+    pub fn new(
+        i2c: I,
+        scl: SCL,
+        sda: SDA,
+        address: u8,
+        address_mask: u8,
+        cmu: &mut Cmu,
+    ) -> Result<Self, Error>
+    where
+        SCL: PinLocation<I, SclPin>,
+        SDA: PinLocation<I, SdaPin>,
+    {
+        if address > 0x7F || address_mask > 0x7F {
+            return Err(Error::Impl(ImplError::InvalidConfiguration));
+        }
+
+        cmu.enable_clock(&i2c);
+        i2c.saddr.write(|w| unsafe { w.addr().bits(address) });
+        i2c.saddrmask
+            .write(|w| unsafe { w.mask().bits(address_mask) });
+        i2c.ctrl.write(|w| w.slave().set_bit().en().set_bit());
+
+        // Busy flag is set after reset, use the ABORT command to clear it.
+        if i2c.state.read().busy().bit_is_set() {
+            i2c.cmd.write(|w| w.abort().set_bit());
+        }
+
+        // Clear pendig commands and the TX buffers.
+        i2c.cmd.write(|w| w.clearpc().set_bit().cleartx().set_bit());
+
+        // Route peripheral to pins.
+        i2c.routeloc0
+            .write(|w| unsafe { w.sclloc().bits(SCL::LOCATION).sdaloc().bits(SDA::LOCATION) });
+        i2c.routepen
+            .write(|w| w.sclpen().set_bit().sdapen().set_bit());
+
+        i2c.ifc.write(|w| unsafe { w.bits(!0) });
+        i2c.ien.write(|w| {
+            w.addr()
+                .set_bit()
+                .rxdatav()
+                .set_bit()
+                .ack()
+                .set_bit()
+                .sstop()
+                .set_bit()
+                .buserr()
+                .set_bit()
+        });
+
+        Ok(Self {
+            raw: i2c,
+            pins: (scl, sda),
+        })
+    }
+
+    /// Disables the peripheral, its interrupts and its pin routes and returns
+    /// the peripheral and the pins.
+    ///
+    /// A transfer in progress is cut off, the master sees the bus released.
+    pub fn release_with_pins(self) -> (I, SCL, SDA) {
+        self.disable();
+        let (scl, sda) = self.pins;
+        (self.raw, scl, sda)
+    }
+}
+
+impl<I: I2CX, P> I2cSlave<I, P> {
+    /// Returns the NVIC line whose handler must call
+    /// [`I2cSlave::on_interrupt()`]. The application unmasks it.
+    pub fn interrupt(&self) -> Interrupt {
+        I::INTERRUPT
+    }
+
+    /// Returns the oldest event that was not reported yet, `None` when there
+    /// is nothing to do.
+    ///
+    /// Call it from the `I2Cn` interrupt handler until it returns `None`.
+    /// The interrupt stays pending while an event was not reported.
+    pub fn on_interrupt(&mut self) -> Option<Event> {
+        let if_ = self.raw.if_.read();
+        if if_.buserr().bit_is_set() {
+            self.raw.ifc.write(|w| w.buserr().set_bit());
+            return Some(Event::BusError);
+        }
+        // A stop condition that is reported together with one of the events
+        // below ended the previous transfer. The bus is held for those, so
+        // nothing can happen on the bus after them.
+        if if_.sstop().bit_is_set() {
+            self.raw.ifc.write(|w| w.sstop().set_bit().nack().set_bit());
+            return Some(Event::Stop);
+        }
+        if if_.addr().bit_is_set() {
+            // The address byte with the R/W bit is stored in the receive
+            // buffer, reading it clears `RXDATAV`.
+            let address = self.raw.rxdata.read().rxdata().bits();
+            self.raw.ifc.write(|w| w.addr().set_bit());
+            return Some(Event::AddressMatch {
+                read: address & 1 != 0,
+            });
+        }
+        if if_.rxdatav().bit_is_set() {
+            let byte = self.raw.rxdata.read().rxdata().bits();
+            return Some(Event::DataReceived(byte));
+        }
+        if if_.ack().bit_is_set() {
+            self.raw.ifc.write(|w| w.ack().set_bit());
+            return Some(Event::DataRequested);
+        }
+        None
+    }
+
+    /// Acknowledges the address or the received byte.
+    pub fn ack(&mut self) {
+        self.raw.cmd.write(|w| w.ack().set_bit());
+    }
+
+    /// Rejects the address or the received byte. The master ends the
+    /// transfer.
+    pub fn nack(&mut self) {
+        self.raw.cmd.write(|w| w.nack().set_bit());
+    }
+
+    /// Sends `byte` to the master after [`Event::DataRequested`] or a read
+    /// [`Event::AddressMatch`].
+    ///
+    /// The master NACKs the last byte it reads and ends the transfer, no
+    /// event is reported until the following [`Event::Stop`].
+    pub fn write(&mut self, byte: u8) {
+        self.raw.txdata.write(|w| unsafe { w.txdata().bits(byte) });
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// clearing interrupt flags loses events.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`I2cSlave::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut I {
+        &mut self.raw
+    }
+
+    /// Disables the peripheral, its interrupts and its pin routes and returns
+    /// the raw interface to the underlying peripheral. The pins are dropped,
+    /// see [`I2cSlave::release_with_pins()`] to keep them.
+    pub fn release(self) -> I {
+        self.disable();
+        self.raw
+    }
+
+    fn disable(&self) {
+        self.raw.ien.reset();
+        self.raw.routepen.reset();
+        self.raw.routeloc0.reset();
+        self.raw.ctrl.reset();
+    }
+}
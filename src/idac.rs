@@ -0,0 +1,152 @@
+//! Current digital to analog converter (IDAC) API
+//!
+//! The IDAC sources or sinks a programmable current on its main output pin,
+//! e.g. to bias a sensor or to measure a resistance with the ADC.
+//!
+//! # Output glitches
+//!
+//! Enabling the current source and connecting the output in the same write
+//! lets the pin see the current while the source is still settling, which
+//! shows up as a short spike above the programmed current. A sensor biased
+//! by the IDAC, e.g. a photodiode or a gas sensor heater, can be upset by
+//! that spike.
+//!
+//! [`Idac::enable_glitch_free()`] programs the current first, enables the
+//! source with the output disconnected, waits until the current is stable
+//! (`STATUS.CURSTABLE`) and then connects the output with the minimum output
+//! transition mode, which switches the output path without a spike.
+//! [`Idac::disable()`] disconnects the output before turning the source off.
+//! [`Idac::enable()`] enables the source and the output at once for
+//! applications that do not care about the transition.
+pub use crate::pac::idac0::curprog::RANGESEL_A as Range;
+
+use crate::{cmu::Cmu, pac::IDAC0};
+
+/// IDAC configuration.
+///
+/// The output current is `range_start + step * step_size` with 32 steps per
+/// range:
+///
+/// | Range    | Current         | Step size |
+/// |----------|----------------:|----------:|
+/// | `RANGE0` | 0.05 to 1.6 µA  |   50 nA   |
+/// | `RANGE1` | 1.6 to 4.7 µA   |  100 nA   |
+/// | `RANGE2` | 0.5 to 16 µA    |  500 nA   |
+/// | `RANGE3` | 2 to 64 µA      |    2 µA   |
+///
+/// Defaults to the lowest current of the lowest range, sourced.
+pub struct Config {
+    /// Current range.
+    pub range: Range,
+    /// Step within the range, `0..=31`.
+    pub step: u8,
+    /// Sinks the current instead of sourcing it.
+    pub sink: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            range: Range::RANGE0,
+            step: 0,
+            sink: false,
+        }
+    }
+}
+
+/// IDAC API
+pub struct Idac {
+    raw: IDAC0,
+}
+
+impl Idac {
+    /// Creates the HAL instance and programs the current. The IDAC stays
+    /// disabled.
+    ///
+    /// Panics when the step is out of range.
+    pub fn new(idac: IDAC0, config: &Config, cmu: &mut Cmu) -> Idac {
+        cmu.enable_clock(&idac);
+
+        let mut idac = Idac { raw: idac };
+        idac.set_current(config.range, config.step);
+        idac.raw.ctrl.write(|w| w.cursink().bit(config.sink));
+        idac
+    }
+
+    /// Changes the output current, also while the IDAC is enabled.
+    ///
+    /// Panics when the step is out of range.
+    pub fn set_current(&mut self, range: Range, step: u8) {
+        assert!(step <= 31);
+        // Keep the factory tuning of the reset value.
+        self.raw
+            .curprog
+            .modify(|_, w| unsafe { w.rangesel().variant(range).stepsel().bits(step) });
+    }
+
+    /// Changes between sinking and sourcing the current.
+    ///
+    /// Change the direction while the IDAC is disabled, the pin otherwise
+    /// sees the current reversing through zero.
+    pub fn set_sink(&mut self, sink: bool) {
+        self.raw.ctrl.modify(|_, w| w.cursink().bit(sink));
+    }
+
+    /// Enables the current source and connects the output pin once the
+    /// current is stable.
+    ///
+    /// See the [module documentation](self) for the sequence.
+    pub fn enable_glitch_free(&mut self) {
+        self.raw.ctrl.modify(|_, w| {
+            w.mainouten()
+                .clear_bit()
+                .minouttrans()
+                .set_bit()
+                .en()
+                .set_bit()
+        });
+        while self.raw.status.read().curstable().bit_is_clear() {}
+        self.raw.ctrl.modify(|_, w| w.mainouten().set_bit());
+    }
+
+    /// Enables the current source and the output pin at the same time.
+    pub fn enable(&mut self) {
+        self.raw
+            .ctrl
+            .modify(|_, w| w.en().set_bit().mainouten().set_bit());
+    }
+
+    /// Disconnects the output pin and disables the current source.
+    pub fn disable(&mut self) {
+        self.raw.ctrl.modify(|_, w| w.mainouten().clear_bit());
+        self.raw.ctrl.modify(|_, w| w.en().clear_bit());
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// connecting the output before the current is stable reintroduces the
+    /// glitch.
+    pub unsafe fn raw(&self) -> &crate::pac::idac0::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Idac::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut IDAC0 {
+        &mut self.raw
+    }
+
+    /// Disables the IDAC and returns the raw interface to the underlying
+    /// peripheral.
+    pub fn release(mut self) -> IDAC0 {
+        self.disable();
+        self.raw
+    }
+}
@@ -0,0 +1,589 @@
+//! Linked DMA controller (LDMA) API
+//!
+//! [`Ldma::new()`] enables the controller and hands out its eight channels.
+//! Drivers borrow a [`Channel`] for the duration of a DMA transfer, e.g.
+//! [`I2c::read_dma()`](crate::i2c::I2c::read_dma) or
+//! [`Rx::receive_packet_dma()`](crate::usart::Rx::receive_packet_dma).
+//!
+//! The transfers of these drivers are programmed directly into the channel
+//! registers without descriptors in memory. Circular transfers, e.g. for
+//! [`CircularRx`](crate::usart::circular::CircularRx), use a single
+//! descriptor per channel that links to itself.
+//!
+//! For other transfers the application describes each block with a
+//! [`Descriptor`], memory to memory, memory to peripheral or peripheral to
+//! memory. Descriptors can be linked into a list, the channel then continues
+//! with the next descriptor when one is done. [`Channel::start()`] returns a
+//! [`Transfer`] to wait for the completion:
+//!
+//! ```ignore
+//! static SRC: [u32; 64] = [0x55AA_55AA; 64];
+//! let mut dst = [0u32; 64];
+//!
+//! let (_ldma, mut channels) = Ldma::new(p.LDMA, &mut cmu);
+//! let descriptor = Descriptor::memory_to_memory(&SRC, &mut dst);
+//! unsafe { channels.ch0.start(&descriptor, None) }.wait();
+//! ```
+//!
+//! Peripheral transfers are paced by a [`Request`] of the peripheral, e.g.
+//! one unit per [`Request::USART0_TXBL`].
+pub use crate::pac::ldma::ch0_reqsel::SOURCESEL_A as Source;
+
+use crate::{
+    cmu::Cmu,
+    pac::{
+        ldma::{
+            ch0_ctrl::{DSTINC_A, SIZE_A, SRCINC_A},
+            CH0_CFG, CH0_CTRL, CH0_DST, CH0_LINK, CH0_LOOP, CH0_REQSEL, CH0_SRC,
+        },
+        Interrupt, LDMA,
+    },
+};
+use core::{
+    marker::PhantomData,
+    ptr::addr_of_mut,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+/// Maximum number of units moved by a single transfer.
+pub const MAX_TRANSFER_COUNT: usize = 2048;
+
+// Offset of the first channel and distance between the register sets of two
+// channels.
+const CHANNEL_OFFSET: usize = 0x80;
+const CHANNEL_STRIDE: usize = 0x30;
+const CHANNEL_COUNT: usize = 8;
+
+// The register sets of all channels share the layout of channel 0.
+#[repr(C)]
+struct ChannelRegisters {
+    reqsel: CH0_REQSEL,
+    cfg: CH0_CFG,
+    loop_: CH0_LOOP,
+    ctrl: CH0_CTRL,
+    src: CH0_SRC,
+    dst: CH0_DST,
+    link: CH0_LINK,
+}
+
+/// Transfer descriptor in memory, the channel loads it into its `CTRL`,
+/// `SRC`, `DST` and `LINK` registers.
+///
+/// Each descriptor moves up to [`MAX_TRANSFER_COUNT`] units of type `u8`,
+/// `u16` or `u32` and sets the `DONE` interrupt flag of the channel when it
+/// is done.
+#[repr(C)]
+pub struct Descriptor {
+    ctrl: u32,
+    src: u32,
+    dst: u32,
+    link: u32,
+}
+
+// Fields of the `CTRL` word of a descriptor, same layout as `CHx_CTRL`.
+const CTRL_STRUCTREQ: u32 = 1 << 3;
+const CTRL_XFERCNT_SHIFT: u32 = 4;
+const CTRL_DONEIFSEN: u32 = 1 << 20;
+const CTRL_REQMODE_ALL: u32 = 1 << 21;
+const CTRL_SRCINC_SHIFT: u32 = 24;
+const CTRL_SIZE_SHIFT: u32 = 26;
+const CTRL_DSTINC_SHIFT: u32 = 28;
+
+impl Descriptor {
+    /// Copies `src` to `dst` as fast as possible, without waiting for a
+    /// peripheral.
+    ///
+    /// Panics when the slices differ in length, are empty or longer than
+    /// [`MAX_TRANSFER_COUNT`].
+    pub fn memory_to_memory<W: Word>(src: &[W], dst: &mut [W]) -> Self {
+        assert_eq!(src.len(), dst.len());
+        let mut descriptor = Self::new::<W>(
+            (src.as_ptr() as u32, true),
+            (dst.as_mut_ptr() as u32, true),
+            src.len(),
+        );
+        // Requested by loading the descriptor and moved without a break.
+        descriptor.ctrl |= CTRL_STRUCTREQ | CTRL_REQMODE_ALL;
+        descriptor
+    }
+
+    /// Writes `src` to the peripheral register at `dst`, one unit per
+    /// request.
+    ///
+    /// Panics when `src` is empty or longer than [`MAX_TRANSFER_COUNT`].
+    pub fn memory_to_peripheral<W: Word>(src: &[W], dst: *mut W) -> Self {
+        Self::new::<W>((src.as_ptr() as u32, true), (dst as u32, false), src.len())
+    }
+
+    /// Reads the peripheral register at `src` into `dst`, one unit per
+    /// request.
+    ///
+    /// Panics when `dst` is empty or longer than [`MAX_TRANSFER_COUNT`].
+    pub fn peripheral_to_memory<W: Word>(src: *const W, dst: &mut [W]) -> Self {
+        Self::new::<W>(
+            (src as u32, false),
+            (dst.as_mut_ptr() as u32, true),
+            dst.len(),
+        )
+    }
+
+    fn new<W: Word>(
+        (src, src_inc): (u32, bool),
+        (dst, dst_inc): (u32, bool),
+        count: usize,
+    ) -> Self {
+        assert!(count > 0 && count <= MAX_TRANSFER_COUNT);
+        let inc = |inc: bool| u8::from(if inc { SRCINC_A::ONE } else { SRCINC_A::NONE }) as u32;
+        Descriptor {
+            ctrl: (count as u32 - 1) << CTRL_XFERCNT_SHIFT
+                | CTRL_DONEIFSEN
+                | inc(src_inc) << CTRL_SRCINC_SHIFT
+                | (u8::from(W::SIZE) as u32) << CTRL_SIZE_SHIFT
+                | inc(dst_inc) << CTRL_DSTINC_SHIFT,
+            src,
+            dst,
+            link: 0,
+        }
+    }
+
+    /// Continues with `next` when this descriptor is done. The request of
+    /// the transfer applies to all descriptors of the list.
+    ///
+    /// `next` must stay at the same address until the transfer is done.
+    pub fn link(&mut self, next: &Descriptor) {
+        self.link = next as *const _ as u32 | LINK;
+    }
+}
+
+/// Internal trait implemented by the unit types of a transfer. Leaked because
+/// it is used as trait bound. Not relevant for the user.
+pub trait Word {
+    /// Unit size (`CTRL.SIZE`).
+    const SIZE: SIZE_A;
+}
+
+impl Word for u8 {
+    const SIZE: SIZE_A = SIZE_A::BYTE;
+}
+
+impl Word for u16 {
+    const SIZE: SIZE_A = SIZE_A::HALFWORD;
+}
+
+impl Word for u32 {
+    const SIZE: SIZE_A = SIZE_A::WORD;
+}
+
+/// Peripheral signal that requests the next unit of a transfer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Request {
+    source: Source,
+    signal: u8,
+}
+
+impl Request {
+    pub const USART0_RXDATAV: Request = Request::new(Source::USART0, 0);
+    pub const USART0_TXBL: Request = Request::new(Source::USART0, 2);
+    pub const USART0_TXEMPTY: Request = Request::new(Source::USART0, 4);
+    pub const USART1_RXDATAV: Request = Request::new(Source::USART1, 0);
+    pub const USART1_TXBL: Request = Request::new(Source::USART1, 2);
+    pub const USART1_TXEMPTY: Request = Request::new(Source::USART1, 4);
+    pub const USART2_RXDATAV: Request = Request::new(Source::USART2, 0);
+    pub const USART2_TXBL: Request = Request::new(Source::USART2, 2);
+    pub const USART2_TXEMPTY: Request = Request::new(Source::USART2, 4);
+    pub const USART3_RXDATAV: Request = Request::new(Source::USART3, 0);
+    pub const USART3_TXBL: Request = Request::new(Source::USART3, 2);
+    pub const USART3_TXEMPTY: Request = Request::new(Source::USART3, 4);
+    pub const I2C0_RXDATAV: Request = Request::new(Source::I2C0, 0);
+    pub const I2C0_TXBL: Request = Request::new(Source::I2C0, 1);
+    pub const I2C1_RXDATAV: Request = Request::new(Source::I2C1, 0);
+    pub const I2C1_TXBL: Request = Request::new(Source::I2C1, 1);
+    pub const ADC0_SINGLE: Request = Request::new(Source::ADC0, 0);
+    pub const ADC0_SCAN: Request = Request::new(Source::ADC0, 1);
+    pub const VDAC0_CH0: Request = Request::new(Source::VDAC0, 0);
+    pub const VDAC0_CH1: Request = Request::new(Source::VDAC0, 1);
+
+    /// Request with the signal number `signal` of the peripheral `source`
+    /// (`CHx_REQSEL.SIGSEL`), see the reference manual for the signals not
+    /// listed as constants.
+    pub const fn new(source: Source, signal: u8) -> Request {
+        Request { source, signal }
+    }
+
+    /// Requesting peripheral (`CHx_REQSEL.SOURCESEL`).
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// Signal of the requesting peripheral (`CHx_REQSEL.SIGSEL`).
+    pub fn signal(&self) -> u8 {
+        self.signal
+    }
+}
+
+const EMPTY_DESCRIPTOR: Descriptor = Descriptor {
+    ctrl: 0,
+    src: 0,
+    dst: 0,
+    link: 0,
+};
+
+// One descriptor per channel for circular transfers, only accessed through
+// the `Channel` with the same number.
+static mut DESCRIPTORS: [Descriptor; CHANNEL_COUNT] = [EMPTY_DESCRIPTOR; CHANNEL_COUNT];
+
+// `LINK` bit of a descriptor link word: load the descriptor at the absolute
+// address in the upper bits when the transfer is done.
+const LINK: u32 = 1 << 1;
+
+/// LDMA API
+pub struct Ldma {
+    raw: LDMA,
+}
+
+/// Contains a field for each DMA channel, created by [`Ldma::new()`].
+pub struct Channels {
+    pub ch0: Channel,
+    pub ch1: Channel,
+    pub ch2: Channel,
+    pub ch3: Channel,
+    pub ch4: Channel,
+    pub ch5: Channel,
+    pub ch6: Channel,
+    pub ch7: Channel,
+}
+
+impl Ldma {
+    /// Enables the LDMA and returns its channels.
+    pub fn new(ldma: LDMA, cmu: &mut Cmu) -> (Ldma, Channels) {
+        cmu.enable_clock(&ldma);
+
+        ldma.ctrl.reset();
+        ldma.chen.reset();
+        ldma.chdone.reset();
+
+        let channels = Channels {
+            ch0: Channel { nr: 0 },
+            ch1: Channel { nr: 1 },
+            ch2: Channel { nr: 2 },
+            ch3: Channel { nr: 3 },
+            ch4: Channel { nr: 4 },
+            ch5: Channel { nr: 5 },
+            ch6: Channel { nr: 6 },
+            ch7: Channel { nr: 7 },
+        };
+        (Ldma { raw: ldma }, channels)
+    }
+
+    /// Returns the NVIC line of the `DONE` interrupts of all channels, see
+    /// [`Channel::enable_interrupt()`].
+    pub fn interrupt(&self) -> Interrupt {
+        Interrupt::LDMA
+    }
+
+    /// Returns the channels with a pending `DONE` interrupt flag, bit `n` for
+    /// channel `n`.
+    pub fn pending(&self) -> u8 {
+        self.raw.if_.read().done().bits()
+    }
+
+    /// Clears the `DONE` interrupt flags of the channels set in `channels`,
+    /// bit `n` for channel `n`.
+    pub fn clear_pending(&mut self, channels: u8) {
+        self.raw.ifc.write(|w| unsafe { w.done().bits(channels) });
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// disabling a channel while a driver waits for its transfer to complete
+    /// blocks that driver forever.
+    pub unsafe fn raw(&self) -> &crate::pac::ldma::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Ldma::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut LDMA {
+        &mut self.raw
+    }
+
+    /// Return the raw interface to the underlying peripheral.
+    ///
+    /// Takes the channels back to guarantee that no transfer is running.
+    pub fn release(self, _channels: Channels) -> LDMA {
+        self.raw
+    }
+}
+
+/// A single DMA channel.
+pub struct Channel {
+    nr: u8,
+}
+
+impl Channel {
+    fn regs(&self) -> &ChannelRegisters {
+        let base = LDMA::ptr() as usize + CHANNEL_OFFSET + self.nr as usize * CHANNEL_STRIDE;
+        unsafe { &*(base as *const ChannelRegisters) }
+    }
+
+    fn ldma(&self) -> &crate::pac::ldma::RegisterBlock {
+        unsafe { &*LDMA::ptr() }
+    }
+
+    fn mask(&self) -> u8 {
+        1 << self.nr
+    }
+
+    /// Number of the channel.
+    pub fn number(&self) -> u8 {
+        self.nr
+    }
+
+    /// Starts the transfer described by `descriptor` and the descriptors
+    /// linked to it.
+    ///
+    /// Peripheral transfers wait for `request` before each unit. Memory to
+    /// memory transfers pass `None` and start right away.
+    ///
+    /// # Safety
+    ///
+    /// The DMA accesses the memory of the descriptors and the buffers in the
+    /// background. All of them must stay valid and must not be accessed
+    /// otherwise until the transfer is done or aborted. Dropping the
+    /// [`Transfer`] aborts it, but leaking it with `mem::forget()` lets the
+    /// DMA continue.
+    pub unsafe fn start<'a>(
+        &'a mut self,
+        descriptor: &'a Descriptor,
+        request: Option<Request>,
+    ) -> Transfer<'a> {
+        let ldma = self.ldma();
+        let mask = self.mask() as u32;
+        ldma.chen.modify(|r, w| w.bits(r.bits() & !mask));
+        ldma.chdone.modify(|r, w| w.bits(r.bits() & !mask));
+        ldma.ifc.write(|w| w.bits(mask));
+
+        let ch = self.regs();
+        match request {
+            Some(request) => ch.reqsel.write(|w| {
+                w.sourcesel()
+                    .variant(request.source)
+                    .sigsel()
+                    .bits(request.signal)
+            }),
+            None => ch.reqsel.reset(),
+        }
+        ch.cfg.reset();
+        ch.loop_.reset();
+        ch.link
+            .write(|w| w.bits(descriptor as *const _ as u32 | LINK));
+
+        self.enable();
+        self.ldma().linkload.write(|w| w.bits(mask));
+
+        Transfer {
+            channel: self,
+            _descriptor: PhantomData,
+        }
+    }
+
+    /// Enables the `DONE` interrupt of the channel, which fires when a
+    /// descriptor is done. The NVIC line is shared by all channels, see
+    /// [`Ldma::interrupt()`] and [`Ldma::pending()`].
+    pub fn enable_interrupt(&mut self) {
+        let mask = self.mask() as u32;
+        self.ldma()
+            .ien
+            .modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+    }
+
+    /// Disables the `DONE` interrupt of the channel.
+    pub fn disable_interrupt(&mut self) {
+        let mask = self.mask() as u32;
+        self.ldma()
+            .ien
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+    }
+
+    // Starts a transfer of `count` bytes, one byte per request of the
+    // peripheral signal. Addresses of the side with increment are advanced by
+    // one byte per unit.
+    //
+    // The caller must keep the memory valid until the transfer is done or
+    // stopped.
+    pub(crate) fn start_bytes(
+        &mut self,
+        source: Source,
+        signal: u8,
+        src: (u32, bool),
+        dst: (u32, bool),
+        count: usize,
+    ) {
+        self.configure_bytes(source, signal, src, dst, count);
+        self.enable();
+    }
+
+    // Like `start_bytes()` but restarts the transfer with the initial
+    // addresses whenever it completes, until the channel is stopped.
+    //
+    // The channel reloads its registers from a descriptor that links to
+    // itself. The caller must keep the memory valid until the channel is
+    // stopped.
+    pub(crate) fn start_circular_bytes(
+        &mut self,
+        source: Source,
+        signal: u8,
+        src: (u32, bool),
+        dst: (u32, bool),
+        count: usize,
+    ) {
+        self.configure_bytes(source, signal, src, dst, count);
+
+        let ch = self.regs();
+        // The channel is disabled and this channel owns its descriptor.
+        let descriptor = unsafe { addr_of_mut!(DESCRIPTORS[self.nr as usize]) };
+        let link = descriptor as u32 | LINK;
+        unsafe {
+            descriptor.write_volatile(Descriptor {
+                ctrl: ch.ctrl.read().bits(),
+                src: ch.src.read().bits(),
+                dst: ch.dst.read().bits(),
+                link,
+            });
+        }
+        ch.link.write(|w| unsafe { w.bits(link) });
+
+        self.enable();
+    }
+
+    fn configure_bytes(
+        &mut self,
+        source: Source,
+        signal: u8,
+        (src, src_inc): (u32, bool),
+        (dst, dst_inc): (u32, bool),
+        count: usize,
+    ) {
+        assert!(count > 0 && count <= MAX_TRANSFER_COUNT);
+
+        let ldma = self.ldma();
+        let mask = self.mask() as u32;
+        ldma.chen.modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+        ldma.chdone
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+        ldma.ifc.write(|w| unsafe { w.bits(mask) });
+
+        let src_inc = if src_inc {
+            SRCINC_A::ONE
+        } else {
+            SRCINC_A::NONE
+        };
+        let dst_inc = if dst_inc {
+            DSTINC_A::ONE
+        } else {
+            DSTINC_A::NONE
+        };
+
+        let ch = self.regs();
+        ch.reqsel
+            .write(|w| unsafe { w.sourcesel().variant(source).sigsel().bits(signal) });
+        ch.cfg.reset();
+        ch.loop_.reset();
+        ch.ctrl.write(|w| unsafe {
+            w.xfercnt()
+                .bits(count as u16 - 1)
+                .size()
+                .variant(SIZE_A::BYTE)
+                .srcinc()
+                .variant(src_inc)
+                .dstinc()
+                .variant(dst_inc)
+        });
+        ch.src.write(|w| unsafe { w.bits(src) });
+        ch.dst.write(|w| unsafe { w.bits(dst) });
+        ch.link.reset();
+    }
+
+    fn enable(&mut self) {
+        let mask = self.mask() as u32;
+        // Memory accesses to the buffer must not be moved after the start.
+        compiler_fence(Ordering::Release);
+        self.ldma()
+            .chen
+            .modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+    }
+
+    // Returns `true` when the last transfer completed.
+    pub(crate) fn is_done(&self) -> bool {
+        let done = self.ldma().chdone.read().bits() & self.mask() as u32 != 0;
+        // Memory accesses to the buffer must not be moved before completion.
+        compiler_fence(Ordering::Acquire);
+        done
+    }
+
+    // Returns the number of units the last transfer did not move yet.
+    //
+    // Only meaningful after the transfer completed or was stopped.
+    pub(crate) fn remaining(&self) -> usize {
+        if self.is_done() {
+            0
+        } else {
+            self.regs().ctrl.read().xfercnt().bits() as usize + 1
+        }
+    }
+
+    // Returns the address the next unit of the running transfer is written
+    // to.
+    pub(crate) fn dst_address(&self) -> u32 {
+        let dst = self.regs().dst.read().bits();
+        // Memory accesses to the buffer must not be moved before the read.
+        compiler_fence(Ordering::Acquire);
+        dst
+    }
+
+    // Aborts the transfer.
+    pub(crate) fn stop(&mut self) {
+        let mask = self.mask() as u32;
+        self.ldma()
+            .chen
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+        while self.ldma().chbusy.read().bits() & mask != 0 {}
+    }
+}
+
+/// Running transfer of a channel, created by [`Channel::start()`].
+///
+/// Dropping the transfer aborts it.
+pub struct Transfer<'a> {
+    channel: &'a mut Channel,
+    _descriptor: PhantomData<&'a Descriptor>,
+}
+
+impl Transfer<'_> {
+    /// Returns `true` when the last descriptor of the list is done.
+    pub fn is_done(&self) -> bool {
+        self.channel.is_done()
+    }
+
+    /// Waits until the last descriptor of the list is done.
+    pub fn wait(self) {
+        while !self.is_done() {}
+    }
+
+    /// Aborts the transfer. Units of the current descriptor that were not
+    /// moved yet are lost.
+    pub fn abort(self) {}
+}
+
+impl Drop for Transfer<'_> {
+    fn drop(&mut self) {
+        self.channel.stop();
+    }
+}
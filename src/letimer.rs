@@ -0,0 +1,351 @@
+//! Low energy timer (LETIMER) API
+//!
+//! The LETIMER is a 16-bit down counter clocked by LFACLK, so it keeps
+//! counting and driving its outputs in EM2 (LFRCO, LFXO) and EM3 (ULFRCO)
+//! while the core sleeps. Select the LFACLK source with
+//! [`Cmu::set_lfa_source()`] before creating the driver.
+//!
+//! The counter counts down from the top value to 0 and reloads the top value
+//! on the underflow, one period is `top + 1` ticks. Each underflow can
+//! toggle, pulse or, together with `COMP1`, pulse width modulate an output
+//! pin, see [`OutputAction`]. Blinking LED0 of the starter kit at 1 Hz in
+//! EM2:
+//!
+//! ```ignore
+//! cmu.enable_lfxo();
+//! cmu.set_lfa_source(LfClkSource::LFXO);
+//! let mut letimer = Letimer::new(p.LETIMER0, &letimer::Config::default(), &mut cmu);
+//! // Toggling twice per second gives 1 Hz.
+//! letimer.set_top(16_383);
+//! letimer.route_output0(gpio.pf4.push_pull_output(false), OutputAction::TOGGLE);
+//! letimer.start();
+//! ```
+pub use crate::pac::{
+    cmu::lfapresc0::LETIMER0_A as Prescaler,
+    letimer0::ctrl::{REPMODE_A as RepeatMode, UFOA0_A as OutputAction},
+};
+
+use crate::{
+    cmu::Cmu,
+    gpio::*,
+    pac::{letimer0::RegisterBlock, Interrupt, LETIMER0},
+    time::Hertz,
+};
+
+/// LETIMER configuration.
+///
+/// Defaults to no prescaler, free running with the full 16-bit period and a
+/// counter that stops while the core is halted by a debugger.
+pub struct Config {
+    /// Divides LFACLK to derive the counter clock (`CMU_LFAPRESC0`).
+    pub prescaler: Prescaler,
+    /// Free running, or stopping after the number of periods set with
+    /// [`Letimer::set_repeat()`].
+    pub repeat_mode: RepeatMode,
+    /// Counter value after the underflow (`COMP0`), see
+    /// [`Letimer::set_top()`].
+    pub top: u16,
+    /// Keeps counting while the core is halted by a debugger.
+    pub debug_run: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prescaler: Prescaler::DIV1,
+            repeat_mode: RepeatMode::FREE,
+            top: 0xFFFF,
+            debug_run: false,
+        }
+    }
+}
+
+/// LETIMER API
+pub struct Letimer {
+    raw: LETIMER0,
+    tick_frequency: Hertz,
+}
+
+impl Letimer {
+    /// Creates the HAL instance and applies the configuration. The counter
+    /// is not started.
+    ///
+    /// Panics when no LFACLK source is selected.
+    pub fn new(letimer: LETIMER0, config: &Config, cmu: &mut Cmu) -> Self {
+        let lfaclk = cmu.lfaclk();
+        assert!(lfaclk.0 != 0, "no LF clock source selected");
+
+        cmu.enable_clock(&letimer);
+        cmu.set_letimer_prescaler(config.prescaler);
+
+        let letimer = Letimer {
+            raw: letimer,
+            tick_frequency: Hertz(lfaclk.0 >> u8::from(config.prescaler)),
+        };
+        letimer.command(|w| w.stop().set_bit().clear().set_bit());
+        letimer.raw.ien.reset();
+        letimer.raw.ifc.write(|w| unsafe { w.bits(!0) });
+        letimer.raw.ctrl.write(|w| {
+            w.repmode()
+                .variant(config.repeat_mode)
+                .comp0top()
+                .set_bit()
+                .debugrun()
+                .bit(config.debug_run)
+        });
+        letimer
+            .raw
+            .comp0
+            .write(|w| unsafe { w.comp0().bits(config.top) });
+        letimer
+    }
+
+    // Writes to CMD are synchronized into the LFACLK domain and must not
+    // overlap.
+    fn command<F>(&self, f: F)
+    where
+        F: FnOnce(&mut crate::pac::letimer0::cmd::W) -> &mut crate::pac::letimer0::cmd::W,
+    {
+        while self.raw.syncbusy.read().cmd().bit_is_set() {}
+        self.raw.cmd.write(f);
+    }
+
+    /// Frequency of the counter ticks.
+    pub fn tick_frequency(&self) -> Hertz {
+        self.tick_frequency
+    }
+
+    /// Frequency of the underflows with the current top value.
+    pub fn frequency(&self) -> Hertz {
+        Hertz(self.tick_frequency.0 / (self.top() as u32 + 1))
+    }
+
+    /// Sets the value the counter reloads on each underflow, the period is
+    /// `top + 1` ticks.
+    ///
+    /// Takes effect with the next underflow when the counter is running.
+    pub fn set_top(&mut self, top: u16) {
+        self.raw.comp0.write(|w| unsafe { w.comp0().bits(top) });
+    }
+
+    /// Value the counter reloads on each underflow.
+    pub fn top(&self) -> u16 {
+        self.raw.comp0.read().comp0().bits()
+    }
+
+    /// Sets `COMP1`, which starts the active phase of the outputs with
+    /// [`OutputAction::PWM`] when the counter reaches it. The active phase
+    /// lasts `compare + 1` ticks until the underflow.
+    pub fn set_compare1(&mut self, compare: u16) {
+        self.raw.comp1.write(|w| unsafe { w.comp1().bits(compare) });
+    }
+
+    /// Sets the number of periods of [`RepeatMode::ONESHOT`] (`REP0`) after
+    /// which the counter stops.
+    ///
+    /// The outputs only act while the count is not zero, also in
+    /// [`RepeatMode::FREE`], where it is not decremented.
+    pub fn set_repeat(&mut self, periods: u8) {
+        self.raw.rep0.write(|w| unsafe { w.rep0().bits(periods) });
+    }
+
+    /// Starts the counter.
+    pub fn start(&mut self) {
+        self.command(|w| w.start().set_bit());
+    }
+
+    /// Stops the counter, the outputs keep their level.
+    pub fn stop(&mut self) {
+        self.command(|w| w.stop().set_bit());
+    }
+
+    /// Returns `true` while the counter runs.
+    pub fn is_running(&self) -> bool {
+        self.raw.status.read().running().bit_is_set()
+    }
+
+    /// Current value of the counter.
+    pub fn counter(&self) -> u16 {
+        self.raw.cnt.read().cnt().bits()
+    }
+
+    /// Routes output 0 to `pin` and selects what happens to it on each
+    /// underflow. The pin idles low.
+    ///
+    /// Also sets the repeat count to 1 when it is 0, see
+    /// [`Letimer::set_repeat()`].
+    pub fn route_output0<P>(&mut self, _pin: P, action: OutputAction)
+    where
+        P: PinLocation<LETIMER0, Out0Pin>,
+    {
+        self.ensure_repeat();
+        self.raw.ctrl.modify(|_, w| w.ufoa0().variant(action));
+        self.raw
+            .routeloc0
+            .modify(|_, w| unsafe { w.out0loc().bits(P::LOCATION) });
+        self.raw.routepen.modify(|_, w| w.out0pen().set_bit());
+    }
+
+    /// Same as [`Letimer::route_output0()`] for output 1, which is driven
+    /// independently from output 0 with the same counter.
+    pub fn route_output1<P>(&mut self, _pin: P, action: OutputAction)
+    where
+        P: PinLocation<LETIMER0, Out1Pin>,
+    {
+        self.ensure_repeat();
+        // Both output action fields share the encoding.
+        self.raw
+            .ctrl
+            .modify(|_, w| w.ufoa1().bits(u8::from(action)));
+        self.raw
+            .routeloc0
+            .modify(|_, w| unsafe { w.out1loc().bits(P::LOCATION) });
+        self.raw.routepen.modify(|_, w| w.out1pen().set_bit());
+    }
+
+    fn ensure_repeat(&mut self) {
+        if self.raw.rep0.read().rep0().bits() == 0 {
+            self.set_repeat(1);
+        }
+    }
+
+    /// Returns the NVIC line of the LETIMER interrupt, which the application
+    /// unmasks.
+    pub fn interrupt(&self) -> Interrupt {
+        Interrupt::LETIMER0
+    }
+
+    /// Enables the underflow interrupt, which fires at the end of each
+    /// period and also wakes the core from EM2 and EM3.
+    pub fn enable_interrupt(&mut self) {
+        self.raw.ien.modify(|_, w| w.uf().set_bit());
+    }
+
+    /// Disables the underflow interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.raw.ien.modify(|_, w| w.uf().clear_bit());
+    }
+
+    /// Returns `true` when an underflow occurred since the flag was cleared.
+    pub fn is_interrupt_pending(&self) -> bool {
+        self.raw.if_.read().uf().bit_is_set()
+    }
+
+    /// Clears the underflow flag, call this from the interrupt handler.
+    pub fn clear_interrupt(&mut self) {
+        self.raw.ifc.write(|w| w.uf().set_bit());
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// clearing `CTRL.COMP0TOP` makes the counter wrap at `0xFFFF` instead of
+    /// the value of [`Letimer::top()`].
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Letimer::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut LETIMER0 {
+        &mut self.raw
+    }
+
+    /// Stops the counter, disables the pin routes and interrupts and returns
+    /// the raw interface to the underlying peripheral.
+    pub fn release(self) -> LETIMER0 {
+        self.command(|w| w.stop().set_bit());
+        self.raw.routepen.reset();
+        self.raw.routeloc0.reset();
+        self.raw.ien.reset();
+        self.raw.ctrl.reset();
+        self.raw
+    }
+}
+
+/// Marks a pin that can be used as LETIMER output 0.
+///
+/// LED0 (PF4) and LED1 (PF5) of the starter kit are locations 28 and 29.
+pub struct Out0Pin;
+
+impl_pin_locations!(LETIMER0, Out0Pin, Output, {
+    PA0: 0,
+    PA1: 1,
+    PA2: 2,
+    PA3: 3,
+    PA4: 4,
+    PA5: 5,
+    PB11: 6,
+    PB12: 7,
+    PB13: 8,
+    PB14: 9,
+    PB15: 10,
+    PC6: 11,
+    PC7: 12,
+    PC8: 13,
+    PC9: 14,
+    PC10: 15,
+    PC11: 16,
+    PD9: 17,
+    PD10: 18,
+    PD11: 19,
+    PD12: 20,
+    PD13: 21,
+    PD14: 22,
+    PD15: 23,
+    // Overwriting debug pins is not supported
+    // PF0: 24,
+    // PF1: 25,
+    // PF2: 26,
+    // PF3: 27,
+    PF4: 28,
+    PF5: 29,
+    PF6: 30,
+    PF7: 31,
+});
+/// Marks a pin that can be used as LETIMER output 1.
+///
+/// The same pins as for output 0, with locations shifted by one.
+pub struct Out1Pin;
+
+impl_pin_locations!(LETIMER0, Out1Pin, Output, {
+    PA0: 31,
+    PA1: 0,
+    PA2: 1,
+    PA3: 2,
+    PA4: 3,
+    PA5: 4,
+    PB11: 5,
+    PB12: 6,
+    PB13: 7,
+    PB14: 8,
+    PB15: 9,
+    PC6: 10,
+    PC7: 11,
+    PC8: 12,
+    PC9: 13,
+    PC10: 14,
+    PC11: 15,
+    PD9: 16,
+    PD10: 17,
+    PD11: 18,
+    PD12: 19,
+    PD13: 20,
+    PD14: 21,
+    PD15: 22,
+    // Overwriting debug pins is not supported
+    // PF0: 23,
+    // PF1: 24,
+    // PF2: 25,
+    // PF3: 26,
+    PF4: 27,
+    PF5: 28,
+    PF6: 29,
+    PF7: 30,
+});
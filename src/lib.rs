@@ -1,14 +1,51 @@
-#![no_std]
+//! Hardware abstraction layer (HAL) for Silicon Labs EFM32PG12 microcontrollers
+//!
+//! Each driver constructor takes ownership of exactly the peripheral and pins
+//! it uses. The remaining fields of `pac::Peripherals` and `gpio::Gpio` can
+//! be moved to other parts of the firmware independently. The [`cmu::Cmu`] is
+//! only borrowed mutably while a driver is constructed, to enable the
+//! peripheral clock, and can be passed from module to module. See the
+//! `split_peripherals` example.
+#![cfg_attr(not(test), no_std)]
 
 pub use efm32pg12_pac as pac;
 use embedded_hal as hal;
 
+#[macro_use]
+mod log;
+
+pub mod acmp;
+pub mod adc;
 pub mod cmu;
+pub mod cryotimer;
+pub mod delay;
+pub mod devinfo;
+pub mod error;
+pub mod gpcrc;
 #[macro_use]
 pub mod gpio;
 pub mod i2c;
+pub mod idac;
+pub mod ldma;
+pub mod letimer;
+pub mod prs;
+pub mod rtcc;
+pub mod spi;
+pub mod time;
+pub mod timer;
+pub mod trng;
 pub mod usart;
+pub mod vdac;
+pub mod wdog;
+/// Extension traits of the HAL and the embedded-hal traits implemented by the
+/// drivers, e.g. for `peripherals.GPIO.split(&mut cmu)`.
 pub mod prelude {
-    pub use crate::hal::{digital::v2::*, prelude::*};
+    pub use crate::{
+        cmu::{ClockControlExt, CmuExt},
+        gpio::GpioExt,
+        hal::{digital::v2::*, prelude::*},
+        time::U32Ext,
+        usart::UsartExt,
+    };
 }
 mod util;
@@ -0,0 +1,26 @@
+//! Internal logging macros
+//!
+//! Forward to the `defmt` macros of the same name when the `defmt` feature is
+//! enabled and expand to nothing otherwise. Arguments are not evaluated
+//! without the feature, so logging does not affect code size.
+
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::trace!($($arg)*);
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::debug!($($arg)*);
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::warn!($($arg)*);
+    };
+}
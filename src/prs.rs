@@ -0,0 +1,181 @@
+//! Peripheral reflex system (PRS) API
+//!
+//! The PRS routes signals of one peripheral (the producer) to another
+//! peripheral (the consumer) without CPU involvement. [`Prs::new()`] hands
+//! out the twelve channels. A producer is connected to a channel with
+//! [`Channel::connect()`], consumers then select the channel as their input,
+//! e.g. [`Timer::into_prs_capture()`](crate::timer::Timer::into_prs_capture).
+//!
+//! Drivers provide the producers they support as [`PrsSource`], e.g.
+//! [`ExtiPin::prs_source()`](crate::gpio::ExtiPin::prs_source) or
+//! [`PeriodicTimer::prs_source()`](crate::timer::PeriodicTimer::prs_source).
+pub use crate::pac::prs::ch0_ctrl::{EDSEL_A as Edge, SOURCESEL_A as Source};
+
+use crate::{
+    cmu::Cmu,
+    pac::{prs::CH0_CTRL, PRS},
+};
+
+// Offset of the first channel control register.
+const CH_CTRL_OFFSET: usize = 0x50;
+
+/// Producer signal that can be connected to a PRS channel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrsSource {
+    source: Source,
+    signal: u8,
+}
+
+impl PrsSource {
+    pub(crate) fn new(source: Source, signal: u8) -> Self {
+        PrsSource { source, signal }
+    }
+
+    /// Producer peripheral (`CHx_CTRL.SOURCESEL`).
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// Signal of the producer peripheral (`CHx_CTRL.SIGSEL`).
+    pub fn signal(&self) -> u8 {
+        self.signal
+    }
+}
+
+/// PRS API
+pub struct Prs {
+    raw: PRS,
+}
+
+/// Contains a field for each PRS channel, created by [`Prs::new()`].
+pub struct Channels {
+    pub ch0: Channel,
+    pub ch1: Channel,
+    pub ch2: Channel,
+    pub ch3: Channel,
+    pub ch4: Channel,
+    pub ch5: Channel,
+    pub ch6: Channel,
+    pub ch7: Channel,
+    pub ch8: Channel,
+    pub ch9: Channel,
+    pub ch10: Channel,
+    pub ch11: Channel,
+}
+
+impl Prs {
+    /// Enables the PRS and returns its channels, all disconnected.
+    pub fn new(prs: PRS, cmu: &mut Cmu) -> (Prs, Channels) {
+        cmu.enable_clock(&prs);
+
+        let channels = Channels {
+            ch0: Channel::new(0),
+            ch1: Channel::new(1),
+            ch2: Channel::new(2),
+            ch3: Channel::new(3),
+            ch4: Channel::new(4),
+            ch5: Channel::new(5),
+            ch6: Channel::new(6),
+            ch7: Channel::new(7),
+            ch8: Channel::new(8),
+            ch9: Channel::new(9),
+            ch10: Channel::new(10),
+            ch11: Channel::new(11),
+        };
+        (Prs { raw: prs }, channels)
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// reconfiguring a channel changes the input of every consumer using it.
+    pub unsafe fn raw(&self) -> &crate::pac::prs::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Prs::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut PRS {
+        &mut self.raw
+    }
+
+    /// Return the raw interface to the underlying peripheral.
+    ///
+    /// Takes the channels back and disconnects them.
+    pub fn release(self, channels: Channels) -> PRS {
+        for mut channel in [
+            channels.ch0,
+            channels.ch1,
+            channels.ch2,
+            channels.ch3,
+            channels.ch4,
+            channels.ch5,
+            channels.ch6,
+            channels.ch7,
+            channels.ch8,
+            channels.ch9,
+            channels.ch10,
+            channels.ch11,
+        ] {
+            channel.disconnect();
+        }
+        self.raw
+    }
+}
+
+/// A single PRS channel.
+pub struct Channel {
+    nr: u8,
+}
+
+impl Channel {
+    fn new(nr: u8) -> Self {
+        let mut channel = Channel { nr };
+        channel.disconnect();
+        channel
+    }
+
+    fn ctrl(&self) -> &CH0_CTRL {
+        // The channel control registers are consecutive words.
+        let address = PRS::ptr() as usize + CH_CTRL_OFFSET + self.nr as usize * 4;
+        unsafe { &*(address as *const CH0_CTRL) }
+    }
+
+    /// Returns the channel number (`0..=11`).
+    pub fn nr(&self) -> u8 {
+        self.nr
+    }
+
+    /// Connects the producer `source` to the channel.
+    ///
+    /// With [`Edge::OFF`] the channel passes the level of the signal. The
+    /// other settings turn edges of the signal into one HFCLK cycle pulses,
+    /// for consumers that expect pulses.
+    pub fn connect(&mut self, source: PrsSource, edge: Edge) {
+        self.ctrl().write(|w| unsafe {
+            w.sourcesel()
+                .variant(source.source)
+                .sigsel()
+                .bits(source.signal)
+                .edsel()
+                .variant(edge)
+        });
+    }
+
+    /// Disconnects the producer, the channel outputs a constant low level.
+    pub fn disconnect(&mut self) {
+        self.ctrl().reset();
+    }
+
+    /// Returns the current level of the channel.
+    pub fn is_high(&self) -> bool {
+        let prs = unsafe { &*PRS::ptr() };
+        prs.peek.read().bits() & (1 << self.nr) != 0
+    }
+}
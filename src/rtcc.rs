@@ -0,0 +1,377 @@
+//! Real time counter and calendar (RTCC) API
+//!
+//! The RTCC is a 32-bit counter clocked by LFECLK. It keeps counting in EM2
+//! and EM3, so [`Rtcc::now()`] is a monotonic time base across sleep, and a
+//! compare match on channel 1 wakes the core on a schedule:
+//!
+//! ```ignore
+//! cmu.use_lfxo()?;
+//! let mut rtcc = Rtcc::new(p.RTCC, &rtcc::Config::default(), &mut cmu);
+//! rtcc.set_alarm(rtcc.now() + 60);
+//! rtcc.enable_alarm_interrupt();
+//! ```
+//!
+//! The prescaler divides LFECLK by a power of two, the tick frequency is the
+//! closest one to [`Config::tick`]. With the 32768 Hz LFXO or LFRCO a 1 Hz
+//! tick is exact and a 1 kHz tick is 1024 Hz. With the 1 kHz ULFRCO a 1 kHz
+//! tick is exact and a 1 Hz tick is 1000/1024 Hz. Check
+//! [`Rtcc::tick_frequency()`] when converting ticks to time.
+//!
+//! [`Rtcc::into_calendar()`] switches to the calendar mode, in which the RTCC
+//! counts seconds, minutes, hours, days, months and years in BCD registers
+//! instead, see [`Calendar`].
+//!
+//! Compare channel 0 and 2 stay unused by the driver, but capture channel 0
+//! is used by `adc::logging::LoggingAdc`, which takes the raw RTCC.
+use crate::{
+    cmu::Cmu,
+    pac::{rtcc::cc1_ctrl::MODE_A, Interrupt, RTCC},
+    time::Hertz,
+};
+
+// `CTRL.CNTPRESC` of the 1 Hz calendar tick from a 32768 Hz LFECLK.
+const CALENDAR_PRESCALER: u8 = 15;
+
+/// RTCC configuration.
+///
+/// Defaults to a 1 Hz tick.
+pub struct Config {
+    /// Requested tick frequency, rounded to the closest power of two divider
+    /// of LFECLK.
+    pub tick: Hertz,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { tick: Hertz(1) }
+    }
+}
+
+/// RTCC API in counter mode.
+pub struct Rtcc {
+    raw: RTCC,
+    lfeclk: Hertz,
+}
+
+impl Rtcc {
+    /// Starts the counter from zero.
+    ///
+    /// An LF clock source must be selected before, see
+    /// [`Cmu::use_lfxo()`](crate::cmu::Cmu::use_lfxo). Panics when no source
+    /// is selected.
+    pub fn new(rtcc: RTCC, config: &Config, cmu: &mut Cmu) -> Self {
+        let lfeclk = cmu.lfeclk();
+        assert!(lfeclk.0 != 0, "no LF clock source selected");
+
+        cmu.enable_clock(&rtcc);
+
+        // The divider is a power of two, round to the nearest one.
+        let ratio = (lfeclk.0 / config.tick.0.max(1)).max(1);
+        let mut presc = 31 - ratio.leading_zeros();
+        if presc < 31 && ratio - (1 << presc) > (2 << presc) - ratio {
+            presc += 1;
+        }
+        let presc = presc.min(15) as u8;
+
+        rtcc.ctrl.reset();
+        rtcc.cnt.reset();
+        rtcc.precnt.reset();
+        rtcc.ctrl
+            .write(|w| w.cntpresc().bits(presc).enable().set_bit());
+        debug!("RTCC: CNTPRESC {=u8}", presc);
+
+        Rtcc { raw: rtcc, lfeclk }
+    }
+
+    /// Frequency of the counter ticks.
+    pub fn tick_frequency(&self) -> Hertz {
+        Hertz(self.lfeclk.0 >> self.raw.ctrl.read().cntpresc().bits())
+    }
+
+    /// Current value of the counter.
+    ///
+    /// The counter wraps around after `2^32` ticks, i.e. after 136 years with
+    /// a 1 Hz tick and 48.5 days with a 1024 Hz tick.
+    pub fn now(&self) -> u32 {
+        self.raw.cnt.read().bits()
+    }
+
+    /// Sets the counter to `counter`, e.g. to a time restored from a
+    /// retention register.
+    pub fn set(&mut self, counter: u32) {
+        self.raw.precnt.reset();
+        self.raw.cnt.write(|w| unsafe { w.cnt().bits(counter) });
+    }
+
+    /// Raises the alarm flag when the counter reaches `counter`.
+    ///
+    /// Replaces a previous alarm and clears a pending alarm flag.
+    pub fn set_alarm(&mut self, counter: u32) {
+        set_alarm(&self.raw);
+        self.raw.cc1_ccv.write(|w| unsafe { w.ccv().bits(counter) });
+    }
+
+    /// Returns `true` once the alarm matched, until the flag is cleared.
+    pub fn is_alarm_pending(&self) -> bool {
+        self.raw.if_.read().cc1().bit_is_set()
+    }
+
+    /// Clears the alarm flag. Call this from the `RTCC` interrupt handler.
+    pub fn clear_alarm(&mut self) {
+        self.raw.ifc.write(|w| w.cc1().set_bit());
+    }
+
+    /// Disables the alarm and clears its flag.
+    pub fn cancel_alarm(&mut self) {
+        cancel_alarm(&self.raw);
+    }
+
+    /// Enables the interrupt of the alarm flag, which wakes the core from
+    /// EM1 to EM3. The application unmasks [`Rtcc::interrupt()`] in the NVIC.
+    pub fn enable_alarm_interrupt(&mut self) {
+        self.raw.ien.modify(|_, w| w.cc1().set_bit());
+    }
+
+    /// Disables the interrupt of the alarm flag.
+    pub fn disable_alarm_interrupt(&mut self) {
+        self.raw.ien.modify(|_, w| w.cc1().clear_bit());
+    }
+
+    /// Returns the NVIC line of the alarm interrupt.
+    pub fn interrupt(&self) -> Interrupt {
+        Interrupt::RTCC
+    }
+
+    /// Switches to the calendar mode and starts counting from `now`.
+    ///
+    /// The calendar advances once per second, which requires a 32768 Hz
+    /// LFECLK, i.e. the LFXO or the LFRCO. Panics with the ULFRCO or when
+    /// `now` is not a valid date and time. A pending alarm is cancelled.
+    pub fn into_calendar(self, now: &DateTime) -> Calendar {
+        assert!(self.lfeclk.0 == 32_768, "calendar needs a 32768 Hz LFECLK");
+        now.check();
+
+        let raw = &self.raw;
+        let presc = raw.ctrl.read().cntpresc().bits();
+        cancel_alarm(raw);
+        raw.ctrl.reset();
+        raw.precnt.reset();
+        write_datetime(raw, now);
+        raw.ctrl.write(|w| {
+            w.cntpresc()
+                .bits(CALENDAR_PRESCALER)
+                .cntmode()
+                .set_bit()
+                .enable()
+                .set_bit()
+        });
+
+        Calendar { rtcc: self, presc }
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// a changed prescaler invalidates the tick frequency.
+    pub unsafe fn raw(&self) -> &crate::pac::rtcc::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Rtcc::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut RTCC {
+        &mut self.raw
+    }
+
+    /// Stops the counter and returns the raw interface to the underlying
+    /// peripheral.
+    pub fn release(self) -> RTCC {
+        cancel_alarm(&self.raw);
+        self.raw.ien.reset();
+        self.raw.ctrl.reset();
+        self.raw
+    }
+}
+
+/// RTCC API in calendar mode, created by [`Rtcc::into_calendar()`].
+///
+/// The calendar covers the years 2000 to 2099 and inserts the 29th of February
+/// in every year divisible by four, which is correct for this range. The day
+/// of the week is counted from 0 to 6 along with the date, its meaning is up
+/// to the application.
+pub struct Calendar {
+    rtcc: Rtcc,
+    // Counter mode prescaler restored by `free()`.
+    presc: u8,
+}
+
+impl Calendar {
+    /// Current date and time.
+    pub fn now(&self) -> DateTime {
+        let raw = &self.rtcc.raw;
+        // The date can change in between reading both registers, e.g. at
+        // midnight. The time read after the date tells.
+        loop {
+            let time = raw.time.read().bits();
+            let date = raw.date.read().bits();
+            if raw.time.read().bits() == time {
+                return DateTime::from_registers(time, date);
+            }
+        }
+    }
+
+    /// Sets the date and time.
+    ///
+    /// The next second starts after one full second. Panics when `now` is not
+    /// a valid date and time.
+    pub fn set(&mut self, now: &DateTime) {
+        now.check();
+        let raw = &self.rtcc.raw;
+        raw.precnt.reset();
+        write_datetime(raw, now);
+    }
+
+    /// Raises the alarm flag when the calendar reaches the day of the month
+    /// and time of `at`. Month, year and day of the week are ignored.
+    ///
+    /// Replaces a previous alarm and clears a pending alarm flag. Panics when
+    /// `at` is not a valid date and time.
+    pub fn set_alarm(&mut self, at: &DateTime) {
+        at.check();
+        let raw = &self.rtcc.raw;
+        set_alarm(raw);
+        raw.cc1_time.write(|w| unsafe { w.bits(at.time_bits()) });
+        raw.cc1_date
+            .write(|w| unsafe { w.bits(at.date_bits() & 0x3F) });
+    }
+
+    /// Returns `true` once the alarm matched, until the flag is cleared.
+    pub fn is_alarm_pending(&self) -> bool {
+        self.rtcc.is_alarm_pending()
+    }
+
+    /// Clears the alarm flag. Call this from the `RTCC` interrupt handler.
+    pub fn clear_alarm(&mut self) {
+        self.rtcc.clear_alarm();
+    }
+
+    /// Disables the alarm and clears its flag.
+    pub fn cancel_alarm(&mut self) {
+        self.rtcc.cancel_alarm();
+    }
+
+    /// Enables the interrupt of the alarm flag, see
+    /// [`Rtcc::enable_alarm_interrupt()`].
+    pub fn enable_alarm_interrupt(&mut self) {
+        self.rtcc.enable_alarm_interrupt();
+    }
+
+    /// Disables the interrupt of the alarm flag.
+    pub fn disable_alarm_interrupt(&mut self) {
+        self.rtcc.disable_alarm_interrupt();
+    }
+
+    /// Returns the NVIC line of the alarm interrupt.
+    pub fn interrupt(&self) -> Interrupt {
+        Interrupt::RTCC
+    }
+
+    /// Returns to the counter mode with the previous tick frequency, starting
+    /// from zero. A pending alarm is cancelled.
+    pub fn free(self) -> Rtcc {
+        let raw = &self.rtcc.raw;
+        cancel_alarm(raw);
+        raw.ctrl.reset();
+        raw.cnt.reset();
+        raw.precnt.reset();
+        raw.ctrl
+            .write(|w| w.cntpresc().bits(self.presc).enable().set_bit());
+        self.rtcc
+    }
+}
+
+/// Date and time of the [`Calendar`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateTime {
+    /// `2000..=2099`
+    pub year: u16,
+    /// `1..=12`
+    pub month: u8,
+    /// `1..=31`
+    pub day: u8,
+    /// `0..=6`
+    pub weekday: u8,
+    /// `0..=23`
+    pub hour: u8,
+    /// `0..=59`
+    pub minute: u8,
+    /// `0..=59`
+    pub second: u8,
+}
+
+impl DateTime {
+    fn check(&self) {
+        assert!((2000..=2099).contains(&self.year));
+        assert!((1..=12).contains(&self.month));
+        assert!((1..=31).contains(&self.day));
+        assert!(self.weekday <= 6);
+        assert!(self.hour <= 23 && self.minute <= 59 && self.second <= 59);
+    }
+
+    // Layout of the TIME and CCx_TIME registers.
+    fn time_bits(&self) -> u32 {
+        bcd(self.second) | (bcd(self.minute) << 8) | (bcd(self.hour) << 16)
+    }
+
+    // Layout of the DATE register, the lower 16 bits match CCx_DATE.
+    fn date_bits(&self) -> u32 {
+        bcd(self.day)
+            | (bcd(self.month) << 8)
+            | (bcd((self.year - 2000) as u8) << 16)
+            | ((self.weekday as u32) << 24)
+    }
+
+    fn from_registers(time: u32, date: u32) -> Self {
+        DateTime {
+            year: 2000 + from_bcd(date >> 16) as u16,
+            month: from_bcd((date >> 8) & 0x1F),
+            day: from_bcd(date & 0x3F),
+            weekday: ((date >> 24) & 0x7) as u8,
+            hour: from_bcd((time >> 16) & 0x3F),
+            minute: from_bcd((time >> 8) & 0x7F),
+            second: from_bcd(time & 0x7F),
+        }
+    }
+}
+
+fn bcd(value: u8) -> u32 {
+    (((value / 10) << 4) | (value % 10)) as u32
+}
+
+fn from_bcd(bits: u32) -> u8 {
+    (((bits >> 4) & 0xF) * 10 + (bits & 0xF)) as u8
+}
+
+fn write_datetime(raw: &RTCC, now: &DateTime) {
+    raw.time.write(|w| unsafe { w.bits(now.time_bits()) });
+    raw.date.write(|w| unsafe { w.bits(now.date_bits()) });
+}
+
+// Channel 1 compares with CNT in counter mode and with TIME and the day of
+// the month in calendar mode, with the reset values of COMPBASE and DAYCC.
+fn set_alarm(raw: &RTCC) {
+    raw.ifc.write(|w| w.cc1().set_bit());
+    raw.cc1_ctrl
+        .write(|w| w.mode().variant(MODE_A::OUTPUTCOMPARE));
+}
+
+fn cancel_alarm(raw: &RTCC) {
+    raw.cc1_ctrl.reset();
+    raw.ifc.write(|w| w.cc1().set_bit());
+}
@@ -0,0 +1,482 @@
+//! Serial peripheral interface (SPI) master API on the USART peripheral
+//!
+//! The USART shifts frames of 4 to 16 data bits MSB first. The frame size is
+//! part of the [`Config`] and fixed for an SPI instance, because the hardware
+//! has a single `FRAME.DATABITS` setting for both directions and all
+//! transfers.
+//!
+//! Frames of up to 8 bits are transferred as `u8` words through the
+//! `TXDATA`/`RXDATA` registers. Wider frames require `u16` words, which use
+//! the extended (`TXDATAX`/`RXDATAX`, 9 bits) or double (`TXDOUBLE`/`RXDOUBLE`,
+//! 10 to 16 bits) data registers. Select the word type when creating the
+//! instance, e.g. `Spi::<_, u16>::new(...)`.
+pub use crate::{
+    hal::spi::{Mode, Phase, Polarity, MODE_0, MODE_1, MODE_2, MODE_3},
+    pac::usart0::frame::DATABITS_A as FrameSize,
+};
+
+use crate::{
+    cmu::Cmu,
+    gpio::*,
+    hal::{
+        blocking,
+        digital::v2::{OutputPin, StatefulOutputPin},
+        spi::FullDuplex,
+    },
+    pac::usart0::RegisterBlock,
+    usart::{sync_divider, ClkPin, CsPin, Instance, MisoPin, MosiPin},
+};
+use core::marker::PhantomData;
+pub use embedded_error::SpiError as Error;
+
+/// SPI configuration.
+///
+/// Defaults to 1 MHz, SPI mode 0 and 8 bit frames.
+pub struct Config {
+    /// Maximum clock frequency in Hz.
+    pub frequency: u32,
+    /// Clock polarity and phase.
+    pub mode: Mode,
+    /// Number of data bits per frame.
+    pub frame_size: FrameSize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            frequency: 1_000_000,
+            mode: MODE_0,
+            frame_size: FrameSize::EIGHT,
+        }
+    }
+}
+
+/// Data type of a single SPI frame.
+///
+/// Implemented for `u8` for frames of up to 8 bits and for `u16` for frames
+/// of up to 16 bits.
+pub trait Word: Copy + Default {
+    /// Largest frame size in bits that fits into the word.
+    const MAX_FRAME_BITS: u8;
+
+    #[doc(hidden)]
+    fn write(usart: &RegisterBlock, frame_bits: u8, word: Self);
+
+    #[doc(hidden)]
+    fn read(usart: &RegisterBlock, frame_bits: u8) -> Self;
+}
+
+impl Word for u8 {
+    const MAX_FRAME_BITS: u8 = 8;
+
+    fn write(usart: &RegisterBlock, _frame_bits: u8, word: Self) {
+        usart.txdata.write(|w| unsafe { w.txdata().bits(word) });
+    }
+
+    fn read(usart: &RegisterBlock, _frame_bits: u8) -> Self {
+        usart.rxdata.read().rxdata().bits()
+    }
+}
+
+impl Word for u16 {
+    const MAX_FRAME_BITS: u8 = 16;
+
+    fn write(usart: &RegisterBlock, frame_bits: u8, word: Self) {
+        if frame_bits > 9 {
+            // Both bytes of the double register form a single frame.
+            usart.txdouble.write(|w| unsafe { w.bits(word as u32) });
+        } else {
+            usart
+                .txdatax
+                .write(|w| unsafe { w.bits(word as u32 & 0x1FF) });
+        }
+    }
+
+    fn read(usart: &RegisterBlock, frame_bits: u8) -> Self {
+        if frame_bits > 9 {
+            usart.rxdouble.read().bits() as u16
+        } else {
+            usart.rxdatax.read().rxdata().bits()
+        }
+    }
+}
+
+/// SPI master interface with words of type `W`.
+///
+/// Chip select is not handled by the driver unless created with
+/// [`Spi::new_with_cs()`], otherwise use a GPIO output pin wrapped in a
+/// [`ChipSelect`].
+pub struct Spi<I, W = u8> {
+    raw: I,
+    frame_bits: u8,
+    _word: PhantomData<W>,
+}
+
+impl<I, W> Spi<I, W>
+where
+    I: Instance,
+    W: Word,
+{
+    /// Configures the USART as SPI master.
+    ///
+    /// The actual clock frequency is `hfperclk / (2 * n)` for the smallest
+    /// integer `n` that does not exceed `config.frequency`.
+    ///
+    /// Returns `Err(Error::Impl(ImplError::InvalidConfiguration))` when the
+    /// frequency is zero or too low for the 20-bit clock divider. The
+    /// peripheral is not touched in that case.
+    ///
+    /// Panics when the frame size does not fit into the word type `W`.
+    pub fn new<CLK, MOSI, MISO>(
+        usart: I,
+        _clk: CLK,
+        _mosi: MOSI,
+        _miso: MISO,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<Self, Error>
+    where
+        CLK: PinLocation<I, ClkPin>,
+        MOSI: PinLocation<I, MosiPin>,
+        MISO: PinLocation<I, MisoPin>,
+    {
+        // DATABITS encodes the frame size as `bits - 3`.
+        let frame_bits = u8::from(config.frame_size) + 3;
+        assert!(frame_bits <= W::MAX_FRAME_BITS);
+        let clkdiv = sync_divider(cmu.hfperclk().0, config.frequency).map_err(Error::Impl)?;
+
+        cmu.enable_clock(&usart);
+
+        usart.ctrl.write(|w| {
+            w.sync()
+                .set_bit()
+                .msbf()
+                .set_bit()
+                .clkpol()
+                .bit(config.mode.polarity == Polarity::IdleHigh)
+                .clkpha()
+                .bit(config.mode.phase == Phase::CaptureOnSecondTransition)
+        });
+        usart
+            .frame
+            .modify(|_, w| w.databits().variant(config.frame_size));
+
+        usart.clkdiv.write(|w| unsafe { w.div().bits(clkdiv) });
+
+        usart.routeloc0.write(|w| unsafe {
+            w.clkloc()
+                .bits(CLK::LOCATION)
+                .txloc()
+                .bits(MOSI::LOCATION)
+                .rxloc()
+                .bits(MISO::LOCATION)
+        });
+        usart
+            .routepen
+            .write(|w| w.clkpen().set_bit().txpen().set_bit().rxpen().set_bit());
+
+        usart.cmd.write(|w| {
+            w.masteren()
+                .set_bit()
+                .txen()
+                .set_bit()
+                .rxen()
+                .set_bit()
+                .clearrx()
+                .set_bit()
+                .cleartx()
+                .set_bit()
+        });
+
+        Ok(Spi {
+            raw: usart,
+            frame_bits,
+            _word: PhantomData,
+        })
+    }
+
+    /// Configures the USART as SPI master that drives the active low chip
+    /// select of a single device.
+    ///
+    /// The USART asserts `CS` while it shifts frames and deasserts it as soon
+    /// as the transmit buffer runs empty, so a transfer only stays selected
+    /// throughout when the words are supplied without gaps, as the blocking
+    /// transfers do. Use a [`ChipSelect`] for devices that need to stay
+    /// selected across several transfers.
+    ///
+    /// Fails and panics like [`Spi::new()`].
+    pub fn new_with_cs<CLK, MOSI, MISO, CS>(
+        usart: I,
+        clk: CLK,
+        mosi: MOSI,
+        miso: MISO,
+        _cs: CS,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<Self, Error>
+    where
+        CLK: PinLocation<I, ClkPin>,
+        MOSI: PinLocation<I, MosiPin>,
+        MISO: PinLocation<I, MisoPin>,
+        CS: PinLocation<I, CsPin>,
+    {
+        let spi = Self::new(usart, clk, mosi, miso, config, cmu)?;
+        let usart = &spi.raw;
+        usart.ctrl.modify(|_, w| w.autocs().set_bit());
+        usart
+            .routeloc0
+            .modify(|_, w| unsafe { w.csloc().bits(CS::LOCATION) });
+        usart.routepen.modify(|_, w| w.cspen().set_bit());
+        Ok(spi)
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// a frame size change without a matching word type corrupts the data.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Spi::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut I {
+        &mut self.raw
+    }
+
+    /// Disables the transmitter, the receiver and the pin routes and returns
+    /// the raw interface to the underlying peripheral.
+    ///
+    /// The pins fall back to their GPIO configuration. A frame in progress is
+    /// cut off, wait for the transfer to complete before.
+    pub fn release(self) -> I {
+        self.raw.routepen.reset();
+        self.raw.routeloc0.reset();
+        self.raw
+            .cmd
+            .write(|w| w.masterdis().set_bit().txdis().set_bit().rxdis().set_bit());
+        self.raw
+    }
+}
+
+impl<I, W> FullDuplex<W> for Spi<I, W>
+where
+    I: Instance,
+    W: Word,
+{
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<W, Error> {
+        if self.raw.if_.read().rxof().bit_is_set() {
+            self.raw.ifc.write(|w| w.rxof().set_bit());
+            return Err(nb::Error::Other(Error::Overrun));
+        }
+
+        if self.raw.status.read().rxdatav().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        Ok(W::read(&self.raw, self.frame_bits))
+    }
+
+    fn send(&mut self, word: W) -> nb::Result<(), Error> {
+        if self.raw.status.read().txbl().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        W::write(&self.raw, self.frame_bits, word);
+        Ok(())
+    }
+}
+
+impl<I: Instance, W: Word> blocking::spi::transfer::Default<W> for Spi<I, W> {}
+
+impl<I: Instance, W: Word> blocking::spi::write::Default<W> for Spi<I, W> {}
+
+/// Level of the chip select line that selects a device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActiveLevel {
+    /// Selected while low, the common case.
+    Low,
+    /// Selected while high.
+    High,
+}
+
+/// Chip select line of a single SPI device.
+///
+/// Remembers the active level of the device so that callers only deal with
+/// `select()` and `deselect()`. The device stays selected across any number
+/// of transfers until it is deselected, [`ChipSelect::with_cs()`] scopes the
+/// selection to a closure.
+///
+/// This is the per-device primitive for sharing one [`Spi`] bus between
+/// several devices: each device owns its `ChipSelect` and selects itself only
+/// while it has access to the bus.
+pub struct ChipSelect<T: PinTrait> {
+    pin: Pin<T, Output>,
+    active_level: ActiveLevel,
+}
+
+impl<T: PinTrait> ChipSelect<T> {
+    /// Wraps the output pin and deselects the device.
+    pub fn new(pin: Pin<T, Output>, active_level: ActiveLevel) -> Self {
+        let mut cs = ChipSelect { pin, active_level };
+        cs.deselect();
+        cs
+    }
+
+    /// Drives the line to the active level.
+    pub fn select(&mut self) {
+        let _ = match self.active_level {
+            ActiveLevel::Low => self.pin.set_low(),
+            ActiveLevel::High => self.pin.set_high(),
+        };
+    }
+
+    /// Drives the line to the inactive level.
+    pub fn deselect(&mut self) {
+        let _ = match self.active_level {
+            ActiveLevel::Low => self.pin.set_high(),
+            ActiveLevel::High => self.pin.set_low(),
+        };
+    }
+
+    /// Returns `true` while the device is selected.
+    pub fn is_selected(&self) -> bool {
+        let high = self.pin.is_set_high().unwrap_or(false);
+        high == (self.active_level == ActiveLevel::High)
+    }
+
+    /// Selects the device, runs `f` with the bus and deselects the device
+    /// afterwards, also when `f` returns an error.
+    ///
+    /// ```ignore
+    /// let id = cs.with_cs(&mut spi, |spi| {
+    ///     spi.write(&[READ_ID])?;
+    ///     spi.transfer(&mut [0; 2]).map(|id| [id[0], id[1]])
+    /// })?;
+    /// ```
+    pub fn with_cs<S, R>(&mut self, spi: &mut S, f: impl FnOnce(&mut S) -> R) -> R {
+        self.select();
+        let result = f(spi);
+        self.deselect();
+        result
+    }
+
+    /// Returns the output pin, the line keeps its current level.
+    pub fn free(self) -> Pin<T, Output> {
+        self.pin
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<I: Instance, W: Word> embedded_hal_1::spi::ErrorType for Spi<I, W> {
+    type Error = crate::error::Decoded<Error>;
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<I: Instance, W: Word + 'static> embedded_hal_1::spi::SpiBus<W> for Spi<I, W> {
+    fn read(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.exchange(W::default())?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[W]) -> Result<(), Self::Error> {
+        for &word in words {
+            self.exchange(word)?;
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [W], write: &[W]) -> Result<(), Self::Error> {
+        for i in 0..read.len().max(write.len()) {
+            let received = self.exchange(write.get(i).copied().unwrap_or_default())?;
+            if let Some(word) = read.get_mut(i) {
+                *word = received;
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.exchange(*word)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        // Each word is exchanged completely, i.e. nothing is in flight when the
+        // methods above return. `STATUS.TXC` cannot be polled here, it is
+        // clear after reset until the first transfer.
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<I: Instance, W: Word> Spi<I, W> {
+    // Sends one word and returns the word received at the same time.
+    fn exchange(&mut self, word: W) -> Result<W, crate::error::Decoded<Error>> {
+        nb::block!(FullDuplex::send(self, word)).map_err(crate::error::Decoded)?;
+        nb::block!(FullDuplex::read(self)).map_err(crate::error::Decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::{size_of, MaybeUninit};
+
+    // Zeroed memory in place of the USART registers. The receive registers
+    // are loaded from the transmit registers, like the internal loopback does.
+    fn registers() -> Box<MaybeUninit<RegisterBlock>> {
+        Box::new(MaybeUninit::zeroed())
+    }
+
+    fn peek<R>(reg: &R) -> u32 {
+        assert_eq!(size_of::<R>(), 4);
+        unsafe { (reg as *const R as *const u32).read_volatile() }
+    }
+
+    fn load<R>(reg: &R, value: u32) {
+        assert_eq!(size_of::<R>(), 4);
+        unsafe { (reg as *const R as *mut u32).write_volatile(value) }
+    }
+
+    fn round_trip<W: Word>(frame_bits: u8, word: W) -> W {
+        let mut memory = registers();
+        let usart = unsafe { &*memory.as_mut_ptr() };
+        W::write(usart, frame_bits, word);
+        load(&usart.rxdata, peek(&usart.txdata));
+        load(&usart.rxdatax, peek(&usart.txdatax));
+        load(&usart.rxdouble, peek(&usart.txdouble));
+        W::read(usart, frame_bits)
+    }
+
+    #[test]
+    fn round_trip_u8() {
+        assert_eq!(round_trip(8, 0xA5u8), 0xA5);
+        assert_eq!(round_trip(4, 0x0Au8), 0x0A);
+    }
+
+    #[test]
+    fn round_trip_u16_extended() {
+        assert_eq!(round_trip(9, 0x1A5u16), 0x1A5);
+        // Bits beyond the extended data registers are dropped.
+        assert_eq!(round_trip(9, 0xFFFFu16), 0x1FF);
+    }
+
+    #[test]
+    fn round_trip_u16_double() {
+        assert_eq!(round_trip(16, 0xBEEFu16), 0xBEEF);
+        assert_eq!(round_trip(12, 0x0ABCu16), 0x0ABC);
+    }
+}
@@ -0,0 +1,49 @@
+//! Time units
+
+/// Frequency in Hz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hertz(pub u32);
+
+/// Duration in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MilliSeconds(pub u32);
+
+/// Extension trait to create frequencies and durations from integers, e.g.
+/// `400.khz()` or `50.ms()`.
+pub trait U32Ext {
+    /// Frequency in Hz.
+    fn hz(self) -> Hertz;
+
+    /// Frequency in kHz.
+    fn khz(self) -> Hertz;
+
+    /// Frequency in MHz.
+    fn mhz(self) -> Hertz;
+
+    /// Duration in milliseconds.
+    fn ms(self) -> MilliSeconds;
+}
+
+impl U32Ext for u32 {
+    fn hz(self) -> Hertz {
+        Hertz(self)
+    }
+
+    fn khz(self) -> Hertz {
+        Hertz(self * 1_000)
+    }
+
+    fn mhz(self) -> Hertz {
+        Hertz(self * 1_000_000)
+    }
+
+    fn ms(self) -> MilliSeconds {
+        MilliSeconds(self)
+    }
+}
+
+impl From<Hertz> for u32 {
+    fn from(f: Hertz) -> u32 {
+        f.0
+    }
+}
@@ -0,0 +1,1027 @@
+//! Timer/Counter (TIMER) API
+//!
+//! [`Timer`] owns a TIMER or WTIMER peripheral. The timer modes are created
+//! from it and hand it back in its stopped reset state, so a timer can be
+//! repurposed, e.g. for a delay during initialization and later for PWM,
+//! without going through the raw peripheral.
+pub use crate::pac::timer0::{cc0_ctrl::ICEDGE_A as Edge, ctrl::PRESC_A as Prescaler};
+
+use crate::{
+    cmu::{ClockControlExt, Cmu},
+    gpio::*,
+    hal::{
+        timer::{CountDown, Periodic},
+        PwmPin,
+    },
+    pac::{
+        generic::Variant,
+        timer0::{
+            cc0_ctrl::{CMOA_A, MODE_A},
+            RegisterBlock,
+        },
+        Interrupt, TIMER0, TIMER1, WTIMER0, WTIMER1,
+    },
+    prs::{self, PrsSource},
+    time::Hertz,
+};
+use core::convert::Infallible;
+use void::Void;
+
+/// Timer configuration.
+///
+/// The counter counts up from 0 to `top` with the prescaled peripheral
+/// clock and overflows with the frequency
+/// `hfperclk / (prescaler * (top + 1))`.
+///
+/// Defaults to no prescaler and a `top` value of `0xFFFF`.
+pub struct Config {
+    /// Divides HFPERCLK to derive the counter clock.
+    pub prescaler: Prescaler,
+    /// Counter value at which the counter overflows. Must fit into the width
+    /// of the counter, i.e. 16 bits for TIMER and 32 bits for WTIMER.
+    pub top: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prescaler: Prescaler::DIV1,
+            top: 0xFFFF,
+        }
+    }
+}
+
+/// TIMER API
+pub struct Timer<T> {
+    raw: T,
+    hfperclk: Hertz,
+}
+
+impl<T: Instance> Timer<T> {
+    /// Creates the HAL instance, puts the timer into its reset state and
+    /// applies the configuration. The counter is not started.
+    pub fn new(timer: T, config: &Config, cmu: &mut Cmu) -> Self {
+        cmu.enable_clock(&timer);
+
+        let mut timer = Timer {
+            raw: timer,
+            hfperclk: cmu.hfperclk(),
+        };
+        timer.reset();
+        timer.configure(config.prescaler, config.top);
+        timer
+    }
+
+    pub(crate) fn regs(&self) -> &RegisterBlock {
+        unsafe { &*T::ptr() }
+    }
+
+    /// Sets the prescaler and the top value of the counter.
+    ///
+    /// Panics when `top` does not fit into the counter width.
+    pub fn configure(&mut self, prescaler: Prescaler, top: u32) {
+        assert!(top <= T::MAX_TOP);
+
+        let timer = self.regs();
+        timer.ctrl.modify(|_, w| w.presc().variant(prescaler));
+        timer.top.write(|w| unsafe { w.bits(top) });
+    }
+
+    // Prescaler of the current configuration.
+    fn prescaler(&self) -> Prescaler {
+        match self.regs().ctrl.read().presc().variant() {
+            Variant::Val(prescaler) => prescaler,
+            // Only written through the raw peripheral.
+            Variant::Res(_) => Prescaler::DIV1024,
+        }
+    }
+
+    /// Frequency of the counter overflows with the current configuration.
+    pub fn overflow_frequency(&self) -> Hertz {
+        let timer = self.regs();
+        let prescaler = 1 << timer.ctrl.read().presc().bits();
+        let top = timer.top.read().bits() as u64;
+        Hertz((self.hfperclk.0 as u64 / (prescaler * (top + 1))) as u32)
+    }
+
+    // Stops the counter and restores the reset value of all registers used
+    // by the HAL. Interrupts are disabled and pending flags are cleared.
+    pub(crate) fn reset(&mut self) {
+        let timer = self.regs();
+
+        timer.cmd.write(|w| w.stop().set_bit());
+        while timer.status.read().running().bit_is_set() {}
+
+        timer.ien.reset();
+        timer.ifc.write(|w| unsafe { w.bits(!0) });
+        timer.routepen.reset();
+        timer.routeloc0.reset();
+        timer.cc0_ctrl.reset();
+        timer.cc1_ctrl.reset();
+        timer.cc2_ctrl.reset();
+        timer.cc3_ctrl.reset();
+        timer.ctrl.reset();
+        timer.top.reset();
+        timer.topb.reset();
+        timer.cnt.reset();
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// the overflow frequency assumes the HFPERCLK frequency at construction
+    /// time and the capture mode assumes the full 32-bit top value.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        self.regs()
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Timer::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut T {
+        &mut self.raw
+    }
+
+    /// Returns the raw interface to the underlying peripheral.
+    ///
+    /// The timer is stopped, all registers used by the HAL are restored to
+    /// their reset values and the peripheral clock is disabled.
+    pub fn release(mut self, cmu: &mut Cmu) -> T {
+        self.reset();
+        cmu.disable_clock(&self.raw);
+        self.raw
+    }
+}
+
+impl<T: Instance> Timer<T> {
+    /// Outputs a square wave with 50 % duty cycle and a frequency close to
+    /// `frequency` on the pin of compare/capture channel 0.
+    ///
+    /// The pin toggles on each compare match, see [`SquareWave`] for the
+    /// achievable frequencies.
+    pub fn into_square_wave<P>(self, _pin: P, frequency: Hertz) -> SquareWave<T>
+    where
+        P: PinLocation<T, Cc0Pin>,
+    {
+        let timer = self.regs();
+        timer
+            .routeloc0
+            .write(|w| unsafe { w.cc0loc().bits(P::LOCATION) });
+        timer.routepen.write(|w| w.cc0pen().set_bit());
+        timer.cc0_ctrl.write(|w| {
+            w.mode()
+                .variant(MODE_A::OUTPUTCOMPARE)
+                .cmoa()
+                .variant(CMOA_A::TOGGLE)
+        });
+        timer.cc0_ccv.write(|w| unsafe { w.bits(0) });
+
+        let mut square_wave = SquareWave { timer: self };
+        square_wave.set_frequency(frequency);
+        square_wave
+    }
+}
+
+/// Square wave output for tone generation, e.g. on a piezo buzzer.
+///
+/// The output toggles whenever the counter matches zero, so the frequency is
+/// `hfperclk / (2 * prescaler * (top + 1))`. The range reaches from
+/// `hfperclk / 4` to `hfperclk / (2 * 1024 * (MAX_TOP + 1))`, i.e. from
+/// 4.75 MHz down to 0.14 Hz for TIMER at the default HFPERCLK of 19 MHz. The
+/// smallest prescaler for the requested frequency is selected to get the
+/// finest resolution.
+pub struct SquareWave<T> {
+    timer: Timer<T>,
+}
+
+impl<T: Instance> SquareWave<T> {
+    /// Changes the output frequency and returns the actual frequency.
+    ///
+    /// The frequency is clamped to the achievable range. The counter restarts,
+    /// which shortens or extends the current half period.
+    pub fn set_frequency(&mut self, frequency: Hertz) -> Hertz {
+        // Clock cycles per half period of the output.
+        let half_period = self.timer.hfperclk.0 as u64 / (2 * frequency.0.max(1) as u64);
+        let (prescaler, top) = divider(half_period, T::MAX_TOP as u64 + 1);
+
+        self.timer.regs().cmd.write(|w| w.stop().set_bit());
+        self.timer.configure(prescaler, top);
+        let timer = self.timer.regs();
+        timer.cnt.write(|w| unsafe { w.bits(0) });
+        timer.cmd.write(|w| w.start().set_bit());
+
+        self.frequency()
+    }
+
+    /// Stops the counter. The pin keeps its current level.
+    pub fn pause(&mut self) {
+        self.timer.regs().cmd.write(|w| w.stop().set_bit());
+    }
+
+    /// Continues the output after [`SquareWave::pause()`].
+    pub fn resume(&mut self) {
+        self.timer.regs().cmd.write(|w| w.start().set_bit());
+    }
+
+    /// Current output frequency.
+    pub fn frequency(&self) -> Hertz {
+        Hertz(self.timer.overflow_frequency().0 / 2)
+    }
+
+    /// Stops the output and returns the timer in its reset state, e.g. to
+    /// configure another mode.
+    pub fn into_timer(mut self) -> Timer<T> {
+        self.timer.reset();
+        self.timer
+    }
+
+    /// Stops the output and returns the raw interface to the underlying
+    /// peripheral, see [`Timer::release()`].
+    pub fn release(self, cmu: &mut Cmu) -> T {
+        self.into_timer().release(cmu)
+    }
+}
+
+impl<T: Instance> Timer<T> {
+    /// Outputs a PWM signal with a frequency close to `frequency` on the pin
+    /// of compare/capture channel 0.
+    ///
+    /// The output starts disabled with a duty cycle of 0, see [`Pwm`].
+    pub fn into_pwm<P>(self, _pin: P, frequency: Hertz) -> Pwm<T>
+    where
+        P: PinLocation<T, Cc0Pin>,
+    {
+        let timer = self.regs();
+        timer
+            .routeloc0
+            .write(|w| unsafe { w.cc0loc().bits(P::LOCATION) });
+        timer.cc0_ctrl.write(|w| w.mode().variant(MODE_A::PWM));
+        timer.cc0_ccv.write(|w| unsafe { w.bits(0) });
+        timer.cc0_ccvb.write(|w| unsafe { w.bits(0) });
+
+        let mut pwm = Pwm { timer: self };
+        pwm.set_frequency(frequency);
+        pwm
+    }
+}
+
+/// Pulse width modulated output, e.g. for dimming an LED.
+///
+/// The output is set when the counter wraps and cleared when it matches the
+/// duty cycle, the period is `top + 1` counter ticks. A duty cycle of 0
+/// keeps the output low, [`PwmPin::get_max_duty()`] keeps it high. New duty
+/// cycles take effect at the next wrap through the buffered compare value, so
+/// no period is cut short.
+///
+/// The smallest prescaler for the requested frequency is selected to get the
+/// finest duty cycle resolution. At the default HFPERCLK of 19 MHz a TIMER
+/// has the full 16-bit resolution down to about 290 Hz.
+pub struct Pwm<T> {
+    timer: Timer<T>,
+}
+
+impl<T: Instance> Pwm<T> {
+    /// Changes the PWM frequency and returns the actual frequency.
+    ///
+    /// The frequency is clamped to the achievable range. The duty cycle is
+    /// kept as a fraction of the period. The counter restarts, which shortens
+    /// or extends the current period.
+    pub fn set_frequency(&mut self, frequency: Hertz) -> Hertz {
+        let duty = self.get_duty() as u64;
+        let old_max_duty = self.get_max_duty() as u64;
+
+        // The maximum duty cycle, `top + 1`, must fit into the compare
+        // register.
+        let period = self.timer.hfperclk.0 as u64 / frequency.0.max(1) as u64;
+        let (prescaler, top) = divider(period, T::MAX_TOP as u64);
+        let duty = (duty * (top as u64 + 1) / old_max_duty.max(1)) as u32;
+
+        self.timer.regs().cmd.write(|w| w.stop().set_bit());
+        self.timer.configure(prescaler, top);
+        let timer = self.timer.regs();
+        timer.cc0_ccv.write(|w| unsafe { w.bits(duty) });
+        timer.cc0_ccvb.write(|w| unsafe { w.bits(duty) });
+        timer.cnt.write(|w| unsafe { w.bits(0) });
+        timer.cmd.write(|w| w.start().set_bit());
+
+        self.frequency()
+    }
+
+    /// Current PWM frequency.
+    pub fn frequency(&self) -> Hertz {
+        self.timer.overflow_frequency()
+    }
+
+    /// Stops the output and returns the timer in its reset state, e.g. to
+    /// configure another mode.
+    pub fn into_timer(mut self) -> Timer<T> {
+        self.timer.reset();
+        self.timer
+    }
+
+    /// Stops the output and returns the raw interface to the underlying
+    /// peripheral, see [`Timer::release()`].
+    pub fn release(self, cmu: &mut Cmu) -> T {
+        self.into_timer().release(cmu)
+    }
+}
+
+/// The duty cycle is measured in counter ticks.
+impl<T: Instance> PwmPin for Pwm<T> {
+    type Duty = u32;
+
+    /// Disconnects the pin from the timer, it returns to the level of its
+    /// GPIO output register. The counter keeps running.
+    fn disable(&mut self) {
+        self.timer
+            .regs()
+            .routepen
+            .modify(|_, w| w.cc0pen().clear_bit());
+    }
+
+    fn enable(&mut self) {
+        self.timer
+            .regs()
+            .routepen
+            .modify(|_, w| w.cc0pen().set_bit());
+    }
+
+    fn get_duty(&self) -> u32 {
+        // The buffered value holds the latest duty cycle, also before it was
+        // transferred to the compare register.
+        self.timer.regs().cc0_ccvb.read().bits()
+    }
+
+    /// `top + 1`, saturated at `u32::MAX` for a WTIMER with the largest top
+    /// value, e.g. after [`Timer::configure()`].
+    fn get_max_duty(&self) -> u32 {
+        self.timer.regs().top.read().bits().saturating_add(1)
+    }
+
+    /// Values above [`PwmPin::get_max_duty()`] are clamped.
+    fn set_duty(&mut self, duty: u32) {
+        let duty = duty.min(self.get_max_duty());
+        self.timer
+            .regs()
+            .cc0_ccvb
+            .write(|w| unsafe { w.bits(duty) });
+    }
+}
+
+impl<T: Instance> Timer<T> {
+    /// Turns the timer into a periodic count down timer. The counter is
+    /// started by [`CountDown::start()`].
+    pub fn into_periodic(self) -> PeriodicTimer<T> {
+        PeriodicTimer { timer: self }
+    }
+}
+
+/// Periodic count down timer, e.g. as a tick source.
+///
+/// [`CountDown::start()`] takes the frequency of the timeouts. The counter
+/// restarts on its own at each overflow, so the timeouts do not drift when
+/// [`CountDown::wait()`] is called late. A timeout is only reported once,
+/// even when several periods elapsed since the last call. The achievable
+/// frequencies are the same as the overflow frequencies of [`Pwm`].
+///
+/// For a tick interrupt, enable it with [`PeriodicTimer::enable_interrupt()`],
+/// unmask [`PeriodicTimer::interrupt()`] and call `wait()` in the handler to
+/// clear the flag.
+pub struct PeriodicTimer<T> {
+    timer: Timer<T>,
+}
+
+impl<T: Instance> PeriodicTimer<T> {
+    /// Returns the NVIC line of the timer interrupt, which the application
+    /// unmasks.
+    pub fn interrupt(&self) -> Interrupt {
+        T::INTERRUPT
+    }
+
+    /// Enables the overflow interrupt, which fires at the end of each period.
+    pub fn enable_interrupt(&mut self) {
+        self.timer.regs().ien.modify(|_, w| w.of().set_bit());
+    }
+
+    /// Disables the overflow interrupt.
+    pub fn disable_interrupt(&mut self) {
+        self.timer.regs().ien.modify(|_, w| w.of().clear_bit());
+    }
+
+    /// Frequency of the timeouts since the last start.
+    pub fn frequency(&self) -> Hertz {
+        self.timer.overflow_frequency()
+    }
+
+    /// Returns the overflow signal as PRS producer, e.g. to trigger ADC
+    /// conversions at the timer frequency.
+    ///
+    /// The signal already is a short pulse per overflow, connect it with
+    /// [`prs::Edge::OFF`].
+    pub fn prs_source(&self) -> PrsSource {
+        // Signal 0 is the underflow, 1 the overflow.
+        PrsSource::new(T::PRS_SOURCE, 1)
+    }
+
+    /// Stops the counter and returns the timer in its reset state, e.g. to
+    /// configure another mode.
+    pub fn into_timer(mut self) -> Timer<T> {
+        self.timer.reset();
+        self.timer
+    }
+
+    /// Stops the counter and returns the raw interface to the underlying
+    /// peripheral, see [`Timer::release()`].
+    pub fn release(self, cmu: &mut Cmu) -> T {
+        self.into_timer().release(cmu)
+    }
+}
+
+impl<T: Instance> CountDown for PeriodicTimer<T> {
+    type Time = Hertz;
+
+    /// Restarts the counter with the period closest to `frequency`.
+    fn start<F>(&mut self, frequency: F)
+    where
+        F: Into<Hertz>,
+    {
+        let period = self.timer.hfperclk.0 as u64 / frequency.into().0.max(1) as u64;
+        let (prescaler, top) = divider(period, T::MAX_TOP as u64 + 1);
+
+        self.timer.regs().cmd.write(|w| w.stop().set_bit());
+        self.timer.configure(prescaler, top);
+        let timer = self.timer.regs();
+        timer.cnt.write(|w| unsafe { w.bits(0) });
+        timer.ifc.write(|w| w.of().set_bit());
+        timer.cmd.write(|w| w.start().set_bit());
+    }
+
+    fn wait(&mut self) -> nb::Result<(), Void> {
+        let timer = self.timer.regs();
+        if timer.if_.read().of().bit_is_set() {
+            timer.ifc.write(|w| w.of().set_bit());
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<T: Instance> Periodic for PeriodicTimer<T> {}
+
+impl<T: WideInstance> Timer<T> {
+    /// Starts the counter over the full 32-bit range and captures its value
+    /// on `edge` of the input pin with compare/capture channel 0.
+    ///
+    /// The prescaler of the current configuration is kept, the top value is
+    /// set to `0xFFFF_FFFF`.
+    pub fn into_capture<P>(mut self, _pin: P, edge: Edge) -> Capture<T>
+    where
+        P: PinLocation<T, Cc0Pin>,
+    {
+        self.configure(self.prescaler(), T::MAX_TOP);
+        let timer = self.regs();
+        timer
+            .routeloc0
+            .write(|w| unsafe { w.cc0loc().bits(P::LOCATION) });
+        timer.routepen.write(|w| w.cc0pen().set_bit());
+        timer.cc0_ctrl.write(|w| {
+            w.mode()
+                .variant(MODE_A::INPUTCAPTURE)
+                .icedge()
+                .variant(edge)
+        });
+        timer.cmd.write(|w| w.start().set_bit());
+
+        Capture { timer: self }
+    }
+
+    /// Same as [`Timer::into_capture()`] but captures on `edge` of a PRS
+    /// channel instead of a pin.
+    ///
+    /// Connect the producer with [`prs::Edge::OFF`], the timer detects the
+    /// edges of the channel level itself.
+    pub fn into_prs_capture(mut self, channel: &prs::Channel, edge: Edge) -> Capture<T> {
+        self.configure(self.prescaler(), T::MAX_TOP);
+        let timer = self.regs();
+        timer.cc0_ctrl.write(|w| unsafe {
+            w.mode()
+                .variant(MODE_A::INPUTCAPTURE)
+                .icedge()
+                .variant(edge)
+                .insel()
+                .set_bit()
+                .prssel()
+                .bits(channel.nr())
+        });
+        timer.cmd.write(|w| w.start().set_bit());
+
+        Capture { timer: self }
+    }
+}
+
+/// 32-bit input capture on a wide timer.
+///
+/// Captured values are timestamps in counter ticks. The duration between two
+/// captures is `later.wrapping_sub(earlier)`, which is correct as long as the
+/// counter did not overflow more than once in between. The longest
+/// measurable interval is `2^32 * prescaler / hfperclk`, e.g. at the default
+/// HFPERCLK of 19 MHz:
+///
+/// | Prescaler | Resolution | Maximum interval |
+/// |-----------|-----------:|-----------------:|
+/// | `DIV1`    |     53 ns  |          3.8 min |
+/// | `DIV16`   |    842 ns  |           60 min |
+/// | `DIV256`  |   13.5 µs  |           16 h   |
+/// | `DIV1024` |   53.9 µs  |           2.7 d  |
+///
+/// Longer spans can be measured by counting the overflows reported by
+/// [`Capture::take_overflow()`] in between captures.
+pub struct Capture<T> {
+    timer: Timer<T>,
+}
+
+impl<T: WideInstance> Capture<T> {
+    /// Returns the oldest captured counter value.
+    ///
+    /// The hardware buffers up to two captures. Further captures overwrite the
+    /// newest buffered value.
+    pub fn read(&mut self) -> nb::Result<u32, Infallible> {
+        let timer = self.timer.regs();
+        if timer.status.read().icv0().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(timer.cc0_ccv.read().bits())
+    }
+
+    /// Returns `true` when the counter overflowed since the previous call.
+    pub fn take_overflow(&mut self) -> bool {
+        let timer = self.timer.regs();
+        let overflow = timer.if_.read().of().bit_is_set();
+        if overflow {
+            timer.ifc.write(|w| w.of().set_bit());
+        }
+        overflow
+    }
+
+    /// Returns the number of ticks from the capture `earlier` to the capture
+    /// `later`, for spans longer than the counter range.
+    ///
+    /// `overflows` is the number of counter overflows in between, e.g. counted
+    /// with [`Capture::take_overflow()`] in the overflow interrupt. A wrap
+    /// from `earlier` to `later` is one of them.
+    pub fn interval(&self, earlier: u32, later: u32, overflows: u32) -> u64 {
+        capture_interval(earlier, later, overflows)
+    }
+
+    /// Frequency of the counter ticks.
+    pub fn tick_frequency(&self) -> Hertz {
+        let prescaler = 1 << self.timer.regs().ctrl.read().presc().bits();
+        Hertz(self.timer.hfperclk.0 / prescaler)
+    }
+
+    /// Stops the capture and returns the timer in its reset state, e.g. to
+    /// configure another mode.
+    pub fn into_timer(mut self) -> Timer<T> {
+        self.timer.reset();
+        self.timer
+    }
+
+    /// Stops the capture and returns the raw interface to the underlying
+    /// peripheral, see [`Timer::release()`].
+    pub fn release(self, cmu: &mut Cmu) -> T {
+        self.into_timer().release(cmu)
+    }
+}
+
+impl<T: Instance> Timer<T> {
+    /// Starts the counter over its full range as a free-running time base.
+    ///
+    /// The prescaler of the current configuration is kept and sets the tick
+    /// rate, the top value is set to the largest value of the counter.
+    pub fn into_monotonic(mut self) -> Monotonic<T> {
+        self.configure(self.prescaler(), T::MAX_TOP);
+        let timer = self.regs();
+        timer.cmd.write(|w| w.start().set_bit());
+
+        Monotonic { timer: self }
+    }
+}
+
+/// Free-running counter for measuring execution time.
+///
+/// Take a timestamp with [`Monotonic::now()`] before and after the code under
+/// test and get the difference with [`Monotonic::elapsed()`]:
+///
+/// ```ignore
+/// let start = monotonic.now();
+/// i2c.write_read(0x40, &[0xE5], &mut buffer)?;
+/// let ticks = monotonic.elapsed(start);
+/// let us = ticks as u64 * 1_000_000 / monotonic.ticks_per_second() as u64;
+/// ```
+///
+/// The counter wraps after `MAX_TOP + 1` ticks, i.e. after 3.4 ms for TIMER
+/// and after 3.8 min for WTIMER at the default HFPERCLK of 19 MHz without
+/// prescaler. Longer sections need a prescaler, see the table at
+/// [`Capture`].
+///
+/// The DWT cycle counter of the Cortex-M4 (`cortex_m::peripheral::DWT`)
+/// counts core clock cycles instead, 32 bits wide and without occupying a
+/// timer, but it stops while the core sleeps and is not available in all
+/// debug configurations. The timer keeps counting in EM1.
+pub struct Monotonic<T> {
+    timer: Timer<T>,
+}
+
+impl<T: Instance> Monotonic<T> {
+    /// Returns the current counter value.
+    pub fn now(&self) -> u32 {
+        self.timer.regs().cnt.read().bits()
+    }
+
+    /// Returns the ticks since `earlier`, a result of [`Monotonic::now()`].
+    ///
+    /// Correct as long as the counter did not wrap more than once in
+    /// between.
+    pub fn elapsed(&self, earlier: u32) -> u32 {
+        self.now().wrapping_sub(earlier) & T::MAX_TOP
+    }
+
+    /// Frequency of the counter ticks.
+    pub fn ticks_per_second(&self) -> Hertz {
+        let prescaler = 1 << self.timer.regs().ctrl.read().presc().bits();
+        Hertz(self.timer.hfperclk.0 / prescaler)
+    }
+
+    /// Stops the counter and returns the timer in its reset state, e.g. to
+    /// configure another mode.
+    pub fn into_timer(mut self) -> Timer<T> {
+        self.timer.reset();
+        self.timer
+    }
+
+    /// Stops the counter and returns the raw interface to the underlying
+    /// peripheral, see [`Timer::release()`].
+    pub fn release(self, cmu: &mut Cmu) -> T {
+        self.into_timer().release(cmu)
+    }
+}
+
+// Returns the smallest prescaler, for the finest resolution, and the top
+// value for a period of `cycles` clock cycles with at most `max` ticks. The
+// period is clamped to the achievable range.
+fn divider(cycles: u64, max: u64) -> (Prescaler, u32) {
+    const PRESCALERS: [Prescaler; 11] = [
+        Prescaler::DIV1,
+        Prescaler::DIV2,
+        Prescaler::DIV4,
+        Prescaler::DIV8,
+        Prescaler::DIV16,
+        Prescaler::DIV32,
+        Prescaler::DIV64,
+        Prescaler::DIV128,
+        Prescaler::DIV256,
+        Prescaler::DIV512,
+        Prescaler::DIV1024,
+    ];
+
+    let mut presc = 0;
+    while presc < PRESCALERS.len() - 1 && cycles >> presc > max {
+        presc += 1;
+    }
+    let top = ((cycles >> presc).clamp(2, max) - 1) as u32;
+    (PRESCALERS[presc], top)
+}
+
+// Ticks between two captures of the 32-bit counter with `overflows` overflows
+// in between. An overflow count of zero with a wrapped counter is treated as a
+// single wrap.
+fn capture_interval(earlier: u32, later: u32, overflows: u32) -> u64 {
+    let wraps = u64::from(overflows).saturating_sub(u64::from(later < earlier));
+    (wraps << 32) + u64::from(later.wrapping_sub(earlier))
+}
+
+/// Internal trait used to implement the timer API for PAC TIMER and WTIMER
+/// instances.
+pub trait Instance: ClockControlExt {
+    /// Largest value of the `TOP` register.
+    const MAX_TOP: u32;
+    /// NVIC line of the timer interrupts.
+    const INTERRUPT: Interrupt;
+    /// PRS producer of the timer signals.
+    const PRS_SOURCE: prs::Source;
+
+    /// Returns a pointer to the register block.
+    ///
+    /// WTIMER instances have the same register layout as TIMER instances
+    /// with wider counter and compare fields. Those are always accessed with
+    /// their raw bits to support the full width.
+    fn ptr() -> *const RegisterBlock;
+}
+
+impl Instance for TIMER0 {
+    const MAX_TOP: u32 = 0xFFFF;
+    const INTERRUPT: Interrupt = Interrupt::TIMER0;
+    const PRS_SOURCE: prs::Source = prs::Source::TIMER0;
+    fn ptr() -> *const RegisterBlock {
+        TIMER0::ptr()
+    }
+}
+
+impl Instance for TIMER1 {
+    const MAX_TOP: u32 = 0xFFFF;
+    const INTERRUPT: Interrupt = Interrupt::TIMER1;
+    const PRS_SOURCE: prs::Source = prs::Source::TIMER1;
+    fn ptr() -> *const RegisterBlock {
+        TIMER1::ptr()
+    }
+}
+
+impl Instance for WTIMER0 {
+    const MAX_TOP: u32 = 0xFFFF_FFFF;
+    const INTERRUPT: Interrupt = Interrupt::WTIMER0;
+    const PRS_SOURCE: prs::Source = prs::Source::WTIMER0;
+    fn ptr() -> *const RegisterBlock {
+        WTIMER0::ptr() as *const RegisterBlock
+    }
+}
+
+impl Instance for WTIMER1 {
+    const MAX_TOP: u32 = 0xFFFF_FFFF;
+    const INTERRUPT: Interrupt = Interrupt::WTIMER1;
+    const PRS_SOURCE: prs::Source = prs::Source::WTIMER1;
+    fn ptr() -> *const RegisterBlock {
+        WTIMER1::ptr() as *const RegisterBlock
+    }
+}
+
+/// Timer instances with a 32-bit counter.
+pub trait WideInstance: Instance {}
+
+impl WideInstance for WTIMER0 {}
+impl WideInstance for WTIMER1 {}
+
+/// Marks a pin that can be used as compare/capture channel 0 signal.
+///
+/// Output pins are used for [`Pwm`] and [`SquareWave`], input pins for
+/// [`Capture`]. TIMER0 and TIMER1 share one table, WTIMER0 and WTIMER1 each
+/// have their own. LED0 (PF4) and LED1 (PF5) of the starter kit are
+/// locations 28 and 29 of TIMER0 and TIMER1 and locations 17 and 18 of
+/// WTIMER0.
+pub struct Cc0Pin;
+
+impl_pin_locations!(TIMER0, Cc0Pin, Output, {
+    PA0: 0,
+    PA1: 1,
+    PA2: 2,
+    PA3: 3,
+    PA4: 4,
+    PA5: 5,
+    PB11: 6,
+    PB12: 7,
+    PB13: 8,
+    PB14: 9,
+    PB15: 10,
+    PC6: 11,
+    PC7: 12,
+    PC8: 13,
+    PC9: 14,
+    PC10: 15,
+    PC11: 16,
+    PD9: 17,
+    PD10: 18,
+    PD11: 19,
+    PD12: 20,
+    PD13: 21,
+    PD14: 22,
+    PD15: 23,
+    // Overwriting debug pins is not supported
+    // PF0: 24,
+    // PF1: 25,
+    // PF2: 26,
+    // PF3: 27,
+    PF4: 28,
+    PF5: 29,
+    PF6: 30,
+    PF7: 31,
+});
+
+impl_pin_locations!(TIMER1, Cc0Pin, Output, {
+    PA0: 0,
+    PA1: 1,
+    PA2: 2,
+    PA3: 3,
+    PA4: 4,
+    PA5: 5,
+    PB11: 6,
+    PB12: 7,
+    PB13: 8,
+    PB14: 9,
+    PB15: 10,
+    PC6: 11,
+    PC7: 12,
+    PC8: 13,
+    PC9: 14,
+    PC10: 15,
+    PC11: 16,
+    PD9: 17,
+    PD10: 18,
+    PD11: 19,
+    PD12: 20,
+    PD13: 21,
+    PD14: 22,
+    PD15: 23,
+    // Overwriting debug pins is not supported
+    // PF0: 24,
+    // PF1: 25,
+    // PF2: 26,
+    // PF3: 27,
+    PF4: 28,
+    PF5: 29,
+    PF6: 30,
+    PF7: 31,
+});
+
+impl_pin_locations!(WTIMER0, Cc0Pin, Input, {
+    PA5: 0,
+    PA6: 1,
+    PA7: 2,
+    PA8: 3,
+    PA9: 4,
+    PI0: 5,
+    PI1: 6,
+    PI2: 7,
+    PI3: 8,
+    PB6: 9,
+    PB7: 10,
+    PB8: 11,
+    PB9: 12,
+    PB10: 13,
+    // Overwriting debug pins is not supported
+    // PF0: 14,
+    // PF1: 15,
+    // PF3: 16,
+    PF4: 17,
+    PF5: 18,
+    PF6: 19,
+    PF7: 20,
+    PF8: 21,
+    PF9: 22,
+    PF10: 23,
+    PF11: 24,
+    PF12: 25,
+    PF13: 26,
+    PF14: 27,
+    PF15: 28,
+    PK0: 29,
+    PK1: 30,
+    PK2: 31,
+});
+
+impl_pin_locations!(WTIMER0, Cc0Pin, Output, {
+    PA5: 0,
+    PA6: 1,
+    PA7: 2,
+    PA8: 3,
+    PA9: 4,
+    PI0: 5,
+    PI1: 6,
+    PI2: 7,
+    PI3: 8,
+    PB6: 9,
+    PB7: 10,
+    PB8: 11,
+    PB9: 12,
+    PB10: 13,
+    // Overwriting debug pins is not supported
+    // PF0: 14,
+    // PF1: 15,
+    // PF3: 16,
+    PF4: 17,
+    PF5: 18,
+    PF6: 19,
+    PF7: 20,
+    PF8: 21,
+    PF9: 22,
+    PF10: 23,
+    PF11: 24,
+    PF12: 25,
+    PF13: 26,
+    PF14: 27,
+    PF15: 28,
+    PK0: 29,
+    PK1: 30,
+    PK2: 31,
+});
+
+impl_pin_locations!(WTIMER1, Cc0Pin, Input, {
+    PD8: 0,
+    PD9: 1,
+    PD10: 2,
+    PD11: 3,
+    PD12: 4,
+    PD13: 5,
+    PD14: 6,
+    PD15: 7,
+    PI2: 8,
+    PI3: 9,
+    PB6: 10,
+    PB7: 11,
+    PB8: 12,
+    PB9: 13,
+    PB10: 14,
+    PB11: 15,
+    PJ14: 16,
+    PJ15: 17,
+    PC0: 18,
+    PC1: 19,
+    PC2: 20,
+    PC3: 21,
+    PC4: 22,
+    PC5: 23,
+    PF11: 24,
+    PF12: 25,
+    PF13: 26,
+    PF14: 27,
+    PF15: 28,
+    PK0: 29,
+    PK1: 30,
+    PK2: 31,
+});
+
+impl_pin_locations!(WTIMER1, Cc0Pin, Output, {
+    PD8: 0,
+    PD9: 1,
+    PD10: 2,
+    PD11: 3,
+    PD12: 4,
+    PD13: 5,
+    PD14: 6,
+    PD15: 7,
+    PI2: 8,
+    PI3: 9,
+    PB6: 10,
+    PB7: 11,
+    PB8: 12,
+    PB9: 13,
+    PB10: 14,
+    PB11: 15,
+    PJ14: 16,
+    PJ15: 17,
+    PC0: 18,
+    PC1: 19,
+    PC2: 20,
+    PC3: 21,
+    PC4: 22,
+    PC5: 23,
+    PF11: 24,
+    PF12: 25,
+    PF13: 26,
+    PF14: 27,
+    PF15: 28,
+    PK0: 29,
+    PK1: 30,
+    PK2: 31,
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn divider_smallest_prescaler() {
+        assert_eq!(divider(1000, 0x1_0000), (Prescaler::DIV1, 999));
+        assert_eq!(divider(0x1_0000, 0x1_0000), (Prescaler::DIV1, 0xFFFF));
+        assert_eq!(divider(0x1_0001, 0x1_0000), (Prescaler::DIV2, 0x7FFF));
+        assert_eq!(divider(19_000_000, 0x1_0000), (Prescaler::DIV512, 37_108));
+    }
+
+    #[test]
+    fn divider_clamped() {
+        assert_eq!(divider(0, 0x1_0000), (Prescaler::DIV1, 1));
+        assert_eq!(divider(u64::MAX, 0x1_0000), (Prescaler::DIV1024, 0xFFFF));
+    }
+
+    #[test]
+    fn capture_interval_beyond_16_bits() {
+        assert_eq!(capture_interval(0x10, 0x0012_3456, 0), 0x0012_3446);
+        assert!(capture_interval(0, 0x0001_0000, 0) > 0xFFFF);
+    }
+
+    #[test]
+    fn capture_interval_across_wrap() {
+        assert_eq!(capture_interval(0xFFFF_FFF0, 0x10, 1), 0x20);
+        assert_eq!(capture_interval(0xFFFF_FFF0, 0x10, 0), 0x20);
+    }
+
+    #[test]
+    fn capture_interval_with_overflows() {
+        assert_eq!(capture_interval(0x10, 0x20, 1), (1 << 32) + 0x10);
+        assert_eq!(capture_interval(0xFFFF_FFF0, 0x10, 3), (2 << 32) + 0x20);
+    }
+}
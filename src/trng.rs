@@ -0,0 +1,246 @@
+//! True random number generator (TRNG) API
+//!
+//! The TRNG continuously monitors its entropy source with the health tests
+//! recommended by NIST SP 800-90B (repetition count test and adaptive
+//! proportion test) and the AIS31 online tests. The test thresholds are fixed
+//! in hardware. [`HealthTestConfig`] selects which test failures are reported
+//! and raise an interrupt, and whether data is withheld while an alarm is
+//! latched.
+//!
+//! With the `rand_core` feature [`Trng`] implements `rand_core::RngCore` and
+//! `CryptoRng`. Those methods block until the FIFO holds enough data.
+//! `try_fill_bytes()` reports a latched alarm as error with the code
+//! `Error::CUSTOM_START + n`, where `n` is the [`HealthAlarm`] discriminant,
+//! the infallible methods panic.
+use crate::{cmu::Cmu, error::Describe, pac::TRNG0};
+use core::fmt;
+
+/// Failure of one of the continuous health tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HealthAlarm {
+    /// NIST SP 800-90B repetition count test failed.
+    RepetitionCount,
+    /// NIST SP 800-90B adaptive proportion test failed (64-sample window).
+    AdaptiveProportion64,
+    /// NIST SP 800-90B adaptive proportion test failed (4096-sample window).
+    AdaptiveProportion4096,
+    /// AIS31 preliminary noise alarm.
+    ///
+    /// Occurs occasionally even with a healthy entropy source.
+    Ais31Preliminary,
+    /// AIS31 noise alarm.
+    Ais31Noise,
+}
+
+impl Describe for HealthAlarm {
+    fn describe(&self) -> &'static str {
+        match self {
+            HealthAlarm::RepetitionCount => "repetition count test failed",
+            HealthAlarm::AdaptiveProportion64 => "adaptive proportion test (64 samples) failed",
+            HealthAlarm::AdaptiveProportion4096 => "adaptive proportion test (4096 samples) failed",
+            HealthAlarm::Ais31Preliminary => "AIS31 preliminary noise alarm",
+            HealthAlarm::Ais31Noise => "AIS31 noise alarm",
+        }
+    }
+}
+
+impl fmt::Display for HealthAlarm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.describe())
+    }
+}
+
+/// Health test configuration.
+///
+/// Monitored tests also raise the `TRNG0` interrupt on failure.
+///
+/// Defaults to monitoring all tests and withholding data on alarms.
+pub struct HealthTestConfig {
+    /// Monitor the repetition count test.
+    pub repetition_count: bool,
+    /// Monitor the adaptive proportion tests for both window sizes.
+    pub adaptive_proportion: bool,
+    /// Monitor the AIS31 noise alarms.
+    pub ais31: bool,
+    /// Return an error instead of random data while a monitored alarm is latched.
+    pub fail_on_alarm: bool,
+}
+
+impl Default for HealthTestConfig {
+    fn default() -> Self {
+        Self {
+            repetition_count: true,
+            adaptive_proportion: true,
+            ais31: true,
+            fail_on_alarm: true,
+        }
+    }
+}
+
+/// TRNG API
+pub struct Trng {
+    raw: TRNG0,
+    fail_on_alarm: bool,
+}
+
+impl Trng {
+    /// Creates the HAL instance and starts the entropy source with all health
+    /// tests monitored.
+    pub fn new(trng: TRNG0, cmu: &mut Cmu) -> Trng {
+        cmu.enable_clock(&trng);
+
+        let mut trng = Trng {
+            raw: trng,
+            fail_on_alarm: true,
+        };
+        trng.configure_health_tests(&HealthTestConfig::default());
+        trng
+    }
+
+    /// Restarts the TRNG with a new health test configuration.
+    ///
+    /// The FIFO is flushed and the start-up tests run again before new data
+    /// becomes available.
+    pub fn configure_health_tests(&mut self, config: &HealthTestConfig) {
+        self.fail_on_alarm = config.fail_on_alarm;
+
+        // Soft reset to flush the FIFO and clear latched alarms.
+        self.raw.control.modify(|_, w| w.softreset().set_bit());
+        self.raw.control.write(|w| {
+            w.repcountien()
+                .bit(config.repetition_count)
+                .apt64ien()
+                .bit(config.adaptive_proportion)
+                .apt4096ien()
+                .bit(config.adaptive_proportion)
+                .preien()
+                .bit(config.ais31)
+                .almien()
+                .bit(config.ais31)
+                .enable()
+                .set_bit()
+        });
+    }
+
+    /// Returns the latched alarm of a monitored health test, if any.
+    pub fn last_alarm(&self) -> Option<HealthAlarm> {
+        let control = self.raw.control.read();
+        let status = self.raw.status.read();
+        if control.repcountien().bit() && status.repcountif().bit() {
+            Some(HealthAlarm::RepetitionCount)
+        } else if control.apt64ien().bit() && status.apt64if().bit() {
+            Some(HealthAlarm::AdaptiveProportion64)
+        } else if control.apt4096ien().bit() && status.apt4096if().bit() {
+            Some(HealthAlarm::AdaptiveProportion4096)
+        } else if control.almien().bit() && status.almif().bit() {
+            Some(HealthAlarm::Ais31Noise)
+        } else if control.preien().bit() && status.preif().bit() {
+            Some(HealthAlarm::Ais31Preliminary)
+        } else {
+            None
+        }
+    }
+
+    /// Clears all latched alarms.
+    ///
+    /// Failures of the NIST tests or the AIS31 noise alarm indicate a
+    /// degraded entropy source. The TRNG is restarted and possibly biased data
+    /// in the FIFO is discarded in that case.
+    pub fn clear_alarms(&mut self) {
+        match self.last_alarm() {
+            None => {}
+            Some(HealthAlarm::Ais31Preliminary) => {
+                // Leaves the other flags of the register untouched.
+                self.raw.status.modify(|_, w| w.preif().clear_bit());
+            }
+            Some(_) => {
+                self.raw.control.modify(|_, w| w.softreset().set_bit());
+                self.raw.control.modify(|_, w| w.softreset().clear_bit());
+            }
+        }
+    }
+
+    /// Reads one random word from the FIFO.
+    ///
+    /// Returns the latched alarm as error when configured to fail on alarms.
+    pub fn read(&mut self) -> nb::Result<u32, HealthAlarm> {
+        if self.fail_on_alarm {
+            if let Some(alarm) = self.last_alarm() {
+                return Err(nb::Error::Other(alarm));
+            }
+        }
+
+        if self.raw.fifolevel.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        Ok(self.raw.fifo.read().bits())
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// masking a health test interrupt silently disables the corresponding
+    /// alarm check in `read()`.
+    pub unsafe fn raw(&self) -> &crate::pac::trng0::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Trng::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut TRNG0 {
+        &mut self.raw
+    }
+
+    /// Disables the TRNG and returns the raw interface to the underlying
+    /// peripheral.
+    pub fn release(self) -> TRNG0 {
+        self.raw.control.reset();
+        self.raw
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl Trng {
+    fn read_blocking(&mut self) -> Result<u32, rand_core::Error> {
+        nb::block!(self.read()).map_err(|alarm| {
+            let code = rand_core::Error::CUSTOM_START + alarm as u32;
+            rand_core::Error::from(core::num::NonZeroU32::new(code).unwrap())
+        })
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl rand_core::RngCore for Trng {
+    fn next_u32(&mut self) -> u32 {
+        self.read_blocking().expect("TRNG health test failed")
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = self.next_u32() as u64;
+        let high = self.next_u32() as u64;
+        high << 32 | low
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest).expect("TRNG health test failed")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        for chunk in dest.chunks_mut(4) {
+            let word = self.read_blocking()?.to_le_bytes();
+            chunk.copy_from_slice(&word[..chunk.len()]);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl rand_core::CryptoRng for Trng {}
@@ -1,182 +1,2012 @@
 //! Serial API for the USART peripheral
-pub use crate::pac::usart0::frame::{PARITY_A as Parity, STOPBITS_A as StopBits};
+//!
+//! All four USARTs are clocked from HFPERCLK, there is neither an alternate
+//! clock source nor a per-instance prescaler on this device. The baud rate is
+//! derived with the fractional `CLKDIV` divider of each instance, see
+//! [`Usart::baudrate()`] for the achieved value. A slower clock for all
+//! high frequency peripherals can be selected with
+//! [`Cmu::set_hfperclk_divider()`], which affects every USART, I2C and timer.
+pub use crate::pac::usart0::{
+    ctrl::OVS_A as Oversampling,
+    frame::{DATABITS_A as DataBits, PARITY_A as Parity, STOPBITS_A as StopBits},
+    timing::ICS_A as GuardTime,
+};
 use crate::{
     cmu::{ClockControlExt, Cmu},
     gpio::*,
     hal::{
         blocking::serial::write::Default as BlockingWriteDefault,
+        digital::v2::OutputPin,
         serial::{Read, Write},
     },
-    pac::{usart0::RegisterBlock, USART0, USART1, USART2, USART3},
+    ldma::{self, Channel},
+    pac::{
+        generic::Variant,
+        usart0::{
+            timecmp0::{TSTART_A, TSTOP_A},
+            RegisterBlock,
+        },
+        Interrupt, USART0, USART1, USART2, USART3,
+    },
+    time::Hertz,
     util::PeripheralClearSetExt,
 };
 use core::{convert::Infallible, fmt, marker::PhantomData, ops::Deref};
+use embedded_error::ImplError;
 pub use embedded_error::SerialError as Error;
-use nb::{self, block};
+
+#[cfg(feature = "async")]
+pub mod async_serial;
+#[cfg(feature = "heapless")]
+pub mod buffered;
+pub mod circular;
+pub mod multiprocessor;
+pub mod rs485;
+
+// Baud rate tolerance of the default configuration in per mille.
+const DEFAULT_BAUD_TOLERANCE: u16 = 20;
 
 /// Serial configuration.
 ///
-/// Defaults to 115200bps, 8 data bits, no parity and 1 stop bit.
+/// Defaults to 115200bps, 8 data bits, no parity and 1 stop bit with a baud
+/// rate tolerance of 2 %.
 pub struct Config {
     /// Baudrate in bps.
     baudrate: u32,
+    data_bits: DataBits,
     parity: Parity,
     stop_bits: StopBits,
+    preset: Option<BaudPreset>,
+    baud_tolerance: u16,
+    /// Compares each transmitted byte with the byte read back from the bus.
+    /// Only used by [`HalfDuplex`], disabled by default.
+    pub collision_detection: bool,
+    /// Inverts the RX input (`CTRL.RXINV`), i.e. the line idles low, e.g.
+    /// behind an inverting transceiver or opto-isolator. Disabled by default.
+    pub invert_rx: bool,
+    /// Inverts the TX output (`CTRL.TXINV`), i.e. the line idles low.
+    /// Disabled by default.
+    pub invert_tx: bool,
+    /// Connects the receiver to the transmitter output internally
+    /// (`CTRL.LOOPBK`), so that every transmitted frame is received again.
+    ///
+    /// Only the TX pin needs to be routed in this mode, the RX pin of
+    /// [`Usart::new()`] is ignored. See [`Usart::self_test()`] for a ready
+    /// made check. Disabled by default.
+    pub loopback: bool,
+    /// Multi-processor mode (`CTRL.MPM`) for multidrop buses, where the ninth
+    /// data bit marks address frames. Needs 9 data bits, see
+    /// [`Config::with_data_bits()`] and [`multiprocessor`]. Disabled by
+    /// default.
+    pub multi_processor: bool,
+    /// Value of the ninth bit that marks an address frame in multi-processor
+    /// mode (`CTRL.MPAB`). High (`true`) by default.
+    pub address_bit: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             baudrate: 115200,
+            data_bits: DataBits::EIGHT,
+            parity: Parity::NONE,
+            stop_bits: StopBits::ONE,
+            preset: None,
+            baud_tolerance: DEFAULT_BAUD_TOLERANCE,
+            collision_detection: false,
+            invert_rx: false,
+            invert_tx: false,
+            loopback: false,
+            multi_processor: false,
+            address_bit: true,
+        }
+    }
+}
+
+impl Config {
+    /// 8N1 configuration with a baud rate divider computed at compile time,
+    /// see [`Presets`].
+    pub const fn from_preset(preset: BaudPreset) -> Self {
+        Self {
+            baudrate: preset.baudrate,
+            data_bits: DataBits::EIGHT,
             parity: Parity::NONE,
             stop_bits: StopBits::ONE,
+            preset: Some(preset),
+            baud_tolerance: DEFAULT_BAUD_TOLERANCE,
+            collision_detection: false,
+            invert_rx: false,
+            invert_tx: false,
+            loopback: false,
+            multi_processor: false,
+            address_bit: true,
+        }
+    }
+
+    /// Baud rate in bps.
+    pub fn baudrate(&self) -> u32 {
+        self.baudrate
+    }
+
+    /// Number of data bits per frame.
+    pub fn data_bits(&self) -> DataBits {
+        self.data_bits
+    }
+
+    /// Changes the number of data bits per frame.
+    ///
+    /// Frames of up to 8 data bits are exchanged as `u8` words. Wider frames,
+    /// e.g. the 9 bit frames of multidrop protocols that mark addresses with
+    /// the ninth bit, need `u16` words, see [`Usart::split_u16()`].
+    pub const fn with_data_bits(mut self, data_bits: DataBits) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    /// Parity bit mode.
+    pub fn parity(&self) -> Parity {
+        self.parity
+    }
+
+    /// Number of stop bits.
+    pub fn stop_bits(&self) -> StopBits {
+        self.stop_bits
+    }
+
+    /// Largest accepted deviation of the actual from the requested baud rate
+    /// in per mille.
+    pub fn baud_tolerance(&self) -> u16 {
+        self.baud_tolerance
+    }
+
+    /// Changes the largest accepted baud rate deviation, in per mille.
+    ///
+    /// The constructors fail when the divider closest to the requested baud
+    /// rate misses it by more than the tolerance. Both ends of a link add
+    /// their deviations, the 2 % default leaves room for a peer with a
+    /// similar error.
+    pub const fn with_baud_tolerance(mut self, permille: u16) -> Self {
+        self.baud_tolerance = permille;
+        self
+    }
+}
+
+/// USART API
+///
+/// Owns the routed pins as `P`, so they can be reconfigured or routed to a
+/// different location after [`Usart::release_with_pins()`].
+pub struct Usart<I, P = ()> {
+    raw: I,
+    pins: P,
+    oversampling: Oversampling,
+    baudrate: u32,
+    hfperclk: Hertz,
+}
+
+impl<I: Instance, TX, RX> Usart<I, (TX, RX)> {
+    /// Configures the USART for asynchronous communication on two pins.
+    ///
+    /// Selects the oversampling factor and the fractional divider with the
+    /// lowest baud rate error. Returns
+    /// `Err(Error::Impl(ImplError::InvalidConfiguration))` when the baud rate
+    /// is not reachable with the HFPERCLK frequency or misses the requested
+    /// value by more than [`Config::baud_tolerance()`].
+    pub fn new(usart: I, tx: TX, rx: RX, config: &Config, cmu: &mut Cmu) -> Result<Self, Error>
+    where
+        TX: PinLocation<I, TxPin>,
+        RX: PinLocation<I, RxPin>,
+    {
+        let (oversampling, baudrate) = Usart::configure(&usart, config, cmu)?;
+
+        // Route peripheral to pins.
+        usart
+            .routeloc0
+            .write(|w| unsafe { w.txloc().bits(TX::LOCATION).rxloc().bits(RX::LOCATION) });
+        usart
+            .routepen
+            .write(|w| w.txpen().set_bit().rxpen().set_bit());
+
+        Ok(Usart {
+            raw: usart,
+            pins: (tx, rx),
+            oversampling,
+            baudrate,
+            hfperclk: cmu.hfperclk(),
+        })
+    }
+
+    /// Disables the pin routes and returns the peripheral and the pins.
+    ///
+    /// The pins fall back to their GPIO configuration. A frame in progress is
+    /// cut off, call `flush()` on the transmitter before.
+    pub fn release_with_pins(self) -> (I, TX, RX) {
+        self.raw.routepen.reset();
+        self.raw.routeloc0.reset();
+        let (tx, rx) = self.pins;
+        (self.raw, tx, rx)
+    }
+}
+
+impl<I: Instance, TX: PinTrait, RX: PinTrait> Usart<I, (Pin<TX, Output>, Pin<RX, Input>)> {
+    /// Configures the TX pin as push-pull output and the RX pin as input, then
+    /// the USART like [`Usart::new()`].
+    ///
+    /// The TX pin starts at the idle level of the line, high or low with
+    /// [`Config::invert_tx`], so that the peer does not see a start bit. The
+    /// RX pin is a floating input, configure it with [`Usart::new()`] when it
+    /// needs a pull resistor. The pins are checked against the location
+    /// tables at compile time like for [`Usart::new()`].
+    ///
+    /// ```ignore
+    /// let usart = Usart::with_pins(p.USART0, gpio.pa0, gpio.pa1, &Config::default(), &mut cmu)?;
+    /// ```
+    ///
+    /// When the configuration is rejected the pins are configured already
+    /// and dropped.
+    pub fn with_pins(
+        usart: I,
+        tx: PinBuilder<TX, Floating, NoFilter>,
+        rx: PinBuilder<RX, Floating, NoFilter>,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<Self, Error>
+    where
+        Pin<TX, Output>: PinLocation<I, TxPin>,
+        Pin<RX, Input>: PinLocation<I, RxPin>,
+    {
+        let tx = tx.push_pull_output(!config.invert_tx);
+        let rx = rx.input();
+        Usart::new(usart, tx, rx, config, cmu)
+    }
+}
+
+impl<I: Instance, TX, RX, CTS, RTS> Usart<I, (TX, RX, CTS, RTS)> {
+    /// Creates a serial interface with RTS/CTS hardware flow control.
+    ///
+    /// The transmitter only starts a frame while the peer asserts CTS (low),
+    /// otherwise the transmit buffer fills up and `write()` returns
+    /// `WouldBlock` until the peer is ready again. A frame in progress is
+    /// always completed. RTS is asserted (low) while the receive buffer has
+    /// room and deasserted when it is full. The threshold is fixed in
+    /// hardware, this device has no programmable RTS watermark.
+    ///
+    /// Fails like [`Usart::new()`].
+    pub fn new_with_flow_control(
+        usart: I,
+        tx: TX,
+        rx: RX,
+        cts: CTS,
+        rts: RTS,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<Self, Error>
+    where
+        TX: PinLocation<I, TxPin>,
+        RX: PinLocation<I, RxPin>,
+        CTS: PinLocation<I, CtsPin>,
+        RTS: PinLocation<I, RtsPin>,
+    {
+        let (oversampling, baudrate) = Usart::configure(&usart, config, cmu)?;
+
+        usart.ctrlx.modify(|_, w| w.ctsen().set_bit());
+
+        // Route peripheral to pins.
+        usart
+            .routeloc0
+            .write(|w| unsafe { w.txloc().bits(TX::LOCATION).rxloc().bits(RX::LOCATION) });
+        usart
+            .routeloc1
+            .write(|w| unsafe { w.ctsloc().bits(CTS::LOCATION).rtsloc().bits(RTS::LOCATION) });
+        usart.routepen.write(|w| {
+            w.txpen()
+                .set_bit()
+                .rxpen()
+                .set_bit()
+                .ctspen()
+                .set_bit()
+                .rtspen()
+                .set_bit()
+        });
+
+        Ok(Usart {
+            raw: usart,
+            pins: (tx, rx, cts, rts),
+            oversampling,
+            baudrate,
+            hfperclk: cmu.hfperclk(),
+        })
+    }
+
+    /// Disables the pin routes and flow control and returns the peripheral
+    /// and the pins, see [`Usart::release_with_pins()`].
+    pub fn release_with_pins(self) -> (I, TX, RX, CTS, RTS) {
+        self.raw.routepen.reset();
+        self.raw.routeloc0.reset();
+        self.raw.routeloc1.reset();
+        self.raw.ctrlx.modify(|_, w| w.ctsen().clear_bit());
+        let (tx, rx, cts, rts) = self.pins;
+        (self.raw, tx, rx, cts, rts)
+    }
+}
+
+impl<I: Instance> Usart<I> {
+    /// Creates a receive only serial interface.
+    ///
+    /// Only the RX pin is routed to the peripheral. This saves a pin on
+    /// constrained packages, e.g. when listening to a GPS receiver. Fails
+    /// like [`Usart::new()`].
+    pub fn rx_only<RX>(
+        usart: I,
+        rx: RX,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<RxOnly<I, RX>, Error>
+    where
+        RX: PinLocation<I, RxPin>,
+    {
+        Self::configure(&usart, config, cmu)?;
+
+        usart
+            .routeloc0
+            .write(|w| unsafe { w.rxloc().bits(RX::LOCATION) });
+        usart.routepen.write(|w| w.rxpen().set_bit());
+
+        usart.cmd.write(|w| w.rxen().set_bit());
+        Ok(RxOnly {
+            raw: usart,
+            pin: rx,
+            rx: Rx { _priv: PhantomData },
+        })
+    }
+
+    /// Creates a transmit only serial interface.
+    ///
+    /// Only the TX pin is routed to the peripheral. This saves a pin on
+    /// constrained packages, e.g. for a debug log output. Fails like
+    /// [`Usart::new()`].
+    pub fn tx_only<TX>(
+        usart: I,
+        tx: TX,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<TxOnly<I, TX>, Error>
+    where
+        TX: PinLocation<I, TxPin>,
+    {
+        Self::configure(&usart, config, cmu)?;
+
+        usart
+            .routeloc0
+            .write(|w| unsafe { w.txloc().bits(TX::LOCATION) });
+        usart.routepen.write(|w| w.txpen().set_bit());
+
+        usart.cmd.write(|w| w.txen().set_bit());
+        // Nothing was sent yet, mark the transmission as complete for `flush()`.
+        usart.ifs.write(|w| w.txc().set_bit());
+        Ok(TxOnly {
+            raw: usart,
+            pin: tx,
+            tx: Tx { _priv: PhantomData },
+        })
+    }
+
+    /// Checks that the USART receives what it transmits, without external
+    /// wiring, e.g. for a power-on self-test.
+    ///
+    /// Configures the USART with the internal loopback, see
+    /// [`Config::loopback`], transmits a test frame and compares it with the
+    /// received one. Returns `false` when the configuration fails, nothing
+    /// or a different frame is received, or a frame or parity error occurs.
+    /// The test frame appears on the TX pin, which must be an output that
+    /// idles high, as for [`Usart::new()`].
+    ///
+    /// The USART is disabled and its pin route removed afterwards, the
+    /// peripheral and the pin can then be used to create the actual serial
+    /// interface.
+    pub fn self_test<TX>(usart: &mut I, _tx: &TX, config: &Config, cmu: &mut Cmu) -> bool
+    where
+        TX: PinLocation<I, TxPin>,
+    {
+        const PATTERN: u8 = 0xA5;
+
+        if Self::configure(usart, config, cmu).is_err() {
+            return false;
+        }
+        usart.ctrl.modify(|_, w| w.loopbk().set_bit());
+        usart
+            .routeloc0
+            .write(|w| unsafe { w.txloc().bits(TX::LOCATION) });
+        usart.routepen.write(|w| w.txpen().set_bit());
+        usart.cmd.write(|w| {
+            w.clearrx()
+                .set_bit()
+                .cleartx()
+                .set_bit()
+                .rxen()
+                .set_bit()
+                .txen()
+                .set_bit()
+        });
+        usart.ifc.write(|w| w.txc().set_bit());
+
+        usart.txdata.write(|w| unsafe { w.txdata().bits(PATTERN) });
+        // The receiver sampled the stop bit before the transmitter finished
+        // it.
+        while usart.if_.read().txc().bit_is_clear() {}
+
+        let mask = (0xFFFF_u16 >> (16 - frame_bits(usart).min(8))) as u8;
+        let passed = usart.status.read().rxdatav().bit_is_set() && {
+            let rxdatax = usart.rxdatax.read();
+            rxdatax.ferr().bit_is_clear()
+                && rxdatax.perr().bit_is_clear()
+                && rxdatax.rxdata().bits() as u8 == PATTERN & mask
+        };
+        if !passed {
+            warn!("USART: self-test failed");
+        }
+
+        usart
+            .cmd
+            .write(|w| w.rxdis().set_bit().txdis().set_bit().clearrx().set_bit());
+        usart.ctrl.modify(|_, w| w.loopbk().clear_bit());
+        usart.routepen.reset();
+        usart.routeloc0.reset();
+        passed
+    }
+
+    /// Same as [`Smartcard::new()`].
+    pub fn new_smartcard<IO, RST>(
+        usart: I,
+        io: IO,
+        reset: RST,
+        config: &SmartcardConfig,
+        cmu: &mut Cmu,
+    ) -> Result<Smartcard<I, RST>, Error>
+    where
+        IO: PinLocation<I, TxPin>,
+        RST: OutputPin,
+    {
+        Smartcard::new(usart, io, reset, config, cmu)
+    }
+
+    // Enables the clock and configures the frame format and baud rate.
+    // Returns the selected oversampling and the actual baud rate.
+    fn configure(usart: &I, config: &Config, cmu: &mut Cmu) -> Result<(Oversampling, u32), Error> {
+        let hfperclk = cmu.hfperclk().0;
+        let preset = config.preset.filter(|preset| preset.hfperclk == hfperclk);
+        if preset.is_none() && config.preset.is_some() {
+            warn!(
+                "USART: baud rate preset not computed for {=u32} Hz",
+                hfperclk
+            );
+        }
+        let (oversampling, clkdiv, baudrate) = match preset {
+            Some(preset) => (preset.oversampling, preset.clkdiv, preset.actual),
+            None => match find_divider(hfperclk, config.baudrate) {
+                Some(divider) => divider,
+                None => {
+                    warn!(
+                        "USART: {=u32} bps not reachable with {=u32} Hz",
+                        config.baudrate, hfperclk
+                    );
+                    return Err(Error::Impl(ImplError::InvalidConfiguration));
+                }
+            },
+        };
+        debug!(
+            "USART: requested {=u32} bps, actual {=u32} bps (OVS field {=u8}, CLKDIV {=u32})",
+            config.baudrate,
+            baudrate,
+            u8::from(oversampling),
+            clkdiv
+        );
+        if !within_tolerance(config.baudrate, baudrate, config.baud_tolerance) {
+            warn!("USART: baud rate error above tolerance");
+            return Err(Error::Impl(ImplError::InvalidConfiguration));
+        }
+
+        if config.multi_processor && config.data_bits != DataBits::NINE {
+            warn!("USART: multi-processor mode needs 9 data bits");
+            return Err(Error::Impl(ImplError::InvalidConfiguration));
+        }
+
+        cmu.enable_clock(usart);
+        write_config(usart, config, oversampling, clkdiv);
+
+        Ok((oversampling, baudrate))
+    }
+}
+
+impl<I: Instance, P> Usart<I, P> {
+    /// Oversampling factor selected for the requested baud rate.
+    pub fn oversampling(&self) -> Oversampling {
+        self.oversampling
+    }
+
+    /// Actual baud rate, which might deviate from the requested baud rate.
+    pub fn baudrate(&self) -> u32 {
+        self.baudrate
+    }
+
+    /// Reads the frame format and baud rate back from the `FRAME`, `CTRL` and
+    /// `CLKDIV` registers.
+    ///
+    /// Reflects the live hardware state, including changes made through the
+    /// raw peripheral. The baud rate is calculated from the HFPERCLK frequency
+    /// at construction time. `collision_detection` is not a hardware setting
+    /// and always reads as `false`.
+    pub fn config(&self) -> Config {
+        read_config(&self.raw, self.hfperclk)
+    }
+
+    pub fn split(self) -> (Tx<I>, Rx<I>) {
+        self.split_words()
+    }
+
+    /// Same as [`Usart::split()`] with `u16` words, for frames of more than
+    /// 8 data bits, see [`Config::with_data_bits()`].
+    pub fn split_u16(self) -> (Tx<I, u16>, Rx<I, u16>) {
+        self.split_words()
+    }
+
+    fn split_words<W>(self) -> (Tx<I, W>, Rx<I, W>) {
+        self.raw.cmd.write(|w| w.txen().set_bit().rxen().set_bit());
+        // Nothing was sent yet, mark the transmission as complete for `flush()`.
+        self.raw.ifs.write(|w| w.txc().set_bit());
+        (Tx { _priv: PhantomData }, Rx { _priv: PhantomData })
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// the baud rate returned by `baudrate()` is cached and not updated by a
+    /// write to `CLKDIV`.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Usart::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut I {
+        &mut self.raw
+    }
+
+    /// Disables the pin routes and returns the raw interface to the underlying
+    /// peripheral. The pins are dropped, see [`Usart::release_with_pins()`]
+    /// to keep them.
+    pub fn release(self) -> I {
+        self.raw.routepen.reset();
+        self.raw
+    }
+}
+
+// Configures the frame format, the line options and the baud rate divider.
+fn write_config(usart: &RegisterBlock, config: &Config, oversampling: Oversampling, clkdiv: u32) {
+    usart.frame.modify(|_, w| {
+        w.databits()
+            .variant(config.data_bits)
+            .parity()
+            .variant(config.parity)
+            .stopbits()
+            .variant(config.stop_bits)
+    });
+    // The inversion must be in place before the receiver and transmitter
+    // are enabled, an idle-low line would look like a start bit otherwise.
+    usart.ctrl.modify(|_, w| {
+        w.ovs()
+            .variant(oversampling)
+            .rxinv()
+            .bit(config.invert_rx)
+            .txinv()
+            .bit(config.invert_tx)
+            .loopbk()
+            .bit(config.loopback)
+            .mpm()
+            .bit(config.multi_processor)
+            .mpab()
+            .bit(config.address_bit)
+    });
+    usart.clkdiv.modify(|_, w| unsafe { w.div().bits(clkdiv) });
+}
+
+// Decodes the configuration from the registers written by `write_config()`.
+fn read_config(usart: &RegisterBlock, hfperclk: Hertz) -> Config {
+    let frame = usart.frame.read();
+    let default = Config::default();
+
+    let ctrl = usart.ctrl.read();
+    let oversampling = ctrl.ovs().variant();
+    let div = usart.clkdiv.read().div().bits() as u64;
+    let baudrate =
+        (32 * hfperclk.0 as u64 / (oversampling_factor(oversampling) * (32 + div))) as u32;
+
+    Config {
+        baudrate,
+        data_bits: match frame.databits().variant() {
+            Variant::Val(data_bits) => data_bits,
+            Variant::Res(_) => default.data_bits,
+        },
+        parity: match frame.parity().variant() {
+            Variant::Val(parity) => parity,
+            Variant::Res(_) => default.parity,
+        },
+        stop_bits: frame.stopbits().variant(),
+        preset: None,
+        baud_tolerance: default.baud_tolerance,
+        collision_detection: false,
+        invert_rx: ctrl.rxinv().bit_is_set(),
+        invert_tx: ctrl.txinv().bit_is_set(),
+        loopback: ctrl.loopbk().bit_is_set(),
+        multi_processor: ctrl.mpm().bit_is_set(),
+        address_bit: ctrl.mpab().bit_is_set(),
+    }
+}
+
+const fn oversampling_factor(oversampling: Oversampling) -> u64 {
+    match oversampling {
+        Oversampling::X16 => 16,
+        Oversampling::X8 => 8,
+        Oversampling::X6 => 6,
+        Oversampling::X4 => 4,
+    }
+}
+
+/// Returns the value for the `CLKDIV.DIV` field in asynchronous mode.
+///
+/// The baud rate is `hfperclk / (ovs * (1 + DIV / 32))`, `DIV` is rounded to
+/// the nearest value. Baud rates too low for the 20-bit divider saturate at
+/// the largest divider. Returns
+/// `Err(Error::Impl(ImplError::InvalidConfiguration))` when the baud rate is
+/// zero or higher than `hfperclk / ovs`.
+///
+/// Pure function without hardware access, used by the constructors and by
+/// [`BaudPreset::new()`] at compile time.
+pub const fn compute_clkdiv(
+    hfperclk: u32,
+    oversampling: Oversampling,
+    baudrate: u32,
+) -> Result<u32, Error> {
+    const MAX_DIV: u64 = (1 << 20) - 1;
+
+    let divisor = oversampling_factor(oversampling) * baudrate as u64;
+    if divisor == 0 {
+        return Err(Error::Impl(ImplError::InvalidConfiguration));
+    }
+    let div = (32 * hfperclk as u64 + divisor / 2) / divisor;
+    if div < 32 {
+        // Baud rate not achievable with this oversampling factor.
+        return Err(Error::Impl(ImplError::InvalidConfiguration));
+    }
+    let div = div - 32;
+    Ok(if div > MAX_DIV { MAX_DIV } else { div } as u32)
+}
+
+/// Finds the oversampling factor and clock divider with the lowest baud rate
+/// error for asynchronous mode.
+///
+/// Tries 16x, 8x, 6x and 4x oversampling in that order. A lower oversampling
+/// factor is only selected when it strictly reduces the error, because it
+/// also reduces the immunity against noise and clock deviations.
+///
+/// Returns the oversampling, the value for the `CLKDIV.DIV` field and the
+/// resulting baud rate. Returns `None` when no oversampling factor reaches
+/// the baud rate.
+const fn find_divider(hfperclk: u32, baudrate: u32) -> Option<(Oversampling, u32, u32)> {
+    const OVERSAMPLING: [Oversampling; 4] = [
+        Oversampling::X16,
+        Oversampling::X8,
+        Oversampling::X6,
+        Oversampling::X4,
+    ];
+
+    let mut best = None;
+    let mut best_error = u32::MAX;
+    let mut i = 0;
+    while i < OVERSAMPLING.len() {
+        let oversampling = OVERSAMPLING[i];
+        i += 1;
+        let div = match compute_clkdiv(hfperclk, oversampling, baudrate) {
+            Ok(div) => div,
+            Err(_) => continue,
+        };
+
+        let ovs = oversampling_factor(oversampling);
+        let actual = (32 * hfperclk as u64 / (ovs * (32 + div as u64))) as u32;
+        let error = actual.abs_diff(baudrate);
+        if error < best_error {
+            best = Some((oversampling, div, actual));
+            best_error = error;
+        }
+    }
+    best
+}
+
+// Returns the oversampling and the `CLKDIV.DIV` value for an ETU of 372 card
+// clock cycles, `None` when the baud rate misses it by more than the default
+// tolerance or is not reachable at all.
+fn smartcard_divider(hfperclk: u32, card_clock: u32) -> Option<(Oversampling, u32)> {
+    let baudrate = card_clock / 372;
+    match find_divider(hfperclk, baudrate) {
+        Some((oversampling, clkdiv, actual))
+            if within_tolerance(baudrate, actual, DEFAULT_BAUD_TOLERANCE) =>
+        {
+            Some((oversampling, clkdiv))
+        }
+        _ => None,
+    }
+}
+
+// Returns `true` when `actual` deviates from `requested` by at most
+// `permille`.
+const fn within_tolerance(requested: u32, actual: u32, permille: u16) -> bool {
+    actual.abs_diff(requested) as u64 * 1000 <= requested as u64 * permille as u64
+}
+
+/// Baud rate divider computed at compile time, see [`Presets`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BaudPreset {
+    hfperclk: u32,
+    oversampling: Oversampling,
+    clkdiv: u32,
+    baudrate: u32,
+    actual: u32,
+}
+
+impl BaudPreset {
+    /// Computes the divider for `baudrate` at a HFPERCLK frequency of
+    /// `hfperclk` Hz.
+    ///
+    /// Panics when the baud rate is not reachable or the achieved baud rate
+    /// deviates by more than 2 %. In a constant, e.g. in [`Presets`], this
+    /// panic is a compile error.
+    pub const fn new(hfperclk: u32, baudrate: u32) -> Self {
+        let (oversampling, clkdiv, actual) = match find_divider(hfperclk, baudrate) {
+            Some(divider) => divider,
+            None => panic!("baud rate not reachable with this HFPERCLK frequency"),
+        };
+        if !within_tolerance(baudrate, actual, DEFAULT_BAUD_TOLERANCE) {
+            panic!("baud rate error above 2 % with this HFPERCLK frequency");
+        }
+        BaudPreset {
+            hfperclk,
+            oversampling,
+            clkdiv,
+            baudrate,
+            actual,
+        }
+    }
+
+    /// HFPERCLK frequency the divider was computed for.
+    pub const fn hfperclk(&self) -> u32 {
+        self.hfperclk
+    }
+
+    /// Selected oversampling factor.
+    pub const fn oversampling(&self) -> Oversampling {
+        self.oversampling
+    }
+
+    /// Value of the `CLKDIV.DIV` field.
+    pub const fn clkdiv(&self) -> u32 {
+        self.clkdiv
+    }
+
+    /// Achieved baud rate.
+    pub const fn actual_baudrate(&self) -> u32 {
+        self.actual
+    }
+}
+
+/// Serial configurations with the baud rate divider computed at compile time
+/// for a fixed HFPERCLK frequency of `HFPERCLK` Hz.
+///
+/// Bind the clock frequency of the firmware once with a type alias:
+///
+/// ```ignore
+/// type Serial = usart::Presets<19_000_000>;
+/// let usart = Usart::new(usart0, tx, rx, &Serial::BAUD_115200, &mut cmu)?;
+/// ```
+///
+/// A baud rate that the clock frequency cannot generate within 2 % fails to
+/// compile where the constant is used. Other baud rates are available through
+/// [`Config::from_preset()`] with [`BaudPreset::new()`] in a `const`. The
+/// frame format is 8N1.
+///
+/// The constructors check the frequency against the actual HFPERCLK
+/// frequency and fall back to the runtime divider computation on a mismatch,
+/// e.g. after [`Cmu::set_hfperclk_divider()`].
+pub struct Presets<const HFPERCLK: u32>;
+
+impl<const HFPERCLK: u32> Presets<HFPERCLK> {
+    pub const BAUD_9600: Config = Config::from_preset(BaudPreset::new(HFPERCLK, 9600));
+    pub const BAUD_19200: Config = Config::from_preset(BaudPreset::new(HFPERCLK, 19200));
+    pub const BAUD_38400: Config = Config::from_preset(BaudPreset::new(HFPERCLK, 38400));
+    pub const BAUD_57600: Config = Config::from_preset(BaudPreset::new(HFPERCLK, 57600));
+    pub const BAUD_115200: Config = Config::from_preset(BaudPreset::new(HFPERCLK, 115_200));
+    pub const BAUD_230400: Config = Config::from_preset(BaudPreset::new(HFPERCLK, 230_400));
+    pub const BAUD_460800: Config = Config::from_preset(BaudPreset::new(HFPERCLK, 460_800));
+    pub const BAUD_921600: Config = Config::from_preset(BaudPreset::new(HFPERCLK, 921_600));
+}
+
+/// Returns the value for the `CLKDIV.DIV` field in synchronous mode for a
+/// clock frequency of at most `frequency`.
+///
+/// The clock frequency is `hfperclk / (2 * (1 + CLKDIV / 256))`. Only the
+/// integer part of the divider is used, which is rounded up so that the clock
+/// frequency is never higher than requested. Returns
+/// `Err(ImplError::InvalidConfiguration)` when the frequency is zero or too
+/// low for the 20-bit divider.
+pub(crate) fn sync_divider(hfperclk: u32, frequency: u32) -> Result<u32, ImplError> {
+    const MAX_DIV: u64 = (1 << 20) - 1;
+
+    if frequency == 0 {
+        return Err(ImplError::InvalidConfiguration);
+    }
+    let n = (hfperclk as u64).div_ceil(2 * frequency as u64);
+    let div = 32 * (n.max(1) - 1);
+    if div > MAX_DIV {
+        return Err(ImplError::InvalidConfiguration);
+    }
+    Ok(div as u32)
+}
+
+/// Extension trait to create the USART HAL instance from the raw peripheral.
+pub trait UsartExt: Instance + Sized {
+    /// Same as [`Usart::new()`].
+    fn usart<TX, RX>(
+        self,
+        tx: TX,
+        rx: RX,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<Usart<Self, (TX, RX)>, Error>
+    where
+        TX: PinLocation<Self, TxPin>,
+        RX: PinLocation<Self, RxPin>;
+
+    /// Same as [`Usart::rx_only()`].
+    fn split_rx_only<RX>(
+        self,
+        rx: RX,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<RxOnly<Self, RX>, Error>
+    where
+        RX: PinLocation<Self, RxPin>;
+
+    /// Same as [`Usart::tx_only()`].
+    fn split_tx_only<TX>(
+        self,
+        tx: TX,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<TxOnly<Self, TX>, Error>
+    where
+        TX: PinLocation<Self, TxPin>;
+}
+
+impl<I: Instance> UsartExt for I {
+    fn usart<TX, RX>(
+        self,
+        tx: TX,
+        rx: RX,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<Usart<Self, (TX, RX)>, Error>
+    where
+        TX: PinLocation<Self, TxPin>,
+        RX: PinLocation<Self, RxPin>,
+    {
+        Usart::new(self, tx, rx, config, cmu)
+    }
+
+    fn split_rx_only<RX>(
+        self,
+        rx: RX,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<RxOnly<Self, RX>, Error>
+    where
+        RX: PinLocation<Self, RxPin>,
+    {
+        Usart::rx_only(self, rx, config, cmu)
+    }
+
+    fn split_tx_only<TX>(
+        self,
+        tx: TX,
+        config: &Config,
+        cmu: &mut Cmu,
+    ) -> Result<TxOnly<Self, TX>, Error>
+    where
+        TX: PinLocation<Self, TxPin>,
+    {
+        Usart::tx_only(self, tx, config, cmu)
+    }
+}
+
+/// Receive only serial interface, see [`Usart::rx_only()`].
+///
+/// Owns the peripheral and the RX pin until [`RxOnly::release_with_pin()`]
+/// hands them back.
+pub struct RxOnly<I, RX> {
+    raw: I,
+    pin: RX,
+    rx: Rx<I>,
+}
+
+impl<I: Instance, RX> RxOnly<I, RX> {
+    /// Returns the receiver, e.g. for the interrupt configuration or
+    /// [`Rx::read_timeout()`].
+    pub fn rx(&mut self) -> &mut Rx<I> {
+        &mut self.rx
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Usart::raw()`].
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Usart::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut I {
+        &mut self.raw
+    }
+
+    /// Disables the pin route and returns the peripheral and the pin, see
+    /// [`Usart::release_with_pins()`].
+    pub fn release_with_pin(self) -> (I, RX) {
+        self.raw.routepen.reset();
+        self.raw.routeloc0.reset();
+        (self.raw, self.pin)
+    }
+
+    /// Disables the pin route and returns the raw interface to the
+    /// underlying peripheral. The pin is dropped.
+    pub fn release(self) -> I {
+        self.release_with_pin().0
+    }
+}
+
+impl<I: Instance, RX> Read<u8> for RxOnly<I, RX> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.rx.read()
+    }
+}
+
+/// Transmit only serial interface, see [`Usart::tx_only()`].
+///
+/// Owns the peripheral and the TX pin until [`TxOnly::release_with_pin()`]
+/// hands them back.
+pub struct TxOnly<I, TX> {
+    raw: I,
+    pin: TX,
+    tx: Tx<I>,
+}
+
+impl<I: Instance, TX> TxOnly<I, TX> {
+    /// Returns the transmitter, e.g. for the interrupt configuration.
+    pub fn tx(&mut self) -> &mut Tx<I> {
+        &mut self.tx
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Usart::raw()`].
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Usart::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut I {
+        &mut self.raw
+    }
+
+    /// Disables the pin route and returns the peripheral and the pin, see
+    /// [`Usart::release_with_pins()`].
+    ///
+    /// A frame in progress is cut off, call `flush()` before.
+    pub fn release_with_pin(self) -> (I, TX) {
+        self.raw.routepen.reset();
+        self.raw.routeloc0.reset();
+        (self.raw, self.pin)
+    }
+
+    /// Disables the pin route and returns the raw interface to the
+    /// underlying peripheral. The pin is dropped.
+    pub fn release(self) -> I {
+        self.release_with_pin().0
+    }
+}
+
+impl<I: Instance, TX> Write<u8> for TxOnly<I, TX> {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.tx.write(word)
+    }
+
+    /// Same as for [`Tx`].
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.tx.flush()
+    }
+}
+
+impl<I: Instance, TX> BlockingWriteDefault<u8> for TxOnly<I, TX> {}
+
+/// Same as for [`Tx`].
+impl<I: Instance, TX> fmt::Write for TxOnly<I, TX> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.tx.write_str(s)
+    }
+}
+
+/// Transmit part of the serial interface for a USART instance with words of
+/// type `W`.
+///
+/// `u8` words are used for frames of up to 8 data bits, `u16` words for wider
+/// frames, see [`Usart::split_u16()`].
+pub struct Tx<I, W = u8> {
+    _priv: PhantomData<(I, W)>,
+}
+
+impl<I: Instance, W> Tx<I, W> {
+    /// Returns the NVIC line of the transmit interrupts (`USARTn_TX`).
+    ///
+    /// Interrupts are masked at two levels: the `enable_*interrupt()` methods
+    /// set the bits in the `IEN` register of the peripheral, and the NVIC line
+    /// must be unmasked as well, e.g. with
+    /// `cortex_m::peripheral::NVIC::unmask(tx.interrupt())`. Nothing fires
+    /// when either one is missing.
+    pub fn interrupt(&self) -> Interrupt {
+        I::TX_INTERRUPT
+    }
+
+    /// Enables the `TXBL` interrupt which indicates that data can be sent with
+    /// the `write()` method.
+    pub fn enable_interrupt(&mut self) {
+        let usart_set = unsafe { &*I::ptr_set() };
+        usart_set.ien.write(|w| w.txbl().set_bit());
+    }
+
+    /// Disables the `TXBL` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        let usart_clear = unsafe { &*I::ptr_clear() };
+        usart_clear.ien.write(|w| w.txbl().set_bit());
+    }
+
+    /// Enables the `TXC` interrupt which indicates that the last character,
+    /// including its stop bit(s), has left the TX pin.
+    ///
+    /// Unlike `TXBL` the `TXC` flag is latched and keeps the interrupt
+    /// pending until it is cleared. Disable the interrupt in the handler
+    /// instead of clearing the flag through `IFC`: `flush()` relies on the
+    /// flag and the next `write()` clears it anyway.
+    pub fn enable_tx_complete_interrupt(&mut self) {
+        let usart_set = unsafe { &*I::ptr_set() };
+        usart_set.ien.write(|w| w.txc().set_bit());
+    }
+
+    /// Disables the `TXC` interrupt.
+    pub fn disable_tx_complete_interrupt(&mut self) {
+        let usart_clear = unsafe { &*I::ptr_clear() };
+        usart_clear.ien.write(|w| w.txc().set_bit());
+    }
+}
+
+impl<I: Instance> Write<u8> for Tx<I> {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        let usart = unsafe { &*I::ptr() };
+        if usart.status.read().txbl().bit() {
+            usart.ifc.write(|w| w.txc().set_bit());
+            usart.txdata.write(|w| unsafe { w.txdata().bits(word) });
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Completes once the stop bit(s) of the last character have left the TX
+    /// pin. It is safe to toggle an RS-485 driver enable or to enter a sleep
+    /// mode right afterwards, see [`rs485::Rs485Tx`].
+    ///
+    /// `STATUS.TXIDLE` does not guarantee that the shift register is drained.
+    /// The `TXC` interrupt flag is used instead, which the hardware sets once
+    /// the transmit buffer and the shift register are empty. `write()` clears
+    /// it for every new character.
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        let usart = unsafe { &*I::ptr() };
+        if usart.if_.read().txc().bit() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<I: Instance> BlockingWriteDefault<u8> for Tx<I> {}
+
+/// Writes frames of more than 8 data bits.
+///
+/// Frames of up to 9 bits go through `TXDATAX`, wider frames through
+/// `TXDOUBLE`. Bits beyond the configured frame size are ignored.
+impl<I: Instance> Write<u16> for Tx<I, u16> {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u16) -> nb::Result<(), Self::Error> {
+        let usart = unsafe { &*I::ptr() };
+        if usart.status.read().txbl().bit() {
+            usart.ifc.write(|w| w.txc().set_bit());
+            if frame_bits(usart) > 9 {
+                // Both bytes of the double register form a single frame.
+                usart.txdouble.write(|w| unsafe { w.bits(word as u32) });
+            } else {
+                usart
+                    .txdatax
+                    .write(|w| unsafe { w.txdatax().bits(word & 0x1FF) });
+            }
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Same as for `u8` words.
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        let usart = unsafe { &*I::ptr() };
+        if usart.if_.read().txc().bit() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<I: Instance> BlockingWriteDefault<u16> for Tx<I, u16> {}
+
+/// Formatted output, e.g. with `write!`.
+///
+/// `core::fmt` calls `write_str()` for every fragment of a format string, so
+/// it only blocks while the transmit FIFO is full and returns as soon as the
+/// last byte of the fragment is queued. Waiting for each fragment to leave the
+/// shift register, as earlier versions did, stalled every fragment for one to
+/// two character times (87 to 174 µs at 115200 bps). Call `flush()` when the
+/// transmission must be complete, e.g. before entering a sleep mode.
+impl<I: Instance> fmt::Write for Tx<I>
+where
+    Self: BlockingWriteDefault<u8>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        use embedded_hal::blocking::serial::Write;
+        self.bwrite_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+/// Receive part of the serial interface for a USART instance with words of
+/// type `W`.
+///
+/// `u8` words are used for frames of up to 8 data bits, `u16` words for wider
+/// frames, see [`Usart::split_u16()`].
+pub struct Rx<I, W = u8> {
+    _priv: PhantomData<(I, W)>,
+}
+
+impl<I: Instance, W> Rx<I, W> {
+    /// Returns the NVIC line of the receive interrupts (`USARTn_RX`).
+    ///
+    /// See [`Tx::interrupt()`] for the two levels of interrupt masking.
+    pub fn interrupt(&self) -> Interrupt {
+        I::RX_INTERRUPT
+    }
+
+    /// Enables the `RXDATAV` interrupt which indicates that data was received
+    /// and can be read with the `read()` method.
+    pub fn enable_interrupt(&mut self) {
+        let usart_set = unsafe { &*I::ptr_set() };
+        usart_set.ien.write(|w| w.rxdatav().set_bit());
+    }
+
+    /// Disables the `RXDATAV` interrupt.
+    pub fn disable_interrupt(&mut self) {
+        let usart_clear = unsafe { &*I::ptr_clear() };
+        usart_clear.ien.write(|w| w.rxdatav().set_bit());
+    }
+
+    /// Enables the `RXOF` interrupt which indicates that a character was
+    /// received while the receive buffer was full. The character is lost.
+    ///
+    /// The `RXOF` flag is latched, clear it in the handler with
+    /// [`Rx::clear_overflow_interrupt()`]. `RXDATAV` in contrast is cleared by
+    /// the hardware when the receive buffer is read.
+    pub fn enable_overflow_interrupt(&mut self) {
+        let usart_set = unsafe { &*I::ptr_set() };
+        usart_set.ien.write(|w| w.rxof().set_bit());
+    }
+
+    /// Disables the `RXOF` interrupt.
+    pub fn disable_overflow_interrupt(&mut self) {
+        let usart_clear = unsafe { &*I::ptr_clear() };
+        usart_clear.ien.write(|w| w.rxof().set_bit());
+    }
+
+    /// Clears the `RXOF` interrupt flag.
+    pub fn clear_overflow_interrupt(&mut self) {
+        let usart = unsafe { &*I::ptr() };
+        usart.ifc.write(|w| w.rxof().set_bit());
+    }
+
+    /// Enables the `RXUF` interrupt which indicates that the receive buffer
+    /// was read while it was empty, e.g. by a DMA channel triggered by the
+    /// wrong signal.
+    ///
+    /// The `RXUF` flag is latched, clear it in the handler with
+    /// [`Rx::clear_underflow_interrupt()`].
+    pub fn enable_underflow_interrupt(&mut self) {
+        let usart_set = unsafe { &*I::ptr_set() };
+        usart_set.ien.write(|w| w.rxuf().set_bit());
+    }
+
+    /// Disables the `RXUF` interrupt.
+    pub fn disable_underflow_interrupt(&mut self) {
+        let usart_clear = unsafe { &*I::ptr_clear() };
+        usart_clear.ien.write(|w| w.rxuf().set_bit());
+    }
+
+    /// Clears the `RXUF` interrupt flag.
+    pub fn clear_underflow_interrupt(&mut self) {
+        let usart = unsafe { &*I::ptr() };
+        usart.ifc.write(|w| w.rxuf().set_bit());
+    }
+
+    /// Discards all received data and clears the latched receive error
+    /// flags (`RXOF`, `RXUF`, `FERR` and `PERR`).
+    ///
+    /// `read()` recovers from errors by itself. Use this to start over at a
+    /// message boundary, e.g. after a timeout in a request/response protocol,
+    /// so that bytes of a garbled response are not mistaken for the next one.
+    pub fn clear_errors(&mut self) {
+        let usart = unsafe { &*I::ptr() };
+        usart.cmd.write(|w| w.clearrx().set_bit());
+        usart.ifc.write(|w| {
+            w.rxof()
+                .set_bit()
+                .rxuf()
+                .set_bit()
+                .ferr()
+                .set_bit()
+                .perr()
+                .set_bit()
+        });
+    }
+
+    /// Sets the time without received frames after which the line is
+    /// considered idle, in bit times (baud times).
+    ///
+    /// Uses timer comparator 0 of the USART. The timer restarts at the end of
+    /// each received frame and stops when the next start bit arrives. Used by
+    /// [`Rx::receive_packet_dma()`], which defaults to 20 bit times (two
+    /// frames with 8N1) when no idle timeout was set.
+    ///
+    /// Panics when `bit_times` is 0.
+    pub fn set_idle_timeout(&mut self, bit_times: u8) {
+        assert!(bit_times > 0);
+        let usart = unsafe { &*I::ptr() };
+        usart.timecmp0.write(|w| unsafe {
+            w.tcmpval()
+                .bits(bit_times)
+                .tstart()
+                .variant(TSTART_A::RXEOF)
+                .tstop()
+                .variant(TSTOP_A::RXACT)
+                .restarten()
+                .set_bit()
+        });
+        usart.ifc.write(|w| w.tcmp0().set_bit());
+    }
+}
+
+// LDMA request signal of a USART that is active while received data is
+// available.
+const LDMA_SIGNAL_RXDATAV: u8 = 0;
+
+impl<I: Instance> Rx<I> {
+    /// Receives a variable-length packet with DMA and returns its length.
+    ///
+    /// A packet is a sequence of frames that ends when the line stays idle
+    /// for the timeout set with [`Rx::set_idle_timeout()`]. Returns
+    /// `WouldBlock` until the first byte of a packet was received. The DMA
+    /// transfer is then started and the call blocks until the line goes idle,
+    /// so the buffer is never written after it returns. At most
+    /// [`ldma::MAX_TRANSFER_COUNT`] bytes of the buffer are used.
+    ///
+    /// When a packet is longer than the buffer, the DMA transfer stops once
+    /// the buffer is full, the remaining bytes of the packet are received
+    /// until the line goes idle and then discarded. The buffer holds the start
+    /// of the packet and `Error::Overrun` is returned. Frame and parity errors
+    /// within the packet are reported after the line went idle, too.
+    ///
+    /// Panics when the buffer is empty.
+    pub fn receive_packet_dma(
+        &mut self,
+        buffer: &mut [u8],
+        channel: &mut Channel,
+    ) -> nb::Result<usize, Error> {
+        assert!(!buffer.is_empty());
+        let usart = unsafe { &*I::ptr() };
+        if usart.timecmp0.read().tstart().variant() == Variant::Val(TSTART_A::DISABLE) {
+            self.set_idle_timeout(20);
+        }
+
+        if usart.status.read().rxdatav().bit_is_clear() {
+            // Start the next packet with a clean state.
+            usart.ifc.write(|w| {
+                w.tcmp0()
+                    .set_bit()
+                    .rxof()
+                    .set_bit()
+                    .perr()
+                    .set_bit()
+                    .ferr()
+                    .set_bit()
+            });
+            return Err(nb::Error::WouldBlock);
+        }
+
+        // The line might have gone idle already, when the packet is short
+        // enough to fit completely into the receive FIFO.
+        let already_idle = usart.if_.read().tcmp0().bit_is_set();
+
+        let count = buffer.len().min(ldma::MAX_TRANSFER_COUNT);
+        channel.start_bytes(
+            I::LDMA_SOURCE,
+            LDMA_SIGNAL_RXDATAV,
+            (&usart.rxdata as *const _ as u32, false),
+            (buffer.as_mut_ptr() as u32, true),
+            count,
+        );
+
+        if !already_idle {
+            while usart.if_.read().tcmp0().bit_is_clear() {}
+        }
+        // Let the DMA drain the receive FIFO unless the buffer is full.
+        while usart.status.read().rxdatav().bit_is_set() && !channel.is_done() {}
+        channel.stop();
+        let received = count - channel.remaining();
+
+        let flags = usart.if_.read();
+        let overrun = usart.status.read().rxdatav().bit_is_set() || flags.rxof().bit_is_set();
+        usart.cmd.write(|w| w.clearrx().set_bit());
+        usart.ifc.write(|w| {
+            w.tcmp0()
+                .set_bit()
+                .rxof()
+                .set_bit()
+                .perr()
+                .set_bit()
+                .ferr()
+                .set_bit()
+        });
+
+        if overrun {
+            Err(nb::Error::Other(Error::Overrun))
+        } else if flags.ferr().bit_is_set() {
+            Err(nb::Error::Other(Error::FrameFormat))
+        } else if flags.perr().bit_is_set() {
+            Err(nb::Error::Other(Error::Parity))
+        } else {
+            Ok(received)
+        }
+    }
+}
+
+/// Reads frames of up to 8 data bits.
+///
+/// Errors are reported once and do not block the receiver, the next call
+/// returns the next frame:
+///
+/// - `Error::FrameFormat` and `Error::Parity` are reported for the erroneous
+///   frame, which is removed from the receive buffer. The latched `FERR` and
+///   `PERR` interrupt flags are cleared. After noise on the line the
+///   receiver resynchronizes on the next start bit by itself, a few frames
+///   may be erroneous until then. The sender should repeat the message.
+/// - `Error::Overrun` is reported when frames were lost because the receive
+///   buffer was full (`RXOF`). The frames that were received before are still
+///   in the buffer and returned by the following calls. The latched flag is
+///   cleared, so it should not be cleared in an overflow interrupt handler
+///   when `read()` is expected to report it.
+///
+/// See [`Rx::clear_errors()`] to discard the received data as well.
+impl<I: Instance> Read<u8> for Rx<I> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let usart = unsafe { &*I::ptr() };
+        if usart.status.read().rxdatav().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        check_overflow(usart)?;
+
+        let rxdatax = usart.rxdatax.read();
+        check_frame(
+            usart,
+            rxdatax.ferr().bit_is_set(),
+            rxdatax.perr().bit_is_set(),
+        )?;
+
+        Ok(rxdatax.rxdata().bits() as u8)
+    }
+}
+
+/// Reads frames of more than 8 data bits.
+///
+/// Frames of up to 9 bits are read from `RXDATAX`, which holds the ninth bit
+/// next to the error flags of the frame. Wider frames are read from
+/// `RXDOUBLEX`, where the frame occupies both buffer elements. Errors are
+/// reported and cleared like for `u8` words.
+impl<I: Instance> Read<u16> for Rx<I, u16> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u16, Self::Error> {
+        let usart = unsafe { &*I::ptr() };
+        if usart.status.read().rxdatav().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+        check_overflow(usart)?;
+
+        let (word, ferr, perr) = if frame_bits(usart) > 9 {
+            let rxdoublex = usart.rxdoublex.read();
+            (
+                (rxdoublex.rxdata0().bits() & 0xFF) | (rxdoublex.rxdata1().bits() & 0xFF) << 8,
+                rxdoublex.ferr0().bit_is_set() || rxdoublex.ferr1().bit_is_set(),
+                rxdoublex.perr0().bit_is_set() || rxdoublex.perr1().bit_is_set(),
+            )
+        } else {
+            let rxdatax = usart.rxdatax.read();
+            (
+                rxdatax.rxdata().bits(),
+                rxdatax.ferr().bit_is_set(),
+                rxdatax.perr().bit_is_set(),
+            )
+        };
+        check_frame(usart, ferr, perr)?;
+
+        Ok(word)
+    }
+}
+
+// Reports and clears a receive buffer overflow.
+fn check_overflow(usart: &RegisterBlock) -> nb::Result<(), Error> {
+    if usart.if_.read().rxof().bit_is_set() {
+        usart.ifc.write(|w| w.rxof().set_bit());
+        return Err(nb::Error::Other(Error::Overrun));
+    }
+    Ok(())
+}
+
+// Reports the errors of a frame that was read from the receive buffer and
+// clears their latched interrupt flags.
+fn check_frame(usart: &RegisterBlock, ferr: bool, perr: bool) -> nb::Result<(), Error> {
+    if ferr || perr {
+        usart.ifc.write(|w| w.ferr().set_bit().perr().set_bit());
+    }
+    if ferr {
+        return Err(nb::Error::Other(Error::FrameFormat));
+    }
+    if perr {
+        return Err(nb::Error::Other(Error::Parity));
+    }
+    Ok(())
+}
+
+impl<I: Instance, W> Rx<I, W>
+where
+    Self: Read<W, Error = Error>,
+{
+    /// Waits for a received word like `block!(rx.read())`, but gives up after
+    /// polling the receive buffer `polls` times.
+    ///
+    /// Returns `Err(Error::Impl(ImplError::TimedOut))` when nothing was
+    /// received in time. Each poll reads the `STATUS` register, so the
+    /// duration of a poll depends on the HFCORECLK and the wait states. Scale
+    /// the count with the core clock frequency, or use a timer for exact
+    /// timeouts.
+    pub fn read_timeout(&mut self, polls: u32) -> Result<W, Error> {
+        for _ in 0..polls {
+            match self.read() {
+                Ok(word) => return Ok(word),
+                Err(nb::Error::Other(e)) => return Err(e),
+                Err(nb::Error::WouldBlock) => {}
+            }
+        }
+        Err(Error::Impl(ImplError::TimedOut))
+    }
+}
+
+// Number of data bits of the configured frame format.
+fn frame_bits(usart: &RegisterBlock) -> u8 {
+    match usart.frame.read().databits().variant() {
+        // DATABITS encodes the frame size as `bits - 3`.
+        Variant::Val(data_bits) => u8::from(data_bits) + 3,
+        Variant::Res(_) => 8,
+    }
+}
+
+/// Error of the half-duplex serial interface.
+#[derive(Debug, Clone)]
+pub enum HalfDuplexError {
+    /// The byte read back from the bus did not match the transmitted byte.
+    Collision,
+    /// Receive error.
+    Serial(Error),
+}
+
+/// Half-duplex serial interface on a single shared bus line.
+///
+/// The USART transmits on the TX pin, which must be configured as open-drain
+/// output, and receives from the same line through the internal loopback.
+/// Every transmitted byte is therefore also received.
+///
+/// With [`Config::collision_detection`] enabled the echo of each transmitted
+/// byte is compared with the byte that was sent, a lightweight collision
+/// detection for multi-drop buses. This requires that the bus echoes the
+/// transmitter, which is the case for open-drain multi-drop buses or RS-485
+/// transceivers with the receiver enabled while transmitting.
+pub struct HalfDuplex<I> {
+    raw: I,
+    collision_detection: bool,
+    // Polls of the receive buffer before the echo counts as lost.
+    echo_polls: u32,
+}
+
+// Frame times the echo of a transmitted byte may take.
+const ECHO_FRAMES: u64 = 4;
+
+impl<I: Instance> HalfDuplex<I> {
+    /// Configures the USART for half-duplex communication on a single pin.
+    ///
+    /// Fails like [`Usart::new()`].
+    pub fn new<PIN>(usart: I, _pin: PIN, config: &Config, cmu: &mut Cmu) -> Result<Self, Error>
+    where
+        PIN: PinLocation<I, TxPin>,
+    {
+        Usart::configure(&usart, config, cmu)?;
+        // Independent of `Config::loopback`, the echo is always needed.
+        usart.ctrl.modify(|_, w| w.loopbk().set_bit());
+
+        usart
+            .routeloc0
+            .write(|w| unsafe { w.txloc().bits(PIN::LOCATION) });
+        usart.routepen.write(|w| w.txpen().set_bit());
+
+        usart.cmd.write(|w| w.rxen().set_bit().txen().set_bit());
+
+        let echo_polls = echo_polls(&usart, cmu.hfcoreclk(), cmu.hfperclk());
+        Ok(HalfDuplex {
+            raw: usart,
+            collision_detection: config.collision_detection,
+            echo_polls,
+        })
+    }
+
+    /// Transmits all bytes and waits for their echo.
+    ///
+    /// Received data which was not read before calling this method is
+    /// discarded. Returns [`HalfDuplexError::Collision`] as soon as the echo
+    /// does not match when collision detection is enabled.
+    ///
+    /// The echo of each byte is awaited for a few frame times. Without an
+    /// echo, e.g. because another node holds the line low or the pin is not
+    /// an open-drain output, the transfer stops with
+    /// `HalfDuplexError::Serial(Error::Impl(ImplError::TimedOut))`. An echo
+    /// that did not fit into the receive buffer is reported as
+    /// `HalfDuplexError::Serial(Error::Overrun)`.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), HalfDuplexError> {
+        self.raw.cmd.write(|w| w.clearrx().set_bit());
+        self.raw.ifc.write(|w| w.rxof().set_bit());
+
+        for &b in data {
+            while self.raw.status.read().txbl().bit_is_clear() {}
+            self.raw.txdata.write(|w| unsafe { w.txdata().bits(b) });
+
+            let (echo, framing_error) = wait_echo(&self.raw, self.echo_polls)?;
+            if self.collision_detection && collided(b, echo, framing_error) {
+                return Err(HalfDuplexError::Collision);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// disabling the loopback breaks the echo based collision detection.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`HalfDuplex::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut I {
+        &mut self.raw
+    }
+
+    /// Return the raw interface to the underlying peripheral.
+    pub fn release(self) -> I {
+        self.raw
+    }
+}
+
+// Converts `ECHO_FRAMES` frame times at the configured baud rate to busy loop
+// iterations. An iteration polls a register and takes a few cycles.
+fn echo_polls(usart: &RegisterBlock, hfcoreclk: Hertz, hfperclk: Hertz) -> u32 {
+    let oversampling = oversampling_factor(usart.ctrl.read().ovs().variant());
+    let div = usart.clkdiv.read().div().bits() as u64;
+    // Start bit, data bits, parity bit and up to two stop bits.
+    let bits = frame_bits(usart) as u64 + 4;
+    // Frame time in HFCORECLK cycles.
+    let frame = bits * oversampling * (32 + div) * hfcoreclk.0 as u64 / (32 * hfperclk.0 as u64);
+    (ECHO_FRAMES * frame / 4).clamp(1, u32::MAX as u64) as u32
+}
+
+// Waits at most `polls` iterations for the echo of a transmitted byte and
+// returns it with its framing error flag.
+fn wait_echo(usart: &RegisterBlock, polls: u32) -> Result<(u8, bool), HalfDuplexError> {
+    let mut polls = polls;
+    while usart.status.read().rxdatav().bit_is_clear() {
+        if polls == 0 {
+            return Err(HalfDuplexError::Serial(Error::Impl(ImplError::TimedOut)));
+        }
+        polls -= 1;
+    }
+    if let Err(nb::Error::Other(e)) = check_overflow(usart) {
+        return Err(HalfDuplexError::Serial(e));
+    }
+
+    let echo = usart.rxdatax.read();
+    Ok((echo.rxdata().bits() as u8, echo.ferr().bit_is_set()))
+}
+
+// Returns `true` when the echo of `sent` shows that another transmitter drove
+// the bus at the same time. Overlapping start or stop bits show up as a
+// framing error, overlapping data bits as a different byte.
+fn collided(sent: u8, echo: u8, framing_error: bool) -> bool {
+    framing_error || echo != sent
+}
+
+impl<I: Instance> Read<u8> for HalfDuplex<I> {
+    type Error = HalfDuplexError;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        if self.raw.status.read().rxdatav().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
         }
+        check_overflow(&self.raw).map_err(|e| e.map(HalfDuplexError::Serial))?;
+
+        let rxdatax = self.raw.rxdatax.read();
+        check_frame(
+            &self.raw,
+            rxdatax.ferr().bit_is_set(),
+            rxdatax.perr().bit_is_set(),
+        )
+        .map_err(|e| e.map(HalfDuplexError::Serial))?;
+
+        Ok(rxdatax.rxdata().bits() as u8)
     }
 }
 
-/// USART API
-pub struct Usart<I> {
+/// Shifts out bytes on a data and clock pin pair, e.g. to drive 74HC595 shift
+/// registers or LED drivers.
+///
+/// Uses the USART in synchronous master mode. Data is shifted out MSB first
+/// on the TX pin. It changes on the falling clock edge and is stable on the
+/// rising edge, which shifts it into the registers (SPI mode 0). After each
+/// [`ShiftOut::write()`] the latch pin is pulsed high to transfer the shifted
+/// data to the outputs.
+pub struct ShiftOut<I, LATCH> {
     raw: I,
+    latch: LATCH,
 }
 
-impl<I> Usart<I>
+impl<I, LATCH> ShiftOut<I, LATCH>
 where
     I: Instance,
+    LATCH: OutputPin,
 {
-    pub fn new<TX, RX>(usart: I, _tx: TX, _rx: RX, config: &Config, cmu: &mut Cmu) -> Usart<I>
+    /// Configures the USART to shift out data with a clock frequency of at
+    /// most `frequency` Hz.
+    ///
+    /// The clock is derived from the peripheral clock with
+    /// `hfperclk / (2 * (1 + CLKDIV / 256))` where only the integer part of
+    /// CLKDIV is used, i.e. the actual frequency is `hfperclk / (2 * n)` for
+    /// the smallest integer `n` that does not exceed `frequency`.
+    ///
+    /// Returns `Err(Error::Impl(ImplError::InvalidConfiguration))` when the
+    /// frequency is zero or too low for the 20-bit divider. The peripheral is
+    /// not touched in that case.
+    pub fn new<CLK, DATA>(
+        usart: I,
+        _clk: CLK,
+        _data: DATA,
+        mut latch: LATCH,
+        frequency: u32,
+        cmu: &mut Cmu,
+    ) -> Result<Self, Error>
     where
-        TX: PinLocation<I, TxPin>,
-        RX: PinLocation<I, RxPin>,
+        CLK: PinLocation<I, ClkPin>,
+        DATA: PinLocation<I, TxPin>,
     {
+        let clkdiv = sync_divider(cmu.hfperclk().0, frequency).map_err(Error::Impl)?;
+
         cmu.enable_clock(&usart);
 
-        usart.frame.modify(|_, w| {
-            w.parity()
-                .variant(config.parity)
-                .stopbits()
-                .variant(config.stop_bits)
-        });
+        latch.set_low().ok();
 
-        let ovs = 16;
-        let clkdiv = 32 * cmu.hfperclk() / (ovs * config.baudrate) - 32;
-        // TODO: Check accuracy of clock and lower OVS if it is off by too much.
-        usart.clkdiv.modify(|_, w| unsafe { w.div().bits(clkdiv) });
+        usart.ctrl.write(|w| w.sync().set_bit().msbf().set_bit());
+        usart.clkdiv.write(|w| unsafe { w.div().bits(clkdiv) });
 
-        // Route peripheral to pins.
         usart
             .routeloc0
-            .write(|w| unsafe { w.txloc().bits(TX::LOCATION).rxloc().bits(RX::LOCATION) });
+            .write(|w| unsafe { w.clkloc().bits(CLK::LOCATION).txloc().bits(DATA::LOCATION) });
         usart
             .routepen
-            .write(|w| w.txpen().set_bit().rxpen().set_bit());
+            .write(|w| w.clkpen().set_bit().txpen().set_bit());
 
-        Usart { raw: usart }
-    }
+        usart.cmd.write(|w| w.masteren().set_bit().txen().set_bit());
 
-    pub fn split(self) -> (Tx<I>, Rx<I>) {
-        self.raw.cmd.write(|w| w.txen().set_bit().rxen().set_bit());
-        (Tx { _priv: PhantomData }, Rx { _priv: PhantomData })
+        Ok(ShiftOut { raw: usart, latch })
     }
 
-    /// Return the raw interface to the underlying peripheral.
-    pub fn release(self) -> I {
-        self.raw
+    /// Shifts out all bytes and pulses the latch pin once the last bit left
+    /// the shift register.
+    ///
+    /// Does nothing for an empty slice, the latch is not pulsed.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), LATCH::Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        for &b in data {
+            while self.raw.status.read().txbl().bit_is_clear() {}
+            self.raw.txdata.write(|w| unsafe { w.txdata().bits(b) });
+        }
+        while self.raw.status.read().txc().bit_is_clear() {}
+
+        self.latch.set_high()?;
+        self.latch.set_low()
     }
-}
 
-/// Transmit part of the serial interface for a USART instance.
-pub struct Tx<I> {
-    _priv: PhantomData<I>,
-}
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// switching to LSB first reverses the bit order of the shift register
+    /// outputs.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
 
-impl<I: Instance> Tx<I> {
-    /// Enables the `TXBL` interrupt which indicates that data can be sent with
-    /// the `write()` method.
-    pub fn enable_interrupt(&mut self) {
-        let usart_set = unsafe { &*I::ptr_set() };
-        usart_set.ien.write(|w| w.txbl().set_bit());
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`ShiftOut::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut I {
+        &mut self.raw
     }
 
-    /// Disables the `TXBL` interrupt.
-    pub fn disable_interrupt(&mut self) {
-        let usart_clear = unsafe { &*I::ptr_clear() };
-        usart_clear.ien.write(|w| w.txbl().set_bit());
+    /// Return the raw interface to the underlying peripheral and the latch pin.
+    pub fn release(self) -> (I, LATCH) {
+        (self.raw, self.latch)
     }
 }
 
-impl<I: Instance> Write<u8> for Tx<I> {
-    type Error = Infallible;
-
-    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
-        let usart = unsafe { &*I::ptr() };
-        if usart.status.read().txbl().bit() {
-            usart.txdata.write(|w| unsafe { w.txdata().bits(word) });
-            Ok(())
-        } else {
-            Err(nb::Error::WouldBlock)
-        }
-    }
+/// Smartcard configuration.
+///
+/// Defaults to a 4 MHz card clock, no extra guard time and retransmission of
+/// characters rejected by the card.
+pub struct SmartcardConfig {
+    /// Frequency of the clock supplied to the card in Hz.
+    ///
+    /// The USART does not generate the card clock in asynchronous mode. It
+    /// must be provided by other means, e.g. a TIMER output.
+    pub card_clock: u32,
+    /// Extra guard time (N) between characters sent to the card in elementary
+    /// time units.
+    pub extra_guard_time: GuardTime,
+    /// Retransmits a character when the card signals a parity error.
+    pub retransmit: bool,
+}
 
-    fn flush(&mut self) -> nb::Result<(), Self::Error> {
-        let usart = unsafe { &*I::ptr() };
-        if usart.status.read().txidle().bit() {
-            Ok(())
-        } else {
-            Err(nb::Error::WouldBlock)
+impl Default for SmartcardConfig {
+    fn default() -> Self {
+        Self {
+            card_clock: 4_000_000,
+            extra_guard_time: GuardTime::ZERO,
+            retransmit: true,
         }
     }
 }
 
-impl<I: Instance> BlockingWriteDefault<u8> for Tx<I> {}
+/// ISO 7816-3 smartcard interface using the T=0 protocol.
+///
+/// Characters consist of a start bit, 8 data bits (direct convention, LSB
+/// first) and an even parity bit, followed by the guard time. The elementary
+/// time unit (ETU) is 372 card clock cycles as defined for the answer to
+/// reset. A receiver signals a parity error by pulling the I/O line low
+/// during the guard time (NACK), the transmitter then repeats the character.
+///
+/// The single I/O line is shared by both directions. The USART transmits on
+/// the TX pin, which must be configured as open-drain output with pull-up, and
+/// receives through the internal loopback.
+pub struct Smartcard<I, RST> {
+    raw: I,
+    reset: RST,
+}
 
-impl<I: Instance> fmt::Write for Tx<I>
+impl<I, RST> Smartcard<I, RST>
 where
-    Self: BlockingWriteDefault<u8>,
+    I: Instance,
+    RST: OutputPin,
 {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        use embedded_hal::blocking::serial::Write;
-        self.bwrite_all(s.as_bytes()).map_err(|_| fmt::Error)?;
-        block!(self.flush()).map_err(|_| fmt::Error)?;
-        Ok(())
+    /// Configures the USART for smartcard communication and holds the card in
+    /// reset.
+    ///
+    /// Returns `Err(Error::Impl(ImplError::InvalidConfiguration))` when the
+    /// HFPERCLK frequency cannot generate the ETU of the card clock within
+    /// 2 %.
+    pub fn new<IO>(
+        usart: I,
+        _io: IO,
+        mut reset: RST,
+        config: &SmartcardConfig,
+        cmu: &mut Cmu,
+    ) -> Result<Self, Error>
+    where
+        IO: PinLocation<I, TxPin>,
+    {
+        let (oversampling, clkdiv) = smartcard_divider(cmu.hfperclk().0, config.card_clock)
+            .ok_or(Error::Impl(ImplError::InvalidConfiguration))?;
+
+        cmu.enable_clock(&usart);
+
+        reset.set_low().ok();
+
+        usart.frame.write(|w| {
+            w.databits()
+                .eight()
+                .parity()
+                .even()
+                .stopbits()
+                .variant(StopBits::ONEANDAHALF)
+        });
+
+        usart.ctrl.write(|w| {
+            w.ovs()
+                .variant(oversampling)
+                .scmode()
+                .set_bit()
+                .scretrans()
+                .bit(config.retransmit)
+                .loopbk()
+                .set_bit()
+        });
+        usart.clkdiv.write(|w| unsafe { w.div().bits(clkdiv) });
+        usart
+            .timing
+            .modify(|_, w| w.ics().variant(config.extra_guard_time));
+
+        usart
+            .routeloc0
+            .write(|w| unsafe { w.txloc().bits(IO::LOCATION) });
+        usart.routepen.write(|w| w.txpen().set_bit());
+
+        usart.cmd.write(|w| w.rxen().set_bit().txen().set_bit());
+
+        Ok(Smartcard { raw: usart, reset })
     }
-}
 
-/// Receive part of the serial interface for a USART instance.
-pub struct Rx<I> {
-    _priv: PhantomData<I>,
-}
+    /// Releases the reset line. The card responds with its answer to reset
+    /// (ATR), which can be received with `read()`.
+    pub fn activate(&mut self) -> Result<(), RST::Error> {
+        self.reset.set_high()
+    }
 
-impl<I: Instance> Rx<I> {
-    /// Enables the `RXDATAV` interrupt which indicates that data was received
-    /// and can be read with the `read()` method.
-    pub fn enable_interrupt(&mut self) {
-        let usart_set = unsafe { &*I::ptr_set() };
-        usart_set.ien.write(|w| w.rxdatav().set_bit());
+    /// Puts the card into reset.
+    pub fn deactivate(&mut self) -> Result<(), RST::Error> {
+        self.reset.set_low()
     }
 
-    /// Disables the `RXDATAV` interrupt.
-    pub fn disable_interrupt(&mut self) {
-        let usart_clear = unsafe { &*I::ptr_clear() };
-        usart_clear.ien.write(|w| w.rxdatav().set_bit());
+    /// Sends all bytes to the card.
+    ///
+    /// The transmitted characters are also received through the loopback and
+    /// discarded before this method returns.
+    pub fn write(&mut self, data: &[u8]) {
+        for &b in data {
+            while self.raw.status.read().txbl().bit_is_clear() {}
+            self.raw.txdata.write(|w| unsafe { w.txdata().bits(b) });
+            while self.raw.status.read().txc().bit_is_clear() {}
+
+            while self.raw.status.read().rxdatav().bit_is_set() {
+                self.raw.rxdatax.read();
+            }
+        }
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// the received echo of each transmitted character is discarded by
+    /// `write()` and gets out of sync when the loopback is disabled.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Smartcard::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut I {
+        &mut self.raw
+    }
+
+    /// Return the raw interface to the underlying peripheral and the reset pin.
+    pub fn release(self) -> (I, RST) {
+        (self.raw, self.reset)
     }
 }
 
-impl<I: Instance> Read<u8> for Rx<I> {
+impl<I: Instance, RST> Read<u8> for Smartcard<I, RST> {
     type Error = Error;
 
     fn read(&mut self) -> nb::Result<u8, Self::Error> {
-        let usart = unsafe { &*I::ptr() };
-        if usart.status.read().rxdatav().bit_is_clear() {
+        if self.raw.status.read().rxdatav().bit_is_clear() {
             return Err(nb::Error::WouldBlock);
         }
 
-        let rxdatax = usart.rxdatax.read();
-        if rxdatax.ferr().bit_is_set() {
-            return Err(nb::Error::Other(Error::FrameFormat));
-        }
+        let rxdatax = self.raw.rxdatax.read();
         if rxdatax.perr().bit_is_set() {
             return Err(nb::Error::Other(Error::Parity));
         }
+        if rxdatax.ferr().bit_is_set() {
+            return Err(nb::Error::Other(Error::FrameFormat));
+        }
 
         Ok(rxdatax.rxdata().bits() as u8)
     }
@@ -188,12 +2018,43 @@ pub trait Instance:
     + Deref<Target = RegisterBlock>
     + PeripheralClearSetExt<RegisterBlock = RegisterBlock>
 {
+    /// LDMA request source of the instance.
+    const LDMA_SOURCE: ldma::Source;
+    /// NVIC line of the receive interrupts.
+    const RX_INTERRUPT: Interrupt;
+    /// NVIC line of the transmit interrupts.
+    const TX_INTERRUPT: Interrupt;
+    /// Instance number, e.g. 0 for USART0.
+    const INDEX: usize;
+}
+
+impl Instance for USART0 {
+    const LDMA_SOURCE: ldma::Source = ldma::Source::USART0;
+    const RX_INTERRUPT: Interrupt = Interrupt::USART0_RX;
+    const TX_INTERRUPT: Interrupt = Interrupt::USART0_TX;
+    const INDEX: usize = 0;
+}
+
+impl Instance for USART1 {
+    const LDMA_SOURCE: ldma::Source = ldma::Source::USART1;
+    const RX_INTERRUPT: Interrupt = Interrupt::USART1_RX;
+    const TX_INTERRUPT: Interrupt = Interrupt::USART1_TX;
+    const INDEX: usize = 1;
+}
+
+impl Instance for USART2 {
+    const LDMA_SOURCE: ldma::Source = ldma::Source::USART2;
+    const RX_INTERRUPT: Interrupt = Interrupt::USART2_RX;
+    const TX_INTERRUPT: Interrupt = Interrupt::USART2_TX;
+    const INDEX: usize = 2;
 }
 
-impl Instance for USART0 {}
-impl Instance for USART1 {}
-impl Instance for USART2 {}
-impl Instance for USART3 {}
+impl Instance for USART3 {
+    const LDMA_SOURCE: ldma::Source = ldma::Source::USART3;
+    const RX_INTERRUPT: Interrupt = Interrupt::USART3_RX;
+    const TX_INTERRUPT: Interrupt = Interrupt::USART3_TX;
+    const INDEX: usize = 3;
+}
 
 /// Marks a pin that can be used as USART TX signal.
 pub struct TxPin;
@@ -486,3 +2347,973 @@ impl_pin_locations!(USART3, RxPin, Input, {
     PK2: 30,
     PD8: 31,
 });
+
+/// Marks a pin that can be used as USART CLK signal in synchronous mode.
+pub struct ClkPin;
+
+/// Marks a pin that can be used as SPI master output. The USART shifts out
+/// on its TX signal in synchronous mode.
+pub type MosiPin = TxPin;
+
+/// Marks a pin that can be used as SPI master input. The USART samples its
+/// RX signal in synchronous mode.
+pub type MisoPin = RxPin;
+
+impl_pin_locations!(USART0, ClkPin, Output, {
+    PA2: 0,
+    PA3: 1,
+    PA4: 2,
+    PA5: 3,
+    PB11: 4,
+    PB12: 5,
+    PB13: 6,
+    PB14: 7,
+    PB15: 8,
+    PC6: 9,
+    PC7: 10,
+    PC8: 11,
+    PC9: 12,
+    PC10: 13,
+    PC11: 14,
+    PD9: 15,
+    PD10: 16,
+    PD11: 17,
+    PD12: 18,
+    PD13: 19,
+    PD14: 20,
+    PD15: 21,
+    // Overwriting debug pins is not supported
+    // PF0: 22,
+    // PF1: 23,
+    // PF2: 24,
+    // PF3: 25,
+    PF4: 26,
+    PF5: 27,
+    PF6: 28,
+    PF7: 29,
+    PA0: 30,
+    PA1: 31,
+});
+
+impl_pin_locations!(USART1, ClkPin, Output, {
+    PA2: 0,
+    PA3: 1,
+    PA4: 2,
+    PA5: 3,
+    PB11: 4,
+    PB12: 5,
+    PB13: 6,
+    PB14: 7,
+    PB15: 8,
+    PC6: 9,
+    PC7: 10,
+    PC8: 11,
+    PC9: 12,
+    PC10: 13,
+    PC11: 14,
+    PD9: 15,
+    PD10: 16,
+    PD11: 17,
+    PD12: 18,
+    PD13: 19,
+    PD14: 20,
+    PD15: 21,
+    // Overwriting debug pins is not supported
+    // PF0: 22,
+    // PF1: 23,
+    // PF2: 24,
+    // PF3: 25,
+    PF4: 26,
+    PF5: 27,
+    PF6: 28,
+    PF7: 29,
+    PA0: 30,
+    PA1: 31,
+});
+
+impl_pin_locations!(USART2, ClkPin, Output, {
+    PA7: 0,
+    PA8: 1,
+    PA9: 2,
+    PI0: 3,
+    PI1: 4,
+    PI2: 5,
+    PI3: 6,
+    PB6: 7,
+    PB7: 8,
+    PB8: 9,
+    PB9: 10,
+    PB10: 11,
+    // Overwriting debug pins is not supported
+    // PF0: 12,
+    // PF1: 13,
+    // PF3: 14,
+    PF4: 15,
+    PF5: 16,
+    PF6: 17,
+    PF7: 18,
+    PF8: 19,
+    PF9: 20,
+    PF10: 21,
+    PF11: 22,
+    PF12: 23,
+    PF13: 24,
+    PF14: 25,
+    PF15: 26,
+    PK0: 27,
+    PK1: 28,
+    PK2: 29,
+    PA5: 30,
+    PA6: 31,
+});
+
+impl_pin_locations!(USART3, ClkPin, Output, {
+    PD10: 0,
+    PD11: 1,
+    PD12: 2,
+    PD13: 3,
+    PD14: 4,
+    PD15: 5,
+    PI2: 6,
+    PI3: 7,
+    PB6: 8,
+    PB7: 9,
+    PB8: 10,
+    PB9: 11,
+    PB10: 12,
+    PB11: 13,
+    PJ14: 14,
+    PJ15: 15,
+    PC0: 16,
+    PC1: 17,
+    PC2: 18,
+    PC3: 19,
+    PC4: 20,
+    PC5: 21,
+    PF11: 22,
+    PF12: 23,
+    PF13: 24,
+    PF14: 25,
+    PF15: 26,
+    PK0: 27,
+    PK1: 28,
+    PK2: 29,
+    PD8: 30,
+    PD9: 31,
+});
+
+/// Marks a pin that can be used as USART CS signal in synchronous mode.
+pub struct CsPin;
+
+impl_pin_locations!(USART0, CsPin, Output, {
+    PA3: 0,
+    PA4: 1,
+    PA5: 2,
+    PB11: 3,
+    PB12: 4,
+    PB13: 5,
+    PB14: 6,
+    PB15: 7,
+    PC6: 8,
+    PC7: 9,
+    PC8: 10,
+    PC9: 11,
+    PC10: 12,
+    PC11: 13,
+    PD9: 14,
+    PD10: 15,
+    PD11: 16,
+    PD12: 17,
+    PD13: 18,
+    PD14: 19,
+    PD15: 20,
+    // Overwriting debug pins is not supported
+    // PF0: 21,
+    // PF1: 22,
+    // PF2: 23,
+    // PF3: 24,
+    PF4: 25,
+    PF5: 26,
+    PF6: 27,
+    PF7: 28,
+    PA0: 29,
+    PA1: 30,
+    PA2: 31,
+});
+
+impl_pin_locations!(USART1, CsPin, Output, {
+    PA3: 0,
+    PA4: 1,
+    PA5: 2,
+    PB11: 3,
+    PB12: 4,
+    PB13: 5,
+    PB14: 6,
+    PB15: 7,
+    PC6: 8,
+    PC7: 9,
+    PC8: 10,
+    PC9: 11,
+    PC10: 12,
+    PC11: 13,
+    PD9: 14,
+    PD10: 15,
+    PD11: 16,
+    PD12: 17,
+    PD13: 18,
+    PD14: 19,
+    PD15: 20,
+    // Overwriting debug pins is not supported
+    // PF0: 21,
+    // PF1: 22,
+    // PF2: 23,
+    // PF3: 24,
+    PF4: 25,
+    PF5: 26,
+    PF6: 27,
+    PF7: 28,
+    PA0: 29,
+    PA1: 30,
+    PA2: 31,
+});
+
+impl_pin_locations!(USART2, CsPin, Output, {
+    PA8: 0,
+    PA9: 1,
+    PI0: 2,
+    PI1: 3,
+    PI2: 4,
+    PI3: 5,
+    PB6: 6,
+    PB7: 7,
+    PB8: 8,
+    PB9: 9,
+    PB10: 10,
+    // Overwriting debug pins is not supported
+    // PF0: 11,
+    // PF1: 12,
+    // PF3: 13,
+    PF4: 14,
+    PF5: 15,
+    PF6: 16,
+    PF7: 17,
+    PF8: 18,
+    PF9: 19,
+    PF10: 20,
+    PF11: 21,
+    PF12: 22,
+    PF13: 23,
+    PF14: 24,
+    PF15: 25,
+    PK0: 26,
+    PK1: 27,
+    PK2: 28,
+    PA5: 29,
+    PA6: 30,
+    PA7: 31,
+});
+
+impl_pin_locations!(USART3, CsPin, Output, {
+    PD11: 0,
+    PD12: 1,
+    PD13: 2,
+    PD14: 3,
+    PD15: 4,
+    PI2: 5,
+    PI3: 6,
+    PB6: 7,
+    PB7: 8,
+    PB8: 9,
+    PB9: 10,
+    PB10: 11,
+    PB11: 12,
+    PJ14: 13,
+    PJ15: 14,
+    PC0: 15,
+    PC1: 16,
+    PC2: 17,
+    PC3: 18,
+    PC4: 19,
+    PC5: 20,
+    PF11: 21,
+    PF12: 22,
+    PF13: 23,
+    PF14: 24,
+    PF15: 25,
+    PK0: 26,
+    PK1: 27,
+    PK2: 28,
+    PD8: 29,
+    PD9: 30,
+    PD10: 31,
+});
+
+/// Marks a pin that can be used as USART CTS signal for hardware flow control.
+pub struct CtsPin;
+
+impl_pin_locations!(USART0, CtsPin, Input, {
+    PA4: 0,
+    PA5: 1,
+    PB11: 2,
+    PB12: 3,
+    PB13: 4,
+    PB14: 5,
+    PB15: 6,
+    PC6: 7,
+    PC7: 8,
+    PC8: 9,
+    PC9: 10,
+    PC10: 11,
+    PC11: 12,
+    PD9: 13,
+    PD10: 14,
+    PD11: 15,
+    PD12: 16,
+    PD13: 17,
+    PD14: 18,
+    PD15: 19,
+    // Overwriting debug pins is not supported
+    // PF0: 20,
+    // PF1: 21,
+    // PF2: 22,
+    // PF3: 23,
+    PF4: 24,
+    PF5: 25,
+    PF6: 26,
+    PF7: 27,
+    PA0: 28,
+    PA1: 29,
+    PA2: 30,
+    PA3: 31,
+});
+
+impl_pin_locations!(USART1, CtsPin, Input, {
+    PA4: 0,
+    PA5: 1,
+    PB11: 2,
+    PB12: 3,
+    PB13: 4,
+    PB14: 5,
+    PB15: 6,
+    PC6: 7,
+    PC7: 8,
+    PC8: 9,
+    PC9: 10,
+    PC10: 11,
+    PC11: 12,
+    PD9: 13,
+    PD10: 14,
+    PD11: 15,
+    PD12: 16,
+    PD13: 17,
+    PD14: 18,
+    PD15: 19,
+    // Overwriting debug pins is not supported
+    // PF0: 20,
+    // PF1: 21,
+    // PF2: 22,
+    // PF3: 23,
+    PF4: 24,
+    PF5: 25,
+    PF6: 26,
+    PF7: 27,
+    PA0: 28,
+    PA1: 29,
+    PA2: 30,
+    PA3: 31,
+});
+
+impl_pin_locations!(USART2, CtsPin, Input, {
+    PA9: 0,
+    PI0: 1,
+    PI1: 2,
+    PI2: 3,
+    PI3: 4,
+    PB6: 5,
+    PB7: 6,
+    PB8: 7,
+    PB9: 8,
+    PB10: 9,
+    // Overwriting debug pins is not supported
+    // PF0: 10,
+    // PF1: 11,
+    // PF3: 12,
+    PF4: 13,
+    PF5: 14,
+    PF6: 15,
+    PF7: 16,
+    PF8: 17,
+    PF9: 18,
+    PF10: 19,
+    PF11: 20,
+    PF12: 21,
+    PF13: 22,
+    PF14: 23,
+    PF15: 24,
+    PK0: 25,
+    PK1: 26,
+    PK2: 27,
+    PA5: 28,
+    PA6: 29,
+    PA7: 30,
+    PA8: 31,
+});
+
+impl_pin_locations!(USART3, CtsPin, Input, {
+    PD12: 0,
+    PD13: 1,
+    PD14: 2,
+    PD15: 3,
+    PI2: 4,
+    PI3: 5,
+    PB6: 6,
+    PB7: 7,
+    PB8: 8,
+    PB9: 9,
+    PB10: 10,
+    PB11: 11,
+    PJ14: 12,
+    PJ15: 13,
+    PC0: 14,
+    PC1: 15,
+    PC2: 16,
+    PC3: 17,
+    PC4: 18,
+    PC5: 19,
+    PF11: 20,
+    PF12: 21,
+    PF13: 22,
+    PF14: 23,
+    PF15: 24,
+    PK0: 25,
+    PK1: 26,
+    PK2: 27,
+    PD8: 28,
+    PD9: 29,
+    PD10: 30,
+    PD11: 31,
+});
+
+/// Marks a pin that can be used as USART RTS signal for hardware flow control.
+pub struct RtsPin;
+
+impl_pin_locations!(USART0, RtsPin, Output, {
+    PA5: 0,
+    PB11: 1,
+    PB12: 2,
+    PB13: 3,
+    PB14: 4,
+    PB15: 5,
+    PC6: 6,
+    PC7: 7,
+    PC8: 8,
+    PC9: 9,
+    PC10: 10,
+    PC11: 11,
+    PD9: 12,
+    PD10: 13,
+    PD11: 14,
+    PD12: 15,
+    PD13: 16,
+    PD14: 17,
+    PD15: 18,
+    // Overwriting debug pins is not supported
+    // PF0: 19,
+    // PF1: 20,
+    // PF2: 21,
+    // PF3: 22,
+    PF4: 23,
+    PF5: 24,
+    PF6: 25,
+    PF7: 26,
+    PA0: 27,
+    PA1: 28,
+    PA2: 29,
+    PA3: 30,
+    PA4: 31,
+});
+
+impl_pin_locations!(USART1, RtsPin, Output, {
+    PA5: 0,
+    PB11: 1,
+    PB12: 2,
+    PB13: 3,
+    PB14: 4,
+    PB15: 5,
+    PC6: 6,
+    PC7: 7,
+    PC8: 8,
+    PC9: 9,
+    PC10: 10,
+    PC11: 11,
+    PD9: 12,
+    PD10: 13,
+    PD11: 14,
+    PD12: 15,
+    PD13: 16,
+    PD14: 17,
+    PD15: 18,
+    // Overwriting debug pins is not supported
+    // PF0: 19,
+    // PF1: 20,
+    // PF2: 21,
+    // PF3: 22,
+    PF4: 23,
+    PF5: 24,
+    PF6: 25,
+    PF7: 26,
+    PA0: 27,
+    PA1: 28,
+    PA2: 29,
+    PA3: 30,
+    PA4: 31,
+});
+
+impl_pin_locations!(USART2, RtsPin, Output, {
+    PI0: 0,
+    PI1: 1,
+    PI2: 2,
+    PI3: 3,
+    PB6: 4,
+    PB7: 5,
+    PB8: 6,
+    PB9: 7,
+    PB10: 8,
+    // Overwriting debug pins is not supported
+    // PF0: 9,
+    // PF1: 10,
+    // PF3: 11,
+    PF4: 12,
+    PF5: 13,
+    PF6: 14,
+    PF7: 15,
+    PF8: 16,
+    PF9: 17,
+    PF10: 18,
+    PF11: 19,
+    PF12: 20,
+    PF13: 21,
+    PF14: 22,
+    PF15: 23,
+    PK0: 24,
+    PK1: 25,
+    PK2: 26,
+    PA5: 27,
+    PA6: 28,
+    PA7: 29,
+    PA8: 30,
+    PA9: 31,
+});
+
+impl_pin_locations!(USART3, RtsPin, Output, {
+    PD13: 0,
+    PD14: 1,
+    PD15: 2,
+    PI2: 3,
+    PI3: 4,
+    PB6: 5,
+    PB7: 6,
+    PB8: 7,
+    PB9: 8,
+    PB10: 9,
+    PB11: 10,
+    PJ14: 11,
+    PJ15: 12,
+    PC0: 13,
+    PC1: 14,
+    PC2: 15,
+    PC3: 16,
+    PC4: 17,
+    PC5: 18,
+    PF11: 19,
+    PF12: 20,
+    PF13: 21,
+    PF14: 22,
+    PF15: 23,
+    PK0: 24,
+    PK1: 25,
+    PK2: 26,
+    PD8: 27,
+    PD9: 28,
+    PD10: 29,
+    PD11: 30,
+    PD12: 31,
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::PeripheralPtrExt;
+    use core::{
+        cell::UnsafeCell,
+        mem::{size_of, MaybeUninit},
+    };
+
+    // `STATUS` bits.
+    const STATUS_TXBL: u32 = 1 << 6;
+    const STATUS_RXDATAV: u32 = 1 << 7;
+
+    // `RXDATAX` bits.
+    const RXDATAX_FERR: u32 = 1 << 15;
+    const STATUS_TXIDLE: u32 = 1 << 13;
+
+    // `IF`/`IFC` bits.
+    const TXC: u32 = 1 << 0;
+    const RXOF: u32 = 1 << 4;
+    const PERR: u32 = 1 << 8;
+    const FERR: u32 = 1 << 9;
+
+    // Zeroed memory in place of the USART registers.
+    fn registers() -> Box<MaybeUninit<RegisterBlock>> {
+        Box::new(MaybeUninit::zeroed())
+    }
+
+    fn peek<R>(reg: &R) -> u32 {
+        assert_eq!(size_of::<R>(), 4);
+        unsafe { (reg as *const R as *const u32).read_volatile() }
+    }
+
+    fn load<R>(reg: &R, value: u32) {
+        assert_eq!(size_of::<R>(), 4);
+        unsafe { (reg as *const R as *mut u32).write_volatile(value) }
+    }
+
+    // Instance whose registers live in zeroed memory, for the halves that
+    // access the registers through `Instance::ptr()`. Only used by a single
+    // test.
+    struct Fake;
+
+    struct Memory(UnsafeCell<MaybeUninit<RegisterBlock>>);
+
+    unsafe impl Sync for Memory {}
+
+    static MEMORY: Memory = Memory(UnsafeCell::new(MaybeUninit::zeroed()));
+
+    impl PeripheralPtrExt for Fake {
+        type RegisterBlock = RegisterBlock;
+
+        fn ptr() -> *const RegisterBlock {
+            MEMORY.0.get() as *const RegisterBlock
+        }
+    }
+
+    impl Deref for Fake {
+        type Target = RegisterBlock;
+
+        fn deref(&self) -> &RegisterBlock {
+            unsafe { &*Self::ptr() }
+        }
+    }
+
+    impl ClockControlExt for Fake {
+        fn enable_clock(&self, _clocks: &mut Cmu) {}
+        fn disable_clock(&self, _clocks: &mut Cmu) {}
+    }
+
+    impl Instance for Fake {
+        const LDMA_SOURCE: ldma::Source = ldma::Source::USART0;
+        const RX_INTERRUPT: Interrupt = Interrupt::USART0_RX;
+        const TX_INTERRUPT: Interrupt = Interrupt::USART0_TX;
+        const INDEX: usize = 0;
+    }
+
+    fn invalid(result: Result<u32, Error>) -> bool {
+        matches!(result, Err(Error::Impl(ImplError::InvalidConfiguration)))
+    }
+
+    #[test]
+    fn clkdiv_exact() {
+        assert_eq!(
+            compute_clkdiv(16_000_000, Oversampling::X16, 1_000_000).ok(),
+            Some(0)
+        );
+        assert_eq!(
+            compute_clkdiv(16_000_000, Oversampling::X16, 500_000).ok(),
+            Some(32)
+        );
+        assert_eq!(
+            compute_clkdiv(16_000_000, Oversampling::X16, 800_000).ok(),
+            Some(8)
+        );
+        assert_eq!(
+            compute_clkdiv(16_000_000, Oversampling::X4, 1_000_000).ok(),
+            Some(96)
+        );
+    }
+
+    #[test]
+    fn clkdiv_rounding() {
+        // 32 * 19 MHz / (16 * 115200) - 32 = 297.86
+        assert_eq!(
+            compute_clkdiv(19_000_000, Oversampling::X16, 115_200).ok(),
+            Some(298)
+        );
+        // 32 * 19 MHz / (16 * 9600) - 32 = 3926.33
+        assert_eq!(
+            compute_clkdiv(19_000_000, Oversampling::X16, 9600).ok(),
+            Some(3926)
+        );
+    }
+
+    #[test]
+    fn clkdiv_minimum() {
+        assert_eq!(
+            compute_clkdiv(19_000_000, Oversampling::X16, 1_187_500).ok(),
+            Some(0)
+        );
+        // Rounds to the smallest divider.
+        assert_eq!(
+            compute_clkdiv(19_000_000, Oversampling::X16, 1_200_000).ok(),
+            Some(0)
+        );
+        assert!(invalid(compute_clkdiv(
+            19_000_000,
+            Oversampling::X16,
+            2_000_000
+        )));
+        assert!(invalid(compute_clkdiv(19_000_000, Oversampling::X16, 0)));
+    }
+
+    #[test]
+    fn clkdiv_overflow() {
+        assert_eq!(
+            compute_clkdiv(40_000_000, Oversampling::X16, 1).ok(),
+            Some((1 << 20) - 1)
+        );
+        assert_eq!(
+            compute_clkdiv(u32::MAX, Oversampling::X4, 1).ok(),
+            Some((1 << 20) - 1)
+        );
+    }
+
+    #[test]
+    fn divider_prefers_high_oversampling() {
+        assert_eq!(
+            find_divider(16_000_000, 1_000_000),
+            Some((Oversampling::X16, 0, 1_000_000))
+        );
+    }
+
+    #[test]
+    fn divider_lower_oversampling_only_when_better() {
+        // 16x, 8x and 6x give 115151 Bd, 4x gives 115238 Bd.
+        assert_eq!(
+            find_divider(19_000_000, 115_200),
+            Some((Oversampling::X4, 1287, 115_238))
+        );
+        assert_eq!(
+            find_divider(19_000_000, 4_000_000),
+            Some((Oversampling::X4, 6, 4_000_000))
+        );
+        assert_eq!(find_divider(19_000_000, 5_000_000), None);
+    }
+
+    #[test]
+    fn divider_for_rate_clock_pairs() {
+        // Exact with 16x, ties keep the higher oversampling.
+        assert_eq!(
+            find_divider(19_000_000, 9600),
+            Some((Oversampling::X16, 3926, 9600))
+        );
+        assert_eq!(
+            find_divider(19_000_000, 1_000_000),
+            Some((Oversampling::X16, 6, 1_000_000))
+        );
+        // 6x and 4x both miss by 15 Bd, 16x by 58 Bd and 8x by 28 Bd.
+        assert_eq!(
+            find_divider(38_400_000, 115_200),
+            Some((Oversampling::X6, 1746, 115_185))
+        );
+        // 16x and 8x cannot reach the baud rate.
+        assert_eq!(
+            find_divider(14_000_000, 2_000_000),
+            Some((Oversampling::X4, 24, 2_000_000))
+        );
+        assert_eq!(
+            find_divider(19_000_000, 3_000_000),
+            Some((Oversampling::X6, 2, 2_980_392))
+        );
+        assert_eq!(
+            find_divider(1_000_000, 250_000),
+            Some((Oversampling::X4, 0, 250_000))
+        );
+    }
+
+    #[test]
+    fn smartcard_divider_range() {
+        // A 4 MHz card clock gives 10752 Bd.
+        assert_eq!(
+            smartcard_divider(19_000_000, 4_000_000),
+            Some((Oversampling::X16, 3502))
+        );
+        assert_eq!(smartcard_divider(19_000_000, 0), None);
+        // 19 MHz / 4 = 4.75 MBd is the fastest baud rate.
+        assert_eq!(smartcard_divider(19_000_000, 372 * 5_000_000), None);
+        assert!(smartcard_divider(19_000_000, 372 * 4_750_000).is_some());
+    }
+
+    #[test]
+    fn clkdiv_high_baud_rate_does_not_underflow() {
+        // 32 * 19 MHz / (16 * 1 MBd) - 32 = 6, 2 MBd would underflow.
+        assert_eq!(
+            compute_clkdiv(19_000_000, Oversampling::X16, 1_000_000).ok(),
+            Some(6)
+        );
+        assert!(invalid(compute_clkdiv(
+            19_000_000,
+            Oversampling::X16,
+            2_000_000
+        )));
+        assert!(invalid(compute_clkdiv(
+            1_000_000,
+            Oversampling::X4,
+            u32::MAX
+        )));
+    }
+
+    #[test]
+    fn sync_divider_range() {
+        let invalid = |r| matches!(r, Err(ImplError::InvalidConfiguration));
+        assert_eq!(sync_divider(19_000_000, 10_000_000).ok(), Some(0));
+        // 19 MHz / (2 * 10) = 950 kHz, 19 MHz / (2 * 9) is too fast.
+        assert_eq!(sync_divider(19_000_000, 1_000_000).ok(), Some(32 * 9));
+        assert!(invalid(sync_divider(19_000_000, 0)));
+        // The divider of the slowest clock is 32768 at about 290 Hz.
+        assert_eq!(sync_divider(19_000_000, 290).ok(), Some(32 * 32758));
+        assert!(invalid(sync_divider(19_000_000, 289)));
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let memory = registers();
+        let usart = unsafe { &*memory.as_ptr() };
+        let config = Config {
+            baudrate: 57_600,
+            data_bits: DataBits::NINE,
+            parity: Parity::EVEN,
+            stop_bits: StopBits::TWO,
+            invert_rx: true,
+            loopback: true,
+            multi_processor: true,
+            address_bit: false,
+            ..Config::default()
+        };
+        let (oversampling, clkdiv, actual) = find_divider(19_000_000, config.baudrate).unwrap();
+
+        write_config(usart, &config, oversampling, clkdiv);
+        let read = read_config(usart, Hertz(19_000_000));
+
+        assert_eq!(read.baudrate, actual);
+        assert_eq!(read.data_bits, config.data_bits);
+        assert_eq!(read.parity, config.parity);
+        assert_eq!(read.stop_bits, config.stop_bits);
+        assert_eq!(read.invert_rx, config.invert_rx);
+        assert_eq!(read.invert_tx, config.invert_tx);
+        assert_eq!(read.loopback, config.loopback);
+        assert_eq!(read.multi_processor, config.multi_processor);
+        assert_eq!(read.address_bit, config.address_bit);
+    }
+
+    #[test]
+    fn collision_from_echo() {
+        assert!(!collided(0x55, 0x55, false));
+        // An open-drain bus is a wired AND, the dominant low bits win.
+        assert!(collided(0x55, 0x55 & 0x0F, false));
+        assert!(collided(0xFF, 0x7F, false));
+        assert!(collided(0x55, 0x55, true));
+    }
+
+    #[test]
+    fn echo_timeout_spans_frames() {
+        let memory = registers();
+        let usart = unsafe { &*memory.as_ptr() };
+        // 8 data bits (DATABITS = 5), X16, CLKDIV 0: a 12 bit frame takes
+        // 12 * 16 = 192 cycles of equal HFCORECLK and HFPERCLK.
+        load(&usart.frame, 5);
+        let polls = echo_polls(usart, Hertz(19_000_000), Hertz(19_000_000));
+        assert_eq!(polls, 4 * 192 / 4);
+        // A core clock at twice the peripheral clock polls twice as often.
+        let polls = echo_polls(usart, Hertz(38_000_000), Hertz(19_000_000));
+        assert_eq!(polls, 2 * 4 * 192 / 4);
+    }
+
+    #[test]
+    fn missing_echo_times_out() {
+        let memory = registers();
+        let usart = unsafe { &*memory.as_ptr() };
+        assert!(matches!(
+            wait_echo(usart, 10),
+            Err(HalfDuplexError::Serial(Error::Impl(ImplError::TimedOut)))
+        ));
+
+        load(&usart.status, STATUS_RXDATAV);
+        load(&usart.rxdatax, 0x55);
+        assert!(matches!(wait_echo(usart, 10), Ok((0x55, false))));
+        load(&usart.rxdatax, RXDATAX_FERR | 0x55);
+        assert!(matches!(wait_echo(usart, 10), Ok((0x55, true))));
+
+        load(&usart.if_, RXOF);
+        assert!(matches!(
+            wait_echo(usart, 10),
+            Err(HalfDuplexError::Serial(Error::Overrun))
+        ));
+        assert_eq!(peek(&usart.ifc), RXOF);
+    }
+
+    #[test]
+    fn overflow_reported_and_cleared() {
+        let memory = registers();
+        let usart = unsafe { &*memory.as_ptr() };
+        assert!(check_overflow(usart).is_ok());
+        assert_eq!(peek(&usart.ifc), 0);
+
+        load(&usart.if_, RXOF);
+        assert!(matches!(
+            check_overflow(usart),
+            Err(nb::Error::Other(Error::Overrun))
+        ));
+        assert_eq!(peek(&usart.ifc), RXOF);
+    }
+
+    #[test]
+    fn frame_errors_reported_and_cleared() {
+        let memory = registers();
+        let usart = unsafe { &*memory.as_ptr() };
+        assert!(check_frame(usart, false, false).is_ok());
+        assert_eq!(peek(&usart.ifc), 0);
+
+        assert!(matches!(
+            check_frame(usart, false, true),
+            Err(nb::Error::Other(Error::Parity))
+        ));
+        assert_eq!(peek(&usart.ifc), FERR | PERR);
+
+        load(&usart.ifc, 0);
+        // A framing error garbles the parity bit as well.
+        assert!(matches!(
+            check_frame(usart, true, true),
+            Err(nb::Error::Other(Error::FrameFormat))
+        ));
+        assert_eq!(peek(&usart.ifc), FERR | PERR);
+    }
+
+    #[test]
+    fn flush_waits_for_tx_complete() {
+        let usart = &*Fake;
+        let mut tx: Tx<Fake> = Tx { _priv: PhantomData };
+
+        load(&usart.status, STATUS_TXBL);
+        assert!(Write::write(&mut tx, 0x41).is_ok());
+        assert_eq!(peek(&usart.txdata), 0x41);
+        // Each character clears the completion flag of the previous one.
+        assert_eq!(peek(&usart.ifc), TXC);
+
+        // An empty transmit buffer does not mean that the shift register is
+        // empty, `TXIDLE` is not taken as completion.
+        load(&usart.status, STATUS_TXBL | STATUS_TXIDLE);
+        assert!(matches!(tx.flush(), Err(nb::Error::WouldBlock)));
+
+        load(&usart.if_, TXC);
+        assert!(tx.flush().is_ok());
+    }
+}
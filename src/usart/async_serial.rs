@@ -0,0 +1,173 @@
+//! Async serial API woken by the USART interrupts
+//!
+//! The `async fn`s of [`Rx`] and [`Tx`] complete immediately when the
+//! hardware is ready. Otherwise they register the waker of the task, enable
+//! the `RXDATAV`, `TXBL` or `TXC` interrupt and yield. The interrupt handlers
+//! must call [`on_rx_interrupt()`] and [`on_tx_interrupt()`], which disable
+//! the interrupt again and wake the task. The NVIC lines must be unmasked by
+//! the application:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn USART0_RX() {
+//!     async_serial::on_rx_interrupt::<USART0>();
+//! }
+//!
+//! #[interrupt]
+//! fn USART0_TX() {
+//!     async_serial::on_tx_interrupt::<USART0>();
+//! }
+//!
+//! async fn echo(mut tx: Tx<USART0>, mut rx: Rx<USART0>) {
+//!     loop {
+//!         let byte = rx.read_async().await.unwrap_or(b'?');
+//!         tx.write_async(byte).await;
+//!     }
+//! }
+//! ```
+//!
+//! Each USART has one waker for receiving and one for transmitting, so only
+//! one task may wait on each direction at a time. Works with any executor,
+//! no crate besides `core` is needed.
+use super::{Error, Instance, Rx, Tx};
+use crate::hal::serial::{Read, Write};
+use core::{
+    cell::RefCell,
+    future::poll_fn,
+    task::{Poll, Waker},
+};
+use cortex_m::interrupt::{self, Mutex};
+
+// Waker of the task waiting for a USART direction.
+struct WakerSlot(Mutex<RefCell<Option<Waker>>>);
+
+impl WakerSlot {
+    const fn new() -> Self {
+        WakerSlot(Mutex::new(RefCell::new(None)))
+    }
+
+    fn register(&self, waker: &Waker) {
+        interrupt::free(|cs| {
+            let mut slot = self.0.borrow(cs).borrow_mut();
+            match &*slot {
+                Some(registered) if registered.will_wake(waker) => {}
+                _ => *slot = Some(waker.clone()),
+            }
+        });
+    }
+
+    fn wake(&self) {
+        if let Some(waker) = interrupt::free(|cs| self.0.borrow(cs).borrow_mut().take()) {
+            waker.wake();
+        }
+    }
+}
+
+// Indexed by `Instance::INDEX`.
+static RX_WAKERS: [WakerSlot; 4] = [
+    WakerSlot::new(),
+    WakerSlot::new(),
+    WakerSlot::new(),
+    WakerSlot::new(),
+];
+static TX_WAKERS: [WakerSlot; 4] = [
+    WakerSlot::new(),
+    WakerSlot::new(),
+    WakerSlot::new(),
+    WakerSlot::new(),
+];
+
+/// Disables the `RXDATAV` interrupt and wakes the task waiting in
+/// [`Rx::read_async()`].
+///
+/// Must be called from the `USARTn_RX` interrupt handler. The received data
+/// is left in the FIFO for the task.
+pub fn on_rx_interrupt<I: Instance>() {
+    let usart_clear = unsafe { &*I::ptr_clear() };
+    usart_clear.ien.write(|w| w.rxdatav().set_bit());
+    RX_WAKERS[I::INDEX].wake();
+}
+
+/// Disables the `TXBL` and `TXC` interrupts and wakes the task waiting in
+/// [`Tx::write_async()`] or [`Tx::flush_async()`].
+///
+/// Must be called from the `USARTn_TX` interrupt handler.
+pub fn on_tx_interrupt<I: Instance>() {
+    let usart_clear = unsafe { &*I::ptr_clear() };
+    usart_clear
+        .ien
+        .write(|w| w.txbl().set_bit().txc().set_bit());
+    TX_WAKERS[I::INDEX].wake();
+}
+
+impl<I: Instance> Rx<I> {
+    /// Waits for a received byte.
+    ///
+    /// Returns frame and parity errors like [`Read::read()`].
+    pub async fn read_async(&mut self) -> Result<u8, Error> {
+        poll_fn(|cx| match self.read() {
+            Ok(byte) => Poll::Ready(Ok(byte)),
+            Err(nb::Error::Other(e)) => Poll::Ready(Err(e)),
+            Err(nb::Error::WouldBlock) => {
+                // Registered before the interrupt is enabled, so that a byte
+                // arriving in between still wakes the task.
+                RX_WAKERS[I::INDEX].register(cx.waker());
+                let usart_set = unsafe { &*I::ptr_set() };
+                usart_set.ien.write(|w| w.rxdatav().set_bit());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Waits until `buffer` is filled with received bytes.
+    ///
+    /// Stops at the first error, the bytes received before it are in the
+    /// buffer.
+    pub async fn read_exact_async(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        for b in buffer.iter_mut() {
+            *b = self.read_async().await?;
+        }
+        Ok(())
+    }
+}
+
+impl<I: Instance> Tx<I> {
+    /// Waits for space in the transmit buffer and queues `byte`.
+    pub async fn write_async(&mut self, byte: u8) {
+        poll_fn(|cx| match self.write(byte) {
+            Ok(()) => Poll::Ready(()),
+            Err(nb::Error::Other(e)) => match e {},
+            Err(nb::Error::WouldBlock) => {
+                TX_WAKERS[I::INDEX].register(cx.waker());
+                let usart_set = unsafe { &*I::ptr_set() };
+                usart_set.ien.write(|w| w.txbl().set_bit());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Queues all bytes, waiting for space in the transmit buffer as needed.
+    pub async fn write_all_async(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_async(b).await;
+        }
+    }
+
+    /// Waits until the last character has left the TX pin, see
+    /// [`Write::flush()`].
+    pub async fn flush_async(&mut self) {
+        poll_fn(|cx| match self.flush() {
+            Ok(()) => Poll::Ready(()),
+            Err(nb::Error::Other(e)) => match e {},
+            Err(nb::Error::WouldBlock) => {
+                TX_WAKERS[I::INDEX].register(cx.waker());
+                let usart_set = unsafe { &*I::ptr_set() };
+                usart_set.ien.write(|w| w.txc().set_bit());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
@@ -0,0 +1,269 @@
+//! Interrupt driven serial API backed by software ring buffers
+//!
+//! The raw [`Rx`] drops bytes when the application does not poll `read()`
+//! faster than the two byte hardware FIFO fills up. The buffered types move
+//! data between the peripheral and a ring buffer from the USART interrupt
+//! handlers so that the application can process data whenever it is ready.
+//!
+//! Both types must be shared between the application and the interrupt
+//! handler, e.g. with a `cortex_m::interrupt::Mutex<RefCell<Option<_>>>`.
+//! Call [`BufferedRx::on_interrupt()`] from the `USARTn_RX` handler and
+//! [`BufferedTx::on_interrupt()`] from the `USARTn_TX` handler. The NVIC lines
+//! must be unmasked by the application.
+//!
+//! A ring buffer of size `N` holds at most `N - 1` bytes.
+//!
+//! For framed protocols with a CRC, e.g. Modbus RTU, the GPCRC can check
+//! received frames while the bytes arrive, see [`BufferedRx::enable_crc()`],
+//! and append the CRC to transmitted frames, see
+//! [`BufferedTx::write_frame_with_crc()`]. CRC values are transmitted least
+//! significant byte first.
+use super::{Error, Instance, Rx, Tx};
+use crate::{
+    gpcrc::Gpcrc,
+    hal::serial::{Read, Write},
+    pac::Interrupt,
+};
+use core::fmt;
+use heapless::spsc::Queue;
+
+/// Receive part of the serial interface with a ring buffer of size `N`.
+pub struct BufferedRx<I, const N: usize> {
+    rx: Rx<I>,
+    queue: Queue<u8, N>,
+    error: Option<Error>,
+    crc: Option<Gpcrc>,
+}
+
+impl<I: Instance, const N: usize> BufferedRx<I, N> {
+    /// Wraps the receiver and enables the `RXDATAV` interrupt.
+    pub fn new(mut rx: Rx<I>) -> Self {
+        rx.enable_interrupt();
+        BufferedRx {
+            rx,
+            queue: Queue::new(),
+            error: None,
+            crc: None,
+        }
+    }
+
+    /// Returns the NVIC line whose handler must call
+    /// [`BufferedRx::on_interrupt()`]. The application unmasks it.
+    pub fn interrupt(&self) -> Interrupt {
+        self.rx.interrupt()
+    }
+
+    /// Moves all received bytes from the hardware FIFO to the ring buffer.
+    ///
+    /// Must be called from the `USARTn_RX` interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        loop {
+            match self.rx.read() {
+                Ok(b) => {
+                    if let Some(crc) = self.crc.as_mut() {
+                        crc.feed_byte(b);
+                    }
+                    if self.queue.enqueue(b).is_err() {
+                        self.error = Some(Error::Overrun);
+                    }
+                }
+                Err(nb::Error::Other(e)) => self.error = Some(e),
+                Err(nb::Error::WouldBlock) => break,
+            }
+        }
+    }
+
+    /// Returns the oldest received byte, if any.
+    pub fn pop(&mut self) -> Option<u8> {
+        self.queue.dequeue()
+    }
+
+    /// Copies as many received bytes as possible into `buffer` and returns
+    /// the number of bytes copied.
+    pub fn read_all(&mut self, buffer: &mut [u8]) -> usize {
+        let mut n = 0;
+        for b in buffer.iter_mut() {
+            match self.queue.dequeue() {
+                Some(data) => *b = data,
+                None => break,
+            }
+            n += 1;
+        }
+        n
+    }
+
+    /// Returns and clears the last error that occurred since the previous call.
+    ///
+    /// Bytes which caused a framing or parity error are discarded.
+    /// [`Error::Overrun`] indicates that the ring buffer was full and bytes
+    /// were lost.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// Feeds every received byte into the GPCRC from the interrupt handler.
+    ///
+    /// The calculation is reset with [`BufferedRx::reset_crc()`], which must
+    /// be called at the start of each frame, i.e. before its first byte is
+    /// received. Bytes with framing or parity errors are not fed. Once the
+    /// whole frame including its CRC was received, [`BufferedRx::crc_ok()`]
+    /// tells whether the frame is intact.
+    pub fn enable_crc(&mut self, mut crc: Gpcrc) {
+        crc.reset();
+        self.crc = Some(crc);
+    }
+
+    /// Stops the CRC calculation and returns the GPCRC.
+    pub fn disable_crc(&mut self) -> Option<Gpcrc> {
+        self.crc.take()
+    }
+
+    /// Returns the GPCRC while enabled, e.g. to let
+    /// [`BufferedTx::write_frame_with_crc()`] borrow it between the frames of
+    /// a half-duplex protocol.
+    pub fn crc_mut(&mut self) -> Option<&mut Gpcrc> {
+        self.crc.as_mut()
+    }
+
+    /// Starts a new CRC calculation for the next frame.
+    pub fn reset_crc(&mut self) {
+        if let Some(crc) = self.crc.as_mut() {
+            crc.reset();
+        }
+    }
+
+    /// Returns `true` when the bytes received since the last
+    /// [`BufferedRx::reset_crc()`] end with their own valid CRC.
+    ///
+    /// Always `false` while the CRC calculation is disabled.
+    pub fn crc_ok(&self) -> bool {
+        self.crc.as_ref().is_some_and(Gpcrc::is_valid)
+    }
+
+    /// Disables the `RXDATAV` interrupt and returns the unbuffered receiver.
+    ///
+    /// The GPCRC, if enabled, is dropped. Take it back with
+    /// [`BufferedRx::disable_crc()`] before.
+    pub fn free(mut self) -> Rx<I> {
+        self.rx.disable_interrupt();
+        self.rx
+    }
+}
+
+/// Transmit part of the serial interface with a ring buffer of size `N`.
+pub struct BufferedTx<I, const N: usize> {
+    tx: Tx<I>,
+    queue: Queue<u8, N>,
+}
+
+impl<I: Instance, const N: usize> BufferedTx<I, N> {
+    /// Wraps the transmitter.
+    pub fn new(tx: Tx<I>) -> Self {
+        BufferedTx {
+            tx,
+            queue: Queue::new(),
+        }
+    }
+
+    /// Copies as many bytes from `data` as fit into the ring buffer and returns
+    /// the number of bytes accepted.
+    ///
+    /// Enables the `TXBL` interrupt which drains the ring buffer.
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        let mut n = 0;
+        for &b in data {
+            if self.queue.enqueue(b).is_err() {
+                break;
+            }
+            n += 1;
+        }
+        if !self.queue.is_empty() {
+            self.tx.enable_interrupt();
+        }
+        n
+    }
+
+    /// Queues `frame` followed by its CRC, least significant byte first.
+    ///
+    /// The GPCRC is reset before the calculation. Returns `false` without
+    /// queueing anything when the frame and CRC do not fit into the ring
+    /// buffer completely.
+    pub fn write_frame_with_crc(&mut self, frame: &[u8], crc: &mut Gpcrc) -> bool {
+        let width = crc.width();
+        if self.queue.capacity() - self.queue.len() < frame.len() + width {
+            return false;
+        }
+
+        crc.reset();
+        crc.feed(frame);
+        let value = crc.value().to_le_bytes();
+        let written = self.write(frame) + self.write(&value[..width]);
+        debug_assert_eq!(written, frame.len() + width);
+        true
+    }
+
+    /// Queues all bytes of `data`.
+    ///
+    /// Returns immediately when `data` fits into the ring buffer. Otherwise
+    /// the ring buffer is drained to the hardware by polling until enough
+    /// space is available, which also works with interrupts disabled, e.g.
+    /// inside a critical section guarding the shared instance.
+    pub fn write_all(&mut self, mut data: &[u8]) {
+        loop {
+            data = &data[self.write(data)..];
+            if data.is_empty() {
+                return;
+            }
+            self.drain();
+        }
+    }
+
+    /// Returns `true` when all buffered bytes were moved to the hardware.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Returns the NVIC line whose handler must call
+    /// [`BufferedTx::on_interrupt()`]. The application unmasks it.
+    pub fn interrupt(&self) -> Interrupt {
+        self.tx.interrupt()
+    }
+
+    /// Moves bytes from the ring buffer to the hardware FIFO.
+    ///
+    /// Must be called from the `USARTn_TX` interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        if self.drain() {
+            self.tx.disable_interrupt();
+        }
+    }
+
+    // Moves bytes to the hardware FIFO until it is full. Returns `true` when
+    // the ring buffer is empty.
+    fn drain(&mut self) -> bool {
+        while let Some(&b) = self.queue.peek() {
+            if self.tx.write(b).is_err() {
+                return false;
+            }
+            self.queue.dequeue();
+        }
+        true
+    }
+
+    /// Disables the `TXBL` interrupt and returns the unbuffered transmitter.
+    ///
+    /// Bytes remaining in the ring buffer are discarded.
+    pub fn free(mut self) -> Tx<I> {
+        self.tx.disable_interrupt();
+        self.tx
+    }
+}
+
+/// Queues formatted output with [`BufferedTx::write_all()`], e.g. for
+/// non-blocking logging with `write!`.
+impl<I: Instance, const N: usize> fmt::Write for BufferedTx<I, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes());
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! Continuous DMA reception into a circular buffer
+//!
+//! [`CircularRx`] lets an LDMA channel move every received byte from `RXDATA`
+//! to a buffer without CPU involvement. The channel starts over at the
+//! beginning of the buffer when it reached its end, so reception never stops.
+//! The application copies out the bytes that arrived since the last call with
+//! [`CircularRx::read_available()`], which takes care of the wrap-around:
+//!
+//! ```ignore
+//! static mut BUFFER: [u8; 256] = [0; 256];
+//!
+//! let (tx, rx) = usart.split();
+//! let (_ldma, channels) = Ldma::new(p.LDMA, &mut cmu);
+//! let mut rx = CircularRx::new(rx, unsafe { &mut *addr_of_mut!(BUFFER) }, channels.ch0);
+//! let mut chunk = [0; 64];
+//! loop {
+//!     let n = rx.read_available(&mut chunk);
+//!     process(&chunk[..n]);
+//! }
+//! ```
+//!
+//! The DMA overwrites bytes that were not read within one pass through the
+//! buffer without notice. Choose the buffer size so that it holds all bytes
+//! received between two calls of [`CircularRx::read_available()`]. Frame and
+//! parity errors are not reported either.
+use super::{Instance, Rx, LDMA_SIGNAL_RXDATAV};
+use crate::ldma::{self, Channel};
+use core::ptr;
+
+/// Receive part of the serial interface that is continuously filling a
+/// circular buffer with DMA.
+pub struct CircularRx<I> {
+    rx: Rx<I>,
+    buffer: &'static mut [u8],
+    channel: Channel,
+    // Number of bytes of the buffer used by the DMA.
+    len: usize,
+    // Index of the oldest byte not copied out yet.
+    read: usize,
+}
+
+impl<I: Instance> CircularRx<I> {
+    /// Starts the DMA reception into the buffer.
+    ///
+    /// At most [`ldma::MAX_TRANSFER_COUNT`] bytes of the buffer are used.
+    ///
+    /// Panics when the buffer is empty.
+    pub fn new(rx: Rx<I>, buffer: &'static mut [u8], mut channel: Channel) -> Self {
+        assert!(!buffer.is_empty());
+        let len = buffer.len().min(ldma::MAX_TRANSFER_COUNT);
+
+        let usart = unsafe { &*I::ptr() };
+        channel.start_circular_bytes(
+            I::LDMA_SOURCE,
+            LDMA_SIGNAL_RXDATAV,
+            (&usart.rxdata as *const _ as u32, false),
+            (buffer.as_mut_ptr() as u32, true),
+            len,
+        );
+
+        CircularRx {
+            rx,
+            buffer,
+            channel,
+            len,
+            read: 0,
+        }
+    }
+
+    // Index of the byte the DMA writes next.
+    fn write_position(&self) -> usize {
+        let start = self.buffer.as_ptr() as usize;
+        // Right after the last byte of the buffer was written the address
+        // points past its end until the descriptor was reloaded.
+        (self.channel.dst_address() as usize - start) % self.len
+    }
+
+    /// Returns the number of received bytes that were not copied out yet.
+    pub fn available(&self) -> usize {
+        let write = self.write_position();
+        if write >= self.read {
+            write - self.read
+        } else {
+            self.len - self.read + write
+        }
+    }
+
+    /// Copies the received bytes that were not copied out yet to `dst`,
+    /// oldest first, and returns their number.
+    ///
+    /// Copies at most `dst.len()` bytes, the remaining ones are returned by
+    /// the next call.
+    pub fn read_available(&mut self, dst: &mut [u8]) -> usize {
+        let write = self.write_position();
+        let mut copied = 0;
+        while copied < dst.len() && self.read != write {
+            // Bytes up to the end of the buffer first, when the DMA wrapped.
+            let end = if write > self.read { write } else { self.len };
+            let count = (end - self.read).min(dst.len() - copied);
+            // The DMA writes to other parts of the buffer concurrently.
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    self.buffer.as_ptr().add(self.read),
+                    dst[copied..].as_mut_ptr(),
+                    count,
+                );
+            }
+            copied += count;
+            self.read = (self.read + count) % self.len;
+        }
+        copied
+    }
+
+    /// Stops the DMA reception and returns the receiver, the buffer and the
+    /// channel.
+    ///
+    /// Bytes not copied out yet are discarded.
+    pub fn free(mut self) -> (Rx<I>, &'static mut [u8], Channel) {
+        self.channel.stop();
+        (self.rx, self.buffer, self.channel)
+    }
+}
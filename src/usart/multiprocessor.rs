@@ -0,0 +1,139 @@
+//! Multi-processor mode for multidrop buses
+//!
+//! On a multidrop bus, e.g. RS-485, all nodes receive every frame. The ninth
+//! data bit distinguishes address frames, which select the node that the
+//! following data frames are meant for, from the data frames themselves. The
+//! bit value that marks an address frame is [`Config::address_bit`].
+//!
+//! [`MultiProcessorRx`] blocks the receiver (`CMD.RXBLOCKEN`), so the
+//! hardware discards data frames. Address frames are received regardless.
+//! When one matches the address of the node the receiver is unblocked until
+//! the next address frame arrives. The transmitter sends address and data
+//! frames with [`Tx::write_address()`] and [`Tx::write_data()`]:
+//!
+//! ```ignore
+//! let mut config = Config::default().with_data_bits(DataBits::NINE);
+//! config.multi_processor = true;
+//! let (mut tx, rx) = Usart::new(p.USART0, tx_pin, rx_pin, &config, &mut cmu)?.split_u16();
+//! let mut rx = MultiProcessorRx::new(rx, 0x12);
+//!
+//! block!(tx.write_address(0x34))?;
+//! block!(tx.write_data(0x42))?;
+//! let byte = block!(rx.read())?;
+//! ```
+//!
+//! An address frame must be read before the end of the first data frame that
+//! follows it, i.e. within one frame time, otherwise that data frame is
+//! discarded while the receiver is still blocked. Read the receiver from the
+//! `RXDATAV` interrupt at high baud rates.
+//!
+//! [`Config::address_bit`]: super::Config::address_bit
+use super::{Error, Instance, Rx, Tx};
+use crate::hal::serial::{Read, Write};
+use core::convert::Infallible;
+
+/// Receive part of the serial interface in multi-processor mode that only
+/// delivers the data frames addressed to this node.
+pub struct MultiProcessorRx<I> {
+    rx: Rx<I, u16>,
+    address: u8,
+}
+
+impl<I: Instance> MultiProcessorRx<I> {
+    /// Blocks the receiver until an address frame with `address` arrives.
+    ///
+    /// The USART must have been configured with
+    /// [`Config::multi_processor`](super::Config::multi_processor).
+    pub fn new(rx: Rx<I, u16>, address: u8) -> Self {
+        let mut rx = MultiProcessorRx { rx, address };
+        rx.deselect();
+        rx
+    }
+
+    /// Address of this node.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Changes the address of this node.
+    ///
+    /// Blocks the receiver until an address frame with the new address
+    /// arrives, data frames of a transfer in progress are discarded.
+    pub fn set_address(&mut self, address: u8) {
+        self.address = address;
+        self.deselect();
+    }
+
+    /// Returns `true` while the last address frame matched, i.e. data frames
+    /// are delivered.
+    pub fn is_selected(&self) -> bool {
+        let usart = unsafe { &*I::ptr() };
+        usart.status.read().rxblock().bit_is_clear()
+    }
+
+    fn deselect(&mut self) {
+        let usart = unsafe { &*I::ptr() };
+        usart.cmd.write(|w| w.rxblocken().set_bit());
+    }
+
+    /// Unblocks the receiver and returns the receive part of the serial
+    /// interface, which delivers address and data frames alike.
+    pub fn free(self) -> Rx<I, u16> {
+        let usart = unsafe { &*I::ptr() };
+        usart.cmd.write(|w| w.rxblockdis().set_bit());
+        self.rx
+    }
+}
+
+/// Reads the data frames addressed to this node.
+///
+/// Address frames are consumed: a matching address unblocks the receiver,
+/// any other address blocks it again. A frame with a frame or parity error
+/// might have been an address frame, so the error is returned and the
+/// receiver is blocked until the next matching address.
+impl<I: Instance> Read<u8> for MultiProcessorRx<I> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        let usart = unsafe { &*I::ptr() };
+        loop {
+            let frame = match self.rx.read() {
+                Ok(frame) => frame,
+                Err(nb::Error::Other(e)) => {
+                    self.deselect();
+                    return Err(nb::Error::Other(e));
+                }
+                Err(nb::Error::WouldBlock) => return Err(nb::Error::WouldBlock),
+            };
+            let address_bit = usart.ctrl.read().mpab().bit_is_set();
+            if (frame & 0x100 != 0) != address_bit {
+                return Ok(frame as u8);
+            }
+
+            if frame as u8 == self.address {
+                usart.cmd.write(|w| w.rxblockdis().set_bit());
+            } else {
+                self.deselect();
+            }
+        }
+    }
+}
+
+impl<I: Instance> Tx<I, u16> {
+    /// Sends an address frame, i.e. the ninth bit is set to
+    /// [`Config::address_bit`](super::Config::address_bit).
+    pub fn write_address(&mut self, address: u8) -> nb::Result<(), Infallible> {
+        let usart = unsafe { &*I::ptr() };
+        let address_bit = usart.ctrl.read().mpab().bit_is_set();
+        self.write(address as u16 | (address_bit as u16) << 8)
+    }
+
+    /// Sends a data frame, i.e. the ninth bit is cleared when
+    /// [`Config::address_bit`](super::Config::address_bit) is set and vice
+    /// versa.
+    pub fn write_data(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        let usart = unsafe { &*I::ptr() };
+        let address_bit = usart.ctrl.read().mpab().bit_is_set();
+        self.write(byte as u16 | (!address_bit as u16) << 8)
+    }
+}
@@ -0,0 +1,53 @@
+//! Half-duplex RS-485 transmitter with a driver enable pin
+//!
+//! [`Rs485Tx`] drives the DE pin of an RS-485 transceiver high for the
+//! duration of each transmission. The pin goes low once the stop bit(s) of
+//! the last byte left the TX pin, which [`Tx::flush()`](Write::flush) reports
+//! with the `TXC` flag. Releasing the bus any earlier truncates the last
+//! byte, any later collides with the response of a fast device:
+//!
+//! ```ignore
+//! let de = gpio.pa4.push_pull_output(PinState::Low);
+//! let (tx, mut rx) = usart.split();
+//! let mut tx = Rs485Tx::new(tx, de)?;
+//! tx.write_all(&request)?;
+//! let response = rx.read_timeout(100_000);
+//! ```
+//!
+//! The receiver of most transceivers is disabled while DE is high (with the
+//! inverted RE tied to DE), so the own transmission is not received.
+use super::{Instance, Tx};
+use crate::hal::{digital::v2::OutputPin, serial::Write};
+
+/// Transmit part of the serial interface that enables an RS-485 driver while
+/// it transmits.
+pub struct Rs485Tx<I, DE> {
+    tx: Tx<I>,
+    de: DE,
+}
+
+impl<I: Instance, DE: OutputPin> Rs485Tx<I, DE> {
+    /// Drives the DE pin low, i.e. releases the bus.
+    pub fn new(tx: Tx<I>, mut de: DE) -> Result<Self, DE::Error> {
+        de.set_low()?;
+        Ok(Rs485Tx { tx, de })
+    }
+
+    /// Enables the driver, transmits all bytes and disables the driver once
+    /// the last stop bit went out.
+    ///
+    /// Blocks until the transmission is complete.
+    pub fn write_all(&mut self, bytes: &[u8]) -> Result<(), DE::Error> {
+        self.de.set_high()?;
+        for &b in bytes {
+            nb::block!(self.tx.write(b)).ok();
+        }
+        nb::block!(self.tx.flush()).ok();
+        self.de.set_low()
+    }
+
+    /// Returns the transmitter and the DE pin, which is left low.
+    pub fn free(self) -> (Tx<I>, DE) {
+        (self.tx, self.de)
+    }
+}
@@ -0,0 +1,169 @@
+//! Voltage digital to analog converter (VDAC) API
+//!
+//! Each of the two channels drives its main output pin through the output
+//! buffer of an operational amplifier (channel 0 through OPA0, channel 1
+//! through OPA1), see `VDAC0_OUT0` and `VDAC0_OUT1` in the datasheet.
+//!
+//! # Output glitches
+//!
+//! The main output of the buffer is connected to the pin from reset on. When
+//! a channel is enabled, the buffer drives the pin while the channel is still
+//! warming up and its output is undefined, which shows up as a spike towards
+//! one of the rails before the pin settles at the converted value. The same
+//! happens on a value written after enabling, the pin first briefly shows the
+//! stale data.
+//!
+//! [`Vdac::enable_glitch_free()`] avoids both: it disconnects the pin, writes
+//! the data, enables the channel, waits until the channel is warm and only
+//! then connects the pin. [`Vdac::disable_glitch_free()`] disconnects the pin
+//! before the channel is turned off, so the pin goes to high impedance
+//! instead of following the output while it collapses. Use these by default,
+//! [`Vdac::enable()`] and [`Vdac::disable()`] only flip the channel enable
+//! for applications that do not care about the transition.
+//!
+//! The channels use the calibration loaded at reset.
+pub use crate::pac::vdac0::ctrl::REFSEL_A as Reference;
+
+use crate::{cmu::Cmu, pac::VDAC0};
+
+// Fastest DAC_CLK for continuous conversions.
+const MAX_DAC_CLK: u32 = 1_000_000;
+
+/// VDAC channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Ch0,
+    Ch1,
+}
+
+/// VDAC configuration.
+///
+/// Defaults to the internal low noise 1.25 V reference.
+pub struct Config {
+    /// Full scale voltage of both channels.
+    pub reference: Reference,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            reference: Reference::_1V25LN,
+        }
+    }
+}
+
+/// VDAC API
+pub struct Vdac {
+    raw: VDAC0,
+}
+
+impl Vdac {
+    /// Creates the HAL instance and configures the reference and the clock.
+    /// Both channels stay disabled.
+    pub fn new(vdac: VDAC0, config: &Config, cmu: &mut Cmu) -> Vdac {
+        cmu.enable_clock(&vdac);
+
+        // DAC_CLK = HFPERCLK / (PRESC + 1)
+        let presc = (cmu.hfperclk().0.div_ceil(MAX_DAC_CLK) - 1) as u8;
+        vdac.ctrl
+            .write(|w| unsafe { w.refsel().variant(config.reference).presc().bits(presc) });
+        debug!("VDAC: PRESC {=u8}", presc);
+
+        Vdac { raw: vdac }
+    }
+
+    /// Writes a 12-bit value to a channel and starts the conversion.
+    ///
+    /// Panics when `value` does not fit into 12 bits.
+    pub fn set_value(&mut self, channel: Channel, value: u16) {
+        assert!(value <= 0xFFF);
+        match channel {
+            Channel::Ch0 => self.raw.ch0data.write(|w| unsafe { w.data().bits(value) }),
+            Channel::Ch1 => self.raw.ch1data.write(|w| unsafe { w.data().bits(value) }),
+        }
+    }
+
+    /// Enables a channel and connects its output pin once the channel
+    /// outputs `value`.
+    ///
+    /// Blocks for the warm-up time of the channel. See the
+    /// [module documentation](self) for the sequence.
+    pub fn enable_glitch_free(&mut self, channel: Channel, value: u16) {
+        self.set_main_output(channel, false);
+        self.set_value(channel, value);
+        self.enable(channel);
+        match channel {
+            Channel::Ch0 => while self.raw.status.read().ch0warm().bit_is_clear() {},
+            Channel::Ch1 => while self.raw.status.read().ch1warm().bit_is_clear() {},
+        }
+        self.set_main_output(channel, true);
+    }
+
+    /// Disconnects the output pin of a channel and disables the channel.
+    ///
+    /// The pin stays at high impedance until the next
+    /// [`Vdac::enable_glitch_free()`].
+    pub fn disable_glitch_free(&mut self, channel: Channel) {
+        self.set_main_output(channel, false);
+        self.disable(channel);
+    }
+
+    /// Enables a channel without disconnecting its output pin during the
+    /// warm-up.
+    pub fn enable(&mut self, channel: Channel) {
+        match channel {
+            Channel::Ch0 => self.raw.cmd.write(|w| w.ch0en().set_bit()),
+            Channel::Ch1 => self.raw.cmd.write(|w| w.ch1en().set_bit()),
+        }
+    }
+
+    /// Disables a channel and waits until it is off. The output pin is left
+    /// connected.
+    pub fn disable(&mut self, channel: Channel) {
+        match channel {
+            Channel::Ch0 => {
+                self.raw.cmd.write(|w| w.ch0dis().set_bit());
+                while self.raw.status.read().ch0ens().bit_is_set() {}
+            }
+            Channel::Ch1 => {
+                self.raw.cmd.write(|w| w.ch1dis().set_bit());
+                while self.raw.status.read().ch1ens().bit_is_set() {}
+            }
+        }
+    }
+
+    fn set_main_output(&mut self, channel: Channel, enabled: bool) {
+        match channel {
+            Channel::Ch0 => self.raw.opa0_out.modify(|_, w| w.mainouten().bit(enabled)),
+            Channel::Ch1 => self.raw.opa1_out.modify(|_, w| w.mainouten().bit(enabled)),
+        }
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// reconnecting an output pin during the warm-up reintroduces the glitch.
+    pub unsafe fn raw(&self) -> &crate::pac::vdac0::RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Vdac::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut VDAC0 {
+        &mut self.raw
+    }
+
+    /// Disables both channels glitch free and returns the raw interface to
+    /// the underlying peripheral.
+    pub fn release(mut self) -> VDAC0 {
+        self.disable_glitch_free(Channel::Ch0);
+        self.disable_glitch_free(Channel::Ch1);
+        self.raw
+    }
+}
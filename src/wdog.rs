@@ -0,0 +1,215 @@
+//! Watchdog timer (WDOG) API
+//!
+//! [`Wdog`] resets the device when it is not fed within the timeout period
+//! passed to [`WatchdogEnable::start()`]. Feed it from the main loop, not from
+//! an interrupt handler, so that a stalled loop is detected:
+//!
+//! ```ignore
+//! let mut wdog = Wdog::new(p.WDOG0, &wdog::Config::default(), &mut cmu);
+//! wdog.start(2000.ms());
+//! loop {
+//!     poll_sensors(&mut i2c);
+//!     wdog.feed();
+//! }
+//! ```
+//!
+//! # Clock source
+//!
+//! The timeout period is `2^(3 + PERSEL) + 1` cycles of the watchdog clock,
+//! for `PERSEL` in `0..=15`. The requested period is rounded to the closest
+//! one, so the available periods depend on [`Config::clock_source`]:
+//!
+//! | Clock source           | Shortest | Longest |
+//! |------------------------|---------:|--------:|
+//! | ULFRCO (1 kHz)         |     9 ms |   262 s |
+//! | LFRCO, LFXO (32768 Hz) |  0.27 ms |     8 s |
+//!
+//! The ULFRCO is always running, also in EM3, and cannot be stopped by
+//! software, which makes it the most robust choice. Its frequency varies by
+//! several percent with temperature and supply voltage, so keep a margin
+//! between the feed interval and the period. The LFRCO and the LFXO are more
+//! accurate but stop in EM3, and the watchdog stops counting with them. A
+//! failing crystal would also stop the watchdog, the LFXO must already be
+//! running when the watchdog is started.
+pub use crate::pac::wdog0::ctrl::CLKSEL_A as ClockSource;
+
+use crate::{
+    cmu::{ClockControlExt, Cmu},
+    hal::watchdog::{Watchdog, WatchdogDisable, WatchdogEnable},
+    pac::{wdog0::RegisterBlock, WDOG0, WDOG1},
+    time::MilliSeconds,
+};
+use core::ops::Deref;
+
+// Largest `CTRL.PERSEL` value.
+const MAX_PERSEL: u8 = 15;
+
+/// Watchdog configuration.
+///
+/// Defaults to the ULFRCO, with the counter frozen in EM2 and EM3 and while
+/// the core is halted by a debugger.
+pub struct Config {
+    /// Clock of the watchdog counter, see the [module documentation](self).
+    ///
+    /// The LFRCO is started by [`Wdog::new()`]. The LFXO must already be
+    /// running.
+    pub clock_source: ClockSource,
+    /// Keeps counting in EM2 and EM3, which requires the application to wake
+    /// up and feed the watchdog within each period.
+    pub run_in_sleep: bool,
+    /// Keeps counting while the core is halted by a debugger.
+    pub debug_run: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            clock_source: ClockSource::ULFRCO,
+            run_in_sleep: false,
+            debug_run: false,
+        }
+    }
+}
+
+/// WDOG API
+pub struct Wdog<W> {
+    raw: W,
+    clock_frequency: u32,
+}
+
+impl<W: Instance> Wdog<W> {
+    /// Applies the configuration, the watchdog stays disabled until
+    /// [`WatchdogEnable::start()`].
+    pub fn new(wdog: W, config: &Config, cmu: &mut Cmu) -> Self {
+        cmu.enable_clock(&wdog);
+        let clock_frequency = match config.clock_source {
+            ClockSource::ULFRCO => 1_000,
+            ClockSource::LFRCO => {
+                cmu.enable_lfrco();
+                32_768
+            }
+            ClockSource::LFXO => 32_768,
+            ClockSource::HFCORECLK => cmu.hfcoreclk().0,
+        };
+
+        let mut wdog = Wdog {
+            raw: wdog,
+            clock_frequency,
+        };
+        wdog.disable();
+        wdog.raw.ctrl.write(|w| {
+            w.clksel()
+                .variant(config.clock_source)
+                .em2run()
+                .bit(config.run_in_sleep)
+                .em3run()
+                .bit(config.run_in_sleep)
+                .debugrun()
+                .bit(config.debug_run)
+        });
+        wdog
+    }
+
+    /// Timeout period of the current configuration, rounded down to full
+    /// milliseconds.
+    pub fn period(&self) -> MilliSeconds {
+        let persel = self.raw.ctrl.read().persel().bits();
+        MilliSeconds((period_cycles(persel) * 1000 / self.clock_frequency as u64) as u32)
+    }
+
+    /// Returns `true` when the watchdog is running.
+    pub fn is_enabled(&self) -> bool {
+        self.raw.ctrl.read().en().bit_is_set()
+    }
+
+    // Writes to CTRL are synchronized into the watchdog clock domain and must
+    // not overlap.
+    fn wait_ctrl_sync(&self) {
+        while self.raw.syncbusy.read().ctrl().bit_is_set() {}
+    }
+
+    /// Returns the registers of the underlying peripheral without giving up
+    /// the driver.
+    ///
+    /// # Safety
+    ///
+    /// Register writes bypass the driver and can violate its invariants, e.g.
+    /// a changed clock source invalidates [`Wdog::period()`], and setting
+    /// `CTRL.LOCK` makes [`WatchdogDisable::disable()`] ineffective until the
+    /// next reset.
+    pub unsafe fn raw(&self) -> &RegisterBlock {
+        &self.raw
+    }
+
+    /// Returns the underlying peripheral without giving up the driver.
+    ///
+    /// # Safety
+    ///
+    /// Same as for [`Wdog::raw()`].
+    pub unsafe fn raw_mut(&mut self) -> &mut W {
+        &mut self.raw
+    }
+
+    /// Disables the watchdog and returns the raw interface to the underlying
+    /// peripheral.
+    pub fn release(mut self) -> W {
+        self.disable();
+        self.raw
+    }
+}
+
+// Timeout period of `persel` in watchdog clock cycles.
+fn period_cycles(persel: u8) -> u64 {
+    (1 << (3 + persel)) + 1
+}
+
+impl<W: Instance> WatchdogEnable for Wdog<W> {
+    type Time = MilliSeconds;
+
+    /// Starts the watchdog with the available period closest to `period`.
+    fn start<T>(&mut self, period: T)
+    where
+        T: Into<MilliSeconds>,
+    {
+        let cycles = period.into().0 as u64 * self.clock_frequency as u64 / 1000;
+        let persel = (0..=MAX_PERSEL)
+            .min_by_key(|&persel| period_cycles(persel).abs_diff(cycles))
+            .unwrap_or(MAX_PERSEL);
+        debug!("WDOG: PERSEL {=u8}", persel);
+
+        // The period must not change while the watchdog runs.
+        self.disable();
+        self.raw
+            .ctrl
+            .modify(|_, w| unsafe { w.persel().bits(persel) });
+        self.wait_ctrl_sync();
+        self.raw.cmd.write(|w| w.clear().set_bit());
+        self.raw.ctrl.modify(|_, w| w.en().set_bit());
+    }
+}
+
+impl<W: Instance> Watchdog for Wdog<W> {
+    /// Restarts the timeout period.
+    fn feed(&mut self) {
+        // A clear that is still being synchronized restarts the period anyway.
+        if self.raw.syncbusy.read().cmd().bit_is_clear() {
+            self.raw.cmd.write(|w| w.clear().set_bit());
+        }
+    }
+}
+
+impl<W: Instance> WatchdogDisable for Wdog<W> {
+    /// Stops the watchdog.
+    fn disable(&mut self) {
+        self.wait_ctrl_sync();
+        self.raw.ctrl.modify(|_, w| w.en().clear_bit());
+        self.wait_ctrl_sync();
+    }
+}
+
+/// Internal trait used to implement the WDOG API for PAC WDOG instances.
+pub trait Instance: Deref<Target = RegisterBlock> + ClockControlExt {}
+
+impl Instance for WDOG0 {}
+
+impl Instance for WDOG1 {}